@@ -0,0 +1,19 @@
+use std::process::Command;
+
+// bakes the git commit into the wasm for `get_build_info`, CI may pass it through the environment
+fn main() {
+    let hash = std::env::var("GIT_COMMIT_HASH").ok().unwrap_or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|s| s.trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    });
+    println!("cargo:rustc-env=GIT_COMMIT_HASH={}", hash);
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT_HASH");
+    println!("cargo:rerun-if-changed=../../../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../../../.git/refs/heads");
+}
@@ -19,13 +19,34 @@ use candid::Principal;
 //
 // ------------------
 // injected macros
+use dod_mod::common::ONE_DAY_NS;
+use dod_mod::service::audit::AuditCall;
 use dod_mod::service::DodService;
 use dod_mod::state::*;
-use dod_mod::types::UserDetail;
+use dod_mod::types::{
+    ClaimLegResult, ClaimStatement, Dashboard, IdentityOverview, LedgerDeadLetter, LedgerOp,
+    PendingClaim, UserDetail, UserStats,
+};
+use dod_utils::bitwork::Bitwork;
 use dod_utils::types::{
-    BlockData, BlockDataFull, BlockSigs, BootStrapParams, DodCanisters, HalvingSettings, Height,
-    MinerBlockData, MinerCandidate, MinerInfo, MinerSubmitPayload, MinerSubmitResponse,
-    NewBlockOrderValue, OrderStatus, UserBlockOrderRes,
+    AddressValidationError, AdminAuditEntry, AssetReward, BidCurrency, BlackholeProposal,
+    BlockArchive, BlockBundle, BlockData, BlockDataFull, BlockDataFullPage, BlockDonations,
+    BlockEntropy, BlockEra, BlockFilter, BlockImport, BlockLedgerLinks, BlockOrdersPage,
+    BlockRange, BlockSigs, BootStrapParams, BroadcastInfo, BtcNetwork, BuildInfo, BurnReceipt,
+    BuybackSettings, ClaimsHaltStatus, CyclesHealth, DeployPreview, DepositAccount, DepositRecord,
+    DepositStatus, DifficultySteps, DifficultyTiers, DodCanisters, DodEvent, EfficiencyStats,
+    EmissionStatus, EpochSummary, FailedBlockPolicy, FeeSettings, GenesisInfo, GenesisParams,
+    GovernanceStatus, HalvingSettings, HealthStatus, Height, HttpRequest, HttpResponse, Integrator,
+    InternalTransfer, LedgerMetadata, LockInBonus, LogEntry, LogLevel, MaintenanceStatus,
+    MemoryReport, MinerBlockData, MinerCandidate, MinerInfo, MinerReward,
+    MinerRewardsReconciliation, MinerStatus, MinerSubmitPayload, MinerSubmitResponse, MinersPage,
+    MiningAsset, MiningAssetArgs, MiningPool, NewBlockOrderValue, NotificationPreferences,
+    OrderPlacementResult, OrderPreview, OrderStatus, PendingRewards, PoolCandidate, PoolTwap,
+    PoolWorkerClaim, PriorityStats, ProtocolConstants, RecoveryStatus, ReferralInfo,
+    ReferralSettings, ReferrerStats, ReplicationInfo, RewardBoost, RewardDustPolicy, RewardPolicy,
+    ScheduledBlock, SettlementCheckpoint, SolvencyReport, StateDelta, TopUpEstimate, TopUpSettings,
+    TreasuryBucket, TreasuryBucketInfo, TreasurySplit, UnbroadcastWinsPage, UserBlockOrderRes,
+    UserNotification, UserOrdersRes, WasmInfo, WinnerEnvelope, WinnerPaymentProof, WorkEstimate,
 };
 use ic_cdk::caller;
 use ic_cdk_macros::*;
@@ -41,6 +62,10 @@ use icrc_ledger_types::icrc1::account::Account;
 inject_ego_api!();
 inject_app_info_api!();
 
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+// set by build.rs
+const GIT_COMMIT_HASH: &str = env!("GIT_COMMIT_HASH");
+
 #[cfg(not(feature = "no_candid"))]
 #[init]
 #[candid_method(init, rename = "init")]
@@ -58,19 +83,23 @@ pub fn pre_upgrade() {
 #[post_upgrade]
 pub fn post_upgrade() {
     dod_mod::state::post_upgrade();
+    DodService::record_upgrade(VERSION, GIT_COMMIT_HASH);
+    DodService::start_watchdog();
+    DodService::resume_ledger_queue();
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "whoAmI", guard = "owner_guard")]
+#[update(name = "whoAmI", guard = "owner_activity_guard")]
 #[candid_method(update, rename = "whoAmI")]
 pub fn who_am_i() -> Principal {
     ic_cdk::api::caller()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "bootstrap", guard = "owner_guard")]
+#[update(name = "bootstrap", guard = "owner_update_guard")]
 #[candid_method(update, rename = "bootstrap")]
 pub fn bootstrap(params: BootStrapParams) {
+    let audit = AuditCall::begin("bootstrap");
     DodService::new(
         params.block_timer,
         params.difficulty_epoch,
@@ -80,50 +109,74 @@ pub fn bootstrap(params: BootStrapParams) {
         params.dod_token_canister,
         params.start_difficulty,
     );
+    audit.done();
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "add_archive_wasm", guard = "owner_guard")]
+#[update(name = "add_archive_wasm", guard = "owner_update_guard")]
 #[candid_method(update, rename = "add_archive_wasm")]
 pub fn add_archive_wasm(wasm: Vec<u8>) -> Result<(), String> {
-    DodService::get_current_service()
+    let audit = AuditCall::begin("add_archive_wasm");
+    let res = DodService::get_current_service()
         .and_then(|mut service| {
             service.add_archive_wasm(wasm);
             Some(())
         })
-        .ok_or_else(|| "No service found".to_string())
+        .ok_or_else(|| "No service found".to_string());
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "add_index_wasm", guard = "owner_guard")]
+#[update(name = "add_index_wasm", guard = "owner_update_guard")]
 #[candid_method(update, rename = "add_index_wasm")]
 pub fn add_index_wasm(wasm: Vec<u8>) -> Result<(), String> {
-    DodService::get_current_service()
+    let audit = AuditCall::begin("add_index_wasm");
+    let res = DodService::get_current_service()
         .and_then(|mut service| {
             service.add_index_wasm(wasm);
             Some(())
         })
-        .ok_or_else(|| "No service found".to_string())
+        .ok_or_else(|| "No service found".to_string());
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "add_ledger_wasm", guard = "owner_guard")]
+#[update(name = "add_ledger_wasm", guard = "owner_update_guard")]
 #[candid_method(update, rename = "add_ledger_wasm")]
 pub fn add_ledger_wasm(wasm: Vec<u8>) -> Result<(), String> {
-    DodService::get_current_service()
+    let audit = AuditCall::begin("add_ledger_wasm");
+    let res = DodService::get_current_service()
         .and_then(|mut service| {
             service.add_ledger_wasm(wasm);
             Some(())
         })
-        .ok_or_else(|| "No service found".to_string())
+        .ok_or_else(|| "No service found".to_string());
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "set_dod_canisters", guard = "owner_guard")]
+#[update(name = "set_dod_canisters", guard = "owner_update_guard")]
 #[candid_method(update, rename = "set_dod_canisters")]
-pub fn set_dod_canisters(canisters: DodCanisters) {
-    DodService::set_token_canister(canisters.ledger);
-    DodService::set_dod_canisters(canisters);
+pub async fn set_dod_canisters(canisters: DodCanisters) -> Result<LedgerMetadata, String> {
+    let audit = AuditCall::begin("set_dod_canisters");
+    let res = DodService::configure_dod_canisters(canisters).await;
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "refresh_ledger_metadata", guard = "owner_update_guard")]
+#[candid_method(update, rename = "refresh_ledger_metadata")]
+pub async fn refresh_ledger_metadata() -> Result<LedgerMetadata, String> {
+    let audit = AuditCall::begin("refresh_ledger_metadata");
+    let res = DodService::refresh_ledger_metadata().await;
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_ledger_metadata")]
+#[candid_method(query, rename = "get_ledger_metadata")]
+pub fn get_ledger_metadata() -> LedgerMetadata {
+    DodService::get_ledger_metadata()
 }
 
 #[cfg(not(feature = "no_candid"))]
@@ -133,6 +186,8 @@ pub fn get_dod_canister() -> Result<Principal, String> {
     DodService::get_token_canister()
 }
 
+/// The whole staged wasm in one reply, which fails once the wasm outgrows a reply,
+/// `get_ledger_wasm_info` and `get_ledger_wasm_chunk` fetch it in chunks.
 #[cfg(not(feature = "no_candid"))]
 #[query(name = "get_ledger_wasm", guard = "owner_guard")]
 #[candid_method(query, rename = "get_ledger_wasm")]
@@ -141,36 +196,65 @@ pub fn get_ledger_wasm() -> Option<Vec<u8>> {
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "deploy_canisters", guard = "owner_guard")]
+#[query(name = "get_ledger_wasm_info", guard = "owner_guard")]
+#[candid_method(query, rename = "get_ledger_wasm_info")]
+pub fn get_ledger_wasm_info() -> Option<WasmInfo> {
+    DodService::get_ledger_wasm_info()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_ledger_wasm_chunk", guard = "owner_guard")]
+#[candid_method(query, rename = "get_ledger_wasm_chunk")]
+pub fn get_ledger_wasm_chunk(sha256: Vec<u8>, index: u64) -> Result<Vec<u8>, String> {
+    DodService::get_ledger_wasm_chunk(sha256, index)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "deploy_canisters", guard = "owner_update_guard")]
 #[candid_method(update, rename = "deploy_canisters")]
 pub async fn deploy_canisters() -> Result<Principal, String> {
-    if let Some(service) = DodService::get_current_service() {
+    let audit = AuditCall::begin("deploy_canisters");
+    let res = if let Some(service) = DodService::get_current_service() {
         service.deploy_dod_ledger().await
     } else {
         Err("No service found".to_string())
-    }
+    };
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "preview_deploy", guard = "owner_guard")]
+#[candid_method(query, rename = "preview_deploy")]
+pub fn preview_deploy() -> Result<DeployPreview, String> {
+    DodService::get_current_service()
+        .map(|service| service.preview_deploy())
+        .ok_or_else(|| "No service found".to_string())
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "reset_ledgers", guard = "owner_guard")]
+#[update(name = "reset_ledgers", guard = "owner_update_guard")]
 #[candid_method(update, rename = "reset_ledgers")]
 pub async fn reset_ledgers() -> Result<(), String> {
-    if let Some(service) = DodService::get_current_service() {
+    let audit = AuditCall::begin("reset_ledgers");
+    let res = if let Some(service) = DodService::get_current_service() {
         service.reset_ledgers().await
     } else {
         Err("No service found".to_string())
-    }
+    };
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "upgrade_ledger", guard = "owner_guard")]
+#[update(name = "upgrade_ledger", guard = "owner_update_guard")]
 #[candid_method(update, rename = "upgrade_ledger")]
 pub async fn upgrade_ledger() -> Result<(), String> {
-    if let Some(service) = DodService::get_current_service() {
+    let audit = AuditCall::begin("upgrade_ledger");
+    let res = if let Some(service) = DodService::get_current_service() {
         service.upgrade_ledger().await
     } else {
         Err("No service found".to_string())
-    }
+    };
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
@@ -181,10 +265,28 @@ pub async fn get_deployed_canisters() -> Option<DodCanisters> {
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "set_halving_settings", guard = "owner_guard")]
+#[update(name = "set_block_subscriber", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_block_subscriber")]
+pub fn set_block_subscriber(subscriber: Option<Principal>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_block_subscriber");
+    let res = DodService::set_block_subscriber(subscriber);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_subscriber", guard = "owner_guard")]
+#[candid_method(query, rename = "get_block_subscriber")]
+pub fn get_block_subscriber() -> Option<Principal> {
+    DodService::get_block_subscriber()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_halving_settings", guard = "owner_update_guard")]
 #[candid_method(update, rename = "set_halving_settings")]
 pub fn set_halving_settings(settings: HalvingSettings) -> Result<(), String> {
-    DodService::set_halving_settings(settings)
+    let audit = AuditCall::begin("set_halving_settings");
+    let res = DodService::set_halving_settings(settings);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
@@ -195,261 +297,1794 @@ pub fn get_halving_settings() -> Option<HalvingSettings> {
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "register", guard = "anon_guard")]
+#[update(name = "register", guard = "anon_update_guard")]
 #[candid_method(update, rename = "register")]
 pub fn register(address: String, ecdsa_pubkey: String) -> Result<MinerInfo, String> {
     let pubkey = hex::decode(ecdsa_pubkey).map_err(|_| "Can not decode ecdsa pubkey")?;
     let miner = DodService::register_miner(caller(), address, pubkey)?;
-    DodService::register_user(caller())
-        .map(|_| miner)
-        .map_err(|e| e)
+    // stakers are created on their first deposit, unless configured as before
+    if DodService::get_register_miners_as_stakers() {
+        DodService::register_user(caller())?;
+    }
+    Ok(miner)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "miner_add_operator", guard = "anon_update_guard")]
+#[candid_method(update, rename = "miner_add_operator")]
+pub fn miner_add_operator(operator: Principal) -> Result<(), String> {
+    DodService::miner_add_operator(caller(), operator)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "miner_remove_operator", guard = "anon_update_guard")]
+#[candid_method(update, rename = "miner_remove_operator")]
+pub fn miner_remove_operator(operator: Principal) -> Result<(), String> {
+    DodService::miner_remove_operator(caller(), operator)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_miner_operators")]
+#[candid_method(query, rename = "get_miner_operators")]
+pub fn get_miner_operators(owner: Principal) -> Vec<Principal> {
+    DodService::get_miner_operators(owner)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_btc_network", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_btc_network")]
+pub fn set_btc_network(network: Option<BtcNetwork>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_btc_network");
+    let res = DodService::set_btc_network(network);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_btc_network")]
+#[candid_method(query, rename = "get_btc_network")]
+pub fn get_btc_network() -> Option<BtcNetwork> {
+    DodService::get_btc_network()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "validate_btc_address")]
+#[candid_method(query, rename = "validate_btc_address")]
+pub fn validate_btc_address(address: String) -> Result<(), AddressValidationError> {
+    DodService::validate_btc_address(address)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "import_blocks", guard = "owner_update_guard")]
+#[candid_method(update, rename = "import_blocks")]
+pub fn import_blocks(blocks: Vec<BlockData>) -> Result<BlockImport, String> {
+    let audit = AuditCall::begin("import_blocks");
+    let res = DodService::import_blocks(blocks);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "import_sigs", guard = "owner_update_guard")]
+#[candid_method(update, rename = "import_sigs")]
+pub fn import_sigs(sigs: Vec<(Height, BlockSigs)>) -> Result<BlockImport, String> {
+    let audit = AuditCall::begin("import_sigs");
+    let res = DodService::import_sigs(sigs);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "import_miners", guard = "owner_update_guard")]
+#[candid_method(update, rename = "import_miners")]
+pub fn import_miners(miners: Vec<MinerInfo>) -> Result<BlockImport, String> {
+    let audit = AuditCall::begin("import_miners");
+    let res = DodService::import_miners(miners);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "seal_import", guard = "owner_update_guard")]
+#[candid_method(update, rename = "seal_import")]
+pub async fn seal_import() -> Result<BlockData, String> {
+    let audit = AuditCall::begin("seal_import");
+    let res = DodService::seal_import().await;
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_ledger_queue", guard = "owner_guard")]
+#[candid_method(query, rename = "get_ledger_queue")]
+pub fn get_ledger_queue() -> Vec<(u64, LedgerOp)> {
+    DodService::get_ledger_queue()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_ledger_dead_letters", guard = "owner_guard")]
+#[candid_method(query, rename = "get_ledger_dead_letters")]
+pub fn get_ledger_dead_letters() -> Vec<(u64, LedgerDeadLetter)> {
+    DodService::get_ledger_dead_letters()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "requeue_ledger_op", guard = "owner_update_guard")]
+#[candid_method(update, rename = "requeue_ledger_op")]
+pub fn requeue_ledger_op(seq: u64) -> Result<LedgerOp, String> {
+    let audit = AuditCall::begin("requeue_ledger_op");
+    let res = DodService::requeue_ledger_op(seq);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_import", guard = "owner_guard")]
+#[candid_method(query, rename = "get_block_import")]
+pub fn get_block_import() -> Option<BlockImport> {
+    DodService::get_block_import()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_block_archive", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_block_archive")]
+pub fn set_block_archive(canister: Principal, retain_blocks: u64) -> Result<BlockArchive, String> {
+    let audit = AuditCall::begin("set_block_archive");
+    let res = DodService::set_block_archive(canister, retain_blocks);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_archive", guard = "owner_guard")]
+#[candid_method(query, rename = "get_block_archive")]
+pub fn get_block_archive() -> Option<BlockArchive> {
+    DodService::get_block_archive()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "archive_blocks", guard = "owner_update_guard")]
+#[candid_method(update, rename = "archive_blocks")]
+pub async fn archive_blocks() -> Result<u64, String> {
+    let audit = AuditCall::begin("archive_blocks");
+    let res = DodService::archive_blocks().await;
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "init_genesis", guard = "owner_update_guard")]
+#[candid_method(update, rename = "init_genesis")]
+pub fn init_genesis(params: GenesisParams) -> Result<BlockData, String> {
+    let audit = AuditCall::begin("init_genesis");
+    let res = DodService::init_genesis(params);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_genesis_info")]
+#[candid_method(query, rename = "get_genesis_info")]
+pub fn get_genesis_info() -> Option<GenesisInfo> {
+    DodService::get_genesis_info()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "start_generating_blocks", guard = "owner_guard")]
+#[update(name = "start_generating_blocks", guard = "owner_update_guard")]
 #[candid_method(update, rename = "start_generating_blocks")]
 pub async fn start_generating_blocks() -> Result<(), String> {
-    DodService::start_generate_blocks().await
+    let audit = AuditCall::begin("start_generating_blocks");
+    let res = DodService::start_generate_blocks().await;
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "clean_up", guard = "owner_guard")]
+#[update(name = "clean_up", guard = "owner_update_guard")]
 #[candid_method(update, rename = "clean_up")]
 pub fn clean_up() {
-    DodService::clean_up()
+    let audit = AuditCall::begin("clean_up");
+    DodService::clean_up();
+    audit.done();
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_last_block")]
-#[candid_method(query, rename = "get_last_block")]
-pub fn get_last_block() -> Option<(u64, BlockData)> {
-    DodService::get_last_block()
+#[update(name = "set_cycles_alert_threshold", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_cycles_alert_threshold")]
+pub fn set_cycles_alert_threshold(threshold: Option<u128>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_cycles_alert_threshold");
+    let res = DodService::set_cycles_alert_threshold(threshold);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_blocks_range")]
-#[candid_method(query, rename = "get_blocks_range")]
-pub fn get_blocks_range(from: Height, to: Height) -> Vec<BlockData> {
-    DodService::get_blocks_range(from, to)
+#[query(name = "get_cycles_health", guard = "owner_guard")]
+#[candid_method(query, rename = "get_cycles_health")]
+pub fn get_cycles_health() -> CyclesHealth {
+    DodService::get_cycles_health()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "miner_submit_hash")]
-#[candid_method(update, rename = "miner_submit_hash")]
-pub fn miner_submit_hash(payload: MinerSubmitPayload) -> Result<MinerSubmitResponse, String> {
-    let caller = caller();
-    DodService::miner_submit_hashes(
-        caller,
-        payload.btc_address,
-        payload.signed_commit_psbt,
-        payload.signed_reveal_psbt,
-        payload.cycles_price,
-    )
+#[update(name = "set_top_up_settings", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_top_up_settings")]
+pub fn set_top_up_settings(settings: Option<TopUpSettings>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_top_up_settings");
+    let res = DodService::set_top_up_settings(caller(), settings);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "load_sigs_by_height")]
-#[candid_method(query, rename = "load_sigs_by_height")]
-pub fn load_sigs_by_height(height: Height) -> Option<BlockSigs> {
-    DodService::load_sigs_by_height(height)
+#[query(name = "get_top_up_settings", guard = "owner_guard")]
+#[candid_method(query, rename = "get_top_up_settings")]
+pub fn get_top_up_settings() -> Option<TopUpSettings> {
+    DodService::get_top_up_settings()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_history_miner_candidates")]
-#[candid_method(query, rename = "get_history_miner_candidates")]
-pub fn get_history_miner_candidates(height: Height) -> Result<Vec<MinerCandidate>, String> {
-    let last_block_height = DodService::get_last_block()
-        .ok_or_else(|| "Can not get last block".to_string())?
-        .0;
+#[update(name = "estimate_top_up", guard = "owner_update_guard")]
+#[candid_method(update, rename = "estimate_top_up")]
+pub async fn estimate_top_up() -> Result<TopUpEstimate, String> {
+    let audit = AuditCall::begin("estimate_top_up");
+    let res = DodService::estimate_top_up().await;
+    audit.finish(res)
+}
 
-    if height >= last_block_height {
-        Err("Only before last block data is available".to_string())
-    } else {
-        Ok(DodService::get_block_candidates(height))
-    }
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_failed_block_policy", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_failed_block_policy")]
+pub fn set_failed_block_policy(policy: FailedBlockPolicy) -> Result<(), String> {
+    let audit = AuditCall::begin("set_failed_block_policy");
+    let res = DodService::set_failed_block_policy(policy);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_mining_history_for_miners", guard = "anon_guard")]
-#[candid_method(query, rename = "get_mining_history_for_miners")]
-pub fn get_mining_history_for_miners(
-    btc_address: String,
-    from: Height,
-    to: Height,
-) -> Vec<MinerBlockData> {
-    DodService::get_mining_history_for_miners(btc_address, (from, to))
+#[update(name = "set_bid_currency", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_bid_currency")]
+pub fn set_bid_currency(currency: BidCurrency) -> Result<(), String> {
+    let audit = AuditCall::begin("set_bid_currency");
+    let res = DodService::set_bid_currency(currency);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "user_register", guard = "anon_guard")]
-#[candid_method(update, rename = "user_register")]
-pub fn user_register() -> Result<(), String> {
-    DodService::register_user(caller())
+#[query(name = "get_bid_currency")]
+#[candid_method(query, rename = "get_bid_currency")]
+pub fn get_bid_currency() -> BidCurrency {
+    DodService::get_bid_currency()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "deposit_cycles_from_icp", guard = "anon_guard")]
-#[candid_method(update, rename = "deposit_cycles_from_icp")]
-pub async fn deposit_cycles_from_icp(amount: u64) -> Result<(), String> {
-    DodService::deposit_cycles_from_icp(caller(), amount).await;
-    Ok(())
+#[update(name = "set_difficulty_tiers", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_difficulty_tiers")]
+pub fn set_difficulty_tiers(tiers: Option<DifficultyTiers>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_difficulty_tiers");
+    let res = DodService::set_difficulty_tiers(tiers);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "user_set_burning_rate_combine", guard = "anon_guard")]
-#[candid_method(update, rename = "user_set_burning_rate_combine")]
-pub fn user_set_burning_rate_combine(br: u128, height: Height, amount: u128) -> Result<(), String> {
-    let caller = caller();
-    DodService::user_set_burnrate(caller, br)?;
-    DodService::user_put_burnrate_orders(caller, height, amount)
+#[query(name = "get_difficulty_tiers")]
+#[candid_method(query, rename = "get_difficulty_tiers")]
+pub fn get_difficulty_tiers() -> Option<DifficultyTiers> {
+    DodService::get_difficulty_tiers()
 }
 
-// pub fn user_instant_bid(br: u128, height: Height, amount: u128) -> Result<(), String> {
-//     let caller = caller();
-//     DodService::user_put_burnrate_orders(caller, height, amount)
-// }
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_lock_in_bonus", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_lock_in_bonus")]
+pub fn set_lock_in_bonus(bonus: Option<LockInBonus>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_lock_in_bonus");
+    let res = DodService::set_lock_in_bonus(bonus);
+    audit.finish(res)
+}
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "user_set_burning_rate", guard = "anon_guard")]
-#[candid_method(update, rename = "user_set_burning_rate")]
-pub fn user_set_burning_rate(br: u128) -> Result<(), String> {
-    DodService::user_set_burnrate(caller(), br)
+#[query(name = "get_lock_in_bonus")]
+#[candid_method(query, rename = "get_lock_in_bonus")]
+pub fn get_lock_in_bonus() -> Option<LockInBonus> {
+    DodService::get_lock_in_bonus()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "set_difficulty_adjust_epoch", guard = "owner_guard")]
-#[candid_method(update, rename = "set_difficulty_adjust_epoch")]
-pub fn set_difficulty_adjust_epoch(difficulty_adjust_epoch: u64) -> Result<(), String> {
-    DodService::set_difficulty_adjust_epoch(difficulty_adjust_epoch)
+#[query(name = "get_failed_block_policy")]
+#[candid_method(query, rename = "get_failed_block_policy")]
+pub fn get_failed_block_policy() -> FailedBlockPolicy {
+    DodService::get_failed_block_policy()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_user_orders_by_blocks", guard = "anon_guard")]
-#[candid_method(query, rename = "get_user_orders_by_blocks")]
-pub fn get_user_orders_by_blocks(from: Height, to: Height) -> UserBlockOrderRes {
-    let (data, total) =
-        DodService::get_user_orders_by_blocks(caller(), from, to, OrderStatus::Filled);
-    UserBlockOrderRes {
-        total,
-        from,
-        to,
-        data,
-    }
+#[update(name = "set_reward_policy", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_reward_policy")]
+pub fn set_reward_policy(policy: Option<RewardPolicy>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_reward_policy");
+    let res = DodService::set_reward_policy(policy);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "inner_transfer_cycles", guard = "anon_guard")]
-#[candid_method(update, rename = "inner_transfer_cycles")]
-pub fn inner_transfer_cycles(to: Vec<(Principal, u128)>) -> Result<(), String> {
-    DodService::inner_transfer_cycles(caller(), to)
+#[query(name = "get_reward_policy")]
+#[candid_method(query, rename = "get_reward_policy")]
+pub fn get_reward_policy() -> Option<RewardPolicy> {
+    DodService::get_reward_policy()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_next_difficulty_adjust_height")]
-#[candid_method(query, rename = "get_next_difficulty_adjust_height")]
-pub fn get_next_difficulty_adjust_height() -> Result<Option<u64>, String> {
-    DodService::get_consider_increase()
+#[update(name = "set_fee_settings", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_fee_settings")]
+pub fn set_fee_settings(settings: FeeSettings) -> Result<(), String> {
+    let audit = AuditCall::begin("set_fee_settings");
+    let res = DodService::set_fee_settings(settings);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_user_burning_range", guard = "anon_guard")]
-#[candid_method(query, rename = "get_user_burning_range")]
-pub fn get_user_burning_range() -> Option<NewBlockOrderValue> {
-    DodService::get_user_range(caller())
+#[query(name = "get_fee_settings")]
+#[candid_method(query, rename = "get_fee_settings")]
+pub fn get_fee_settings() -> FeeSettings {
+    DodService::get_fee_settings()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "user_put_orders", guard = "anon_guard")]
-#[candid_method(update, rename = "user_put_orders")]
-pub fn user_put_orders(height: Height, amount: u128) -> Result<(), String> {
-    DodService::user_put_burnrate_orders(caller(), height, amount)
+#[update(name = "set_reward_dust_policy", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_reward_dust_policy")]
+pub fn set_reward_dust_policy(policy: RewardDustPolicy) -> Result<(), String> {
+    let audit = AuditCall::begin("set_reward_dust_policy");
+    let res = DodService::set_reward_dust_policy(policy);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_user_detail", guard = "anon_guard")]
-#[candid_method(query, rename = "get_user_detail")]
-pub fn get_user_detail() -> Option<UserDetail> {
-    DodService::get_user_detail(caller())
+#[query(name = "get_reward_dust_policy")]
+#[candid_method(query, rename = "get_reward_dust_policy")]
+pub fn get_reward_dust_policy() -> RewardDustPolicy {
+    DodService::get_reward_dust_policy()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_user_detail_indexer")]
-#[candid_method(query, rename = "get_user_detail_indexer")]
-pub fn get_user_detail_indexer(principal: Principal) -> Option<UserDetail> {
-    DodService::get_user_detail(principal)
+#[update(name = "set_submission_grace", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_submission_grace")]
+pub fn set_submission_grace(grace: u64) -> Result<(), String> {
+    let audit = AuditCall::begin("set_submission_grace");
+    let res = DodService::set_submission_grace(grace);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_user_subaccount", guard = "anon_guard")]
-#[candid_method(query, rename = "get_user_subaccount")]
-pub fn get_user_subaccount(id: Principal) -> Subaccount {
-    DodService::user_subaccount(id)
+#[query(name = "get_submission_grace")]
+#[candid_method(query, rename = "get_submission_grace")]
+pub fn get_submission_grace() -> u64 {
+    DodService::get_submission_grace()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_canister_cycles", guard = "owner_guard")]
-#[candid_method(query, rename = "get_canister_cycles")]
-pub fn get_canister_cycles() -> u128 {
-    ic_cdk::api::canister_balance128()
+#[update(name = "set_claim_dedup_window", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_claim_dedup_window")]
+pub fn set_claim_dedup_window(window: u64) -> Result<(), String> {
+    let audit = AuditCall::begin("set_claim_dedup_window");
+    let res = DodService::set_claim_dedup_window(window);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "claim_dod_to_wallet", guard = "anon_guard")]
-#[candid_method(update, rename = "claim_dod_to_wallet")]
-pub async fn claim_dod_to_wallet(
-    to: Option<String>,
-    claim_amount: Option<u64>,
-) -> Result<String, String> {
-    let mut _to = None;
-    if to.is_some() {
-        _to = Some(Account::from_str(to.unwrap().as_str()).unwrap());
-    }
+#[query(name = "get_claim_dedup_window")]
+#[candid_method(query, rename = "get_claim_dedup_window")]
+pub fn get_claim_dedup_window() -> u64 {
+    DodService::get_claim_dedup_window()
+}
 
-    match DodService::claim_reward(caller(), _to, claim_amount).await {
-        Ok(res) => Ok(res.to_string()),
-        Err(e) => Err(e.to_string()),
-    }
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_register_miners_as_stakers", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_register_miners_as_stakers")]
+pub fn set_register_miners_as_stakers(enabled: bool) -> Result<(), String> {
+    let audit = AuditCall::begin("set_register_miners_as_stakers");
+    let res = DodService::set_register_miners_as_stakers(enabled);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "is_miner", guard = "anon_guard")]
-#[candid_method(query, rename = "is_miner")]
-pub fn is_miner(btc_address: String) -> Option<MinerInfo> {
-    DodService::check_miner_if_existed(caller(), btc_address)
+#[query(name = "get_register_miners_as_stakers", guard = "owner_guard")]
+#[candid_method(query, rename = "get_register_miners_as_stakers")]
+pub fn get_register_miners_as_stakers() -> bool {
+    DodService::get_register_miners_as_stakers()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "am_i_candidate", guard = "anon_guard")]
-#[candid_method(query, rename = "am_i_candidate")]
-pub fn am_i_candidate(height: Height) -> bool {
-    DodService::get_miner_by_principal(caller())
-        .and_then(|miner| DodService::check_if_in_candidate(miner.btc_address, height))
-        .is_some()
+#[update(name = "set_buyback_settings", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_buyback_settings")]
+pub fn set_buyback_settings(settings: Option<BuybackSettings>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_buyback_settings");
+    let res = DodService::set_buyback_settings(caller(), settings);
+    audit.finish(res)
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_orders_by_block_v2", guard = "owner_guard")]
-#[candid_method(query, rename = "get_orders_by_block_v2")]
-pub fn get_orders_by_block_v2(from: u64, to: u64) -> Vec<BlockDataFull> {
-    DodService::get_orders_by_block_v2(from, to)
+#[query(name = "get_buyback_settings")]
+#[candid_method(query, rename = "get_buyback_settings")]
+pub fn get_buyback_settings() -> Option<BuybackSettings> {
+    DodService::get_buyback_settings()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[query(name = "get_block_total_cycles", guard = "anon_guard")]
-#[candid_method(query, rename = "get_block_total_cycles")]
-pub fn get_block_total_cycles(height: Height) -> u128 {
-    DodService::get_block_total_cycles(height, false)
+#[query(name = "get_emission_status")]
+#[candid_method(query, rename = "get_emission_status")]
+pub fn get_emission_status() -> Result<EmissionStatus, String> {
+    DodService::get_emission_status()
 }
 
 #[cfg(not(feature = "no_candid"))]
-#[update(name = "blackhole_ledger", guard = "owner_guard")]
-#[candid_method(update, rename = "blackhole_ledger")]
-pub async fn blackhole_ledger() -> Result<(), String> {
-    if let Some(service) = DodService::get_current_service() {
-        service.blockhole_ledger().await
-    } else {
-        Err("No service found".to_string())
-    }
+#[query(name = "get_build_info")]
+#[candid_method(query, rename = "get_build_info")]
+pub fn get_build_info() -> BuildInfo {
+    DodService::get_build_info(VERSION, GIT_COMMIT_HASH, crate::__export_service().as_str())
+}
+
+/// The candid interface of this canister, for tools that read it from a running canister.
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "__get_candid_interface_tmp_hack")]
+#[candid_method(query, rename = "__get_candid_interface_tmp_hack")]
+pub fn __get_candid_interface_tmp_hack() -> String {
+    crate::__export_service()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "estimate_work")]
+#[candid_method(query, rename = "estimate_work")]
+pub fn estimate_work(current_difficulty: Bitwork, hashrate: u64) -> Result<WorkEstimate, String> {
+    DodService::estimate_work(current_difficulty, hashrate)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_efficiency_stats")]
+#[candid_method(query, rename = "get_efficiency_stats")]
+pub fn get_efficiency_stats(window_blocks: u64) -> EfficiencyStats {
+    DodService::get_efficiency_stats(window_blocks)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_skipped_generation_ticks")]
+#[candid_method(query, rename = "get_skipped_generation_ticks")]
+pub fn get_skipped_generation_ticks() -> u64 {
+    DodService::get_skipped_generation_ticks()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_last_block")]
+#[candid_method(query, rename = "get_last_block")]
+pub fn get_last_block() -> Option<(u64, BlockData)> {
+    DodService::get_last_block()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "bitwork_check")]
+#[candid_method(query, rename = "bitwork_check")]
+pub fn bitwork_check(
+    current_hash: String,
+    target_hash: String,
+    bitwork: Bitwork,
+    reverse: bool,
+) -> Result<bool, String> {
+    DodService::bitwork_check(current_hash, target_hash, bitwork, reverse)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "bitwork_for_height")]
+#[candid_method(query, rename = "bitwork_for_height")]
+pub fn bitwork_for_height(height: Height) -> Result<Bitwork, String> {
+    DodService::bitwork_for_height(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_entropy")]
+#[candid_method(query, rename = "get_block_entropy")]
+pub fn get_block_entropy(height: Height) -> Option<BlockEntropy> {
+    DodService::get_block_entropy(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_schedule")]
+#[candid_method(query, rename = "get_block_schedule")]
+pub fn get_block_schedule(n: u64) -> Result<Vec<ScheduledBlock>, String> {
+    DodService::get_block_schedule(n)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_era")]
+#[candid_method(query, rename = "get_block_era")]
+pub fn get_block_era() -> Option<BlockEra> {
+    DodService::get_block_era()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_blocks_filtered")]
+#[candid_method(query, rename = "get_blocks_filtered")]
+pub fn get_blocks_filtered(filter: BlockFilter, page: u64, limit: u64) -> Vec<BlockData> {
+    if let Some(from) = filter.from {
+        DodService::check_block_kept(from).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    }
+    DodService::get_blocks_filtered(filter, page, limit)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "http_request")]
+#[candid_method(query, rename = "http_request")]
+pub fn http_request(req: HttpRequest) -> HttpResponse {
+    DodService::http_request(req)
+}
+
+/// Traps on an inverted range or archived blocks, `get_blocks_range_v2` reads archived blocks
+/// and returns the error instead.
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_blocks_range")]
+#[candid_method(query, rename = "get_blocks_range")]
+pub fn get_blocks_range(from: Height, to: Height) -> Vec<BlockData> {
+    let range = BlockRange::inclusive(from, to).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    DodService::check_block_kept(from).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    DodService::get_blocks_range(range)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_blocks_range_v2", composite = true)]
+#[candid_method(composite_query, rename = "get_blocks_range_v2")]
+pub async fn get_blocks_range_v2(from: Height, to: Height) -> Result<Vec<BlockData>, String> {
+    DodService::get_archived_blocks_range(BlockRange::inclusive(from, to)?).await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_by_height", composite = true)]
+#[candid_method(composite_query, rename = "get_block_by_height")]
+pub async fn get_block_by_height(height: Height) -> Result<Option<BlockData>, String> {
+    DodService::get_archived_block_by_height(height).await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "miner_submit_hash", guard = "maintenance_guard")]
+#[candid_method(update, rename = "miner_submit_hash")]
+pub fn miner_submit_hash(payload: MinerSubmitPayload) -> Result<MinerSubmitResponse, String> {
+    let caller = caller();
+    DodService::miner_submit_hashes(
+        caller,
+        payload.btc_address,
+        payload.signed_commit_psbt,
+        payload.signed_reveal_psbt,
+        payload.cycles_price,
+        payload.dod_bid,
+    )
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "load_sigs_by_height")]
+#[candid_method(query, rename = "load_sigs_by_height")]
+pub fn load_sigs_by_height(height: Height) -> Option<BlockSigs> {
+    DodService::load_sigs_by_height(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_winner_envelope")]
+#[candid_method(query, rename = "get_winner_envelope")]
+pub fn get_winner_envelope(height: Height) -> Option<WinnerEnvelope> {
+    DodService::get_winner_envelope(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_winner_payment_proof")]
+#[candid_method(query, rename = "get_winner_payment_proof")]
+pub fn get_winner_payment_proof(height: Height) -> Option<WinnerPaymentProof> {
+    DodService::get_winner_payment_proof(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_treasury_split", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_treasury_split")]
+pub fn set_treasury_split(split: Option<TreasurySplit>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_treasury_split");
+    let res = DodService::set_treasury_split(split);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_treasury_split")]
+#[candid_method(query, rename = "get_treasury_split")]
+pub fn get_treasury_split() -> Option<TreasurySplit> {
+    DodService::get_treasury_split()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_treasury_buckets")]
+#[candid_method(query, rename = "get_treasury_buckets")]
+pub fn get_treasury_buckets() -> Result<Vec<TreasuryBucketInfo>, String> {
+    DodService::get_treasury_buckets()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "transfer_from_treasury_bucket", guard = "owner_update_guard")]
+#[candid_method(update, rename = "transfer_from_treasury_bucket")]
+pub async fn transfer_from_treasury_bucket(
+    bucket: TreasuryBucket,
+    to: String,
+    amount: u64,
+) -> Result<String, String> {
+    let audit = AuditCall::begin("transfer_from_treasury_bucket");
+    let res = match Account::from_str(to.as_str()) {
+        Ok(account) => DodService::transfer_from_treasury_bucket(bucket, account, amount)
+            .await
+            .map(|index| index.to_string()),
+        Err(e) => Err(format!("Invalid account {}: {:?}", to, e)),
+    };
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_json")]
+#[candid_method(query, rename = "get_block_json")]
+pub fn get_block_json(height: Height) -> Result<String, String> {
+    DodService::get_block_json(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_burn_receipts")]
+#[candid_method(query, rename = "get_burn_receipts")]
+pub fn get_burn_receipts(from: Height, to: Height) -> Result<Vec<BurnReceipt>, String> {
+    Ok(DodService::get_burn_receipts(BlockRange::inclusive(
+        from, to,
+    )?))
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_ledger_links")]
+#[candid_method(query, rename = "get_block_ledger_links")]
+pub fn get_block_ledger_links(height: Height) -> Option<BlockLedgerLinks> {
+    DodService::get_block_ledger_links(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_bundle")]
+#[candid_method(query, rename = "get_block_bundle")]
+pub fn get_block_bundle(height: Height) -> Option<BlockBundle> {
+    DodService::check_block_kept(height).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    DodService::get_block_bundle(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_bundles")]
+#[candid_method(query, rename = "get_block_bundles")]
+pub fn get_block_bundles(heights: Vec<Height>) -> Result<Vec<BlockBundle>, String> {
+    DodService::get_block_bundles(heights)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_miners")]
+#[candid_method(query, rename = "get_miners")]
+pub fn get_miners(
+    page: u64,
+    limit: u64,
+    status: Option<MinerStatus>,
+    include_pubkey: Option<bool>,
+) -> MinersPage {
+    DodService::get_miners(page, limit, status, include_pubkey.unwrap_or(false))
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_my_winning_sigs", guard = "anon_guard")]
+#[candid_method(query, rename = "get_my_winning_sigs")]
+pub fn get_my_winning_sigs(page: u64, limit: u64) -> Result<Vec<(Height, BlockSigs)>, String> {
+    DodService::get_my_winning_sigs(caller(), page, limit)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_sigs_retention", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_sigs_retention")]
+pub fn set_sigs_retention(retention: Option<u64>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_sigs_retention");
+    let res = DodService::set_sigs_retention(retention);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_sigs_retention")]
+#[candid_method(query, rename = "get_sigs_retention")]
+pub fn get_sigs_retention() -> Option<u64> {
+    DodService::get_sigs_retention()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "prune_sigs", guard = "owner_update_guard")]
+#[candid_method(update, rename = "prune_sigs")]
+pub fn prune_sigs(limit: u64) -> u64 {
+    let audit = AuditCall::begin("prune_sigs");
+    let removed = DodService::prune_sigs(limit);
+    audit.done();
+    removed
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_history_miner_candidates")]
+#[candid_method(query, rename = "get_history_miner_candidates")]
+pub fn get_history_miner_candidates(height: Height) -> Result<Vec<MinerCandidate>, String> {
+    let last_block_height = DodService::get_last_block()
+        .ok_or_else(|| "Can not get last block".to_string())?
+        .0;
+
+    if height >= last_block_height {
+        Err("Only before last block data is available".to_string())
+    } else {
+        Ok(DodService::get_block_candidates(height))
+    }
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_mining_history_for_miners", guard = "anon_guard")]
+#[candid_method(query, rename = "get_mining_history_for_miners")]
+pub fn get_mining_history_for_miners(
+    btc_address: String,
+    from: Height,
+    to: Height,
+) -> Vec<MinerBlockData> {
+    // `to` is excluded, as it always was for this endpoint
+    let range = BlockRange::new(from, to).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    DodService::check_block_kept(from).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    DodService::get_mining_history_for_miners(btc_address, range)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "user_register", guard = "anon_update_guard")]
+#[candid_method(update, rename = "user_register")]
+pub fn user_register() -> Result<(), String> {
+    DodService::register_user(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "register_with_referral", guard = "anon_update_guard")]
+#[candid_method(update, rename = "register_with_referral")]
+pub fn register_with_referral(code: String) -> Result<ReferralInfo, String> {
+    DodService::register_with_referral(caller(), code)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "create_referral_code", guard = "anon_update_guard")]
+#[candid_method(update, rename = "create_referral_code")]
+pub fn create_referral_code() -> Result<String, String> {
+    DodService::create_referral_code(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_my_referrer_stats", guard = "anon_guard")]
+#[candid_method(query, rename = "get_my_referrer_stats")]
+pub fn get_my_referrer_stats() -> Option<ReferrerStats> {
+    DodService::get_referrer_stats(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_my_referral", guard = "anon_guard")]
+#[candid_method(query, rename = "get_my_referral")]
+pub fn get_my_referral() -> Option<ReferralInfo> {
+    DodService::get_referral(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_referral_settings", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_referral_settings")]
+pub fn set_referral_settings(settings: ReferralSettings) -> Result<(), String> {
+    let audit = AuditCall::begin("set_referral_settings");
+    let res = DodService::set_referral_settings(settings);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_referral_enabled", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_referral_enabled")]
+pub fn set_referral_enabled(enabled: bool) -> Result<(), String> {
+    let audit = AuditCall::begin("set_referral_enabled");
+    let res = DodService::set_referral_enabled(enabled);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_referral_settings")]
+#[candid_method(query, rename = "get_referral_settings")]
+pub fn get_referral_settings() -> Option<ReferralSettings> {
+    DodService::get_referral_settings()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "deposit_cycles_from_icp", guard = "anon_update_guard")]
+#[candid_method(update, rename = "deposit_cycles_from_icp")]
+pub async fn deposit_cycles_from_icp(amount: u64) -> Result<(), String> {
+    let deposit = DodService::deposit_cycles_from_icp(caller(), amount).await?;
+    match deposit.status {
+        DepositStatus::Completed => Ok(()),
+        _ => Err(deposit
+            .error
+            .unwrap_or_else(|| format!("Deposit {} is {:?}", deposit.id, deposit.status))),
+    }
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "deposit_attached_cycles", guard = "anon_update_guard")]
+#[candid_method(update, rename = "deposit_attached_cycles")]
+pub fn deposit_attached_cycles() -> Result<u128, String> {
+    DodService::deposit_attached_cycles(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "begin_deposit", guard = "anon_update_guard")]
+#[candid_method(update, rename = "begin_deposit")]
+pub async fn begin_deposit(amount: u64) -> Result<DepositRecord, String> {
+    DodService::deposit_cycles_from_icp(caller(), amount).await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "retry_deposit", guard = "anon_update_guard")]
+#[candid_method(update, rename = "retry_deposit")]
+pub async fn retry_deposit(deposit_id: u64) -> Result<DepositRecord, String> {
+    DodService::retry_deposit(caller(), deposit_id).await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_my_deposits", guard = "anon_guard")]
+#[candid_method(query, rename = "get_my_deposits")]
+pub fn get_my_deposits() -> Vec<DepositRecord> {
+    DodService::get_deposits(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "register_integrator", guard = "owner_update_guard")]
+#[candid_method(update, rename = "register_integrator")]
+pub fn register_integrator(integrator: Principal, name: String) -> Result<Integrator, String> {
+    let audit = AuditCall::begin("register_integrator");
+    let res = DodService::register_integrator(integrator, name);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "register_pool", guard = "owner_update_guard")]
+#[candid_method(update, rename = "register_pool")]
+pub fn register_pool(
+    pool: Principal,
+    name: String,
+    max_candidates_per_block: Option<u64>,
+) -> Result<MiningPool, String> {
+    let audit = AuditCall::begin("register_pool");
+    let res = DodService::register_pool(pool, name, max_candidates_per_block);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_pool")]
+#[candid_method(query, rename = "get_pool")]
+pub fn get_pool(pool: Principal) -> Option<MiningPool> {
+    DodService::get_pool(pool)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "pool_submit_candidates", guard = "maintenance_guard")]
+#[candid_method(update, rename = "pool_submit_candidates")]
+pub fn pool_submit_candidates(
+    candidates: Vec<PoolCandidate>,
+) -> Result<Vec<Result<MinerSubmitResponse, String>>, String> {
+    DodService::pool_submit_candidates(caller(), candidates)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "pool_claim_worker_rewards", guard = "anon_update_guard")]
+#[candid_method(update, rename = "pool_claim_worker_rewards")]
+pub fn pool_claim_worker_rewards(btc_address: String) -> Result<PoolWorkerClaim, String> {
+    DodService::pool_claim_worker_rewards(caller(), &btc_address)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_integrator")]
+#[candid_method(query, rename = "get_integrator")]
+pub fn get_integrator(integrator: Principal) -> Option<Integrator> {
+    DodService::get_integrator(integrator)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "integrator_deposit_cycles", guard = "anon_update_guard")]
+#[candid_method(update, rename = "integrator_deposit_cycles")]
+pub fn integrator_deposit_cycles(end_user: String) -> Result<u128, String> {
+    DodService::integrator_deposit_cycles(caller(), &end_user)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "integrator_put_orders", guard = "anon_update_guard")]
+#[candid_method(update, rename = "integrator_put_orders")]
+pub fn integrator_put_orders(
+    end_user: String,
+    height: Height,
+    amount: u128,
+    replace_existing: bool,
+    priority_fee: Option<u128>,
+) -> Result<(), String> {
+    DodService::integrator_put_orders(
+        caller(),
+        &end_user,
+        height,
+        amount,
+        replace_existing,
+        priority_fee,
+    )
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "integrator_claim_reward", guard = "anon_update_guard")]
+#[candid_method(update, rename = "integrator_claim_reward")]
+pub async fn integrator_claim_reward(
+    end_user: String,
+    to: Account,
+    amount: u64,
+) -> Result<Vec<ClaimLegResult>, String> {
+    DodService::integrator_claim_reward(caller(), &end_user, to, amount).await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "integrator_get_user_detail", guard = "anon_guard")]
+#[candid_method(query, rename = "integrator_get_user_detail")]
+pub fn integrator_get_user_detail(end_user: String) -> Result<Option<UserDetail>, String> {
+    DodService::integrator_get_user_detail(caller(), &end_user)
+}
+
+/// `replace_existing` is optional so that clients sending only the first arguments still
+/// decode, without it an existing range is not replaced.
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "user_set_burning_rate_combine", guard = "anon_update_guard")]
+#[candid_method(update, rename = "user_set_burning_rate_combine")]
+pub fn user_set_burning_rate_combine(
+    br: u128,
+    height: Height,
+    amount: u128,
+    replace_existing: Option<bool>,
+    priority_fee: Option<u128>,
+) -> Result<(), String> {
+    let caller = caller();
+    DodService::user_set_burnrate(caller, br)?;
+    DodService::user_put_burnrate_orders(
+        caller,
+        height,
+        amount,
+        replace_existing.unwrap_or(false),
+        priority_fee,
+    )
+    .map(|_| ())
+}
+
+// pub fn user_instant_bid(br: u128, height: Height, amount: u128) -> Result<(), String> {
+//     let caller = caller();
+//     DodService::user_put_burnrate_orders(caller, height, amount)
+// }
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "user_set_burning_rate", guard = "anon_update_guard")]
+#[candid_method(update, rename = "user_set_burning_rate")]
+pub fn user_set_burning_rate(br: u128) -> Result<(), String> {
+    DodService::user_set_burnrate(caller(), br)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_difficulty_adjust_epoch", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_difficulty_adjust_epoch")]
+pub fn set_difficulty_adjust_epoch(difficulty_adjust_epoch: u64) -> Result<(), String> {
+    let audit = AuditCall::begin("set_difficulty_adjust_epoch");
+    let res = DodService::set_difficulty_adjust_epoch(difficulty_adjust_epoch);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_difficulty_steps", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_difficulty_steps")]
+pub fn set_difficulty_steps(steps: DifficultySteps) -> Result<(), String> {
+    let audit = AuditCall::begin("set_difficulty_steps");
+    let res = DodService::set_difficulty_steps(steps);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_difficulty_steps")]
+#[candid_method(query, rename = "get_difficulty_steps")]
+pub fn get_difficulty_steps() -> DifficultySteps {
+    DodService::get_difficulty_steps()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_user_orders_by_blocks", guard = "anon_guard")]
+#[candid_method(query, rename = "get_user_orders_by_blocks")]
+pub fn get_user_orders_by_blocks(from: Height, to: Height) -> UserBlockOrderRes {
+    let range = BlockRange::inclusive(from, to).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    let (data, total) = DodService::get_user_orders_by_blocks(caller(), range, OrderStatus::Filled);
+    UserBlockOrderRes {
+        total,
+        from,
+        to,
+        data,
+    }
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_user_orders", guard = "anon_guard")]
+#[candid_method(query, rename = "get_user_orders")]
+pub fn get_user_orders(
+    from: Height,
+    to: Height,
+    status: Option<OrderStatus>,
+) -> Result<UserOrdersRes, String> {
+    let range = BlockRange::inclusive(from, to)?;
+    let (data, total) = DodService::get_user_orders(caller(), range, status.clone());
+    Ok(UserOrdersRes {
+        total,
+        from,
+        to,
+        status,
+        data,
+    })
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "inner_transfer_cycles", guard = "anon_update_guard")]
+#[candid_method(update, rename = "inner_transfer_cycles")]
+pub fn inner_transfer_cycles(
+    to: Vec<(Principal, u128)>,
+    memo: Option<Vec<u8>>,
+) -> Result<(), String> {
+    DodService::inner_transfer_cycles(caller(), to, memo)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_internal_transfers", guard = "anon_guard")]
+#[candid_method(query, rename = "get_internal_transfers")]
+pub fn get_internal_transfers(user: Principal, page: u64, limit: u64) -> Vec<InternalTransfer> {
+    // a user reads its own transfers, owners read anyone's
+    if caller() != user {
+        owner_guard().unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    }
+    DodService::get_internal_transfers(user, page, limit)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_next_difficulty_adjust_height")]
+#[candid_method(query, rename = "get_next_difficulty_adjust_height")]
+pub fn get_next_difficulty_adjust_height() -> Result<Option<u64>, String> {
+    DodService::get_consider_increase()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_user_burning_range", guard = "anon_guard")]
+#[candid_method(query, rename = "get_user_burning_range")]
+pub fn get_user_burning_range() -> Option<NewBlockOrderValue> {
+    DodService::get_user_range(caller())
+}
+
+/// `replace_existing` is optional so that clients sending only the first arguments still
+/// decode, without it an existing range is not replaced.
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "user_put_orders", guard = "anon_update_guard")]
+#[candid_method(update, rename = "user_put_orders")]
+pub fn user_put_orders(
+    height: Height,
+    amount: u128,
+    replace_existing: Option<bool>,
+    priority_fee: Option<u128>,
+) -> Result<(), String> {
+    DodService::user_put_burnrate_orders(
+        caller(),
+        height,
+        amount,
+        replace_existing.unwrap_or(false),
+        priority_fee,
+    )
+    .map(|_| ())
+}
+
+/// `user_put_orders` returning what was placed.
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "user_put_orders_v2", guard = "anon_update_guard")]
+#[candid_method(update, rename = "user_put_orders_v2")]
+pub fn user_put_orders_v2(
+    height: Height,
+    amount: u128,
+    replace_existing: bool,
+    priority_fee: Option<u128>,
+) -> Result<OrderPlacementResult, String> {
+    DodService::user_put_burnrate_orders(caller(), height, amount, replace_existing, priority_fee)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_notification_preferences", guard = "anon_update_guard")]
+#[candid_method(update, rename = "set_notification_preferences")]
+pub fn set_notification_preferences(preferences: Option<NotificationPreferences>) {
+    DodService::set_notification_preferences(caller(), preferences)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_notification_preferences", guard = "anon_guard")]
+#[candid_method(query, rename = "get_notification_preferences")]
+pub fn get_notification_preferences() -> Option<NotificationPreferences> {
+    DodService::get_notification_preferences(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_notifications", guard = "anon_guard")]
+#[candid_method(query, rename = "get_notifications")]
+pub fn get_notifications(since: Option<Height>) -> Vec<UserNotification> {
+    DodService::get_notifications(caller(), since)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_pending_rewards", guard = "anon_guard")]
+#[candid_method(query, rename = "get_pending_rewards")]
+pub fn get_pending_rewards() -> PendingRewards {
+    DodService::get_pending_rewards(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_priority_fee", guard = "anon_guard")]
+#[candid_method(query, rename = "get_priority_fee")]
+pub fn get_priority_fee() -> u128 {
+    DodService::get_priority_fee(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_settlement_capacity", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_settlement_capacity")]
+pub fn set_settlement_capacity(capacity: Option<u64>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_settlement_capacity");
+    let res = DodService::set_settlement_capacity(capacity);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_settlement_capacity")]
+#[candid_method(query, rename = "get_settlement_capacity")]
+pub fn get_settlement_capacity() -> Option<u64> {
+    DodService::get_settlement_capacity()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_priority_stats")]
+#[candid_method(query, rename = "get_priority_stats")]
+pub fn get_priority_stats(height: Height) -> Option<PriorityStats> {
+    DodService::get_priority_stats(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "preview_put_orders", guard = "anon_guard")]
+#[candid_method(query, rename = "preview_put_orders")]
+pub fn preview_put_orders(height: Height, amount: u128) -> Result<OrderPreview, String> {
+    DodService::preview_put_orders(caller(), height, amount)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_user_detail", guard = "anon_guard")]
+#[candid_method(query, rename = "get_user_detail")]
+pub fn get_user_detail() -> Option<UserDetail> {
+    DodService::get_user_detail(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_identity_overview", guard = "owner_guard")]
+#[candid_method(query, rename = "get_identity_overview")]
+pub fn get_identity_overview(principal: Principal) -> IdentityOverview {
+    DodService::get_identity_overview(principal)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_dashboard")]
+#[candid_method(query, rename = "get_dashboard")]
+pub fn get_dashboard(principal: Principal) -> Dashboard {
+    DodService::get_dashboard(principal)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_user_stats")]
+#[candid_method(query, rename = "get_user_stats")]
+pub fn get_user_stats(principal: Principal) -> UserStats {
+    DodService::get_user_stats(principal)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_user_detail_indexer")]
+#[candid_method(query, rename = "get_user_detail_indexer")]
+pub fn get_user_detail_indexer(principal: Principal) -> Option<UserDetail> {
+    DodService::get_user_detail(principal)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_user_subaccount", guard = "anon_guard")]
+#[candid_method(query, rename = "get_user_subaccount")]
+pub fn get_user_subaccount(id: Principal) -> Subaccount {
+    DodService::user_subaccount(id)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_deposit_account", guard = "anon_guard")]
+#[candid_method(query, rename = "get_deposit_account")]
+pub fn get_deposit_account() -> DepositAccount {
+    DodService::get_deposit_account(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_canister_cycles", guard = "owner_guard")]
+#[candid_method(query, rename = "get_canister_cycles")]
+pub fn get_canister_cycles() -> u128 {
+    ic_cdk::api::canister_balance128()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "claim_dod_to_wallet", guard = "anon_update_guard")]
+#[candid_method(update, rename = "claim_dod_to_wallet")]
+pub async fn claim_dod_to_wallet(
+    to: Option<String>,
+    claim_amount: Option<u64>,
+) -> Result<String, String> {
+    let mut _to = None;
+    if to.is_some() {
+        _to = Some(Account::from_str(to.unwrap().as_str()).unwrap());
+    }
+
+    match DodService::claim_reward(caller(), _to, claim_amount).await {
+        Ok(res) => Ok(res.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "claim_dod_to_wallets", guard = "anon_update_guard")]
+#[candid_method(update, rename = "claim_dod_to_wallets")]
+pub async fn claim_dod_to_wallets(
+    payouts: Vec<(String, u64)>,
+) -> Result<Vec<ClaimLegResult>, String> {
+    let mut _payouts = Vec::with_capacity(payouts.len());
+    for (to, amount) in payouts {
+        let account = Account::from_str(to.as_str())
+            .map_err(|e| format!("Invalid account {}: {:?}", to, e))?;
+        _payouts.push((account, amount));
+    }
+
+    DodService::claim_reward_split(caller(), _payouts).await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "claim_rewards_as_cycles", guard = "anon_update_guard")]
+#[candid_method(update, rename = "claim_rewards_as_cycles")]
+pub async fn claim_rewards_as_cycles(amount_dod: u64) -> Result<u128, String> {
+    DodService::claim_rewards_as_cycles(caller(), amount_dod).await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "miner_claim_dod", guard = "anon_update_guard")]
+#[candid_method(update, rename = "miner_claim_dod")]
+pub async fn miner_claim_dod(to: Option<String>, amount: u64) -> Result<String, String> {
+    let to = to
+        .map(|to| {
+            Account::from_str(to.as_str()).map_err(|e| format!("Invalid account {}: {:?}", to, e))
+        })
+        .transpose()?;
+    DodService::miner_claim_reward(caller(), to, amount)
+        .await
+        .map(|res| res.to_string())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_miner_reward")]
+#[candid_method(query, rename = "get_miner_reward")]
+pub fn get_miner_reward(height: Height) -> Option<MinerReward> {
+    DodService::get_miner_reward(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_pending_claims")]
+#[candid_method(query, rename = "get_pending_claims")]
+pub fn get_pending_claims(user: Principal) -> Vec<PendingClaim> {
+    DodService::get_pending_claims(user)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "donate_cycles_to_block", guard = "anon_update_guard")]
+#[candid_method(update, rename = "donate_cycles_to_block")]
+pub fn donate_cycles_to_block(height: Height, amount: u128) -> Result<BlockDonations, String> {
+    DodService::donate_cycles_to_block(caller(), height, amount)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_donations")]
+#[candid_method(query, rename = "get_block_donations")]
+pub fn get_block_donations(height: Height) -> BlockDonations {
+    DodService::get_block_donations(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_pool_twap")]
+#[candid_method(query, rename = "get_pool_twap")]
+pub fn get_pool_twap(window_blocks: u64) -> Result<PoolTwap, String> {
+    DodService::get_pool_twap(window_blocks)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_protocol_constants")]
+#[candid_method(query, rename = "get_protocol_constants")]
+pub fn get_protocol_constants() -> Result<ProtocolConstants, String> {
+    DodService::get_protocol_constants()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "verify_solvency", guard = "anon_update_guard")]
+#[candid_method(update, rename = "verify_solvency")]
+pub async fn verify_solvency() -> Result<SolvencyReport, String> {
+    DodService::verify_solvency().await
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_solvency_report")]
+#[candid_method(query, rename = "get_solvency_report")]
+pub fn get_solvency_report() -> Option<SolvencyReport> {
+    DodService::get_solvency_report()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_claim_statement")]
+#[candid_method(query, rename = "get_claim_statement")]
+pub fn get_claim_statement(claim_tx_index: u64) -> Option<ClaimStatement> {
+    DodService::get_claim_statement(claim_tx_index)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "resolve_pending_claim", guard = "owner_update_guard")]
+#[candid_method(update, rename = "resolve_pending_claim")]
+pub fn resolve_pending_claim(
+    user: Principal,
+    index: u64,
+    landed: bool,
+) -> Result<PendingClaim, String> {
+    let audit = AuditCall::begin("resolve_pending_claim");
+    let res = DodService::resolve_pending_claim(user, index, landed);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "reconcile_miner_rewards", guard = "owner_update_guard")]
+#[candid_method(update, rename = "reconcile_miner_rewards")]
+pub fn reconcile_miner_rewards() -> MinerRewardsReconciliation {
+    let audit = AuditCall::begin("reconcile_miner_rewards");
+    let res = DodService::reconcile_miner_rewards();
+    audit.done();
+    res
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "register_mining_asset", guard = "owner_update_guard")]
+#[candid_method(update, rename = "register_mining_asset")]
+pub fn register_mining_asset(args: MiningAssetArgs) -> Result<MiningAsset, String> {
+    let audit = AuditCall::begin("register_mining_asset");
+    let res = DodService::register_mining_asset(args);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_mining_assets")]
+#[candid_method(query, rename = "get_mining_assets")]
+pub fn get_mining_assets() -> Vec<MiningAsset> {
+    DodService::get_mining_assets()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_asset_rewards")]
+#[candid_method(query, rename = "get_asset_rewards")]
+pub fn get_asset_rewards(
+    asset: String,
+    from: Height,
+    to: Height,
+) -> Result<Vec<AssetReward>, String> {
+    Ok(DodService::get_asset_rewards(
+        asset,
+        BlockRange::inclusive(from, to)?,
+    ))
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "mint_pending_asset_rewards", guard = "owner_update_guard")]
+#[candid_method(update, rename = "mint_pending_asset_rewards")]
+pub async fn mint_pending_asset_rewards() -> u64 {
+    let audit = AuditCall::begin("mint_pending_asset_rewards");
+    let res = DodService::mint_pending_asset_rewards().await;
+    audit.done();
+    res
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "miner_claim_asset_reward", guard = "anon_update_guard")]
+#[candid_method(update, rename = "miner_claim_asset_reward")]
+pub async fn miner_claim_asset_reward(
+    asset: String,
+    height: Height,
+    to: Option<String>,
+) -> Result<String, String> {
+    let to = to
+        .map(|to| {
+            Account::from_str(to.as_str()).map_err(|e| format!("Invalid account {}: {:?}", to, e))
+        })
+        .transpose()?;
+    DodService::miner_claim_asset_reward(caller(), asset, height, to)
+        .await
+        .map(|res| res.to_string())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "invalidate_winner", guard = "owner_update_guard")]
+#[candid_method(update, rename = "invalidate_winner")]
+pub fn invalidate_winner(height: Height, reason: String, promote_next: bool) -> Result<(), String> {
+    let audit = AuditCall::begin("invalidate_winner");
+    let res = DodService::invalidate_winner(caller(), height, reason, promote_next);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "report_broadcast", guard = "anon_update_guard")]
+#[candid_method(update, rename = "report_broadcast")]
+pub fn report_broadcast(height: Height, txid: String) -> Result<(), String> {
+    DodService::report_broadcast(caller(), height, txid)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "confirm_broadcast", guard = "owner_update_guard")]
+#[candid_method(update, rename = "confirm_broadcast")]
+pub fn confirm_broadcast(height: Height, txid: String) -> Result<(), String> {
+    let audit = AuditCall::begin("confirm_broadcast");
+    let res = DodService::confirm_broadcast(caller(), height, txid);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_broadcast_info")]
+#[candid_method(query, rename = "get_broadcast_info")]
+pub fn get_broadcast_info(height: Height) -> Option<BroadcastInfo> {
+    DodService::get_broadcast_info(height)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_unbroadcast_wins")]
+#[candid_method(query, rename = "get_unbroadcast_wins")]
+pub fn get_unbroadcast_wins(btc_address: String, from: Height, limit: u64) -> UnbroadcastWinsPage {
+    DodService::get_unbroadcast_wins(btc_address, from, limit)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "is_miner", guard = "anon_guard")]
+#[candid_method(query, rename = "is_miner")]
+pub fn is_miner(btc_address: String) -> Option<MinerInfo> {
+    DodService::check_miner_if_existed(caller(), btc_address)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "am_i_candidate", guard = "anon_guard")]
+#[candid_method(query, rename = "am_i_candidate")]
+pub fn am_i_candidate(height: Height) -> bool {
+    DodService::get_miner_by_principal(caller())
+        .and_then(|miner| DodService::check_if_in_candidate(miner.btc_address, height))
+        .is_some()
+}
+
+/// The blocks of `get_orders_by_block_page` without telling where the page ends, traps on an
+/// inverted range.
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_orders_by_block_v2", guard = "owner_guard")]
+#[candid_method(query, rename = "get_orders_by_block_v2")]
+pub fn get_orders_by_block_v2(from: u64, to: u64) -> Vec<BlockDataFull> {
+    // `to` is excluded, as it always was for this endpoint
+    let range = BlockRange::new(from, to).unwrap_or_else(|e| ic_cdk::api::trap(&e));
+    DodService::get_orders_by_block_v2(range).data
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_orders_by_block_page", guard = "owner_guard")]
+#[candid_method(query, rename = "get_orders_by_block_page")]
+pub fn get_orders_by_block_page(from: u64, to: u64) -> Result<BlockDataFullPage, String> {
+    // `to` is excluded, as for get_orders_by_block_v2
+    Ok(DodService::get_orders_by_block_v2(BlockRange::new(
+        from, to,
+    )?))
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_orders", guard = "owner_guard")]
+#[candid_method(query, rename = "get_block_orders")]
+pub fn get_block_orders(height: Height, offset: u64, limit: u64) -> BlockOrdersPage {
+    DodService::get_block_orders(height, offset, limit)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_block_total_cycles", guard = "anon_guard")]
+#[candid_method(query, rename = "get_block_total_cycles")]
+pub fn get_block_total_cycles(height: Height) -> u128 {
+    DodService::get_block_total_cycles(height, false)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "propose_blackhole_ledger", guard = "owner_update_guard")]
+#[candid_method(update, rename = "propose_blackhole_ledger")]
+pub async fn propose_blackhole_ledger() -> Result<BlackholeProposal, String> {
+    let audit = AuditCall::begin("propose_blackhole_ledger");
+    let res = DodService::propose_blackhole_ledger(caller()).await;
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "cancel_blackhole_ledger", guard = "owner_update_guard")]
+#[candid_method(update, rename = "cancel_blackhole_ledger")]
+pub fn cancel_blackhole_ledger() -> Result<(), String> {
+    let audit = AuditCall::begin("cancel_blackhole_ledger");
+    let res = DodService::cancel_blackhole_ledger(caller());
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_blackhole_proposal")]
+#[candid_method(query, rename = "get_blackhole_proposal")]
+pub fn get_blackhole_proposal() -> Option<BlackholeProposal> {
+    DodService::get_blackhole_proposal()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "blackhole_ledger", guard = "owner_update_guard")]
+#[candid_method(update, rename = "blackhole_ledger")]
+pub async fn blackhole_ledger() -> Result<(), String> {
+    let audit = AuditCall::begin("blackhole_ledger");
+    let res = if let Some(service) = DodService::get_current_service() {
+        service.blockhole_ledger(caller()).await
+    } else {
+        Err("No service found".to_string())
+    };
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "migrate_to_sns", guard = "owner_update_guard")]
+#[candid_method(update, rename = "migrate_to_sns")]
+pub fn migrate_to_sns(sns_governance: Principal) -> Result<(), String> {
+    let audit = AuditCall::begin("migrate_to_sns");
+    let res = DodService::migrate_to_sns(caller(), sns_governance);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_governance_status")]
+#[candid_method(query, rename = "get_governance_status")]
+pub fn get_governance_status() -> GovernanceStatus {
+    DodService::get_governance_status()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_admin_audit", guard = "owner_guard")]
+#[candid_method(query, rename = "get_admin_audit")]
+pub fn get_admin_audit(from_ts: u64, to_ts: u64) -> Vec<AdminAuditEntry> {
+    DodService::get_admin_audit(from_ts, to_ts)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_maintenance", guard = "owner_activity_guard")]
+#[candid_method(update, rename = "set_maintenance")]
+pub fn set_maintenance(enabled: bool, reason: Option<String>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_maintenance");
+    let res = DodService::set_maintenance(caller(), enabled, reason);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_maintenance_status")]
+#[candid_method(query, rename = "get_maintenance_status")]
+pub fn get_maintenance_status() -> MaintenanceStatus {
+    DodService::get_maintenance_status()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "halt_claims", guard = "owner_activity_guard")]
+#[candid_method(update, rename = "halt_claims")]
+pub fn halt_claims(reason: String) -> Result<(), String> {
+    let audit = AuditCall::begin("halt_claims");
+    let res = DodService::halt_claims(caller(), reason);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "resume_claims", guard = "owner_activity_guard")]
+#[candid_method(update, rename = "resume_claims")]
+pub fn resume_claims() -> Result<(), String> {
+    let audit = AuditCall::begin("resume_claims");
+    let res = DodService::resume_claims(caller());
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_claims_halt_status")]
+#[candid_method(query, rename = "get_claims_halt_status")]
+pub fn get_claims_halt_status() -> ClaimsHaltStatus {
+    DodService::get_claims_halt_status()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_recovery_settings", guard = "owner_activity_guard")]
+#[candid_method(update, rename = "set_recovery_settings")]
+pub fn set_recovery_settings(
+    recovery_principal: Principal,
+    inactivity_days: u64,
+) -> Result<(), String> {
+    let audit = AuditCall::begin("set_recovery_settings");
+    let res = DodService::set_recovery_settings(
+        caller(),
+        recovery_principal,
+        inactivity_days.saturating_mul(ONE_DAY_NS),
+    );
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "cancel_recovery", guard = "owner_activity_guard")]
+#[candid_method(update, rename = "cancel_recovery")]
+pub fn cancel_recovery() -> Result<(), String> {
+    let audit = AuditCall::begin("cancel_recovery");
+    let res = DodService::cancel_recovery(caller());
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_recovery_status")]
+#[candid_method(query, rename = "get_recovery_status")]
+pub fn get_recovery_status() -> Result<RecoveryStatus, String> {
+    DodService::get_recovery_status()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "claim_ownership", guard = "anon_guard")]
+#[candid_method(update, rename = "claim_ownership")]
+pub fn claim_ownership() -> Result<(), String> {
+    DodService::claim_ownership(caller())
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_events", guard = "owner_guard")]
+#[candid_method(query, rename = "get_events")]
+pub fn get_events(from: u64, limit: u64) -> Vec<DodEvent> {
+    DodService::get_events(from, limit)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_logs", guard = "owner_guard")]
+#[candid_method(query, rename = "get_logs")]
+pub fn get_logs(level: Option<LogLevel>, from_ts: Option<u64>, limit: u64) -> Vec<LogEntry> {
+    DodService::get_logs(level, from_ts, limit)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_log_level", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_log_level")]
+pub fn set_log_level(level: LogLevel) -> Result<(), String> {
+    let audit = AuditCall::begin("set_log_level");
+    let res = DodService::set_log_level(level);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_log_level", guard = "owner_guard")]
+#[candid_method(query, rename = "get_log_level")]
+pub fn get_log_level() -> LogLevel {
+    DodService::get_log_level()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_settlement_checkpoint", guard = "owner_guard")]
+#[candid_method(query, rename = "get_settlement_checkpoint")]
+pub fn get_settlement_checkpoint() -> Option<SettlementCheckpoint> {
+    DodService::get_settlement_checkpoint()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_epoch_summaries")]
+#[candid_method(query, rename = "get_epoch_summaries")]
+pub fn get_epoch_summaries(from: u64, to: u64) -> Vec<EpochSummary> {
+    DodService::get_epoch_summaries(from, to)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "health")]
+#[candid_method(query, rename = "health")]
+pub fn health() -> HealthStatus {
+    DodService::health()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_memory_report", guard = "owner_guard")]
+#[candid_method(query, rename = "get_memory_report")]
+pub fn get_memory_report() -> MemoryReport {
+    DodService::get_memory_report()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_reward_boost", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_reward_boost")]
+pub fn set_reward_boost(boost: Option<RewardBoost>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_reward_boost");
+    let res = DodService::set_reward_boost(boost);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_reward_boost")]
+#[candid_method(query, rename = "get_reward_boost")]
+pub fn get_reward_boost() -> Option<RewardBoost> {
+    DodService::get_reward_boost()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_cycles_per_dod", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_cycles_per_dod")]
+pub fn set_cycles_per_dod(cycles_per_dod: Option<u128>) -> Result<(), String> {
+    let audit = AuditCall::begin("set_cycles_per_dod");
+    let res = DodService::set_cycles_per_dod(cycles_per_dod);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_cycles_per_dod")]
+#[candid_method(query, rename = "get_cycles_per_dod")]
+pub fn get_cycles_per_dod() -> Option<u128> {
+    DodService::get_cycles_per_dod()
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[update(name = "set_watchdog_factor", guard = "owner_update_guard")]
+#[candid_method(update, rename = "set_watchdog_factor")]
+pub fn set_watchdog_factor(factor: u64) -> Result<(), String> {
+    let audit = AuditCall::begin("set_watchdog_factor");
+    let res = DodService::set_watchdog_factor(factor);
+    audit.finish(res)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "replication_handshake")]
+#[candid_method(query, rename = "replication_handshake")]
+pub fn replication_handshake(replica_version: u32) -> Result<ReplicationInfo, String> {
+    DodService::replication_handshake(replica_version)
+}
+
+#[cfg(not(feature = "no_candid"))]
+#[query(name = "get_state_deltas")]
+#[candid_method(query, rename = "get_state_deltas")]
+pub fn get_state_deltas(since_seq: Option<u64>, limit: u64) -> Result<Vec<StateDelta>, String> {
+    DodService::get_state_deltas(since_seq, limit)
+}
+
+/// Owner guard for updates, also records the owner activity
+/// used by the recovery inactivity window. Once governance is handed to an SNS,
+/// only its governance canister passes.
+#[inline(always)]
+pub fn owner_activity_guard() -> Result<(), String> {
+    match DodService::get_sns_governance() {
+        Some(sns_governance) if caller() != sns_governance => {
+            return Err(format!(
+                "{} unauthorized, parameters are governed by {}",
+                caller(),
+                sns_governance
+            ));
+        }
+        Some(_) => {}
+        None => owner_guard()?,
+    }
+    DodService::record_owner_activity();
+    Ok(())
+}
+
+/// Rejects state-mutating calls while the canister is in maintenance.
+#[inline(always)]
+pub fn maintenance_guard() -> Result<(), String> {
+    let status = DodService::get_maintenance_status();
+    if status.enabled {
+        Err(format!(
+            "Canister is in maintenance: {}",
+            status.reason.unwrap_or_default()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[inline(always)]
+pub fn owner_update_guard() -> Result<(), String> {
+    maintenance_guard()?;
+    owner_activity_guard()
+}
+
+#[inline(always)]
+pub fn anon_update_guard() -> Result<(), String> {
+    maintenance_guard()?;
+    anon_guard()
 }
 
 #[inline(always)]
@@ -25,3 +25,33 @@ pub fn get_candid_pointer() -> *mut std::os::raw::c_char {
 
     c_string.into_raw()
 }
+
+#[cfg(test)]
+mod test {
+    use candid_parser::utils::{service_compatible, CandidSource};
+    use std::path::Path;
+
+    /// Fails when the interface is no longer a superset of the committed `dod.did`, i.e. when
+    /// an endpoint was removed or changed in a way existing clients cannot decode, or when
+    /// `dod.did` is missing. Run with `UPDATE_CANDID=1` to write the current interface as the
+    /// new baseline once it is released.
+    #[test]
+    fn test_candid_interface_compatible() {
+        let new_interface = super::__export_service();
+        let baseline = Path::new(env!("CARGO_MANIFEST_DIR")).join("dod.did");
+        if std::env::var("UPDATE_CANDID").is_ok() {
+            std::fs::write(&baseline, new_interface).unwrap();
+            return;
+        }
+        assert!(
+            baseline.exists(),
+            "{} is missing, the interface has nothing to be checked against",
+            baseline.display()
+        );
+        service_compatible(
+            CandidSource::Text(&new_interface),
+            CandidSource::File(baseline.as_path()),
+        )
+        .unwrap_or_else(|e| panic!("the candid interface is not backward compatible: {e:?}"));
+    }
+}
@@ -2,11 +2,11 @@ pub const CMC_CAN_ID: &str = "rkp4c-7iaaa-aaaaa-aaaca-cai";
 pub const ICP_CAN_ID: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
 pub const CYCLES_CAN_ID: &str = "um5iw-rqaaa-aaaaq-qaaba-cai";
 pub const MEMO_TOP_UP_CANISTER: u64 = 1347768404_u64;
-pub const ICP_FEE: u64 = 10_000u64;
+pub const DEFAULT_ICP_FEE: u64 = 10_000u64;
 pub const CYCLES_BURNER_FEE: u128 = 1_000_000_000_u128;
 pub const BURN_ORDERS_LIMIT: u128 = 500;
-pub const CYCLES_CREATE_FEE: u128 = 2_000_000_000_000u128;
-pub const MIN_ICP_STAKE_E8S_U64: u64 = 100_0000;
+pub const DEFAULT_CYCLES_CREATE_FEE: u128 = 2_000_000_000_000u128;
+pub const DEFAULT_MIN_ICP_STAKE_E8S: u64 = 100_0000;
 
 pub const ONE_MINUTE_NS: u64 = 1_000_000_000 * 60;
 pub const ONE_HOUR_NS: u64 = ONE_MINUTE_NS * 60;
@@ -14,11 +14,64 @@ pub const ONE_DAY_NS: u64 = ONE_HOUR_NS * 24;
 pub const ONE_WEEK_NS: u64 = ONE_DAY_NS * 7;
 pub const ONE_MONTH_NS: u64 = ONE_WEEK_NS * 30;
 
+use bitcoin::hashes::{sha256, Hash, HashEngine};
 use candid::{CandidType, Deserialize, Nat, Principal};
 use ic_cdk::api::call::CallResult;
 use ic_cdk::call;
 use std::fmt::Display;
 
+/// The canister clock. Outside of wasm it reads a settable thread local clock,
+/// so settlement can be driven by native tests.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> u64 {
+    ic_cdk::api::time()
+}
+
+/// The canister's own id, a fixed principal outside of wasm.
+#[cfg(target_arch = "wasm32")]
+pub fn self_id() -> Principal {
+    ic_cdk::id()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    static NATIVE_TIME: std::cell::Cell<u64> = std::cell::Cell::new(0);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> u64 {
+    NATIVE_TIME.with(|t| t.get())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_native_time(time: u64) {
+    NATIVE_TIME.with(|t| t.set(time));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn self_id() -> Principal {
+    Principal::management_canister()
+}
+
+/// Class byte of principals derived from another principal, self-authenticating principals of
+/// keys end with 0x02 instead.
+const DERIVED_ID_CLASS: u8 = 0x03;
+
+/// A principal derived from `owner` and `id` under `domain`. Nobody holds a key for it, and
+/// having the derived id class it never equals the principal of a real key.
+pub fn derive_principal(domain: &[u8], owner: Principal, id: &[u8]) -> Principal {
+    let mut engine = sha256::Hash::engine();
+    engine.input(&[domain.len() as u8]);
+    engine.input(domain);
+    engine.input(&[owner.as_slice().len() as u8]);
+    engine.input(owner.as_slice());
+    engine.input(id);
+    let hash = sha256::Hash::from_engine(engine).to_byte_array();
+    let mut bytes = hash[..28].to_vec();
+    bytes.push(DERIVED_ID_CLASS);
+    Principal::from_slice(&bytes)
+}
+
 pub type TimestampNs = u64;
 
 pub const TCYCLE_POS_ROUND_BASE_FEE: u64 = 50_000_000_000_u64;
@@ -46,6 +99,19 @@ pub enum NotifyTopUpError {
     TransactionTooOld(u64),
 }
 
+#[derive(CandidType, Deserialize, Debug)]
+pub struct IcpXdrConversionRate {
+    pub timestamp_seconds: u64,
+    pub xdr_permyriad_per_icp: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub struct IcpXdrConversionRateResponse {
+    pub data: IcpXdrConversionRate,
+    pub hash_tree: Vec<u8>,
+    pub certificate: Vec<u8>,
+}
+
 impl CMCClient {
     pub async fn notify_top_up(
         &self,
@@ -53,6 +119,10 @@ impl CMCClient {
     ) -> CallResult<(Result<Nat, NotifyTopUpError>,)> {
         call(self.0, "notify_top_up", (req,)).await
     }
+
+    pub async fn get_icp_xdr_conversion_rate(&self) -> CallResult<(IcpXdrConversionRateResponse,)> {
+        call(self.0, "get_icp_xdr_conversion_rate", ()).await
+    }
 }
 
 #[derive(CandidType, Deserialize, Debug)]
@@ -0,0 +1,340 @@
+//! Drives registrations, deposits, bids, settlement and difficulty adjustment over hundreds
+//! of blocks with randomized inputs and checks the invariants that must hold whatever the
+//! inputs are. Native only, run with `cargo test -p dod_mod --features invariants`.
+
+use crate::common::set_native_time;
+use crate::memory::NEW_BLOCK_ORDERS;
+use crate::orders::NewBlockOrders;
+use crate::service::{cycles, priority, staker, DodService};
+use candid::{Nat, Principal};
+use dod_utils::bitwork::{bitwork_minus_bit_hex, bitwork_plus_bit_hex, Bitwork};
+use dod_utils::types::{BlockRange, HalvingSettings, OrderStatus};
+use std::collections::BTreeMap;
+
+const BLOCKS: u64 = 500;
+const MAX_USERS: u64 = 40;
+const BLOCK_INTERVAL: u64 = 60 * 1_000_000_000;
+const DIFFICULTY_EPOCH: u64 = 8;
+const DEFAULT_REWARDS: u64 = 1_000_000_000;
+const SEEDS: [u64; 8] = [1, 2, 3, 5, 8, 13, 21, 0xdead_beef];
+
+/// xorshift64, enough to spread the inputs without pulling in a rand crate
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn range(&mut self, from: u64, to: u64) -> u64 {
+        from + self.next() % (to - from)
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+fn user(i: u64) -> Principal {
+    Principal::self_authenticating(i.to_be_bytes())
+}
+
+fn nat_to_u128(n: Nat) -> u128 {
+    u128::try_from(n.0).unwrap()
+}
+
+struct Snapshot {
+    balance: u128,
+    total_dod: u64,
+    burned: u128,
+}
+
+fn snapshot(users: &[Principal]) -> BTreeMap<Principal, Snapshot> {
+    users
+        .iter()
+        .map(|u| {
+            let detail = DodService::get_user_detail(*u).unwrap();
+            (
+                *u,
+                Snapshot {
+                    balance: nat_to_u128(detail.balance),
+                    total_dod: detail.total_dod,
+                    burned: staker::get_user_stats(*u).cycles_burned,
+                },
+            )
+        })
+        .collect()
+}
+
+fn pending_total(height: u64) -> u128 {
+    NEW_BLOCK_ORDERS.with_borrow(|v| {
+        NewBlockOrders::get_orders_by_block_height(v, height)
+            .filter(|(_, o)| o.status == OrderStatus::Pending)
+            .map(|(_, o)| o.value)
+            .sum()
+    })
+}
+
+fn simulate(seed: u64) {
+    let mut rng = Rng(seed);
+    let halving_settings = HalvingSettings {
+        interval: 100,
+        ratio: 0.5,
+    };
+    DodService::new(
+        BLOCK_INTERVAL,
+        DIFFICULTY_EPOCH,
+        DEFAULT_REWARDS,
+        Some(halving_settings.clone()),
+        vec![],
+        None,
+        None,
+    );
+    let start_difficulty = DodService::get_start_difficulty().unwrap();
+    let mut difficulty = start_difficulty.clone();
+    let mut users: Vec<Principal> = vec![];
+    // runs of blocks with and without a winner, so the adjust epochs are actually reached
+    let mut has_winner = true;
+
+    for height in 1..=BLOCKS {
+        set_native_time(height * BLOCK_INTERVAL);
+
+        // registrations
+        if (users.len() as u64) < MAX_USERS && rng.chance(30) {
+            let u = user(users.len() as u64);
+            staker::register_user(u).unwrap();
+            users.push(u);
+        }
+        if users.is_empty() {
+            continue;
+        }
+
+        // deposits
+        for _ in 0..rng.range(0, 4) {
+            let u = users[rng.range(0, users.len() as u64) as usize];
+            let amount = rng.range(0, 1_000_000_000_000) as u128;
+            // as a deposit credits it
+            cycles::add_to_user_pool(amount);
+            DodService::increase_user_cycle_balance(u, Nat::from(amount)).unwrap();
+        }
+
+        // bids, some replacing a running range and some with a priority fee
+        for _ in 0..rng.range(0, 3) {
+            let u = users[rng.range(0, users.len() as u64) as usize];
+            let start = height + rng.range(0, 5);
+            let end = start + rng.range(1, 30);
+            let rate = rng.range(1_000_000_000, 50_000_000_000) as u128;
+            DodService::user_put_order_v2(u, BlockRange::new(start, end).unwrap(), rate);
+            let fee = if rng.chance(25) {
+                Some(rng.range(1, 1_000_000_000) as u128)
+            } else {
+                None
+            };
+            priority::set_priority_fee(u, fee);
+        }
+
+        // settlement, at times with fewer slots than orders
+        let capacity = if rng.chance(30) {
+            Some(rng.range(1, 4))
+        } else {
+            None
+        };
+        priority::set_settlement_capacity(capacity).unwrap();
+        let before = snapshot(&users);
+        let pending = pending_total(height);
+        let total_cycles = DodService::get_block_total_cycles(height, false);
+        let refund_percent = [0u8, 0, 50, 100][rng.range(0, 4) as usize];
+        let block_reward =
+            DodService::get_block_reward_by_height(height, Some(halving_settings.clone())).unwrap();
+
+        let (refunded, priority_fees) =
+            DodService::update_users_balance_v2(height, total_cycles, refund_percent);
+        let after = snapshot(&users);
+
+        let sum = |s: &BTreeMap<Principal, Snapshot>, f: fn(&Snapshot) -> u128| -> u128 {
+            s.values().map(f).sum()
+        };
+        let rewarded = sum(&after, |s| s.total_dod as u128) - sum(&before, |s| s.total_dod as u128);
+        let debited = sum(&before, |s| s.balance) - sum(&after, |s| s.balance);
+        let burned = sum(&after, |s| s.burned) - sum(&before, |s| s.burned);
+
+        assert!(
+            rewarded <= block_reward as u128,
+            "seed {} height {}: rewarded {} over the block reward {}",
+            seed,
+            height,
+            rewarded,
+            block_reward
+        );
+        assert_eq!(
+            debited, burned,
+            "seed {} height {}: balances and burn stats disagree",
+            seed, height
+        );
+        assert!(
+            burned + refunded <= pending + priority_fees,
+            "seed {} height {}: burned {} + refunded {} over pending {} + fees {}",
+            seed,
+            height,
+            burned,
+            refunded,
+            pending,
+            priority_fees
+        );
+        assert!(
+            refunded <= pending * refund_percent as u128 / 100,
+            "seed {} height {}: refunded {} over {}% of {}",
+            seed,
+            height,
+            refunded,
+            refund_percent,
+            pending
+        );
+        if refund_percent > 0 {
+            assert_eq!(
+                priority_fees, 0,
+                "seed {} height {}: priority fees charged on a refunded block",
+                seed, height
+            );
+        }
+        let settled = users
+            .iter()
+            .filter(|u| after[*u].burned > before[*u].burned)
+            .count() as u64;
+        assert!(
+            capacity.map_or(true, |capacity| settled <= capacity),
+            "seed {} height {}: {} orders settled over the capacity {:?}",
+            seed,
+            height,
+            settled,
+            capacity
+        );
+        assert_eq!(
+            pending_total(height),
+            0,
+            "seed {} height {}: orders left pending after settlement",
+            seed,
+            height
+        );
+        assert_eq!(
+            cycles::get_user_pool(),
+            sum(&after, |s| s.balance),
+            "seed {} height {}: user pool drifted from the user balances",
+            seed,
+            height
+        );
+        for (u, s) in after.iter() {
+            assert!(
+                s.balance <= before[u].balance + refunded,
+                "seed {} height {}: {} gained more than the refunds",
+                seed,
+                height,
+                u
+            );
+        }
+
+        // difficulty adjustment
+        if rng.chance(15) {
+            has_winner = !has_winner;
+        }
+        let next = DodService::adjust_difficulty(
+            height,
+            difficulty.clone(),
+            &start_difficulty,
+            has_winner,
+            DIFFICULTY_EPOCH,
+        );
+        assert_valid_bitwork(&next);
+        assert!(next >= start_difficulty, "seed {} height {}", seed, height);
+        if has_winner {
+            assert!(next >= difficulty, "seed {} height {}", seed, height);
+        } else {
+            assert!(next <= difficulty, "seed {} height {}", seed, height);
+        }
+        assert!(
+            next == difficulty
+                || next == start_difficulty
+                || next == bitwork_plus_bit_hex(difficulty.clone(), 1).unwrap()
+                || next == bitwork_minus_bit_hex(difficulty.clone(), 1).unwrap(),
+            "seed {} height {}: {:?} moved to {:?} in one block",
+            seed,
+            height,
+            difficulty,
+            next
+        );
+        difficulty = next;
+    }
+}
+
+fn assert_valid_bitwork(bitwork: &Bitwork) {
+    assert!(bitwork.pre <= 64);
+    assert_eq!(bitwork.post_hex.len(), 1);
+    assert!(u8::from_str_radix(bitwork.post_hex.as_str(), 16).is_ok());
+    assert!(bitwork.pre < 64 || bitwork.post_hex == "0");
+}
+
+#[test]
+fn invariants_hold_for_random_lifecycles() {
+    // every seed gets its own thread, so its own stable memory and config
+    for seed in SEEDS {
+        std::thread::spawn(move || simulate(seed))
+            .join()
+            .unwrap_or_else(|_| panic!("seed {} broke an invariant", seed));
+    }
+}
+
+#[test]
+fn difficulty_only_moves_after_a_full_epoch() {
+    DodService::new(
+        BLOCK_INTERVAL,
+        DIFFICULTY_EPOCH,
+        DEFAULT_REWARDS,
+        None,
+        vec![],
+        None,
+        None,
+    );
+    let start_difficulty = DodService::get_start_difficulty().unwrap();
+    let mut difficulty = start_difficulty.clone();
+    let mut changes = vec![];
+    for height in 1..=DIFFICULTY_EPOCH * 4 {
+        let next = DodService::adjust_difficulty(
+            height,
+            difficulty.clone(),
+            &start_difficulty,
+            true,
+            DIFFICULTY_EPOCH,
+        );
+        if next != difficulty {
+            changes.push(height);
+        }
+        difficulty = next;
+    }
+    assert_eq!(
+        changes,
+        vec![
+            DIFFICULTY_EPOCH,
+            DIFFICULTY_EPOCH * 2,
+            DIFFICULTY_EPOCH * 3,
+            DIFFICULTY_EPOCH * 4
+        ]
+    );
+
+    // missed blocks walk it back down, never below the start
+    for height in DIFFICULTY_EPOCH * 4 + 1..=DIFFICULTY_EPOCH * 12 {
+        difficulty = DodService::adjust_difficulty(
+            height,
+            difficulty.clone(),
+            &start_difficulty,
+            false,
+            DIFFICULTY_EPOCH,
+        );
+        assert!(difficulty >= start_difficulty);
+    }
+    assert_eq!(difficulty, start_difficulty);
+}
@@ -1,8 +1,8 @@
 use candid::{CandidType, Principal};
 use ic_cdk::api::management_canister::main::{
-    create_canister, delete_canister, deposit_cycles, install_code, raw_rand, stop_canister,
-    update_settings, CanisterInstallMode, CanisterSettings, CreateCanisterArgument,
-    InstallCodeArgument, UpdateSettingsArgument,
+    canister_status, create_canister, delete_canister, deposit_cycles, install_code, raw_rand,
+    stop_canister, update_settings, CanisterInstallMode, CanisterSettings, CanisterStatusResponse,
+    CreateCanisterArgument, InstallCodeArgument, UpdateSettingsArgument,
 };
 use ic_cdk::api::management_canister::provisional::CanisterIdRecord;
 
@@ -164,6 +164,18 @@ pub async fn canister_add_controllers(
     }
 }
 
+pub async fn canister_status_of(
+    canister_id: Principal,
+) -> Result<CanisterStatusResponse, EgoError> {
+    match canister_status(CanisterIdRecord { canister_id }).await {
+        Ok((status,)) => Ok(status),
+        Err((code, msg)) => {
+            let code = code as u16;
+            Err(EgoError { code, msg })
+        }
+    }
+}
+
 pub async fn random_32() -> Result<Vec<u8>, EgoError> {
     match raw_rand().await {
         Ok((v,)) => Ok(v),
@@ -3,13 +3,17 @@ use ic_stable_structures::{
     DefaultMemoryImpl, Memory, StableBTreeMap,
 };
 
-use crate::types::{BtreeKey, BtreeValue, StableState, UserDetail};
+use crate::types::{
+    BtreeKey, BtreeValue, ClaimCursors, ClaimStatement, GenerationState, LedgerDeadLetter,
+    LedgerOp, PendingClaims, PoolAccumulator, StableState, UserDetail, UserStats,
+};
 use candid::Principal;
 use dod_utils::types::*;
 use ic_cdk::trap;
 use ic_cdk_timers::TimerId;
 use ic_stable_structures::storable::Blob;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 
 #[allow(dead_code)]
 const USER_PROFILE_MEM_ID: MemoryId = MemoryId::new(0);
@@ -31,6 +35,45 @@ const NEW_ORDER_ID: MemoryId = MemoryId::new(8);
 
 const NEW_BLOCK_ORDER_ID: MemoryId = MemoryId::new(9);
 
+const EVENTS_ID: MemoryId = MemoryId::new(10);
+const BROADCASTS_ID: MemoryId = MemoryId::new(11);
+const ADMIN_AUDIT_ID: MemoryId = MemoryId::new(12);
+const UPGRADE_HISTORY_ID: MemoryId = MemoryId::new(13);
+const INTERNAL_TRANSFERS_ID: MemoryId = MemoryId::new(14);
+const USER_TRANSFERS_ID: MemoryId = MemoryId::new(15);
+const PRIORITY_FEES_ID: MemoryId = MemoryId::new(16);
+const PRIORITY_STATS_ID: MemoryId = MemoryId::new(17);
+const USER_STATS_ID: MemoryId = MemoryId::new(18);
+const BLOCK_ENTROPY_ID: MemoryId = MemoryId::new(19);
+const CLAIM_LOCKS_ID: MemoryId = MemoryId::new(20);
+const DEPOSITS_ID: MemoryId = MemoryId::new(21);
+const INTEGRATORS_ID: MemoryId = MemoryId::new(22);
+const MINER_OPERATORS_ID: MemoryId = MemoryId::new(23);
+const REPLICATION_LOG_ID: MemoryId = MemoryId::new(24);
+const REFERRAL_CODES_ID: MemoryId = MemoryId::new(25);
+const REFERRERS_ID: MemoryId = MemoryId::new(26);
+const REFERRALS_ID: MemoryId = MemoryId::new(27);
+const LOGS_ID: MemoryId = MemoryId::new(28);
+const EPOCHS_ID: MemoryId = MemoryId::new(29);
+const WINNER_ENVELOPES_ID: MemoryId = MemoryId::new(30);
+const WINNER_PAYMENTS_ID: MemoryId = MemoryId::new(31);
+const BLOCK_LEDGER_LINKS_ID: MemoryId = MemoryId::new(32);
+const BURN_RECEIPTS_ID: MemoryId = MemoryId::new(33);
+const NOTIFICATION_PREFERENCES_ID: MemoryId = MemoryId::new(34);
+const PSBT_BLOBS_ID: MemoryId = MemoryId::new(35);
+const POOLS_ID: MemoryId = MemoryId::new(36);
+const MINER_REWARDS_ID: MemoryId = MemoryId::new(37);
+const MINING_ASSETS_ID: MemoryId = MemoryId::new(38);
+const ASSET_REWARDS_ID: MemoryId = MemoryId::new(39);
+const PENDING_CLAIMS_ID: MemoryId = MemoryId::new(40);
+const CLAIM_STATEMENTS_ID: MemoryId = MemoryId::new(41);
+const CLAIM_CURSORS_ID: MemoryId = MemoryId::new(42);
+const BLOCK_DONATIONS_ID: MemoryId = MemoryId::new(43);
+const LEDGER_QUEUE_ID: MemoryId = MemoryId::new(44);
+const POOL_ACCUMULATORS_ID: MemoryId = MemoryId::new(45);
+const LEDGER_DEAD_LETTERS_ID: MemoryId = MemoryId::new(46);
+const CREDITED_DEPOSIT_BLOCKS_ID: MemoryId = MemoryId::new(47);
+
 const BTREE_ID: MemoryId = MemoryId::new(91);
 
 #[allow(dead_code)]
@@ -51,6 +94,20 @@ thread_local! {
 
     pub static TIMER_IDS: RefCell<Vec<TimerId>> = RefCell::new(Vec::new());
 
+    pub static GENERATION: RefCell<GenerationState> = RefCell::new(GenerationState::default());
+    // heap only, cleared on every order write
+    pub static TOTAL_CYCLES_CACHE: RefCell<BTreeMap<(BlockNumber, bool), u128>> = RefCell::new(BTreeMap::new());
+    // heap only, last treasury solvency check, made again after an upgrade
+    pub static SOLVENCY_REPORT: RefCell<Option<SolvencyReport>> = RefCell::new(None);
+    // heap only, size of the log buffer, summed again from LOGS after an upgrade
+    pub static LOG_BYTES: RefCell<Option<u64>> = RefCell::new(None);
+    // heap only, sequence number and send time of the ledger operation in flight, the queue starts again after an upgrade
+    pub static LEDGER_OP_IN_FLIGHT: RefCell<Option<(u64, u64)>> = RefCell::new(None);
+    // heap only, set while blocks are sent to the block archive
+    pub static BLOCK_ARCHIVING: RefCell<bool> = RefCell::new(false);
+    // heap only, kept apart from TIMER_IDS which the generation pops
+    pub static WATCHDOG_TIMER: RefCell<Option<TimerId>> = RefCell::new(None);
+
     pub static MINERS: RefCell<StableBTreeMap<BtcAddress, MinerInfo, VM>> = MEMORY_MANAGER.with(|mm| {
         RefCell::new(StableBTreeMap::init(mm.borrow().get(MINER_MEM_ID)))
     });
@@ -70,6 +127,97 @@ thread_local! {
     pub static NEW_BLOCK_ORDERS : RefCell<StableBlockOrders>  = RefCell::new(StableBTreeMap::init(get_new_block_orders_memory()));
     pub static NEW_USER_ORDERS : RefCell<StableUserOrders>  = RefCell::new(StableBTreeMap::init(get_new_orders_memory()));
 
+    pub static EVENTS: RefCell<StableBTreeMap<u64, DodEvent, VM>> = RefCell::new(StableBTreeMap::init(get_events_memory()));
+
+    pub static BROADCASTS: RefCell<StableBTreeMap<u64, BroadcastInfo, VM>> = RefCell::new(StableBTreeMap::init(get_broadcasts_memory()));
+
+    pub static ADMIN_AUDIT: RefCell<StableBTreeMap<u64, AdminAuditEntry, VM>> = RefCell::new(StableBTreeMap::init(get_admin_audit_memory()));
+
+    pub static UPGRADE_HISTORY: RefCell<StableBTreeMap<u64, UpgradeRecord, VM>> = RefCell::new(StableBTreeMap::init(get_upgrade_history_memory()));
+
+    pub static INTERNAL_TRANSFERS: RefCell<StableBTreeMap<u64, InternalTransfer, VM>> = RefCell::new(StableBTreeMap::init(get_internal_transfers_memory()));
+    // (user, transfer id) -> counterparty
+    pub static USER_TRANSFERS: RefCell<StableBTreeMap<(Principal, u64), Principal, VM>> = RefCell::new(StableBTreeMap::init(get_user_transfers_memory()));
+
+    pub static PRIORITY_FEES: RefCell<StableBTreeMap<Principal, u128, VM>> = RefCell::new(StableBTreeMap::init(get_priority_fees_memory()));
+
+    pub static PRIORITY_STATS: RefCell<StableBTreeMap<u64, PriorityStats, VM>> = RefCell::new(StableBTreeMap::init(get_priority_stats_memory()));
+
+    pub static USER_STATS: RefCell<StableBTreeMap<Principal, UserStats, VM>> = RefCell::new(StableBTreeMap::init(get_user_stats_memory()));
+
+    pub static BLOCK_ENTROPY: RefCell<StableBTreeMap<u64, BlockEntropy, VM>> = RefCell::new(StableBTreeMap::init(get_block_entropy_memory()));
+    // user -> time the running claim took the lock
+    pub static CLAIM_LOCKS: RefCell<StableBTreeMap<Principal, u64, VM>> = RefCell::new(StableBTreeMap::init(get_claim_locks_memory()));
+
+    // (user, deposit id) -> deposit
+    pub static DEPOSITS: RefCell<StableBTreeMap<(Principal, u64), DepositRecord, VM>> = RefCell::new(StableBTreeMap::init(get_deposits_memory()));
+
+    pub static INTEGRATORS: RefCell<StableBTreeMap<Principal, Integrator, VM>> = RefCell::new(StableBTreeMap::init(get_integrators_memory()));
+
+    // (miner owner, operator) -> time the operator was added, kept apart from the bounded MinerInfo
+    pub static MINER_OPERATORS: RefCell<StableBTreeMap<(Principal, Principal), u64, VM>> = RefCell::new(StableBTreeMap::init(get_miner_operators_memory()));
+
+    // seq -> state delta, streamed to read replicas
+    pub static REPLICATION_LOG: RefCell<StableBTreeMap<u64, StateDelta, VM>> = RefCell::new(StableBTreeMap::init(get_replication_log_memory()));
+
+    // referral code -> referrer
+    pub static REFERRAL_CODES: RefCell<StableBTreeMap<String, Principal, VM>> = RefCell::new(StableBTreeMap::init(get_referral_codes_memory()));
+
+    pub static REFERRERS: RefCell<StableBTreeMap<Principal, ReferrerStats, VM>> = RefCell::new(StableBTreeMap::init(get_referrers_memory()));
+
+    // referred user -> referral
+    pub static REFERRALS: RefCell<StableBTreeMap<Principal, ReferralInfo, VM>> = RefCell::new(StableBTreeMap::init(get_referrals_memory()));
+
+    pub static LOGS: RefCell<StableBTreeMap<u64, LogEntry, VM>> = RefCell::new(StableBTreeMap::init(get_logs_memory()));
+
+    // epoch index -> summary
+    pub static EPOCHS: RefCell<StableBTreeMap<u64, EpochSummary, VM>> = RefCell::new(StableBTreeMap::init(get_epochs_memory()));
+
+    pub static WINNER_ENVELOPES: RefCell<StableBTreeMap<u64, WinnerEnvelope, VM>> = RefCell::new(StableBTreeMap::init(get_winner_envelopes_memory()));
+
+    pub static WINNER_PAYMENTS: RefCell<StableBTreeMap<u64, WinnerPaymentProof, VM>> = RefCell::new(StableBTreeMap::init(get_winner_payments_memory()));
+
+    pub static BLOCK_LEDGER_LINKS: RefCell<StableBTreeMap<u64, BlockLedgerLinks, VM>> = RefCell::new(StableBTreeMap::init(get_block_ledger_links_memory()));
+
+    pub static BURN_RECEIPTS: RefCell<StableBTreeMap<u64, BurnReceipt, VM>> = RefCell::new(StableBTreeMap::init(get_burn_receipts_memory()));
+
+    pub static NOTIFICATION_PREFERENCES: RefCell<StableBTreeMap<Principal, NotificationPreferences, VM>> = RefCell::new(StableBTreeMap::init(get_notification_preferences_memory()));
+
+    pub static PSBT_BLOBS: RefCell<StableBTreeMap<Blob<32>, PsbtBlob, VM>> = RefCell::new(StableBTreeMap::init(get_psbt_blobs_memory()));
+
+    pub static POOLS: RefCell<StableBTreeMap<Principal, MiningPool, VM>> = RefCell::new(StableBTreeMap::init(get_pools_memory()));
+
+    pub static MINER_REWARDS: RefCell<StableBTreeMap<Height, MinerReward, VM>> = RefCell::new(StableBTreeMap::init(get_miner_rewards_memory()));
+
+    pub static MINING_ASSETS: RefCell<StableBTreeMap<String, MiningAsset, VM>> = RefCell::new(StableBTreeMap::init(get_mining_assets_memory()));
+
+    pub static ASSET_REWARDS: RefCell<StableBTreeMap<AssetRewardKey, AssetReward, VM>> = RefCell::new(StableBTreeMap::init(get_asset_rewards_memory()));
+
+    pub static PENDING_CLAIMS: RefCell<StableBTreeMap<Principal, PendingClaims, VM>> = RefCell::new(StableBTreeMap::init(get_pending_claims_memory()));
+
+    pub static CLAIM_STATEMENTS: RefCell<StableBTreeMap<u64, ClaimStatement, VM>> = RefCell::new(StableBTreeMap::init(get_claim_statements_memory()));
+
+    pub static CLAIM_CURSORS: RefCell<StableBTreeMap<Principal, ClaimCursors, VM>> = RefCell::new(StableBTreeMap::init(get_claim_cursors_memory()));
+
+    pub static BLOCK_DONATIONS: RefCell<StableBTreeMap<(BlockNumber, Principal), u128, VM>> = RefCell::new(StableBTreeMap::init(get_block_donations_memory()));
+
+    pub static LEDGER_QUEUE: RefCell<StableBTreeMap<u64, LedgerOp, VM>> = RefCell::new(StableBTreeMap::init(get_ledger_queue_memory()));
+
+    pub static POOL_ACCUMULATORS: RefCell<StableBTreeMap<BlockNumber, PoolAccumulator, VM>> = RefCell::new(StableBTreeMap::init(get_pool_accumulators_memory()));
+
+    pub static LEDGER_DEAD_LETTERS: RefCell<StableBTreeMap<u64, LedgerDeadLetter, VM>> = RefCell::new(StableBTreeMap::init(get_ledger_dead_letters_memory()));
+
+    // ICP ledger block index of a deposit transfer to the user and deposit it was credited for
+    pub static CREDITED_DEPOSIT_BLOCKS: RefCell<StableBTreeMap<u64, (Principal, u64), VM>> = RefCell::new(StableBTreeMap::init(get_credited_deposit_blocks_memory()));
+
+}
+
+pub fn get_blocks_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(BLOCK_MEM_ID))
+}
+
+pub fn get_miners_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(MINER_MEM_ID))
 }
 
 pub fn get_upgrades_memory() -> VirtualMemory<DefaultMemoryImpl> {
@@ -103,6 +251,158 @@ pub fn get_new_block_orders_memory() -> VirtualMemory<DefaultMemoryImpl> {
     MEMORY_MANAGER.with(|m| m.borrow().get(NEW_BLOCK_ORDER_ID))
 }
 
+pub fn get_events_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(EVENTS_ID))
+}
+
+pub fn get_broadcasts_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(BROADCASTS_ID))
+}
+
+pub fn get_admin_audit_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(ADMIN_AUDIT_ID))
+}
+
+pub fn get_upgrade_history_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(UPGRADE_HISTORY_ID))
+}
+
+pub fn get_internal_transfers_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(INTERNAL_TRANSFERS_ID))
+}
+
+pub fn get_user_transfers_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(USER_TRANSFERS_ID))
+}
+
+pub fn get_priority_fees_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(PRIORITY_FEES_ID))
+}
+
+pub fn get_priority_stats_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(PRIORITY_STATS_ID))
+}
+
+pub fn get_user_stats_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(USER_STATS_ID))
+}
+
+pub fn get_block_entropy_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(BLOCK_ENTROPY_ID))
+}
+
+pub fn get_claim_locks_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CLAIM_LOCKS_ID))
+}
+
+pub fn get_deposits_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(DEPOSITS_ID))
+}
+
+pub fn get_integrators_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(INTEGRATORS_ID))
+}
+
+pub fn get_miner_operators_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(MINER_OPERATORS_ID))
+}
+
+pub fn get_replication_log_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(REPLICATION_LOG_ID))
+}
+
+pub fn get_referral_codes_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(REFERRAL_CODES_ID))
+}
+
+pub fn get_referrers_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(REFERRERS_ID))
+}
+
+pub fn get_referrals_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(REFERRALS_ID))
+}
+
+pub fn get_logs_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(LOGS_ID))
+}
+
+pub fn get_epochs_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(EPOCHS_ID))
+}
+
+pub fn get_winner_envelopes_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(WINNER_ENVELOPES_ID))
+}
+
+pub fn get_winner_payments_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(WINNER_PAYMENTS_ID))
+}
+
+pub fn get_block_ledger_links_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(BLOCK_LEDGER_LINKS_ID))
+}
+
+pub fn get_burn_receipts_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(BURN_RECEIPTS_ID))
+}
+
+pub fn get_notification_preferences_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(NOTIFICATION_PREFERENCES_ID))
+}
+
+pub fn get_psbt_blobs_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(PSBT_BLOBS_ID))
+}
+
+pub fn get_pools_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(POOLS_ID))
+}
+
+pub fn get_miner_rewards_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(MINER_REWARDS_ID))
+}
+
+pub fn get_mining_assets_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(MINING_ASSETS_ID))
+}
+
+pub fn get_asset_rewards_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(ASSET_REWARDS_ID))
+}
+
+pub fn get_pending_claims_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(PENDING_CLAIMS_ID))
+}
+
+pub fn get_claim_statements_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CLAIM_STATEMENTS_ID))
+}
+
+pub fn get_claim_cursors_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CLAIM_CURSORS_ID))
+}
+
+pub fn get_block_donations_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(BLOCK_DONATIONS_ID))
+}
+
+pub fn get_ledger_queue_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(LEDGER_QUEUE_ID))
+}
+
+pub fn get_pool_accumulators_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(POOL_ACCUMULATORS_ID))
+}
+
+pub fn get_ledger_dead_letters_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(LEDGER_DEAD_LETTERS_ID))
+}
+
+pub fn get_credited_deposit_blocks_memory() -> VirtualMemory<DefaultMemoryImpl> {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CREDITED_DEPOSIT_BLOCKS_ID))
+}
+
 pub fn insert_btree(key: String, value: BtreeValue) {
     BTREES.with(|m| m.borrow_mut().insert(BtreeKey(key), value));
 }
@@ -1,4 +1,6 @@
 pub mod common;
+#[cfg(all(test, feature = "invariants", not(target_arch = "wasm32")))]
+mod invariants;
 pub mod management;
 pub mod memory;
 pub mod orders;
@@ -1,8 +1,12 @@
-use crate::memory::{StableBlockOrders, StablePrincipalOrders, StableUserOrders, NEW_USER_ORDERS};
+use crate::memory::{
+    StableBlockOrders, StablePrincipalOrders, StableUserOrders, NEW_USER_ORDERS, TOTAL_CYCLES_CACHE,
+};
 use candid::Principal;
 
-use dod_utils::types::{BlockNumber, BlockRange, NewBlockOrderValue, OrderDetail, OrderStatus};
-use ic_cdk::id;
+use crate::common::self_id;
+use dod_utils::types::{
+    BlockNumber, BlockRange, NewBlockOrderValue, OrderDetail, OrderPreview, OrderStatus,
+};
 
 pub struct NewBlockOrders {}
 
@@ -30,6 +34,7 @@ impl NewBlockOrders {
         value: u128,
         status: OrderStatus,
     ) -> Option<OrderDetail> {
+        Self::invalidate_total_cycles();
         block_orders.insert((block_number, user_id), OrderDetail { value, status })
     }
 
@@ -51,9 +56,45 @@ impl NewBlockOrders {
         block_number: BlockNumber,
         user_id: Principal,
     ) -> Option<OrderDetail> {
+        Self::invalidate_total_cycles();
         block_orders.remove(&(block_number, user_id))
     }
 
+    /// Retrieves the memoized total cycles of a block, computing and caching it on a miss.
+    ///
+    /// The cache lives on the heap and is cleared by every block or user order write, so within a
+    /// single call each block is summed at most once.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_number` - A `BlockNumber` representing the block height.
+    /// * `with_filled` - A `bool` indicating whether filled orders are excluded from the total.
+    /// * `compute` - A closure computing the total on a cache miss.
+    ///
+    /// # Returns
+    ///
+    /// * `u128` - The total cycles for the block.
+    pub fn cached_total_cycles(
+        block_number: BlockNumber,
+        with_filled: bool,
+        compute: impl FnOnce() -> u128,
+    ) -> u128 {
+        if let Some(total) =
+            TOTAL_CYCLES_CACHE.with_borrow(|v| v.get(&(block_number, with_filled)).cloned())
+        {
+            return total;
+        }
+        let total = compute();
+        TOTAL_CYCLES_CACHE.with_borrow_mut(|v| v.insert((block_number, with_filled), total));
+        total
+    }
+
+    /// Clears the memoized block totals. Called on every order write, because the totals depend
+    /// on both the block orders and the users' active ranges.
+    pub fn invalidate_total_cycles() {
+        TOTAL_CYCLES_CACHE.with_borrow_mut(|v| v.clear());
+    }
+
     /// Retrieves orders by block height.
     ///
     /// This function returns an iterator over the orders in the `StableBlockOrders` for a specified block number.
@@ -74,7 +115,9 @@ impl NewBlockOrders {
         block_orders
             .range((block_number, Principal::anonymous())..)
             .take_while(move |(r, _)| r.0 == block_number)
-            .filter(move |&((b, r), _)| NewUserOrders::get_user_bet(r, b).is_some() || r == id())
+            .filter(move |&((b, r), _)| {
+                NewUserOrders::get_user_bet(r, b).is_some() || r == self_id()
+            })
             .map(|((_, s), t)| (s, t))
     }
 
@@ -113,7 +156,7 @@ impl NewBlockOrders {
     /// # Arguments
     ///
     /// * `block_orders` - A reference to `StableBlockOrders` containing the orders.
-    /// * `range` - A `BlockRange` representing the start and end block heights.
+    /// * `range` - A `BlockRange` of the blocks to retrieve, its end excluded.
     ///
     /// # Returns
     ///
@@ -122,8 +165,10 @@ impl NewBlockOrders {
         block_orders: &StableBlockOrders,
         range: BlockRange,
     ) -> impl Iterator<Item = (u64, (Principal, OrderDetail))> + '_ {
+        // the management canister id is empty, so it sorts before every principal
+        let lowest = Principal::management_canister();
         block_orders
-            .range((range.0, Principal::anonymous())..=(range.1, Principal::anonymous()))
+            .range((range.start(), lowest)..(range.end(), lowest))
             .map(|((block_number, p), v)| (block_number, (p, v)))
     }
 
@@ -137,7 +182,7 @@ impl NewBlockOrders {
     ///
     /// * `block_orders` - A reference to `StableBlockOrders` containing the orders.
     /// * `user_id` - A `Principal` representing the user whose orders will be retrieved.
-    /// * `range` - A `BlockRange` representing the start and end block heights.
+    /// * `range` - A `BlockRange` of the blocks to retrieve, its end excluded.
     ///
     /// # Returns
     ///
@@ -148,7 +193,7 @@ impl NewBlockOrders {
         range: BlockRange,
     ) -> impl Iterator<Item = (u64, OrderDetail)> + '_ {
         block_orders
-            .range((range.0, user_id)..=(range.1, user_id))
+            .range((range.start(), user_id)..(range.end(), user_id))
             .filter(move |&((b, r), _)| {
                 NewUserOrders::get_user_bet(user_id, b).is_some() && r == user_id
             })
@@ -165,7 +210,7 @@ impl NewBlockOrders {
     ///
     /// * `principal_orders` - A reference to `StablePrincipalOrders` containing the orders.
     /// * `user_id` - A `Principal` representing the user whose orders will be retrieved.
-    /// * `range` - A `BlockRange` representing the start and end block heights.
+    /// * `range` - A `BlockRange` of the blocks to retrieve, its end excluded.
     ///
     /// # Returns
     ///
@@ -176,7 +221,7 @@ impl NewBlockOrders {
         range: BlockRange,
     ) -> impl Iterator<Item = (u64, OrderDetail)> + '_ {
         principal_orders
-            .range((user_id, range.0)..=(user_id, range.1))
+            .range((user_id, range.start())..(user_id, range.end()))
             // .take_while(move |&((r, _), _)| r == user_id)
             .map(|((_, block_number), v)| (block_number, v))
     }
@@ -195,7 +240,7 @@ impl NewUserOrders {
     ///
     /// * `user_orders` - A mutable reference to `StableUserOrders` where the order will be updated.
     /// * `user_id` - A `Principal` representing the user whose order will be updated.
-    /// * `range` - A `BlockRange` representing the start and end block heights for the order.
+    /// * `range` - A `BlockRange` of the blocks of the order, its end excluded.
     /// * `amount` - A `u128` representing the amount of the order.
     pub fn update_order(
         user_orders: &mut StableUserOrders,
@@ -203,6 +248,7 @@ impl NewUserOrders {
         range: BlockRange,
         amount: u128,
     ) {
+        NewBlockOrders::invalidate_total_cycles();
         // 每个用户只允许有一个��注范围，直接覆盖旧的策略
         user_orders.insert(
             user_id,
@@ -234,7 +280,7 @@ impl NewUserOrders {
                 v: amount,
             }) = user_orders.get(&user_id)
             {
-                if block_number < range.1 {
+                if block_number < range.end() {
                     return Some(amount);
                 }
             }
@@ -256,6 +302,52 @@ impl NewUserOrders {
     pub fn get_user_set_range(user_id: Principal) -> Option<NewBlockOrderValue> {
         NEW_USER_ORDERS.with_borrow(|user_orders| user_orders.get(&user_id))
     }
+
+    /// Previews how a new order range interacts with the user's previous one.
+    ///
+    /// Blocks of the previous range before the new end stay ordered (`kept`),
+    /// blocks of the previous range after the new end are `cancelled`,
+    /// and blocks only covered by the new range are `extended`.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - An `Option<NewBlockOrderValue>` representing the user's current order range.
+    /// * `range` - A `BlockRange` representing the new order range.
+    /// * `amount` - A `u128` representing the new amount per block.
+    ///
+    /// # Returns
+    ///
+    /// * `OrderPreview` - The kept, extended and cancelled block ranges.
+    pub fn preview_update(
+        previous: Option<NewBlockOrderValue>,
+        range: BlockRange,
+        amount: u128,
+    ) -> OrderPreview {
+        let non_empty = |start, end| BlockRange::new(start, end).ok().filter(|r| !r.is_empty());
+        match previous.clone() {
+            None => OrderPreview {
+                range,
+                amount,
+                previous,
+                kept: None,
+                extended: non_empty(range.start(), range.end()).into_iter().collect(),
+                cancelled: None,
+            },
+            Some(NewBlockOrderValue { r: old, .. }) => {
+                let mut extended = vec![];
+                extended.extend(non_empty(range.start(), range.end().min(old.start())));
+                extended.extend(non_empty(range.start().max(old.end()), range.end()));
+                OrderPreview {
+                    range,
+                    amount,
+                    previous,
+                    kept: non_empty(old.start(), old.end().min(range.end())),
+                    extended,
+                    cancelled: non_empty(range.end().max(old.start()), old.end()),
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -263,7 +355,11 @@ mod test {
     use crate::memory::{NEW_BLOCK_ORDERS, NEW_USER_ORDERS};
     use crate::orders::{NewBlockOrders, NewUserOrders};
     use candid::Principal;
-    use dod_utils::types::{OrderDetail, OrderStatus};
+    use dod_utils::types::{BlockRange, NewBlockOrderValue, OrderDetail, OrderStatus};
+
+    fn r(start: u64, end: u64) -> BlockRange {
+        BlockRange::new(start, end).unwrap()
+    }
 
     #[test]
     pub fn test_range() {
@@ -271,7 +367,7 @@ mod test {
         let p2 = Principal::from_text("tmhkz-dyaaa-aaaah-aedeq-cai").unwrap();
 
         NEW_USER_ORDERS.with_borrow_mut(|v| {
-            NewUserOrders::update_order(v, p1, (1, 2), 100);
+            NewUserOrders::update_order(v, p1, r(1, 2), 100);
         });
 
         NEW_BLOCK_ORDERS.with_borrow_mut(|v| {
@@ -299,10 +395,56 @@ mod test {
         });
 
         NEW_BLOCK_ORDERS.with_borrow(|v| {
-            let d = NewBlockOrders::get_user_orders_in_range(&v, p1, (3, 4))
+            let d = NewBlockOrders::get_user_orders_in_range(&v, p1, r(3, 5))
                 .map(|(v, j)| (v, j))
                 .collect::<Vec<(u64, OrderDetail)>>();
             assert_eq!(d, vec![]);
         })
     }
+
+    #[test]
+    pub fn test_preview_update() {
+        let p = NewUserOrders::preview_update(None, r(5, 10), 100);
+        assert_eq!(p.extended, vec![r(5, 10)]);
+        assert_eq!(p.kept, None);
+        assert_eq!(p.cancelled, None);
+
+        let previous = Some(NewBlockOrderValue {
+            r: r(5, 20),
+            v: 100,
+        });
+        let p = NewUserOrders::preview_update(previous.clone(), r(8, 12), 50);
+        assert_eq!(p.kept, Some(r(5, 12)));
+        assert_eq!(p.extended, vec![]);
+        assert_eq!(p.cancelled, Some(r(12, 20)));
+
+        let p = NewUserOrders::preview_update(previous, r(2, 30), 50);
+        assert_eq!(p.kept, Some(r(5, 20)));
+        assert_eq!(p.extended, vec![r(2, 5), r(20, 30)]);
+        assert_eq!(p.cancelled, None);
+    }
+
+    #[test]
+    pub fn test_block_range() {
+        assert!(BlockRange::new(5, 4).is_err());
+        assert!(BlockRange::inclusive(5, 4).is_err());
+        assert_eq!(BlockRange::inclusive(3, 4).unwrap(), r(3, 5));
+        assert_eq!(BlockRange::inclusive(3, u64::MAX).unwrap().end(), u64::MAX);
+        assert_eq!(BlockRange::with_len(3, 2), Some(r(3, 5)));
+        assert_eq!(BlockRange::with_len(u64::MAX, 1), None);
+
+        let range = r(3, 5);
+        assert_eq!(range.len(), 2);
+        assert_eq!(range.last(), Some(4));
+        assert!(range.contains(3) && range.contains(4) && !range.contains(5));
+        assert_eq!(range.blocks().collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(range.limit(1), r(3, 4));
+        assert_eq!(range.limit(10), range);
+        assert_eq!(range.intersect(&r(4, 9)), Some(r(4, 5)));
+        assert_eq!(range.intersect(&r(5, 9)), None);
+
+        let empty = r(5, 5);
+        assert!(empty.is_empty());
+        assert_eq!(empty.last(), None);
+    }
 }
@@ -9,16 +9,46 @@ use bitcoin::script::Instruction::{Op, PushBytes};
 use bitcoin::script::{Instruction, Instructions};
 use bitcoin::{opcodes, script, Script, Transaction};
 use candid::CandidType;
+use dod_utils::types::is_asset_id;
 use serde::{Deserialize, Serialize};
 use tag::Tag;
 
 pub(crate) const PROTOCOL_ID: [u8; 3] = *b"dod";
 pub const MAGIC_VALUE: u64 = 87960;
 
+/// Asset a reveal mines, written as its ticker. DMT is mined for the block, any other ticker
+/// only counts when the asset is registered.
 #[derive(PartialEq, Clone, Serialize, Deserialize, Debug, Eq, Default)]
+#[serde(from = "String", into = "String")]
 pub enum DodAssets {
     #[default]
     DMT,
+    Asset(String),
+}
+
+impl DodAssets {
+    pub fn id(&self) -> &str {
+        match self {
+            DodAssets::DMT => "DMT",
+            DodAssets::Asset(id) => id.as_str(),
+        }
+    }
+}
+
+impl From<String> for DodAssets {
+    fn from(id: String) -> Self {
+        if id == "DMT" {
+            DodAssets::DMT
+        } else {
+            DodAssets::Asset(id)
+        }
+    }
+}
+
+impl From<DodAssets> for String {
+    fn from(asset: DodAssets) -> Self {
+        asset.id().to_string()
+    }
 }
 
 #[derive(Default, PartialEq, Clone, Serialize, Deserialize, Debug, Eq)]
@@ -56,7 +86,7 @@ pub fn decode_cbor_payload(slice: &[u8]) -> Option<DodStruct> {
     let res = serde_cbor::from_slice::<DodStruct>(slice);
     match res {
         Ok(r) => {
-            if r.t != DodAssets::DMT {
+            if !is_asset_id(r.t.id()) {
                 return None;
             }
             return Some(r.clone());
@@ -343,3 +373,37 @@ pub fn vec_to_u84(req: Vec<u8>) -> Result<[u8; 4], String> {
     }
     Ok(salt_bytes.clone())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_asset_payload() {
+        assert_eq!(
+            serde_cbor::to_vec(&DodAssets::DMT).unwrap(),
+            serde_cbor::to_vec(&"DMT").unwrap()
+        );
+        let payload = |t: DodAssets| {
+            serde_cbor::to_vec(&DodStruct {
+                n: None,
+                t,
+                dmt: Some(DodMining { time: 1, nonce: 2 }),
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            decode_cbor_payload(payload(DodAssets::DMT).as_slice()).map(|p| p.t),
+            Some(DodAssets::DMT)
+        );
+        assert_eq!(
+            decode_cbor_payload(payload(DodAssets::Asset("ABC".to_string())).as_slice())
+                .map(|p| p.t),
+            Some(DodAssets::Asset("ABC".to_string()))
+        );
+        assert_eq!(
+            decode_cbor_payload(payload(DodAssets::Asset("abc".to_string())).as_slice()),
+            None
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use dod_utils::types::{AmountError, BlockRange, Height};
+
+/// `percent` of `amount`, rounded down, without overflowing for any amount.
+pub fn percent_of(amount: u128, percent: u8) -> u128 {
+    let percent = percent.min(100) as u128;
+    amount / 100 * percent + amount % 100 * percent / 100
+}
+
+/// Blocks `[start_height, end)` a burn amount covers at `rate` cycles per block.
+pub fn orders_range(
+    start_height: Height,
+    burn_amount: u128,
+    rate: u128,
+) -> Result<BlockRange, AmountError> {
+    if rate == 0 {
+        return Err(AmountError::ZeroRate);
+    }
+    let times = burn_amount / rate;
+    if times == 0 {
+        return Err(AmountError::AmountTooLow);
+    }
+    let times = u64::try_from(times).map_err(|_| AmountError::DoesNotFit("u64".to_string()))?;
+    BlockRange::with_len(start_height, times)
+        .ok_or_else(|| AmountError::Overflow("order range end".to_string()))
+}
+
+/// Cycles the treasury reinvests from a block: half of what the winner was not paid, or half
+/// of what was not refunded when there was no winner.
+pub fn treasury_reinvest(cycle_deposit: u128, paid: Option<u128>, refunded: u128) -> u128 {
+    match paid {
+        Some(paid) => cycle_deposit.saturating_sub(paid) / 2,
+        None => cycle_deposit.saturating_sub(refunded) / 2,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EDGES: [u128; 7] = [0, 1, 2, 99, 100, u64::MAX as u128, u128::MAX];
+
+    #[test]
+    fn test_percent_of() {
+        for amount in EDGES {
+            for percent in [0u8, 1, 33, 50, 99, 100, 255] {
+                let part = percent_of(amount, percent);
+                assert!(part <= amount);
+                if amount <= u64::MAX as u128 {
+                    assert_eq!(part, amount * percent.min(100) as u128 / 100);
+                }
+            }
+        }
+        assert_eq!(percent_of(u128::MAX, 100), u128::MAX);
+        assert_eq!(percent_of(u128::MAX, 0), 0);
+    }
+
+    #[test]
+    fn test_orders_range() {
+        assert_eq!(orders_range(10, 100, 0), Err(AmountError::ZeroRate));
+        assert_eq!(orders_range(10, 99, 100), Err(AmountError::AmountTooLow));
+        assert_eq!(
+            orders_range(10, 250, 100),
+            Ok(BlockRange::new(10, 12).unwrap())
+        );
+        assert_eq!(
+            orders_range(0, u128::MAX, 1),
+            Err(AmountError::DoesNotFit("u64".to_string()))
+        );
+        assert_eq!(
+            orders_range(u64::MAX, 1, 1),
+            Err(AmountError::Overflow("order range end".to_string()))
+        );
+        for amount in EDGES {
+            for rate in EDGES {
+                for start in [0, 1, u64::MAX - 1, u64::MAX] {
+                    if let Ok(range) = orders_range(start, amount, rate) {
+                        assert_eq!(range.start(), start);
+                        assert!(!range.is_empty());
+                        assert!(range.len() as u128 * rate <= amount);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_treasury_reinvest() {
+        assert_eq!(treasury_reinvest(100, Some(40), 0), 30);
+        assert_eq!(treasury_reinvest(100, Some(u128::MAX), 0), 0);
+        assert_eq!(treasury_reinvest(100, None, 20), 40);
+        assert_eq!(treasury_reinvest(u128::MAX, None, 0), u128::MAX / 2);
+        assert_eq!(treasury_reinvest(0, None, u128::MAX), 0);
+    }
+}
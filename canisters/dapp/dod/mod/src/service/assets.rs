@@ -0,0 +1,276 @@
+use crate::common::now;
+use crate::memory::{ASSET_REWARDS, MINING_ASSETS};
+use crate::protocol::DodAssets;
+use crate::service::block::get_last_block;
+use crate::service::config;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use dod_utils::types::{
+    AssetReward, AssetRewardKey, BlockRange, Height, MinerCandidate, MiningAsset, MiningAssetArgs,
+    MAX_MINING_ASSETS,
+};
+use std::collections::BTreeMap;
+
+/// Blocks `get_asset_rewards` looks up at once.
+pub const MAX_ASSET_REWARD_BLOCKS: u64 = 1000;
+
+/// Treasury subaccount of asset `id`, hashed from the block subaccount so no two assets and
+/// no treasury bucket share one.
+pub fn asset_subaccount(block_subaccount: [u8; 32], id: &str) -> [u8; 32] {
+    let mut engine = sha256::Hash::engine();
+    engine.input(block_subaccount.as_slice());
+    engine.input(b"asset");
+    engine.input(id.as_bytes());
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Registers an asset, or updates the rewards and state of a registered one. The halving
+/// schedule of a new asset counts from the open block.
+pub fn register_asset(args: MiningAssetArgs) -> Result<MiningAsset, String> {
+    args.validate()?;
+    let asset = match get_asset(args.id.as_str()) {
+        Some(existing) => MiningAsset {
+            rewards: args.rewards,
+            halving_settings: args.halving_settings,
+            active: args.active,
+            ..existing
+        },
+        None => {
+            if MINING_ASSETS.with_borrow(|v| v.len()) as usize >= MAX_MINING_ASSETS {
+                return Err(format!(
+                    "At most {} assets can be registered",
+                    MAX_MINING_ASSETS
+                ));
+            }
+            MiningAsset {
+                treasury_subaccount: asset_subaccount(
+                    config::get_dod_block_account()?,
+                    args.id.as_str(),
+                )
+                .to_vec(),
+                id: args.id,
+                rewards: args.rewards,
+                halving_settings: args.halving_settings,
+                start_height: get_last_block().map_or(0, |(height, _)| height),
+                active: args.active,
+                registered_at: now(),
+            }
+        }
+    };
+    MINING_ASSETS.with_borrow_mut(|v| v.insert(asset.id.clone(), asset.clone()));
+    Ok(asset)
+}
+
+pub fn get_asset(id: &str) -> Option<MiningAsset> {
+    MINING_ASSETS.with_borrow(|v| v.get(&id.to_string()))
+}
+
+pub fn get_assets() -> Vec<MiningAsset> {
+    MINING_ASSETS.with_borrow(|v| v.iter().map(|(_, asset)| asset).collect())
+}
+
+/// The asset a candidate mining `asset` records, `None` for DMT. Other assets must be
+/// registered and active.
+pub fn candidate_asset(asset: &DodAssets) -> Result<Option<String>, String> {
+    match asset {
+        DodAssets::DMT => Ok(None),
+        DodAssets::Asset(id) => match get_asset(id.as_str()) {
+            Some(registered) if registered.active => Ok(Some(id.clone())),
+            Some(_) => Err(format!("Asset {} is not active", id)),
+            None => Err(format!("Asset {} is not registered", id)),
+        },
+    }
+}
+
+/// Whether `candidate` competes for the block, candidates of other assets only compete for
+/// their asset's reward.
+pub fn is_block_candidate(candidate: &MinerCandidate) -> bool {
+    candidate.asset.is_none()
+}
+
+/// Reward of `asset` at block `height`, halved on the asset's own schedule.
+pub fn asset_reward(asset: &MiningAsset, height: Height) -> u64 {
+    config::halved_reward(
+        asset.rewards,
+        height.saturating_sub(asset.start_height),
+        asset.halving_settings.clone(),
+    )
+}
+
+/// The first candidate of each asset in `candidates`, which are in settlement order.
+pub fn asset_winners(candidates: &[MinerCandidate]) -> BTreeMap<&str, &MinerCandidate> {
+    let mut winners = BTreeMap::new();
+    for candidate in candidates.iter() {
+        if let Some(asset) = candidate.asset.as_deref() {
+            winners.entry(asset).or_insert(candidate);
+        }
+    }
+    winners
+}
+
+/// Records what the best candidate of each asset won in block `height`. A block settled
+/// again keeps the rewards recorded the first time, only new ones are returned to be minted.
+pub fn settle_assets(height: Height, candidates: &[MinerCandidate]) -> Vec<AssetReward> {
+    asset_winners(candidates)
+        .into_iter()
+        .filter_map(|(id, candidate)| {
+            let asset = get_asset(id)?;
+            let key = AssetRewardKey {
+                asset: id.to_string(),
+                height,
+            };
+            if ASSET_REWARDS.with_borrow(|v| v.contains_key(&key)) {
+                return None;
+            }
+            let reward = AssetReward {
+                asset: id.to_string(),
+                height,
+                btc_address: candidate.btc_address.clone(),
+                reward: asset_reward(&asset, height),
+                minted: false,
+                claimed: false,
+            };
+            ASSET_REWARDS.with_borrow_mut(|v| v.insert(key, reward.clone()));
+            Some(reward)
+        })
+        .collect()
+}
+
+pub fn get_asset_reward(asset: &str, height: Height) -> Option<AssetReward> {
+    ASSET_REWARDS.with_borrow(|v| {
+        v.get(&AssetRewardKey {
+            asset: asset.to_string(),
+            height,
+        })
+    })
+}
+
+/// Rewards of `asset` won in the blocks of `range`, at most `MAX_ASSET_REWARD_BLOCKS` blocks.
+pub fn get_asset_rewards(asset: &str, range: BlockRange) -> Vec<AssetReward> {
+    range
+        .limit(MAX_ASSET_REWARD_BLOCKS)
+        .blocks()
+        .filter_map(|height| get_asset_reward(asset, height))
+        .collect()
+}
+
+/// Rewards not minted yet, their mint failed or is still running.
+pub fn unminted_rewards() -> Vec<AssetReward> {
+    ASSET_REWARDS.with_borrow(|v| {
+        v.iter()
+            .map(|(_, reward)| reward)
+            .filter(|reward| !reward.minted)
+            .collect()
+    })
+}
+
+fn update_reward(asset: &str, height: Height, f: impl FnOnce(&mut AssetReward)) {
+    let key = AssetRewardKey {
+        asset: asset.to_string(),
+        height,
+    };
+    ASSET_REWARDS.with_borrow_mut(|v| {
+        if let Some(mut reward) = v.get(&key) {
+            f(&mut reward);
+            v.insert(key, reward);
+        }
+    })
+}
+
+pub fn mark_minted(asset: &str, height: Height) {
+    update_reward(asset, height, |reward| reward.minted = true);
+}
+
+pub fn mark_claimed(asset: &str, height: Height) {
+    update_reward(asset, height, |reward| reward.claimed = true);
+}
+
+/// The reward of `asset` at `height` the miner at `btc_address` can claim now.
+pub fn claimable_reward(
+    asset: &str,
+    height: Height,
+    btc_address: &str,
+) -> Result<AssetReward, String> {
+    let reward = get_asset_reward(asset, height)
+        .ok_or_else(|| format!("No {} reward at block {}", asset, height))?;
+    if reward.btc_address != btc_address {
+        return Err("The reward was won by another miner".to_string());
+    }
+    if reward.claimed {
+        return Err("The reward is claimed already".to_string());
+    }
+    if !reward.minted {
+        return Err("The reward is not minted yet".to_string());
+    }
+    Ok(reward)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dod_utils::types::HalvingSettings;
+
+    fn candidate(btc_address: &str, asset: Option<&str>) -> MinerCandidate {
+        MinerCandidate {
+            btc_address: btc_address.to_string(),
+            submit_time: 0,
+            cycles_price: 0,
+            signed_commit_psbt: String::new(),
+            signed_reveal_psbt: String::new(),
+            signed_commit_psbt_hash: None,
+            signed_reveal_psbt_hash: None,
+            dod_bid: None,
+            match_depth: None,
+            asset: asset.map(|a| a.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_asset_winners() {
+        let candidates = vec![
+            candidate("a", None),
+            candidate("b", Some("ABC")),
+            candidate("c", Some("XYZ")),
+            candidate("d", Some("ABC")),
+        ];
+        let winners = asset_winners(candidates.as_slice());
+        assert_eq!(winners.len(), 2);
+        assert_eq!(winners["ABC"].btc_address, "b");
+        assert_eq!(winners["XYZ"].btc_address, "c");
+        assert!(is_block_candidate(&candidates[0]));
+        assert!(!is_block_candidate(&candidates[1]));
+    }
+
+    #[test]
+    fn test_asset_reward() {
+        let asset = MiningAsset {
+            id: "ABC".to_string(),
+            rewards: 1000,
+            halving_settings: Some(HalvingSettings {
+                interval: 10,
+                ratio: 0.5,
+            }),
+            start_height: 100,
+            active: true,
+            treasury_subaccount: vec![],
+            registered_at: 0,
+        };
+        assert_eq!(asset_reward(&asset, 50), 1000);
+        assert_eq!(asset_reward(&asset, 109), 1000);
+        assert_eq!(asset_reward(&asset, 110), 500);
+        assert_eq!(asset_reward(&asset, 125), 250);
+    }
+
+    #[test]
+    fn test_asset_subaccount() {
+        let block = [7u8; 32];
+        assert_eq!(
+            asset_subaccount(block, "ABC"),
+            asset_subaccount(block, "ABC")
+        );
+        assert_ne!(
+            asset_subaccount(block, "ABC"),
+            asset_subaccount(block, "XYZ")
+        );
+        assert_ne!(asset_subaccount(block, "ABC"), block);
+    }
+}
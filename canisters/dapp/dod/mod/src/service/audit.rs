@@ -0,0 +1,68 @@
+use crate::memory::ADMIN_AUDIT;
+use bitcoin::hashes::{sha256, Hash};
+use candid::Principal;
+use dod_utils::types::AdminAuditEntry;
+
+const MAX_AUDIT_ENTRIES_PER_QUERY: usize = 1000;
+
+/// An owner update being audited. Created at the start of the endpoint, before any await,
+/// because the caller and the arguments are only readable in the original message.
+pub struct AuditCall {
+    caller: Principal,
+    method: String,
+    args_hash: Vec<u8>,
+    time: u64,
+}
+
+impl AuditCall {
+    pub fn begin(method: &str) -> Self {
+        AuditCall {
+            caller: ic_cdk::caller(),
+            method: method.to_string(),
+            args_hash: sha256::Hash::hash(ic_cdk::api::call::arg_data_raw().as_slice())
+                .to_byte_array()
+                .to_vec(),
+            time: ic_cdk::api::time(),
+        }
+    }
+
+    /// Records the result of the call and passes it through.
+    pub fn finish<T>(self, res: Result<T, String>) -> Result<T, String> {
+        self.record(res.as_ref().map(|_| ()).map_err(|e| e.clone()));
+        res
+    }
+
+    /// Records a call that has no result to report.
+    pub fn done(self) {
+        self.record(Ok(()));
+    }
+
+    fn record(self, result: Result<(), String>) {
+        ADMIN_AUDIT.with_borrow_mut(|v| {
+            let id = v.last_key_value().map_or(0, |(k, _)| k + 1);
+            v.insert(
+                id,
+                AdminAuditEntry {
+                    id,
+                    time: self.time,
+                    caller: self.caller,
+                    method: self.method,
+                    args_hash: self.args_hash,
+                    result,
+                },
+            );
+        })
+    }
+}
+
+/// Entries are appended in time order, so the scan stops at the first entry after `to_ts`.
+pub fn get_admin_audit(from_ts: u64, to_ts: u64) -> Vec<AdminAuditEntry> {
+    ADMIN_AUDIT.with_borrow(|v| {
+        v.iter()
+            .map(|(_, e)| e)
+            .skip_while(|e| e.time < from_ts)
+            .take_while(|e| e.time <= to_ts)
+            .take(MAX_AUDIT_ENTRIES_PER_QUERY)
+            .collect()
+    })
+}
@@ -0,0 +1,251 @@
+use crate::common::{now, ONE_MINUTE_NS};
+use crate::management::canister_status_of;
+use crate::memory::CONFIG;
+use crate::service::event::add_event;
+use crate::service::ledger;
+use candid::Principal;
+use dod_utils::types::{BlackholeProposal, ChildCanisterCheck, DodCanisters, EventKind};
+use ic_cdk::api::management_canister::main::CanisterStatusType;
+
+/// Time an owner has to confirm a proposal before it has to be made again.
+pub const BLACKHOLE_CONFIRM_WINDOW: u64 = ONE_MINUTE_NS * 10;
+/// Cycles each canister needs before its controllers are removed, it can only be topped up
+/// from then on.
+pub const MIN_BLACKHOLE_CYCLES: u128 = 5_000_000_000_000;
+
+pub fn get_proposal() -> Option<BlackholeProposal> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.blackhole_proposal.clone())
+    })
+}
+
+fn set_proposal(proposal: Option<BlackholeProposal>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.blackhole_proposal = proposal;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_blackholed_at() -> Option<u64> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.ledger_blackholed_at)
+    })
+}
+
+/// Whether `check` allows removing the controllers of its canister.
+pub fn verify(check: &ChildCanisterCheck) -> Result<(), String> {
+    if !check.running {
+        return Err(format!("Canister {} is not running", check.canister_id));
+    }
+    if check.module_hash.is_none() {
+        return Err(format!(
+            "Canister {} has no code installed",
+            check.canister_id
+        ));
+    }
+    if check.cycles < MIN_BLACKHOLE_CYCLES {
+        return Err(format!(
+            "Canister {} has {} cycles, at least {} are needed",
+            check.canister_id, check.cycles, MIN_BLACKHOLE_CYCLES
+        ));
+    }
+    Ok(())
+}
+
+async fn check_child(canister_id: Principal) -> Result<ChildCanisterCheck, String> {
+    let status = canister_status_of(canister_id).await.map_err(|e| {
+        format!(
+            "Canister {} did not report its status: {}",
+            canister_id, e.msg
+        )
+    })?;
+    let check = ChildCanisterCheck {
+        canister_id,
+        running: status.status == CanisterStatusType::Running,
+        module_hash: status.module_hash,
+        cycles: u128::try_from(status.cycles.0).unwrap_or(u128::MAX),
+    };
+    verify(&check)?;
+    Ok(check)
+}
+
+/// Checks that the ledger answers calls and that the ledger, index and archive are running
+/// with code installed and enough cycles to live on without a controller.
+pub async fn check_children(canisters: &DodCanisters) -> Result<Vec<ChildCanisterCheck>, String> {
+    ledger::fetch_metadata(canisters.ledger).await?;
+    let mut checks = Vec::with_capacity(3);
+    for canister_id in [canisters.ledger, canisters.index, canisters.archive] {
+        checks.push(check_child(canister_id).await?);
+    }
+    Ok(checks)
+}
+
+pub async fn propose(
+    caller: Principal,
+    canisters: &DodCanisters,
+) -> Result<BlackholeProposal, String> {
+    if get_blackholed_at().is_some() {
+        return Err("The ledger canisters are already blackholed".to_string());
+    }
+    let checks = check_children(canisters).await?;
+    let proposed_at = ic_cdk::api::time();
+    let proposal = BlackholeProposal {
+        proposed_by: caller,
+        proposed_at,
+        expires_at: proposed_at + BLACKHOLE_CONFIRM_WINDOW,
+        checks,
+        blackholed: None,
+    };
+    set_proposal(Some(proposal.clone()))?;
+    add_event(
+        caller,
+        EventKind::BlackholeProposed {
+            expires_at: proposal.expires_at,
+        },
+    );
+    Ok(proposal)
+}
+
+/// Canisters of `proposal` whose controllers are removed already.
+pub fn blackholed(proposal: &BlackholeProposal) -> Vec<Principal> {
+    proposal.blackholed.clone().unwrap_or_default()
+}
+
+pub fn cancel(caller: Principal) -> Result<(), String> {
+    let proposal = get_proposal().ok_or("No blackhole proposal found")?;
+    if !blackholed(&proposal).is_empty() {
+        return Err(
+            "Controllers were removed already, confirm the proposal again to finish".to_string(),
+        );
+    }
+    set_proposal(None)?;
+    add_event(caller, EventKind::BlackholeCancelled);
+    Ok(())
+}
+
+/// The proposal to confirm. It stays until every controller is removed, so a confirmation
+/// that failed part way can be made again. An expired proposal is dropped, unless controllers
+/// were removed already.
+pub fn confirmable_proposal(now: u64) -> Result<BlackholeProposal, String> {
+    let proposal = get_proposal().ok_or("No blackhole proposal found, propose it first")?;
+    if now > proposal.expires_at && blackholed(&proposal).is_empty() {
+        set_proposal(None)?;
+        return Err("The blackhole proposal expired, propose it again".to_string());
+    }
+    Ok(proposal)
+}
+
+/// Records that the controllers of `canister_id` are removed, a retry skips it.
+pub fn record_step(canister_id: Principal) -> Result<(), String> {
+    let mut proposal = get_proposal().ok_or("No blackhole proposal found")?;
+    let mut done = blackholed(&proposal);
+    if !done.contains(&canister_id) {
+        done.push(canister_id);
+    }
+    proposal.blackholed = Some(done);
+    set_proposal(Some(proposal))
+}
+
+/// Records a failed step, the proposal is kept for a retry.
+pub fn record_failed(caller: Principal, canister_id: Principal, error: String) {
+    let blackholed = get_proposal().map(|p| blackholed(&p)).unwrap_or_default();
+    add_event(
+        caller,
+        EventKind::BlackholeFailed {
+            blackholed,
+            canister_id,
+            error,
+        },
+    );
+}
+
+pub fn record_blackholed(caller: Principal, canisters: &DodCanisters) {
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.ledger_blackholed_at = Some(now());
+            dod_service.blackhole_proposal = None;
+        }
+    });
+    add_event(
+        caller,
+        EventKind::LedgerBlackholed {
+            ledger: canisters.ledger,
+            index: canisters.index,
+            archive: canisters.archive,
+        },
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::service::DodService;
+
+    #[test]
+    fn test_verify() {
+        let check = ChildCanisterCheck {
+            canister_id: Principal::anonymous(),
+            running: true,
+            module_hash: Some(vec![1; 32]),
+            cycles: MIN_BLACKHOLE_CYCLES,
+        };
+        assert!(verify(&check).is_ok());
+        assert!(verify(&ChildCanisterCheck {
+            running: false,
+            ..check.clone()
+        })
+        .is_err());
+        assert!(verify(&ChildCanisterCheck {
+            module_hash: None,
+            ..check.clone()
+        })
+        .is_err());
+        assert!(verify(&ChildCanisterCheck {
+            cycles: MIN_BLACKHOLE_CYCLES - 1,
+            ..check
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_confirmable_proposal() {
+        DodService::new(60_000_000_000, 10, 1_000, None, vec![], None, None);
+        let proposal = BlackholeProposal {
+            proposed_by: Principal::anonymous(),
+            proposed_at: 0,
+            expires_at: 10,
+            checks: vec![],
+            blackholed: None,
+        };
+        set_proposal(Some(proposal.clone())).unwrap();
+        assert!(confirmable_proposal(10).is_ok());
+        // confirming does not consume it
+        assert!(get_proposal().is_some());
+
+        // once a controller is removed the proposal no longer expires, nor can be cancelled
+        let ledger = Principal::from_slice(&[1; 29]);
+        record_step(ledger).unwrap();
+        record_step(ledger).unwrap();
+        assert_eq!(blackholed(&confirmable_proposal(11).unwrap()), vec![ledger]);
+        assert!(cancel(Principal::anonymous()).is_err());
+
+        set_proposal(Some(proposal)).unwrap();
+        assert!(confirmable_proposal(11).is_err());
+        assert!(get_proposal().is_none());
+    }
+}
@@ -1,6 +1,11 @@
 use crate::memory::BLOCKS;
-use crate::service::config::get_difficulty_adjust_epoch;
-use dod_utils::types::{BlockData, Height};
+use crate::service::config::{self, get_difficulty_adjust_epoch};
+use crate::service::{block_archive, miner};
+use dod_utils::tcycles::to_tcycles;
+use dod_utils::types::{
+    BlockBundle, BlockData, BlockEfficiency, BlockFilter, BlockRange, BlockStatus,
+    CandidateSummary, EfficiencyStats, Height,
+};
 
 pub fn get_last_block() -> Option<(u64, BlockData)> {
     BLOCKS.with_borrow(|b| b.last_key_value())
@@ -10,19 +15,138 @@ pub fn get_block_by_height(height: u64) -> Option<BlockData> {
     BLOCKS.with(|v| v.borrow().get(&height).map(|v| v.clone()))
 }
 
-pub fn get_blocks() -> Vec<BlockData> {
+/// The message the genesis was initialized with, kept beside block 0 rather than on it since
+/// blocks are stored with a bounded size.
+pub fn block_message(height: Height) -> Option<String> {
+    if height != 0 {
+        return None;
+    }
+    config::get_genesis_info().and_then(|genesis| genesis.message)
+}
+
+pub const MAX_BLOCKS_PAGE_LIMIT: u64 = 100;
+pub const MAX_BLOCK_BUNDLES: usize = 50;
+
+fn block_bundle(block: BlockData, last_height: Option<Height>) -> BlockBundle {
+    let sigs = block
+        .winner
+        .as_ref()
+        .and_then(|_| miner::load_sigs_by_height(block.height));
+    let candidates = if last_height.map_or(false, |last| block.height < last) {
+        miner::get_block_candidates(block.height)
+            .into_iter()
+            .map(|c| CandidateSummary {
+                btc_address: c.btc_address,
+                submit_time: c.submit_time,
+                cycles_price: c.cycles_price,
+                cycles_price_t: to_tcycles(c.cycles_price),
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+    BlockBundle {
+        message: block_message(block.height),
+        block,
+        sigs,
+        candidates,
+    }
+}
+
+pub fn get_block_bundle(height: Height) -> Option<BlockBundle> {
+    let last_height = get_last_block().map(|(h, _)| h);
+    get_block_by_height(height).map(|block| block_bundle(block, last_height))
+}
+
+/// Bundles of the existing blocks among `heights`, in the order asked. Archived heights are
+/// rejected.
+pub fn get_block_bundles(heights: Vec<Height>) -> Result<Vec<BlockBundle>, String> {
+    if heights.len() > MAX_BLOCK_BUNDLES {
+        return Err(format!(
+            "At most {} blocks can be bundled at once",
+            MAX_BLOCK_BUNDLES
+        ));
+    }
+    for height in heights.iter() {
+        block_archive::check_kept(*height)?;
+    }
+    let last_height = get_last_block().map(|(h, _)| h);
+    Ok(heights
+        .into_iter()
+        .filter_map(get_block_by_height)
+        .map(|block| block_bundle(block, last_height))
+        .collect())
+}
+
+/// Blocks kept here matching `filter`, from the first kept block when `filter.from` is unset.
+pub fn get_blocks_filtered(filter: BlockFilter, page: u64, limit: u64) -> Vec<BlockData> {
+    let limit = limit.min(MAX_BLOCKS_PAGE_LIMIT);
+    let from = filter.from.unwrap_or_else(block_archive::kept_from);
+    let to = filter.to.unwrap_or(Height::MAX);
+    if from > to {
+        return vec![];
+    }
     BLOCKS.with(|v| {
         v.borrow()
-            .iter()
-            .map(|(_, v)| v.clone())
+            .range(from..=to)
+            .map(|(_, v)| v)
+            .filter(|b| filter.winner.map_or(true, |w| b.winner.is_some() == w))
+            .filter(|b| {
+                filter
+                    .min_cycle_burned
+                    .map_or(true, |m| b.cycle_burned >= m)
+            })
+            .skip(page.saturating_mul(limit) as usize)
+            .take(limit as usize)
             .collect::<Vec<BlockData>>()
     })
 }
 
-pub fn get_blocks_range(from: Height, to: Height) -> Vec<BlockData> {
+pub const MAX_EFFICIENCY_WINDOW: u64 = 1000;
+
+fn dod_per_cycle(rewards: u64, cycle_burned: u128) -> Option<f64> {
+    if cycle_burned == 0 {
+        None
+    } else {
+        Some(rewards as f64 / cycle_burned as f64)
+    }
+}
+
+/// Only finalized blocks are counted, `cycle_burned` is written when a block is finalized.
+pub fn get_efficiency_stats(window_blocks: u64) -> EfficiencyStats {
+    let window_blocks = window_blocks.min(MAX_EFFICIENCY_WINDOW);
+    let last = get_last_block().map_or(0, |(height, _)| height);
+    let blocks = BLOCKS.with(|v| {
+        v.borrow()
+            .range(last.saturating_sub(window_blocks)..=last)
+            .map(|(_, v)| v)
+            .filter(|b| b.status() == BlockStatus::Finalized)
+            .map(|b| BlockEfficiency {
+                height: b.height,
+                rewards: b.rewards,
+                cycle_burned: b.cycle_burned,
+                dod_per_cycle: dod_per_cycle(b.rewards, b.cycle_burned),
+            })
+            .collect::<Vec<BlockEfficiency>>()
+    });
+    let total_rewards = blocks
+        .iter()
+        .fold(0u64, |acc, b| acc.saturating_add(b.rewards));
+    let total_cycle_burned = blocks
+        .iter()
+        .fold(0u128, |acc, b| acc.saturating_add(b.cycle_burned));
+    EfficiencyStats {
+        blocks,
+        total_rewards,
+        total_cycle_burned,
+        dod_per_cycle: dod_per_cycle(total_rewards, total_cycle_burned),
+    }
+}
+
+pub fn get_blocks_range(range: BlockRange) -> Vec<BlockData> {
     BLOCKS.with(|v| {
         v.borrow()
-            .range(from.clone()..=to.clone())
+            .range(range.blocks())
             .map(|(_, v)| v.clone())
             .collect::<Vec<BlockData>>()
     })
@@ -0,0 +1,327 @@
+use crate::common::now;
+use crate::log_warn;
+use crate::memory::{BLOCKS, BLOCK_ARCHIVING};
+use crate::service::block::{self, get_last_block};
+use crate::service::config;
+use candid::Principal;
+use dod_utils::types::{BlockArchive, BlockData, BlockRange, BlockStatus, Height};
+use ic_cdk::api::call::RejectionCode;
+use ic_stable_structures::Storable;
+
+/// Blocks moved to the archive canister at once, well under its `MAX_BLOCKS_PER_CALL`.
+pub const MAX_ARCHIVE_BATCH: u64 = 200;
+/// Fewest blocks kept here, settlement, corrections and difficulty look back at recent blocks.
+pub const MIN_RETAIN_BLOCKS: u64 = 1_000;
+
+/// Blocks from `archived_to` that are more than `retain_blocks` below `last_height`, at most
+/// `MAX_ARCHIVE_BATCH` of them, `None` when there is nothing to move.
+pub fn archivable(
+    archived_to: Height,
+    last_height: Height,
+    retain_blocks: u64,
+) -> Option<BlockRange> {
+    BlockRange::new(archived_to, last_height.saturating_sub(retain_blocks))
+        .ok()
+        .filter(|range| !range.is_empty())
+        .map(|range| range.limit(MAX_ARCHIVE_BATCH))
+}
+
+/// `range` split into the blocks below `archived_to` and the ones from it on.
+pub fn split_range(
+    range: &BlockRange,
+    archived_to: Height,
+) -> (Option<BlockRange>, Option<BlockRange>) {
+    let archived = BlockRange::new(0, archived_to)
+        .ok()
+        .and_then(|r| r.intersect(range));
+    let kept = BlockRange::new(archived_to, Height::MAX)
+        .ok()
+        .and_then(|r| r.intersect(range));
+    (archived, kept)
+}
+
+fn rejected((code, msg): (RejectionCode, String)) -> String {
+    format!("Unable to call the block archive: {:?} {}", code, msg)
+}
+
+pub fn set_block_archive(canister: Principal, retain_blocks: u64) -> Result<BlockArchive, String> {
+    let min_retain = MIN_RETAIN_BLOCKS.max(config::get_difficulty_adjust_epoch()?);
+    if retain_blocks < min_retain {
+        return Err(format!("At least {} blocks must be kept", min_retain));
+    }
+    let current = config::get_block_archive();
+    if let Some(current) = current
+        .as_ref()
+        .filter(|a| a.archived_to > 0 && a.canister != canister)
+    {
+        return Err(format!(
+            "Blocks are archived to {} already",
+            current.canister
+        ));
+    }
+    let archive = BlockArchive {
+        canister,
+        retain_blocks,
+        archived_to: current.as_ref().map_or(0, |a| a.archived_to),
+        last_archived_at: current.as_ref().and_then(|a| a.last_archived_at),
+        last_error: None,
+    };
+    config::set_block_archive(archive.clone())?;
+    Ok(archive)
+}
+
+/// Leading blocks of `sent` still stored here as they were sent, the ones safe to drop.
+fn unchanged_prefix(sent: &[BlockData], stored: impl Fn(Height) -> Option<BlockData>) -> usize {
+    sent.iter()
+        .take_while(|b| stored(b.height).map_or(false, |s| s.to_bytes() == b.to_bytes()))
+        .count()
+}
+
+/// Archived blocks from `from` on, as many as follow one another without a gap.
+fn contiguous(from: Height, page: Vec<BlockData>) -> Vec<BlockData> {
+    page.into_iter()
+        .zip(from..)
+        .take_while(|(b, height)| b.height == *height)
+        .map(|(b, _)| b)
+        .collect()
+}
+
+/// Sends the next blocks old enough to the archive canister and drops them here once it
+/// stored them. Stops before a block that is not settled yet. A block changed while it was
+/// sent, a broadcast confirmed or a winner invalidated, stays here with the ones after it and
+/// is sent again, the archive canister overwrites the copy it got.
+pub async fn archive_blocks() -> Result<u64, String> {
+    let archive =
+        config::get_block_archive().ok_or_else(|| "No block archive is set".to_string())?;
+    let Some((last_height, _)) = get_last_block() else {
+        return Ok(0);
+    };
+    let Some(range) = archivable(archive.archived_to, last_height, archive.retain_blocks) else {
+        return Ok(0);
+    };
+    let blocks: Vec<BlockData> = block::get_blocks_range(range)
+        .into_iter()
+        .take_while(|b| {
+            !matches!(
+                b.status,
+                Some(BlockStatus::Open) | Some(BlockStatus::Closing)
+            )
+        })
+        .collect();
+    let Some(last) = blocks.last().map(|b| b.height) else {
+        return Ok(0);
+    };
+    if blocks[0].height != archive.archived_to {
+        return Err(format!("Block {} is missing", archive.archived_to));
+    }
+    if BLOCK_ARCHIVING.with_borrow(|archiving| *archiving) {
+        return Ok(0);
+    }
+    BLOCK_ARCHIVING.with_borrow_mut(|archiving| *archiving = true);
+    let res = ic_cdk::call::<(Vec<BlockData>,), (Result<Height, String>,)>(
+        archive.canister,
+        "append_blocks",
+        (blocks.clone(),),
+    )
+    .await
+    .map_err(rejected)
+    .and_then(|(res,)| res);
+    BLOCK_ARCHIVING.with_borrow_mut(|archiving| *archiving = false);
+
+    let mut current =
+        config::get_block_archive().ok_or_else(|| "No block archive is set".to_string())?;
+    if current.canister != archive.canister {
+        return Err("The block archive changed while blocks were sent".to_string());
+    }
+    match res {
+        Ok(_) => {
+            let moved = BLOCKS.with_borrow_mut(|v| {
+                let moved = unchanged_prefix(blocks.as_slice(), |height| v.get(&height));
+                for b in blocks.iter().take(moved) {
+                    v.remove(&b.height);
+                }
+                moved as u64
+            });
+            if moved < blocks.len() as u64 {
+                log_warn!(
+                    "block archive: block {} changed while it was sent, it is sent again later",
+                    archive.archived_to + moved
+                );
+            }
+            current.archived_to = archive.archived_to + moved;
+            current.last_archived_at = Some(now());
+            current.last_error = None;
+            config::set_block_archive(current)?;
+            Ok(moved)
+        }
+        Err(e) => {
+            log_warn!(
+                "block archive: moving blocks {} to {} failed: {}",
+                archive.archived_to,
+                last,
+                e
+            );
+            current.last_error = Some(e.clone());
+            config::set_block_archive(current)?;
+            Err(e)
+        }
+    }
+}
+
+/// Moves the next blocks in the background when an archive is set.
+pub fn maybe_archive() {
+    if config::get_block_archive().is_some() {
+        ic_cdk::spawn(async {
+            let _ = archive_blocks().await;
+        });
+    }
+}
+
+fn archive_canister_of(height: Height) -> Option<Principal> {
+    config::get_block_archive()
+        .filter(|a| height < a.archived_to)
+        .map(|a| a.canister)
+}
+
+/// The first block still kept here, the ones below it are in the archive canister.
+pub fn kept_from() -> Height {
+    config::get_block_archive().map_or(0, |a| a.archived_to)
+}
+
+/// Errors for a block moved to the archive canister, which only the composite reads
+/// `get_block_by_height` and `get_blocks_range` here serve.
+pub fn check_kept(height: Height) -> Result<(), String> {
+    match archive_canister_of(height) {
+        Some(canister) => Err(format!(
+            "Block {} is archived to {}, read it with get_block_by_height or get_blocks_range_v2",
+            height, canister
+        )),
+        None => Ok(()),
+    }
+}
+
+pub async fn get_block_by_height(height: Height) -> Result<Option<BlockData>, String> {
+    match archive_canister_of(height) {
+        Some(canister) => ic_cdk::call::<(Height,), (Option<BlockData>,)>(
+            canister,
+            "get_block_by_height",
+            (height,),
+        )
+        .await
+        .map(|(block,)| block)
+        .map_err(rejected),
+        None => Ok(block::get_block_by_height(height)),
+    }
+}
+
+/// Blocks of `range` in height order, the archived ones read from the archive canister a page
+/// of at most its `MAX_BLOCKS_PER_CALL` at a time. The blocks end before the first archived
+/// height the archive canister does not return, so they never skip one.
+pub async fn get_blocks_range(range: BlockRange) -> Result<Vec<BlockData>, String> {
+    let Some(archive) = config::get_block_archive() else {
+        return Ok(block::get_blocks_range(range));
+    };
+    let (archived, kept) = split_range(&range, archive.archived_to);
+    let mut blocks = vec![];
+    if let Some(archived) = archived {
+        let mut next = archived.start();
+        while let Some(to) = archived.last().filter(|to| next <= *to) {
+            let page = ic_cdk::call::<(Height, Height), (Result<Vec<BlockData>, String>,)>(
+                archive.canister,
+                "get_blocks_range",
+                (next, to),
+            )
+            .await
+            .map_err(rejected)
+            .and_then(|(res,)| res)?;
+            let page = contiguous(next, page);
+            let Some(last) = page.last().map(|b| b.height) else {
+                return Ok(blocks);
+            };
+            blocks.extend(page);
+            next = last + 1;
+        }
+    }
+    if let Some(kept) = kept {
+        blocks.extend(block::get_blocks_range(kept));
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dod_utils::bitwork::Bitwork;
+
+    fn block(height: Height) -> BlockData {
+        BlockData {
+            height,
+            rewards: 0,
+            winner: None,
+            difficulty: Bitwork {
+                pre: 0,
+                post_hex: "0".to_string(),
+            },
+            hash: vec![],
+            block_time: 0,
+            next_block_time: 0,
+            history: false,
+            cycle_burned: 0,
+            dod_burned: 0,
+            status: None,
+            broadcast: None,
+            submission_cutoff: None,
+        }
+    }
+
+    #[test]
+    fn test_unchanged_prefix() {
+        let sent = vec![block(5), block(6), block(7)];
+        assert_eq!(unchanged_prefix(&sent, |height| Some(block(height))), 3);
+        // block 6 was settled again while it was sent
+        let stored = |height| {
+            let mut b = block(height);
+            if height == 6 {
+                b.dod_burned = 1;
+            }
+            Some(b)
+        };
+        assert_eq!(unchanged_prefix(&sent, stored), 1);
+        assert_eq!(unchanged_prefix(&sent, |_| None), 0);
+    }
+
+    #[test]
+    fn test_contiguous() {
+        let heights = |blocks: Vec<BlockData>| blocks.iter().map(|b| b.height).collect::<Vec<_>>();
+        assert_eq!(
+            heights(contiguous(5, vec![block(5), block(6), block(7)])),
+            vec![5, 6, 7]
+        );
+        assert_eq!(
+            heights(contiguous(5, vec![block(5), block(7), block(8)])),
+            vec![5]
+        );
+        assert!(contiguous(5, vec![block(6)]).is_empty());
+        assert!(contiguous(5, vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_archivable() {
+        let range = archivable(0, 5_000, 1_000).unwrap();
+        assert_eq!((range.start(), range.end()), (0, MAX_ARCHIVE_BATCH));
+        let range = archivable(3_900, 5_000, 1_000).unwrap();
+        assert_eq!((range.start(), range.end()), (3_900, 4_000));
+        assert!(archivable(4_000, 5_000, 1_000).is_none());
+        assert!(archivable(0, 500, 1_000).is_none());
+    }
+
+    #[test]
+    fn test_split_range() {
+        let range = BlockRange::inclusive(10, 30).unwrap();
+        let (archived, kept) = split_range(&range, 20);
+        assert_eq!(archived.map(|r| (r.start(), r.end())), Some((10, 20)));
+        assert_eq!(kept.map(|r| (r.start(), r.end())), Some((20, 31)));
+        assert!(split_range(&range, 0).0.is_none());
+        assert!(split_range(&range, 40).1.is_none());
+    }
+}
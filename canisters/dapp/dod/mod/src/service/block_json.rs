@@ -0,0 +1,130 @@
+use crate::service::{block, block_archive, ledger_links, miner, priority};
+use dod_utils::block_hash::BlockHash;
+use dod_utils::types::{
+    BlockData, BlockLedgerLinks, Height, MinerInfo, PriorityStats, WinnerEnvelope,
+    WinnerPaymentProof,
+};
+use serde_json::{json, Value};
+
+// integers are rendered as decimal strings, cycles do not fit the numbers of most JSON parsers
+fn int(v: impl ToString) -> Value {
+    Value::String(v.to_string())
+}
+
+fn miner_json(miner: &MinerInfo) -> Value {
+    json!({
+        "owner": miner.owner.to_text(),
+        "btc_address": miner.btc_address,
+        "ecdsa_pubkey": hex::encode(&miner.ecdsa_pubkey),
+        "reward_cycles": miner.reward_cycles.map(int),
+    })
+}
+
+fn block_data_json(block: &BlockData, message: Option<&str>) -> Value {
+    json!({
+        "height": int(block.height),
+        "message": message,
+        "rewards": int(block.rewards),
+        "winner": block.winner.as_ref().map(miner_json),
+        "difficulty": {
+            "pre": int(block.difficulty.pre),
+            "post_hex": block.difficulty.post_hex,
+        },
+        "hash": BlockHash::from(block).to_display_hex(),
+        "block_time": int(block.block_time),
+        "next_block_time": int(block.next_block_time),
+        "history": block.history,
+        "cycle_burned": int(block.cycle_burned),
+        "dod_burned": int(block.dod_burned),
+        "status": format!("{:?}", block.status()),
+        "broadcast": block.broadcast.as_ref().map(|b| format!("{:?}", b)),
+        "submission_cutoff": int(block.submission_cutoff()),
+    })
+}
+
+/// Canonical JSON of a block and what its settlement produced: keys sorted, no whitespace,
+/// integers as decimal strings and bytes as lowercase hex, so equal data hashes equally.
+pub fn render(
+    block: &BlockData,
+    priority: Option<&PriorityStats>,
+    payment: Option<&WinnerPaymentProof>,
+    ledger: Option<&BlockLedgerLinks>,
+    envelope: Option<&WinnerEnvelope>,
+    message: Option<&str>,
+) -> String {
+    json!({
+        "block": block_data_json(block, message),
+        "settlement": {
+            "settled_orders": priority.map(|p| int(p.settled_orders)),
+            "prioritized_orders": priority.map(|p| int(p.prioritized_orders)),
+            "priority_fees": priority.map(|p| int(p.total_fees)),
+            "missed_orders": priority.and_then(|p| p.missed_orders).map(int),
+            "winner_cycles_credited": payment.map(|p| int(p.cycles_credited)),
+            "winner_paid_event_id": payment.and_then(|p| p.event_id).map(int),
+            "mint_ledger_index": ledger.and_then(|l| l.mint_index).map(int),
+            "burn_ledger_index": ledger.and_then(|l| l.burn_index).map(int),
+        },
+        "winner_envelope": envelope.map(|e| json!({
+            "reveal_txid": e.reveal_txid,
+            "name": e.name,
+            "time": e.time.map(int),
+            "nonce": e.nonce.map(int),
+            "stakers": e.stakers,
+        })),
+    })
+    .to_string()
+}
+
+pub fn get_block_json(height: Height) -> Result<String, String> {
+    block_archive::check_kept(height)?;
+    let block =
+        block::get_block_by_height(height).ok_or_else(|| format!("Block {} not found", height))?;
+    Ok(render(
+        &block,
+        priority::get_priority_stats(height).as_ref(),
+        miner::get_winner_payment_proof(height).as_ref(),
+        ledger_links::get_block_ledger_links(height).as_ref(),
+        miner::get_winner_envelope(height).as_ref(),
+        block::block_message(height).as_deref(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+    use dod_utils::bitwork::Bitwork;
+    use dod_utils::types::{BlockData, BlockStatus};
+
+    #[test]
+    fn test_render_is_canonical() {
+        let block = BlockData {
+            height: 7,
+            rewards: 1000,
+            winner: None,
+            difficulty: Bitwork {
+                pre: 5,
+                post_hex: "a".to_string(),
+            },
+            hash: vec![0xab, 0x01],
+            block_time: 1,
+            next_block_time: 2,
+            history: false,
+            cycle_burned: u128::MAX,
+            dod_burned: 0,
+            status: Some(BlockStatus::Finalized),
+            broadcast: None,
+            submission_cutoff: None,
+        };
+        let json = render(&block, None, None, None, None, None);
+        assert!(json.starts_with(
+            r#"{"block":{"block_time":"1","broadcast":null,"cycle_burned":"340282366920938463463374607431768211455","difficulty":{"post_hex":"a","pre":"5"},"dod_burned":"0","hash":"ab01","height":"7","#
+        ));
+        assert!(json.contains(r#""status":"Finalized","submission_cutoff":"2","winner":null}"#));
+        assert!(json.ends_with(r#""winner_envelope":null}"#));
+        assert!(json.contains(r#""history":false,"message":null,"#));
+        assert_eq!(json, render(&block, None, None, None, None, None));
+        assert!(
+            render(&block, None, None, None, None, Some("hello")).contains(r#""message":"hello""#)
+        );
+    }
+}
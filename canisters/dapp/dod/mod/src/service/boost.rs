@@ -0,0 +1,64 @@
+use crate::memory::CONFIG;
+use crate::service::DodService;
+use dod_utils::types::{Height, RewardBoost};
+
+pub fn get_reward_boost() -> Option<RewardBoost> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.reward_boost.clone())
+    })
+}
+
+pub fn set_reward_boost(boost: Option<RewardBoost>) -> Result<(), String> {
+    if let Some(boost) = boost.as_ref() {
+        boost.validate()?;
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.reward_boost = boost;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+/// `reward` of block `height` with the boost for its deposits. The deposits of a block no
+/// longer change once it is closed, so the mint and the settlement see the same reward.
+pub fn apply(height: Height, reward: u64) -> u64 {
+    match get_reward_boost() {
+        None => reward,
+        Some(boost) => boost.apply(reward, DodService::get_block_total_cycles(height, false)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dod_utils::types::RewardBoost;
+
+    #[test]
+    fn test_reward_boost() {
+        let boost = RewardBoost {
+            threshold_cycles: 1_000,
+            max_boost_percent: 100,
+        };
+        assert_eq!(boost.apply(1_000, 0), 2_000);
+        assert_eq!(boost.apply(1_000, 500), 1_500);
+        assert_eq!(boost.apply(1_000, 999), 1_000);
+        assert_eq!(boost.apply(1_000, 1_000), 1_000);
+        assert_eq!(boost.apply(1_000, 5_000), 1_000);
+        assert!(boost.validate().is_ok());
+        assert!(RewardBoost {
+            threshold_cycles: 0,
+            max_boost_percent: 100
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::memory::{BLOCKS, BROADCASTS, SIGS};
+use crate::service::event::add_event;
+use crate::service::replication;
+use candid::Principal;
+use dod_utils::types::{
+    BroadcastInfo, BroadcastStatus, EventKind, Height, StateChange, UnbroadcastWinsPage,
+};
+
+/// Stored winner sigs visited per page at most.
+pub const MAX_UNBROADCAST_WINS_SCAN: u64 = 500;
+
+fn check_txid(txid: &str) -> Result<(), String> {
+    if txid.len() != 64 || !txid.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("Txid must be 64 hex characters".to_string());
+    }
+    Ok(())
+}
+
+fn write_broadcast(info: BroadcastInfo, status: BroadcastStatus) -> Result<(), String> {
+    let mut block = BLOCKS
+        .with_borrow(|v| v.get(&info.height))
+        .ok_or_else(|| "Block not found".to_string())?;
+    block.broadcast = Some(status);
+    BLOCKS.with_borrow_mut(|v| v.insert(block.height, block.clone()));
+    replication::record(StateChange::BlockWritten(block));
+    BROADCASTS.with_borrow_mut(|v| v.insert(info.height, info));
+    Ok(())
+}
+
+/// Called by the winner after broadcasting the reveal transaction of a won block.
+pub fn report_broadcast(caller: Principal, height: Height, txid: String) -> Result<(), String> {
+    check_txid(txid.as_str())?;
+    let block = BLOCKS
+        .with_borrow(|v| v.get(&height))
+        .ok_or_else(|| "Block not found".to_string())?;
+    let winner = block
+        .winner
+        .as_ref()
+        .ok_or_else(|| "Block has no winner".to_string())?;
+    if winner.owner != caller {
+        return Err("Caller is not the winner of this block".to_string());
+    }
+    if block.broadcast == Some(BroadcastStatus::Confirmed) {
+        return Err("Broadcast already confirmed".to_string());
+    }
+    write_broadcast(
+        BroadcastInfo {
+            height,
+            txid,
+            reported_at: ic_cdk::api::time(),
+            confirmed_at: None,
+        },
+        BroadcastStatus::Reported,
+    )
+}
+
+/// Marks the broadcast of a won block as confirmed on Bitcoin. If the winner reported a txid,
+/// it must match the confirmed one.
+pub fn confirm_broadcast(caller: Principal, height: Height, txid: String) -> Result<(), String> {
+    check_txid(txid.as_str())?;
+    let now = ic_cdk::api::time();
+    let info = match BROADCASTS.with_borrow(|v| v.get(&height)) {
+        Some(info) if info.txid != txid => {
+            return Err(format!("Reported txid {} does not match", info.txid));
+        }
+        Some(info) => BroadcastInfo {
+            confirmed_at: Some(now),
+            ..info
+        },
+        None => BroadcastInfo {
+            height,
+            txid: txid.clone(),
+            reported_at: now,
+            confirmed_at: Some(now),
+        },
+    };
+    write_broadcast(info, BroadcastStatus::Confirmed)?;
+    add_event(caller, EventKind::BroadcastConfirmed { height, txid });
+    Ok(())
+}
+
+pub fn get_broadcast_info(height: Height) -> Option<BroadcastInfo> {
+    BROADCASTS.with_borrow(|v| v.get(&height))
+}
+
+/// Heights won by `btc_address` without a reported broadcast, from height `from` on. Only
+/// heights with stored winner sigs are visited, at most `limit` of them per page.
+pub fn get_unbroadcast_wins(btc_address: String, from: Height, limit: u64) -> UnbroadcastWinsPage {
+    let limit = limit.clamp(1, MAX_UNBROADCAST_WINS_SCAN) as usize;
+    let mut heights = SIGS.with_borrow(|v| {
+        v.range(from..)
+            .map(|(k, _)| k)
+            .take(limit + 1)
+            .collect::<Vec<Height>>()
+    });
+    let next = if heights.len() > limit {
+        heights.pop()
+    } else {
+        None
+    };
+    let heights = BLOCKS.with_borrow(|v| {
+        heights
+            .into_iter()
+            .filter(|height| {
+                v.get(height).map_or(false, |block| {
+                    block.broadcast.is_none()
+                        && block
+                            .winner
+                            .as_ref()
+                            .map_or(false, |w| w.btc_address == btc_address)
+                })
+            })
+            .collect()
+    });
+    UnbroadcastWinsPage { heights, next }
+}
@@ -0,0 +1,29 @@
+use crate::memory::UPGRADE_HISTORY;
+use crate::service::block::get_last_block;
+use bitcoin::hashes::{sha256, Hash};
+use dod_utils::types::{BuildInfo, UpgradeRecord};
+
+/// Appends the running build to the upgrade history, called from `post_upgrade`.
+pub fn record_upgrade(version: &str, git_commit: &str) {
+    UPGRADE_HISTORY.with_borrow_mut(|v| {
+        let id = v.last_key_value().map_or(0, |(k, _)| k + 1);
+        v.insert(
+            id,
+            UpgradeRecord {
+                time: ic_cdk::api::time(),
+                height: get_last_block().map(|(height, _)| height),
+                version: version.to_string(),
+                git_commit: git_commit.to_string(),
+            },
+        );
+    })
+}
+
+pub fn get_build_info(version: &str, git_commit: &str, candid: &str) -> BuildInfo {
+    BuildInfo {
+        version: version.to_string(),
+        git_commit: git_commit.to_string(),
+        candid_hash: hex::encode(sha256::Hash::hash(candid.as_bytes()).to_byte_array()),
+        upgrades: UPGRADE_HISTORY.with_borrow(|v| v.iter().map(|(_, r)| r).collect()),
+    }
+}
@@ -0,0 +1,28 @@
+use crate::common::now;
+use crate::memory::BURN_RECEIPTS;
+use dod_utils::types::{BlockRange, BurnReceipt, Height};
+
+pub const MAX_BURN_RECEIPTS_PER_CALL: u64 = 100;
+
+/// Keeps the cycles burned for block `height`, with the canister balance around the burn.
+pub fn record(height: Height, burned: u128, balance_before: u128, balance_after: u128) {
+    BURN_RECEIPTS.with_borrow_mut(|v| {
+        v.insert(
+            height,
+            BurnReceipt {
+                height,
+                burned,
+                balance_before,
+                balance_after,
+                timestamp: now(),
+            },
+        )
+    });
+}
+
+/// Receipts of the blocks in `range`, at most `MAX_BURN_RECEIPTS_PER_CALL` of them. Blocks
+/// that burned nothing have none.
+pub fn get_burn_receipts(range: BlockRange) -> Vec<BurnReceipt> {
+    let range = range.limit(MAX_BURN_RECEIPTS_PER_CALL);
+    BURN_RECEIPTS.with_borrow(|v| v.range(range.blocks()).map(|(_, r)| r).collect())
+}
@@ -0,0 +1,329 @@
+use crate::common::{now, MEMO_BURN_DOD};
+use crate::memory::{CONFIG, MINERS, STAKERS};
+use crate::service::config::{get_dod_block_account, get_token_canister};
+use crate::service::event::add_event;
+use crate::service::{ledger_queue, DodService};
+use crate::types::LedgerOp;
+use candid::{Nat, Principal};
+use dod_utils::types::{BuybackPolicy, BuybackSettings, EventKind};
+use ic_cdk::api::call::RejectionCode;
+use ic_cdk::id;
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{NumTokens, TransferArg, TransferError};
+
+pub fn get_buyback_settings() -> Option<BuybackSettings> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.buyback_settings.clone())
+    })
+}
+
+pub fn set_buyback_settings(
+    caller: Principal,
+    settings: Option<BuybackSettings>,
+) -> Result<(), String> {
+    if let Some(settings) = settings.as_ref() {
+        if settings.interval == 0 {
+            return Err("Buyback interval can not be zero".to_string());
+        }
+        match settings.policy {
+            BuybackPolicy::FixedAmount(0) => {
+                return Err("Buyback amount can not be zero".to_string());
+            }
+            BuybackPolicy::TreasuryPercentage(p) if p == 0 || p > 100 => {
+                return Err("Buyback percentage must be between 1 and 100".to_string());
+            }
+            _ => {}
+        }
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.buyback_settings = settings.clone();
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })?;
+    add_event(caller, EventKind::BuybackConfigured { settings });
+    Ok(())
+}
+
+pub fn get_total_buyback_burned() -> u64 {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.total_buyback_burned)
+            .unwrap_or(0)
+    })
+}
+
+pub fn get_last_buyback_at() -> Option<u64> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.last_buyback_at)
+    })
+}
+
+/// Amount to burn for one epoch. `available` is the treasury balance that is not
+/// owed to stakers or miners, so a burn can never eat into unclaimed rewards.
+pub fn buyback_amount(policy: &BuybackPolicy, available: u64) -> u64 {
+    match policy {
+        BuybackPolicy::FixedAmount(amount) => (*amount).min(available),
+        BuybackPolicy::TreasuryPercentage(percentage) => {
+            (available as u128 * (*percentage).min(100) as u128 / 100) as u64
+        }
+    }
+}
+
+/// Sum of rewards credited to stakers but not claimed yet, walking every staker.
+fn scan_staker_unclaimed() -> u64 {
+    STAKERS.with_borrow(|v| {
+        v.iter().fold(0u64, |acc, (_, user)| {
+            acc.saturating_add(user.total_dod.saturating_sub(user.claimed_dod))
+        })
+    })
+}
+
+/// Sum of rewards credited to miners but not claimed yet, walking every miner.
+fn scan_miner_unclaimed() -> u64 {
+    MINERS.with_borrow(|v| {
+        v.iter().fold(0u64, |acc, (_, miner)| {
+            acc.saturating_add(miner.total_dod.saturating_sub(miner.claimed_dod))
+        })
+    })
+}
+
+/// Running total of unclaimed DOD, seeded once from `scan` when it is not kept yet.
+fn running_total(field: fn(&mut DodService) -> &mut Option<u64>, scan: fn() -> u64) -> u64 {
+    CONFIG.with(|config| {
+        let mut config = config.borrow_mut();
+        match config.dod_service.as_mut() {
+            Some(dod_service) => *field(dod_service).get_or_insert_with(scan),
+            None => scan(),
+        }
+    })
+}
+
+/// Moves a running total from `before` to `after`. A total not kept yet is left alone, the
+/// scan seeding it counts the write already.
+fn track(field: fn(&mut DodService) -> &mut Option<u64>, before: u64, after: u64) {
+    CONFIG.with(|config| {
+        if let Some(total) = config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .and_then(|dod_service| field(dod_service).as_mut())
+        {
+            *total = total.saturating_sub(before).saturating_add(after);
+        }
+    })
+}
+
+fn staker_unclaimed_field(dod_service: &mut DodService) -> &mut Option<u64> {
+    &mut dod_service.staker_unclaimed
+}
+
+fn miner_unclaimed_field(dod_service: &mut DodService) -> &mut Option<u64> {
+    &mut dod_service.miner_unclaimed
+}
+
+/// Sum of rewards credited to stakers but not claimed yet.
+pub fn staker_unclaimed() -> u64 {
+    running_total(staker_unclaimed_field, scan_staker_unclaimed)
+}
+
+/// Sum of rewards credited to miners but not claimed yet.
+pub fn miner_unclaimed() -> u64 {
+    running_total(miner_unclaimed_field, scan_miner_unclaimed)
+}
+
+/// Keeps the unclaimed staker total in step with a write to a staker, `before` and `after`
+/// being its unclaimed DOD around the write.
+pub fn track_staker_unclaimed(before: u64, after: u64) {
+    track(staker_unclaimed_field, before, after);
+}
+
+/// Keeps the unclaimed miner total in step with a write to a miner, `before` and `after`
+/// being its unclaimed DOD around the write.
+pub fn track_miner_unclaimed(before: u64, after: u64) {
+    track(miner_unclaimed_field, before, after);
+}
+
+/// Drops the running totals, the next read seeds them again.
+pub fn reset_unclaimed() {
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.staker_unclaimed = None;
+            dod_service.miner_unclaimed = None;
+        }
+    });
+}
+
+/// Sum of rewards credited to stakers and miners but not claimed yet.
+pub fn unclaimed_rewards() -> u64 {
+    staker_unclaimed().saturating_add(miner_unclaimed())
+}
+
+/// Called once the ledger queue drained. Queues a burn when the configured epoch has elapsed,
+/// so it reads the treasury with every mint and burn of the settled blocks applied and no
+/// other ledger operation runs alongside it.
+///
+/// The epoch is marked as consumed before the ledger calls, so a failed burn waits for
+/// the next epoch instead of being retried on every block.
+pub fn maybe_run_buyback() {
+    let settings = match get_buyback_settings() {
+        None => return,
+        Some(settings) => settings,
+    };
+    let now = now();
+    if let Some(last) = get_last_buyback_at() {
+        if now < last.saturating_add(settings.interval) {
+            return;
+        }
+    }
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.last_buyback_at = Some(now);
+        }
+    });
+    ledger_queue::enqueue(LedgerOp::Buyback);
+}
+
+/// Burns the treasury surplus of one epoch, sent from the ledger queue.
+pub async fn run_buyback() -> Result<(), String> {
+    // switched off since it was queued
+    let policy = match get_buyback_settings() {
+        None => return Ok(()),
+        Some(settings) => settings.policy,
+    };
+    let token_canister = get_token_canister()?;
+    let treasury = get_dod_block_account()?;
+    // read before the balance, a claim paid out meanwhile only lowers the surplus
+    let unclaimed_rewards = unclaimed_rewards();
+    let (balance,): (Nat,) = ic_cdk::call(
+        token_canister,
+        "icrc1_balance_of",
+        (Account {
+            owner: id(),
+            subaccount: Some(treasury),
+        },),
+    )
+    .await
+    .map_err(|(code, msg)| format!("icrc1_balance_of code: {:?}, msg: {}", code, msg))?;
+    let treasury_balance = u64::try_from(balance.0).unwrap_or(u64::MAX);
+    let amount = buyback_amount(&policy, treasury_balance.saturating_sub(unclaimed_rewards));
+    if amount == 0 {
+        return Ok(());
+    }
+
+    // the canister is the minting account, so a transfer to it burns the tokens
+    let arg = TransferArg {
+        from_subaccount: Some(treasury),
+        to: Account {
+            owner: id(),
+            subaccount: None,
+        },
+        fee: None,
+        created_at_time: Some(ic_cdk::api::time()),
+        memo: Some(icrc_ledger_types::icrc1::transfer::Memo::from(
+            MEMO_BURN_DOD,
+        )),
+        amount: NumTokens::from(amount),
+    };
+    let call_result = ic_cdk::api::call::call(token_canister, "icrc1_transfer", (arg,)).await
+        as Result<(Result<Nat, TransferError>,), (RejectionCode, String)>;
+    match call_result {
+        Ok((Ok(_),)) => {}
+        Ok((Err(msg),)) => return Err(format!("icrc1_transfer msg: {:?}", msg)),
+        Err((code, msg)) => {
+            return Err(format!("icrc1_transfer code: {:?}, msg: {}", code, msg));
+        }
+    }
+
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.total_buyback_burned = Some(
+                dod_service
+                    .total_buyback_burned
+                    .unwrap_or(0)
+                    .saturating_add(amount),
+            );
+        }
+    });
+    add_event(
+        id(),
+        EventKind::BuybackBurned {
+            amount,
+            treasury_balance,
+            unclaimed_rewards,
+        },
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::service::miner::{credit_miner_reward, debit_miner_reward};
+    use dod_utils::types::{BtcAddress, MinerInfo, MinerStatus};
+
+    #[test]
+    pub fn test_buyback_amount() {
+        assert_eq!(buyback_amount(&BuybackPolicy::FixedAmount(100), 1_000), 100);
+        assert_eq!(buyback_amount(&BuybackPolicy::FixedAmount(100), 40), 40);
+        assert_eq!(
+            buyback_amount(&BuybackPolicy::TreasuryPercentage(10), 1_000),
+            100
+        );
+        assert_eq!(
+            buyback_amount(&BuybackPolicy::TreasuryPercentage(100), 999),
+            999
+        );
+        assert_eq!(buyback_amount(&BuybackPolicy::TreasuryPercentage(50), 0), 0);
+        assert_eq!(
+            buyback_amount(&BuybackPolicy::TreasuryPercentage(100), u64::MAX),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    pub fn test_unclaimed_running_total() {
+        DodService::new(60_000_000_000, 10, 1_000, None, vec![], None, None);
+        MINERS.with_borrow_mut(|v| {
+            v.insert(
+                BtcAddress("a".to_string()),
+                MinerInfo {
+                    owner: Principal::anonymous(),
+                    status: MinerStatus::Activate,
+                    ecdsa_pubkey: vec![],
+                    btc_address: "a".to_string(),
+                    reward_cycles: None,
+                    claimed_dod: 4,
+                    total_dod: 10,
+                },
+            )
+        });
+        // seeded from the miners once
+        assert_eq!(miner_unclaimed(), 6);
+
+        credit_miner_reward("a", 5);
+        assert_eq!(miner_unclaimed(), 11);
+        assert_eq!(debit_miner_reward("a", 3), 3);
+        assert_eq!(miner_unclaimed(), 8);
+        assert_eq!(miner_unclaimed(), scan_miner_unclaimed());
+
+        reset_unclaimed();
+        assert_eq!(miner_unclaimed(), 8);
+    }
+}
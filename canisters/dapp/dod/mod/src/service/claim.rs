@@ -0,0 +1,262 @@
+use crate::common::{ONE_DAY_NS, ONE_HOUR_NS};
+use crate::memory::{CLAIM_LOCKS, PENDING_CLAIMS};
+use crate::service::config;
+use crate::types::{ClaimSource, PendingClaim, PendingClaims};
+use candid::{Nat, Principal};
+use ic_cdk::api::call::RejectionCode;
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::TransferError;
+
+/// A claim holding the lock longer than this is assumed to have trapped after an await,
+/// its lock is released for the next claim.
+pub const CLAIM_LOCK_TIMEOUT: u64 = 10 * 60 * 1_000_000_000;
+
+/// Takes the claim lock of `user`. Kept in stable memory, so an upgrade between the
+/// transfer and its callback does not release it.
+pub fn lock(user: Principal) -> Result<(), String> {
+    let now = ic_cdk::api::time();
+    CLAIM_LOCKS.with_borrow_mut(|v| {
+        if let Some(locked_at) = v.get(&user) {
+            if now < locked_at.saturating_add(CLAIM_LOCK_TIMEOUT) {
+                return Err("Another claim is in progress".to_string());
+            }
+        }
+        v.insert(user, now);
+        Ok(())
+    })
+}
+
+/// When the claim of `user` in progress took the lock, `None` without one.
+pub fn locked_at(user: Principal) -> Option<u64> {
+    CLAIM_LOCKS.with_borrow(|v| v.get(&user))
+}
+
+pub fn unlock(user: Principal) {
+    CLAIM_LOCKS.with_borrow_mut(|v| v.remove(&user));
+}
+
+/// Retries reuse the `created_at_time` of a pending claim for this long. The ledger keeps
+/// transactions for deduplication for a day, an hour is left for drift and slow retries.
+pub const DEFAULT_CLAIM_DEDUP_WINDOW: u64 = 23 * ONE_HOUR_NS;
+pub const MAX_CLAIM_DEDUP_WINDOW: u64 = ONE_DAY_NS;
+
+/// What became of a claim transfer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClaimOutcome {
+    /// the block index of the transfer, or of the earlier one the ledger found it duplicates
+    Transferred(Nat),
+    /// the ledger rejected it, nothing was transferred
+    Rejected(String),
+    /// it may be retried with the same `created_at_time`
+    Retry(String),
+    /// the ledger no longer deduplicates it, an earlier attempt may have landed
+    TooOld,
+}
+
+pub fn classify_transfer(
+    res: Result<Result<Nat, TransferError>, (RejectionCode, String)>,
+) -> ClaimOutcome {
+    match res {
+        Ok(Ok(index)) => ClaimOutcome::Transferred(index),
+        Ok(Err(TransferError::Duplicate { duplicate_of })) => {
+            ClaimOutcome::Transferred(duplicate_of)
+        }
+        Ok(Err(TransferError::TooOld)) => ClaimOutcome::TooOld,
+        Ok(Err(e @ TransferError::TemporarilyUnavailable))
+        | Ok(Err(e @ TransferError::CreatedInFuture { .. })) => {
+            ClaimOutcome::Retry(format!("{:?}", e))
+        }
+        Ok(Err(e)) => ClaimOutcome::Rejected(format!("{:?}", e)),
+        Err((code, msg)) => ClaimOutcome::Retry(format!("code: {}, msg: {}", code as u16, msg)),
+    }
+}
+
+impl ClaimOutcome {
+    pub fn into_result(self) -> Result<Nat, String> {
+        match self {
+            ClaimOutcome::Transferred(index) => Ok(index),
+            ClaimOutcome::Rejected(e) => Err(format!(
+                "Error calling claim_reward::icrc1_transfer msg: {}",
+                e
+            )),
+            ClaimOutcome::Retry(e) => Err(format!(
+                "Error calling claim_reward::icrc1_transfer {}, the claim is kept pending and \
+                 the same claim can be retried",
+                e
+            )),
+            ClaimOutcome::TooOld => Err(too_old_guidance()),
+        }
+    }
+}
+
+fn too_old_guidance() -> String {
+    "The claim is older than the ledger deduplication window and may have landed already. \
+     Check the ledger for the transfer listed by get_pending_claims and ask the owners to \
+     resolve it"
+        .to_string()
+}
+
+/// Whether a claim first sent at `created_at_time` can no longer be retried at `now`.
+pub fn is_expired(created_at_time: u64, now: u64, window: u64) -> bool {
+    now.saturating_sub(created_at_time) > window
+}
+
+fn matches(claim: &PendingClaim, source: &ClaimSource, to: &Account, amount: u64) -> bool {
+    claim.source == *source && claim.to == *to && claim.amount == amount
+}
+
+pub fn get_pending_claims(user: Principal) -> Vec<PendingClaim> {
+    PENDING_CLAIMS.with_borrow(|v| v.get(&user).unwrap_or_default().claims)
+}
+
+/// DOD of `source` held by pending claims of `user`, other than the ones `legs` retry.
+pub fn reserved(user: Principal, source: &ClaimSource, legs: &[(Account, u64)]) -> u64 {
+    get_pending_claims(user)
+        .iter()
+        .filter(|c| c.source == *source)
+        .filter(|c| {
+            !legs
+                .iter()
+                .any(|(to, amount)| matches(c, source, to, *amount))
+        })
+        .fold(0u64, |acc, c| acc.saturating_add(c.amount))
+}
+
+fn update_claims(user: Principal, f: impl FnOnce(&mut Vec<PendingClaim>)) {
+    PENDING_CLAIMS.with_borrow_mut(|v| {
+        let mut pending = v.get(&user).unwrap_or_default();
+        f(&mut pending.claims);
+        if pending.claims.is_empty() {
+            v.remove(&user);
+        } else {
+            v.insert(user, pending);
+        }
+    })
+}
+
+/// The `created_at_time` to send a claim with: the one it was first sent with when it is
+/// pending, `now` for a new claim, which is kept pending until its outcome is known.
+pub fn begin_transfer(
+    user: Principal,
+    source: &ClaimSource,
+    to: &Account,
+    amount: u64,
+    now: u64,
+) -> Result<u64, String> {
+    let window = config::get_claim_dedup_window();
+    let mut res = Ok(now);
+    update_claims(user, |claims| {
+        match claims.iter_mut().find(|c| matches(c, source, to, amount)) {
+            Some(claim) if is_expired(claim.created_at_time, now, window) => {
+                res = Err(too_old_guidance());
+            }
+            Some(claim) => {
+                claim.attempts += 1;
+                res = Ok(claim.created_at_time);
+            }
+            None => claims.push(PendingClaim {
+                source: source.clone(),
+                to: to.clone(),
+                amount,
+                created_at_time: now,
+                attempts: 1,
+                last_error: None,
+            }),
+        }
+    });
+    res
+}
+
+/// Forgets the pending claim once its outcome is known, keeps it with the error otherwise.
+pub fn end_transfer(
+    user: Principal,
+    source: &ClaimSource,
+    to: &Account,
+    amount: u64,
+    outcome: &ClaimOutcome,
+) {
+    update_claims(user, |claims| match outcome {
+        ClaimOutcome::Transferred(_) | ClaimOutcome::Rejected(_) => {
+            claims.retain(|c| !matches(c, source, to, amount))
+        }
+        ClaimOutcome::Retry(e) => {
+            if let Some(claim) = claims.iter_mut().find(|c| matches(c, source, to, amount)) {
+                claim.last_error = Some(e.clone());
+            }
+        }
+        ClaimOutcome::TooOld => {
+            if let Some(claim) = claims.iter_mut().find(|c| matches(c, source, to, amount)) {
+                claim.last_error = Some("TooOld".to_string());
+            }
+        }
+    });
+}
+
+/// Removes the pending claim at `index` of `user`, for owners to resolve it.
+pub fn take_pending_claim(user: Principal, index: usize) -> Option<PendingClaim> {
+    let mut taken = None;
+    update_claims(user, |claims| {
+        if index < claims.len() {
+            taken = Some(claims.remove(index));
+        }
+    });
+    taken
+}
+
+/// Rejects any outflow of rewards while owners halted claims.
+pub fn check_not_halted() -> Result<(), String> {
+    let status = config::get_claims_halt_status();
+    if status.halted {
+        return Err(format!(
+            "Claims are halted: {}",
+            status.reason.unwrap_or_default()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify_transfer() {
+        assert_eq!(
+            classify_transfer(Ok(Ok(Nat::from(5u64)))),
+            ClaimOutcome::Transferred(Nat::from(5u64))
+        );
+        assert_eq!(
+            classify_transfer(Ok(Err(TransferError::Duplicate {
+                duplicate_of: Nat::from(3u64)
+            }))),
+            ClaimOutcome::Transferred(Nat::from(3u64))
+        );
+        assert_eq!(
+            classify_transfer(Ok(Err(TransferError::TooOld))),
+            ClaimOutcome::TooOld
+        );
+        assert!(matches!(
+            classify_transfer(Ok(Err(TransferError::TemporarilyUnavailable))),
+            ClaimOutcome::Retry(_)
+        ));
+        assert!(matches!(
+            classify_transfer(Ok(Err(TransferError::InsufficientFunds {
+                balance: Nat::from(0u64)
+            }))),
+            ClaimOutcome::Rejected(_)
+        ));
+        assert!(matches!(
+            classify_transfer(Err((RejectionCode::SysTransient, "busy".to_string()))),
+            ClaimOutcome::Retry(_)
+        ));
+    }
+
+    #[test]
+    fn test_is_expired() {
+        assert!(!is_expired(100, 100, 10));
+        assert!(!is_expired(100, 110, 10));
+        assert!(is_expired(100, 111, 10));
+        // a clock behind the claim does not expire it
+        assert!(!is_expired(100, 50, 10));
+    }
+}
@@ -1,8 +1,14 @@
+use crate::common::{DEFAULT_CYCLES_CREATE_FEE, DEFAULT_ICP_FEE, DEFAULT_MIN_ICP_STAKE_E8S};
 use crate::memory::CONFIG;
 use crate::protocol::vec_to_u832;
+use crate::service::claim::DEFAULT_CLAIM_DEDUP_WINDOW;
 use candid::Principal;
 use dod_utils::bitwork::Bitwork;
-use dod_utils::types::{HalvingSettings, Height};
+use dod_utils::types::{
+    BidCurrency, BlockArchive, BlockImport, BtcNetwork, ClaimsHaltStatus, DifficultySteps,
+    DifficultyTiers, FailedBlockPolicy, FeeSettings, GenesisInfo, HalvingSettings, Height,
+    LockInBonus, MaintenanceStatus, RewardDustPolicy, RewardPolicy, ScheduleAnchor,
+};
 
 pub fn get_token_canister() -> Result<Principal, String> {
     CONFIG.with(|config| {
@@ -95,6 +101,453 @@ pub fn set_halving_settings(setting: HalvingSettings) -> Result<(), String> {
     })
 }
 
+pub fn get_btc_network() -> Option<BtcNetwork> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.btc_network)
+    })
+}
+
+pub fn set_btc_network(network: Option<BtcNetwork>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.btc_network = network;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_maintenance_status() -> MaintenanceStatus {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.maintenance.clone())
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_maintenance_status(status: MaintenanceStatus) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.maintenance = Some(status);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_difficulty_steps() -> DifficultySteps {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.difficulty_steps.clone())
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_difficulty_steps(steps: DifficultySteps) -> Result<(), String> {
+    steps.validate()?;
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.difficulty_steps = Some(steps);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_claims_halt_status() -> ClaimsHaltStatus {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.claims_halt.clone())
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_claims_halt_status(status: ClaimsHaltStatus) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.claims_halt = Some(status);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_bid_currency() -> BidCurrency {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.bid_currency.clone())
+            .unwrap_or_default()
+    })
+}
+
+pub fn set_bid_currency(currency: BidCurrency) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.bid_currency = Some(currency);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_difficulty_tiers() -> Option<DifficultyTiers> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.difficulty_tiers.clone())
+    })
+}
+
+pub fn set_difficulty_tiers(tiers: Option<DifficultyTiers>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.difficulty_tiers = tiers;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_lock_in_bonus() -> Option<LockInBonus> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.lock_in_bonus.clone())
+    })
+}
+
+pub fn set_lock_in_bonus(bonus: Option<LockInBonus>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.lock_in_bonus = bonus;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_failed_block_policy() -> FailedBlockPolicy {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.failed_block_policy.clone())
+            .unwrap_or(FailedBlockPolicy::BurnAll)
+    })
+}
+
+pub fn set_failed_block_policy(policy: FailedBlockPolicy) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.failed_block_policy = Some(policy);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_reward_dust_policy() -> RewardDustPolicy {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.reward_dust_policy.clone())
+            .unwrap_or(RewardDustPolicy::Floor)
+    })
+}
+
+pub fn set_reward_dust_policy(policy: RewardDustPolicy) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.reward_dust_policy = Some(policy);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_reward_policy() -> Option<RewardPolicy> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.reward_policy.clone())
+    })
+}
+
+pub fn set_reward_policy(policy: Option<RewardPolicy>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.reward_policy = policy;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_fee_settings() -> FeeSettings {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.fee_settings.clone())
+            .unwrap_or(FeeSettings {
+                icp_fee: DEFAULT_ICP_FEE,
+                min_icp_stake_e8s: DEFAULT_MIN_ICP_STAKE_E8S,
+                cycles_create_fee: DEFAULT_CYCLES_CREATE_FEE,
+            })
+    })
+}
+
+pub fn set_fee_settings(settings: FeeSettings) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.fee_settings = Some(settings);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_submission_grace() -> u64 {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.submission_grace)
+            .unwrap_or(0)
+    })
+}
+
+pub fn set_submission_grace(grace: u64) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.submission_grace = Some(grace);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_schedule_anchor() -> Option<ScheduleAnchor> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.schedule_anchor)
+    })
+}
+
+pub fn set_schedule_anchor(anchor: ScheduleAnchor) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.schedule_anchor = Some(anchor);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_block_import() -> Option<BlockImport> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.block_import.clone())
+    })
+}
+
+pub fn set_block_import(import: BlockImport) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.block_import = Some(import);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_block_archive() -> Option<BlockArchive> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.block_archive.clone())
+    })
+}
+
+pub fn set_block_archive(archive: BlockArchive) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.block_archive = Some(archive);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_register_miners_as_stakers() -> bool {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.register_miners_as_stakers)
+            .unwrap_or(false)
+    })
+}
+
+pub fn set_register_miners_as_stakers(enabled: bool) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.register_miners_as_stakers = Some(enabled);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_claim_dedup_window() -> u64 {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.claim_dedup_window)
+            .unwrap_or(DEFAULT_CLAIM_DEDUP_WINDOW)
+    })
+}
+
+pub fn set_claim_dedup_window(window: u64) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.claim_dedup_window = Some(window);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_sigs_retention() -> Option<u64> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.sigs_retention)
+    })
+}
+
+pub fn set_sigs_retention(retention: Option<u64>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.sigs_retention = retention;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
 pub fn get_consider_decrease() -> Result<Option<u64>, String> {
     CONFIG.with(|config| {
         config
@@ -159,14 +612,82 @@ pub fn set_difficulty_adjust_epoch(difficulty_adjust_epoch: u64) -> Result<(), S
     })
 }
 
+pub fn get_block_subscriber() -> Option<Principal> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.block_subscriber)
+    })
+}
+
+pub fn set_block_subscriber(subscriber: Option<Principal>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.block_subscriber = subscriber;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_genesis_info() -> Option<GenesisInfo> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.genesis.clone())
+    })
+}
+
+pub fn set_genesis_info(genesis: GenesisInfo) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.genesis = Some(genesis);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+/// The ratio the default reward is multiplied by at `block`. An interval of 0 never halves.
 pub fn get_current_halving_ratio(block: Height, halving_settings: HalvingSettings) -> f64 {
+    if halving_settings.interval == 0 {
+        return 1.0;
+    }
     let cycle = block / halving_settings.interval; // halving cycle;
     halving_settings.ratio.powi(cycle as i32)
 }
 
+/// The reward of `block` before any boost, the default reward when there are no halving
+/// settings.
+pub fn halved_reward(
+    default_reward: u64,
+    block: Height,
+    halving_settings: Option<HalvingSettings>,
+) -> u64 {
+    match halving_settings {
+        Some(halving_settings) => {
+            let ratio = get_current_halving_ratio(block, halving_settings);
+            (default_reward as f64 * ratio).floor() as u64
+        }
+        None => default_reward,
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::service::config::{get_current_halving_ratio, get_halving_settings};
+    use crate::service::config::{get_current_halving_ratio, get_halving_settings, halved_reward};
     use dod_utils::types::HalvingSettings;
 
     #[test]
@@ -183,4 +704,21 @@ mod test {
         let f = (10000 as f64 * r).floor() as u64;
         println!("f: {:?}", f);
     }
+
+    #[test]
+    pub fn test_halved_reward() {
+        let s = HalvingSettings {
+            interval: 21000,
+            ratio: 0.5,
+        };
+        assert_eq!(halved_reward(10000, 20999, Some(s.clone())), 10000);
+        assert_eq!(halved_reward(10000, 42000, Some(s)), 2500);
+        // deployments without halving settings keep the default reward
+        assert_eq!(halved_reward(10000, 42000, None), 10000);
+        let never = HalvingSettings {
+            interval: 0,
+            ratio: 0.5,
+        };
+        assert_eq!(halved_reward(10000, 42000, Some(never)), 10000);
+    }
 }
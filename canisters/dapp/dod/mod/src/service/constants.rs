@@ -0,0 +1,113 @@
+use crate::common::{
+    BURN_ORDERS_LIMIT, CYCLES_BURNER_FEE, MEMO_BURN_CYCLES, MEMO_BURN_DOD, MEMO_TOP_UP,
+    MEMO_TOP_UP_CANISTER, MEMO_TRANSFER,
+};
+use crate::protocol::MAGIC_VALUE;
+use crate::service::block::MAX_BLOCKS_PAGE_LIMIT;
+use crate::service::burn_receipts::MAX_BURN_RECEIPTS_PER_CALL;
+use crate::service::donations::{MAX_DONATION_BLOCKS_AHEAD, MIN_DONATION};
+use crate::service::miner::MAX_MINER_OPERATORS;
+use crate::service::schedule::MAX_SCHEDULE_BLOCKS;
+use crate::service::transfer::MAX_TRANSFER_MEMO_LEN;
+use crate::service::{assets, config, MAX_BLOCK_ORDERS_PAGE, MAX_FULL_BLOCKS};
+use bitcoin::hashes::{sha256, Hash};
+use dod_utils::types::{
+    FeeSettings, ProtocolConstants, MAX_ASSET_ID_LEN, MAX_ICP_FEE_E8S, MAX_MINING_ASSETS,
+    MAX_MIN_ICP_STAKE_E8S,
+};
+
+/// Bumped when fields are added to `ProtocolConstants`.
+pub const PROTOCOL_CONSTANTS_VERSION: u32 = 1;
+
+/// The constants with the configured limits, `revision` hashed from all other values.
+pub fn protocol_constants(
+    fee_settings: FeeSettings,
+    block_time_interval: u64,
+    submission_grace: u64,
+    difficulty_adjust_epoch: u64,
+    claim_dedup_window: u64,
+) -> ProtocolConstants {
+    let mut constants = ProtocolConstants {
+        version: PROTOCOL_CONSTANTS_VERSION,
+        revision: 0,
+        magic_value: MAGIC_VALUE,
+        memo_top_up: MEMO_TOP_UP,
+        memo_transfer: MEMO_TRANSFER,
+        memo_burn_dod: MEMO_BURN_DOD,
+        memo_burn_cycles: MEMO_BURN_CYCLES,
+        memo_top_up_canister: MEMO_TOP_UP_CANISTER,
+        cycles_burner_fee: CYCLES_BURNER_FEE,
+        burn_orders_limit: BURN_ORDERS_LIMIT,
+        fee_settings,
+        max_icp_fee_e8s: MAX_ICP_FEE_E8S,
+        max_min_icp_stake_e8s: MAX_MIN_ICP_STAKE_E8S,
+        block_time_interval,
+        submission_grace,
+        difficulty_adjust_epoch,
+        claim_dedup_window,
+        min_donation: MIN_DONATION,
+        max_donation_blocks_ahead: MAX_DONATION_BLOCKS_AHEAD,
+        max_transfer_memo_len: MAX_TRANSFER_MEMO_LEN as u64,
+        max_blocks_page_limit: MAX_BLOCKS_PAGE_LIMIT,
+        max_full_blocks: MAX_FULL_BLOCKS,
+        max_block_orders_page: MAX_BLOCK_ORDERS_PAGE,
+        max_schedule_blocks: MAX_SCHEDULE_BLOCKS,
+        max_burn_receipts_per_call: MAX_BURN_RECEIPTS_PER_CALL,
+        max_asset_reward_blocks: assets::MAX_ASSET_REWARD_BLOCKS,
+        max_mining_assets: MAX_MINING_ASSETS as u64,
+        max_asset_id_len: MAX_ASSET_ID_LEN as u64,
+        max_miner_operators: MAX_MINER_OPERATORS as u64,
+    };
+    let encoded = candid::encode_one(&constants).unwrap_or_default();
+    let hash = sha256::Hash::hash(encoded.as_slice()).to_byte_array();
+    constants.revision = u64::from_be_bytes(hash[..8].try_into().expect("sha256 is 32 bytes"));
+    constants
+}
+
+pub fn get_protocol_constants() -> Result<ProtocolConstants, String> {
+    Ok(protocol_constants(
+        config::get_fee_settings(),
+        config::get_block_time_interval()?,
+        config::get_submission_grace(),
+        config::get_difficulty_adjust_epoch()?,
+        config::get_claim_dedup_window(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_protocol_constants_revision() {
+        let fees = FeeSettings {
+            icp_fee: 10_000,
+            min_icp_stake_e8s: 1_000_000,
+            cycles_create_fee: 2_000_000_000_000,
+        };
+        let constants = protocol_constants(fees.clone(), 60, 10, 8, 100);
+        assert_eq!(constants.version, PROTOCOL_CONSTANTS_VERSION);
+        assert_eq!(
+            constants.revision,
+            protocol_constants(fees.clone(), 60, 10, 8, 100).revision
+        );
+        assert_ne!(
+            constants.revision,
+            protocol_constants(fees.clone(), 60, 20, 8, 100).revision
+        );
+        assert_ne!(
+            constants.revision,
+            protocol_constants(
+                FeeSettings {
+                    icp_fee: 20_000,
+                    ..fees
+                },
+                60,
+                10,
+                8,
+                100
+            )
+            .revision
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use crate::memory::CONFIG;
+
+pub fn get_cycles_per_dod() -> Option<u128> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.cycles_per_dod)
+    })
+}
+
+pub fn set_cycles_per_dod(cycles_per_dod: Option<u128>) -> Result<(), String> {
+    if cycles_per_dod == Some(0) {
+        return Err("Conversion rate can not be zero".to_string());
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.cycles_per_dod = cycles_per_dod;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+/// Cycles credited for `amount` DOD base units, at `cycles_per_dod` cycles per whole DOD of
+/// `decimals` decimals.
+pub fn cycles_for(amount: u64, cycles_per_dod: u128, decimals: u8) -> Result<u128, String> {
+    let unit = 10u128
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| format!("Unsupported ledger decimals {}", decimals))?;
+    let cycles = (amount as u128)
+        .checked_mul(cycles_per_dod)
+        .ok_or_else(|| "Conversion overflow".to_string())?
+        / unit;
+    if cycles == 0 {
+        return Err("Amount too low to convert".to_string());
+    }
+    Ok(cycles)
+}
+
+#[cfg(test)]
+mod test {
+    use super::cycles_for;
+
+    #[test]
+    fn test_cycles_for() {
+        assert_eq!(
+            cycles_for(100_000_000, 2_000_000_000_000, 8),
+            Ok(2_000_000_000_000)
+        );
+        assert_eq!(cycles_for(1, 2_000_000_000_000, 8), Ok(20_000));
+        assert_eq!(cycles_for(150, 1_000, 2), Ok(1_500));
+        assert!(cycles_for(1, 99, 2).is_err());
+        assert!(cycles_for(u64::MAX, u128::MAX, 8).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::memory::{CONFIG, STAKERS};
+use crate::state::info_log_add;
+use dod_utils::tcycles::to_tcycles;
+use dod_utils::types::CyclesHealth;
+
+/// Logical amount of cycles that belong to users: the sum of their balances, kept as a running
+/// total. Canisters upgraded from before the pool existed start from the sum itself.
+pub fn get_user_pool() -> u128 {
+    CONFIG
+        .with(|config| {
+            config
+                .borrow()
+                .dod_service
+                .as_ref()
+                .and_then(|dod_service| dod_service.user_pool)
+        })
+        .unwrap_or_else(|| {
+            STAKERS.with_borrow(|v| {
+                v.iter().fold(0u128, |acc, (_, user)| {
+                    acc.saturating_add(u128::try_from(user.balance.0).unwrap_or(u128::MAX))
+                })
+            })
+        })
+}
+
+pub fn set_user_pool(pool: u128) {
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.user_pool = Some(pool);
+        }
+    })
+}
+
+pub fn add_to_user_pool(cycles: u128) {
+    set_user_pool(get_user_pool().saturating_add(cycles));
+}
+
+/// Takes up to `cycles` out of the pool when they leave user balances, returns how many were
+/// taken.
+pub fn take_from_user_pool(cycles: u128) -> u128 {
+    let pool = get_user_pool();
+    let taken = cycles.min(pool);
+    set_user_pool(pool - taken);
+    taken
+}
+
+pub fn get_cycles_alert_threshold() -> Option<u128> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.cycles_alert_threshold)
+    })
+}
+
+pub fn set_cycles_alert_threshold(threshold: Option<u128>) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.cycles_alert_threshold = threshold;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+pub fn get_cycles_health() -> CyclesHealth {
+    let canister_balance = ic_cdk::api::canister_balance128();
+    let user_pool = get_user_pool();
+    let operational = canister_balance.saturating_sub(user_pool);
+    let alert_threshold = get_cycles_alert_threshold();
+    CyclesHealth {
+        canister_balance,
+        user_pool,
+        operational,
+        alert_threshold,
+        low: alert_threshold.map_or(false, |t| operational < t),
+        canister_balance_t: to_tcycles(canister_balance),
+        user_pool_t: to_tcycles(user_pool),
+        operational_t: to_tcycles(operational),
+    }
+}
+
+/// Logs an alert when the operational headroom is below the configured threshold.
+pub fn check_cycles_health() {
+    let health = get_cycles_health();
+    if health.low {
+        info_log_add(
+            format!(
+                "cycles alert: operational headroom {} is below {}, balance {}, user pool {}",
+                health.operational,
+                health.alert_threshold.unwrap_or(0),
+                health.canister_balance,
+                health.user_pool
+            )
+            .as_str(),
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::service::{config, cycles, DodService};
+use crate::types::{ArchiveOptions, FeatureFlags, InitArgs};
+use bitcoin::hashes::{sha256, Hash};
+use candid::{Nat, Principal};
+use dod_utils::types::{DeployPreview, PlannedCanister, WasmInfo};
+use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue;
+use icrc_ledger_types::icrc1::account::Account;
+
+/// Cycles the IC charges for creating a canister, the rest of the cycles attached stays on it.
+pub const CANISTER_CREATION_FEE: u128 = 500_000_000_000;
+/// Blocks an archive canister takes from the ledger at once.
+pub const ARCHIVE_BLOCKS: u64 = 2000;
+/// Bytes of a staged wasm returned at once, well under the reply size limit.
+pub const WASM_CHUNK_SIZE: u64 = 1024 * 1024;
+
+pub fn wasm_info(wasm: &[u8]) -> WasmInfo {
+    let size = wasm.len() as u64;
+    WasmInfo {
+        size,
+        sha256: sha256::Hash::hash(wasm).to_byte_array().to_vec(),
+        chunk_size: WASM_CHUNK_SIZE,
+        chunks: size.div_ceil(WASM_CHUNK_SIZE),
+    }
+}
+
+/// Chunk `index` of `wasm`, as long as it still hashes to `sha256`, so a wasm staged again
+/// while it is downloaded is not mixed with the previous one.
+pub fn wasm_chunk(wasm: &[u8], sha256: &[u8], index: u64) -> Result<Vec<u8>, String> {
+    if sha256::Hash::hash(wasm).to_byte_array().as_slice() != sha256 {
+        return Err("The staged wasm changed, fetch its info again".to_string());
+    }
+    let start = index
+        .checked_mul(WASM_CHUNK_SIZE)
+        .filter(|start| *start < wasm.len() as u64)
+        .ok_or_else(|| format!("No chunk {}", index))? as usize;
+    let end = wasm.len().min(start + WASM_CHUNK_SIZE as usize);
+    Ok(wasm[start..end].to_vec())
+}
+
+/// Init args of the DOD ledger, minted by `minting_account`.
+pub fn ledger_init_args(minting_account: Principal) -> InitArgs {
+    InitArgs {
+        minting_account: Account {
+            owner: minting_account,
+            subaccount: None,
+        },
+        fee_collector_account: None,
+        initial_balances: vec![],
+        transfer_fee: Nat::from(0u64),
+        decimals: Some(8),
+        token_name: "DOD".to_string(),
+        token_symbol: "𓃡𓃡𓃡".to_string(),
+        metadata: vec![(
+            "content-type".to_string(),
+            MetadataValue::from("application/json"),
+        )],
+        archive_options: ArchiveOptions {
+            trigger_threshold: 1000,
+            num_blocks_to_archive: ARCHIVE_BLOCKS as usize,
+            node_max_memory_size_bytes: None,
+            max_message_size_bytes: None,
+            controller_id: minting_account,
+            more_controller_ids: None,
+            cycles_for_archive_creation: None,
+            max_transactions_per_response: None,
+        },
+        max_memo_length: Some(512),
+        feature_flags: Some(FeatureFlags { icrc2: true }),
+        maximum_number_of_accounts: None,
+        accounts_overflow_trim_quantity: None,
+    }
+}
+
+/// One canister `deploy_canisters` creates with `cycles` attached.
+pub fn planned_canister(
+    name: &str,
+    wasm: Option<&Vec<u8>>,
+    cycles: u128,
+    init_args: String,
+) -> PlannedCanister {
+    PlannedCanister {
+        name: name.to_string(),
+        wasm_staged: wasm.is_some(),
+        wasm_size: wasm.map_or(0, |w| w.len() as u64),
+        cycles,
+        creation_fee: CANISTER_CREATION_FEE.min(cycles),
+        initial_cycles: cycles.saturating_sub(CANISTER_CREATION_FEE),
+        init_args,
+    }
+}
+
+/// Why the deploy would fail or spend cycles it should not, empty when it can go ahead.
+pub fn deploy_issues(
+    canisters: &[PlannedCanister],
+    total_cycles: u128,
+    available: u128,
+    deployed: bool,
+) -> Vec<String> {
+    let mut issues: Vec<String> = canisters
+        .iter()
+        .filter(|c| !c.wasm_staged)
+        .map(|c| format!("{} wasm not staged", c.name))
+        .collect();
+    if canisters.iter().any(|c| c.cycles < CANISTER_CREATION_FEE) {
+        issues.push("Cycles create fee is below the canister creation fee".to_string());
+    }
+    if total_cycles > available {
+        issues.push(format!(
+            "Needs {} cycles, only {} are available besides the user pool",
+            total_cycles, available
+        ));
+    }
+    if deployed {
+        issues.push("Canisters are deployed already, new ones would replace them".to_string());
+    }
+    issues
+}
+
+/// What `deploy_canisters` would do with the staged wasms and the current fee settings,
+/// without calling anything.
+pub fn preview_deploy(service: &DodService) -> DeployPreview {
+    let fee = config::get_fee_settings().cycles_create_fee;
+    let dod_canister = ic_cdk::api::id();
+    let mut controllers = vec![dod_canister];
+    controllers.extend(crate::state::owners().map_or(vec![], |v| {
+        v.iter().map(|v| v.0.clone()).collect::<Vec<Principal>>()
+    }));
+    let canisters = vec![
+        planned_canister(
+            "ledger",
+            service.ledger_wasm.as_ref(),
+            fee,
+            format!("{:?}", ledger_init_args(dod_canister)),
+        ),
+        planned_canister(
+            "index",
+            service.index_wasm.as_ref(),
+            fee,
+            "Init { ledger_id: <ledger> }".to_string(),
+        ),
+        planned_canister(
+            "archive",
+            service.archive_wasm.as_ref(),
+            fee,
+            format!("(<ledger>, {}, null, null)", ARCHIVE_BLOCKS),
+        ),
+    ];
+    let total_cycles = canisters
+        .iter()
+        .fold(0u128, |acc, c| acc.saturating_add(c.cycles));
+    let health = cycles::get_cycles_health();
+    let deployed = DodService::get_dod_canisters();
+    let issues = deploy_issues(
+        &canisters,
+        total_cycles,
+        health.operational,
+        deployed.is_some(),
+    );
+    DeployPreview {
+        ready: issues.is_empty(),
+        issues,
+        canisters,
+        total_cycles,
+        canister_balance: health.canister_balance,
+        available_cycles: health.operational,
+        controllers,
+        deployed,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deploy_issues() {
+        let wasm = vec![0u8; 10];
+        let canisters = vec![
+            planned_canister(
+                "ledger",
+                Some(&wasm),
+                2 * CANISTER_CREATION_FEE,
+                String::new(),
+            ),
+            planned_canister("index", None, 2 * CANISTER_CREATION_FEE, String::new()),
+        ];
+        assert_eq!(canisters[0].wasm_size, 10);
+        assert_eq!(canisters[0].initial_cycles, CANISTER_CREATION_FEE);
+        assert_eq!(
+            deploy_issues(&canisters, 4 * CANISTER_CREATION_FEE, u128::MAX, false),
+            vec!["index wasm not staged".to_string()]
+        );
+        assert!(deploy_issues(&canisters[..1], 100, 100, false).is_empty());
+        assert_eq!(deploy_issues(&canisters[..1], 101, 100, true).len(), 2);
+    }
+
+    #[test]
+    fn test_wasm_chunk() {
+        let wasm: Vec<u8> = (0..WASM_CHUNK_SIZE * 2 + 10).map(|i| i as u8).collect();
+        let info = wasm_info(&wasm);
+        assert_eq!(info.size, wasm.len() as u64);
+        assert_eq!(info.chunks, 3);
+
+        let mut fetched = vec![];
+        for index in 0..info.chunks {
+            fetched.extend(wasm_chunk(&wasm, &info.sha256, index).unwrap());
+        }
+        assert_eq!(fetched, wasm);
+        assert!(wasm_chunk(&wasm, &info.sha256, 3).is_err());
+        assert!(wasm_chunk(&wasm, &[0u8; 32], 0).is_err());
+        assert_eq!(wasm_info(&[]).chunks, 0);
+    }
+}
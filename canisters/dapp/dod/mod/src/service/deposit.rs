@@ -0,0 +1,255 @@
+use crate::common::{
+    CMCClient, NotifyTopUpError, NotifyTopUpRequest, CMC_CAN_ID, ICP_CAN_ID, MEMO_TOP_UP_CANISTER,
+};
+use crate::memory::{CREDITED_DEPOSIT_BLOCKS, DEPOSITS, STAKERS};
+use crate::service::{config, cycles, replication, staker, DodService};
+use crate::types::UserDetail;
+use candid::{Nat, Principal};
+use dod_utils::types::{DepositRecord, DepositStatus};
+use ic_cdk::api::call::{msg_cycles_accept128, msg_cycles_available128};
+use ic_cdk::api::time;
+use ic_cdk::id;
+use ic_ledger_types::{
+    transfer, AccountIdentifier, Memo, Subaccount, Timestamp, Tokens, TransferArgs,
+};
+use ic_stable_structures::storable::Blob;
+
+/// A notification still marked in flight after this long is assumed to have trapped
+/// after its await, the deposit can be retried.
+pub const NOTIFY_RETRY_TIMEOUT: u64 = 10 * 60 * 1_000_000_000;
+
+fn get_deposit(user: Principal, deposit_id: u64) -> Option<DepositRecord> {
+    DEPOSITS.with_borrow(|v| v.get(&(user, deposit_id)))
+}
+
+fn update_deposit(
+    user: Principal,
+    deposit_id: u64,
+    f: impl FnOnce(&mut DepositRecord),
+) -> Option<DepositRecord> {
+    DEPOSITS.with_borrow_mut(|v| {
+        let mut record = v.get(&(user, deposit_id))?;
+        f(&mut record);
+        record.updated_at = time();
+        v.insert((user, deposit_id), record.clone());
+        Some(record)
+    })
+}
+
+/// Marks the ledger block of a deposit transfer as credited, false when it was already.
+fn consume_block(block_index: u64, user: Principal, deposit_id: u64) -> bool {
+    CREDITED_DEPOSIT_BLOCKS.with_borrow_mut(|v| {
+        if v.contains_key(&block_index) {
+            return false;
+        }
+        v.insert(block_index, (user, deposit_id));
+        true
+    })
+}
+
+fn is_block_consumed(block_index: u64) -> bool {
+    CREDITED_DEPOSIT_BLOCKS.with_borrow(|v| v.contains_key(&block_index))
+}
+
+/// Records the deposit intent, transfers the ICP of `user` to the CMC and notifies the top-up.
+/// Every step is written to the deposit record before the next await, a deposit left
+/// `Transferred` can be finished with `retry_deposit`.
+pub async fn begin_deposit(user: Principal, amount_e8s: u64) -> Result<DepositRecord, String> {
+    let fees = config::get_fee_settings();
+    if amount_e8s < fees.min_icp_stake_e8s {
+        return Err(format!(
+            "At least {} e8s of ICP is required to fuel the furnace, but got {}",
+            fees.min_icp_stake_e8s, amount_e8s
+        ));
+    }
+    let now = time();
+    let deposit_id = DEPOSITS.with_borrow_mut(|v| {
+        let deposit_id = v
+            .range((user, 0)..=(user, u64::MAX))
+            .last()
+            .map_or(0, |((_, k), _)| k + 1);
+        v.insert(
+            (user, deposit_id),
+            DepositRecord {
+                id: deposit_id,
+                user,
+                amount_e8s,
+                block_index: None,
+                cycles: None,
+                status: DepositStatus::Pending,
+                error: None,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        deposit_id
+    });
+
+    let icp_can_id = Principal::from_text(ICP_CAN_ID).unwrap();
+    let cmc_can_id = Principal::from_text(CMC_CAN_ID).unwrap();
+    let transfer_args = TransferArgs {
+        amount: Tokens::from_e8s(amount_e8s),
+        to: AccountIdentifier::new(&cmc_can_id, &Subaccount::from(id())),
+        memo: Memo(MEMO_TOP_UP_CANISTER),
+        fee: Tokens::from_e8s(fees.icp_fee),
+        from_subaccount: Some(Subaccount::from(user)),
+        created_at_time: Some(Timestamp {
+            timestamp_nanos: now,
+        }),
+    };
+
+    let error = match transfer(icp_can_id, transfer_args).await {
+        Ok(Ok(block_index)) => {
+            update_deposit(user, deposit_id, |r| {
+                r.block_index = Some(block_index);
+                r.status = DepositStatus::Transferred;
+            });
+            return notify_deposit(user, deposit_id).await;
+        }
+        Ok(Err(e)) => format!("Unable to transfer ICP: {:?}", e),
+        Err((code, msg)) => format!("Unable to call ICP canister: {:?} {}", code, msg),
+    };
+    update_deposit(user, deposit_id, |r| {
+        r.status = DepositStatus::Failed;
+        r.error = Some(error.clone());
+    });
+    Err(error)
+}
+
+/// Notifies the CMC of a transferred deposit and credits the minted cycles.
+/// Errors the CMC may recover from put the deposit back to `Transferred`,
+/// a refunded or rejected transaction fails it.
+async fn notify_deposit(user: Principal, deposit_id: u64) -> Result<DepositRecord, String> {
+    let block_index = get_deposit(user, deposit_id)
+        .and_then(|r| r.block_index)
+        .ok_or_else(|| "Deposit has no ICP transfer".to_string())?;
+    update_deposit(user, deposit_id, |r| r.status = DepositStatus::Notifying);
+
+    let cmc = CMCClient(Principal::from_text(CMC_CAN_ID).unwrap());
+    let res = cmc
+        .notify_top_up(NotifyTopUpRequest {
+            block_index,
+            canister_id: id(),
+        })
+        .await;
+
+    let record = match res {
+        Ok((Ok(minted),)) => {
+            // the CMC answers a repeated notification with the same cycles, a retry racing a
+            // notification still in flight must not credit them twice
+            if consume_block(block_index, user, deposit_id) {
+                credit_cycles(user, minted.clone());
+            }
+            update_deposit(user, deposit_id, |r| {
+                r.cycles = Some(u128::try_from(minted.0).unwrap_or(u128::MAX));
+                r.status = DepositStatus::Completed;
+                r.error = None;
+            })
+        }
+        Ok((Err(
+            e @ (NotifyTopUpError::Refunded { .. }
+            | NotifyTopUpError::InvalidTransaction(_)
+            | NotifyTopUpError::TransactionTooOld(_)),
+        ),)) => update_deposit(user, deposit_id, |r| {
+            r.status = DepositStatus::Failed;
+            r.error = Some(format!("Unable to deposit cycles: {:?}", e));
+        }),
+        Ok((Err(e),)) => update_deposit(user, deposit_id, |r| {
+            r.status = DepositStatus::Transferred;
+            r.error = Some(format!("Unable to deposit cycles: {:?}", e));
+        }),
+        Err((code, msg)) => update_deposit(user, deposit_id, |r| {
+            r.status = DepositStatus::Transferred;
+            r.error = Some(format!("Unable to call cycle canister: {:?} {}", code, msg));
+        }),
+    };
+    record.ok_or_else(|| "Deposit not found".to_string())
+}
+
+/// Retries the top-up notification of a deposit stuck after its ICP transfer.
+pub async fn retry_deposit(user: Principal, deposit_id: u64) -> Result<DepositRecord, String> {
+    let record = get_deposit(user, deposit_id).ok_or_else(|| "Deposit not found".to_string())?;
+    match record.status {
+        DepositStatus::Transferred => {}
+        DepositStatus::Notifying
+            if time() >= record.updated_at.saturating_add(NOTIFY_RETRY_TIMEOUT) => {}
+        DepositStatus::Notifying => {
+            return Err("Deposit notification is in progress".to_string());
+        }
+        status => {
+            return Err(format!("Deposit in status {:?} can not be retried", status));
+        }
+    }
+    // the notification that timed out did credit the cycles after all
+    if record.block_index.map_or(false, is_block_consumed) {
+        return update_deposit(user, deposit_id, |r| {
+            r.status = DepositStatus::Completed;
+            r.error = None;
+        })
+        .ok_or_else(|| "Deposit not found".to_string());
+    }
+    notify_deposit(user, deposit_id).await
+}
+
+/// Deposits of `user`, newest first.
+pub fn get_deposits(user: Principal) -> Vec<DepositRecord> {
+    let mut deposits: Vec<DepositRecord> = DEPOSITS.with_borrow(|v| {
+        v.range((user, 0)..=(user, u64::MAX))
+            .map(|(_, r)| r)
+            .collect()
+    });
+    deposits.reverse();
+    deposits
+}
+
+/// Accepts the cycles attached to the call and credits them to `user`.
+pub fn deposit_attached_cycles(user: Principal) -> Result<u128, String> {
+    let available = msg_cycles_available128();
+    if available == 0 {
+        return Err("No cycles attached".to_string());
+    }
+    let accepted = msg_cycles_accept128(available);
+    credit_cycles(user, Nat::from(accepted));
+    Ok(accepted)
+}
+
+/// Credits `cycles` to the balance of `from`, creating the user if needed.
+fn credit_cycles(from: Principal, cycles: Nat) {
+    cycles::add_to_user_pool(u128::try_from(cycles.0.clone()).unwrap_or(u128::MAX));
+    staker::record_deposit(from, u128::try_from(cycles.0.clone()).unwrap_or(u128::MAX));
+
+    let blob29 = Blob::<29>::try_from(from.as_slice()).expect("error transformation");
+    let user = match DodService::get_user_detail(from) {
+        Some(user) => UserDetail {
+            balance: user.balance + cycles,
+            ..user
+        },
+        None => UserDetail {
+            principal: from,
+            subaccount: Subaccount::from(id()),
+            balance: cycles,
+            claimed_dod: 0,
+            total_dod: 0,
+            cycle_burning_rate: 0,
+            reward_remainder: None,
+        },
+    };
+    replication::record_staker(&user);
+    STAKERS.with(|v| v.borrow_mut().insert(blob29, user));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_consume_block() {
+        let user = Principal::from_slice(&[3; 29]);
+        assert!(!is_block_consumed(42));
+        assert!(consume_block(42, user, 0));
+        assert!(is_block_consumed(42));
+        // a second notification answered with the same block credits nothing
+        assert!(!consume_block(42, user, 0));
+        assert!(consume_block(43, user, 1));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::memory::BLOCK_DONATIONS;
+use crate::orders::NewBlockOrders;
+use crate::service::block::get_last_block;
+use crate::service::{cycles, settlement, DodService};
+use candid::{Nat, Principal};
+use dod_utils::types::{BlockDonation, BlockDonations, Height};
+use ic_cdk::api::call::{msg_cycles_accept128, msg_cycles_available128};
+
+/// Least cycles a donation takes, so donors can not fill a block with dust entries.
+pub const MIN_DONATION: u128 = 1_000_000_000;
+/// Blocks after the open one that can take donations.
+pub const MAX_DONATION_BLOCKS_AHEAD: u64 = 1000;
+
+/// Whether `amount` can be donated to block `height` while block `open` is mined. The open
+/// block takes donations until its settlement starts.
+pub fn check_donation(
+    height: Height,
+    amount: u128,
+    open: Option<Height>,
+    settling: bool,
+) -> Result<(), String> {
+    if amount < MIN_DONATION {
+        return Err(format!("A donation is at least {} cycles", MIN_DONATION));
+    }
+    let open = open.ok_or_else(|| "No block is open".to_string())?;
+    if height < open || (height == open && settling) {
+        return Err(format!("Block {} is settled already", height));
+    }
+    if height - open > MAX_DONATION_BLOCKS_AHEAD {
+        return Err(format!(
+            "Donations are taken at most {} blocks ahead",
+            MAX_DONATION_BLOCKS_AHEAD
+        ));
+    }
+    Ok(())
+}
+
+/// Adds `amount` to what `donor` gave to block `height`.
+pub fn record(height: Height, donor: Principal, amount: u128) {
+    BLOCK_DONATIONS.with_borrow_mut(|v| {
+        let given = v.get(&(height, donor)).unwrap_or(0);
+        v.insert((height, donor), given.saturating_add(amount));
+    });
+    NewBlockOrders::invalidate_total_cycles();
+}
+
+/// Donates `amount` cycles to block `height`, out of the cycles attached to the call when
+/// there are any and out of the staker balance of `donor` otherwise.
+pub fn donate(donor: Principal, height: Height, amount: u128) -> Result<BlockDonations, String> {
+    check_donation(
+        height,
+        amount,
+        get_last_block().map(|(open, _)| open),
+        settlement::pending(height).is_some(),
+    )?;
+    let attached = msg_cycles_available128();
+    if attached > 0 {
+        if attached < amount {
+            return Err(format!(
+                "{} cycles attached, the donation is {}",
+                attached, amount
+            ));
+        }
+        // attached cycles never were user cycles, they are burned with the block
+        msg_cycles_accept128(amount);
+    } else {
+        let user = DodService::get_user_detail(donor).ok_or_else(|| "No user found".to_string())?;
+        if user.balance < Nat::from(amount) {
+            return Err("Not enough balance".to_string());
+        }
+        cycles::take_from_user_pool(amount);
+        DodService::decrease_user_cycle_balance(donor, Nat::from(amount))?;
+    }
+    record(height, donor, amount);
+    Ok(get_block_donations(height))
+}
+
+pub fn get_block_donations(height: Height) -> BlockDonations {
+    let donations: Vec<BlockDonation> = BLOCK_DONATIONS.with_borrow(|v| {
+        v.range((height, Principal::management_canister())..)
+            .take_while(|((block, _), _)| *block == height)
+            .map(|((_, donor), amount)| BlockDonation { donor, amount })
+            .collect()
+    });
+    BlockDonations {
+        height,
+        total: donations
+            .iter()
+            .fold(0u128, |acc, d| acc.saturating_add(d.amount)),
+        donations,
+    }
+}
+
+/// Cycles donated to block `height`, part of its pool.
+pub fn block_total(height: Height) -> u128 {
+    get_block_donations(height).total
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_donation() {
+        assert!(check_donation(10, MIN_DONATION, Some(10), false).is_ok());
+        assert!(check_donation(10, MIN_DONATION, Some(10), true).is_err());
+        assert!(check_donation(11, MIN_DONATION, Some(10), true).is_ok());
+        assert!(check_donation(9, MIN_DONATION, Some(10), false).is_err());
+        assert!(check_donation(10, MIN_DONATION - 1, Some(10), false).is_err());
+        assert!(check_donation(10, MIN_DONATION, None, false).is_err());
+        assert!(check_donation(
+            10 + MAX_DONATION_BLOCKS_AHEAD,
+            MIN_DONATION,
+            Some(10),
+            false
+        )
+        .is_ok());
+        assert!(check_donation(
+            11 + MAX_DONATION_BLOCKS_AHEAD,
+            MIN_DONATION,
+            Some(10),
+            false
+        )
+        .is_err());
+    }
+}
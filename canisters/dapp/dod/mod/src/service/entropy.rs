@@ -0,0 +1,126 @@
+use crate::memory::{get_btree, insert_btree, BLOCK_ENTROPY};
+use crate::service::era;
+use crate::state::info_log_add;
+use crate::types::BtreeValue;
+use crate::verifier::commit_txid;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use candid::Principal;
+use dod_utils::fake_32;
+use dod_utils::types::{BlockEntropy, Height, MinerCandidate};
+
+const NEXT_RAND_KEY: &str = "next_block_rand";
+
+/// Fetches fresh `raw_rand` bytes for the next block. The call is async, so each block
+/// uses the randomness fetched while the previous block was open.
+pub async fn refresh_rand() {
+    match ic_cdk::call::<(), (Vec<u8>,)>(Principal::management_canister(), "raw_rand", ()).await {
+        Ok((rand,)) => insert_btree(
+            NEXT_RAND_KEY.to_string(),
+            BtreeValue {
+                key: NEXT_RAND_KEY.to_string(),
+                value: rand,
+            },
+        ),
+        Err((code, msg)) => {
+            info_log_add(format!("raw_rand code: {:?}, msg: {}", code, msg).as_str());
+        }
+    }
+}
+
+/// Takes the fetched randomness, it is never used for two blocks. Falls back to time
+/// seeded bytes when `raw_rand` has not answered yet.
+fn take_rand() -> (Vec<u8>, bool) {
+    match get_btree(NEXT_RAND_KEY.to_string()).filter(|v| !v.value.is_empty()) {
+        Some(v) => {
+            insert_btree(
+                NEXT_RAND_KEY.to_string(),
+                BtreeValue {
+                    key: NEXT_RAND_KEY.to_string(),
+                    value: vec![],
+                },
+            );
+            (v.value, true)
+        }
+        None => (fake_32(), false),
+    }
+}
+
+/// sha256 over the sorted commit txids, so the order candidates were stored in does not matter.
+pub fn candidates_hash(commit_txids: &[String]) -> Vec<u8> {
+    let mut txids = commit_txids.to_vec();
+    txids.sort();
+    let mut engine = sha256::Hash::engine();
+    for txid in txids.iter() {
+        engine.input(txid.as_bytes());
+    }
+    sha256::Hash::from_engine(engine).to_byte_array().to_vec()
+}
+
+/// `sha256(rand || prev_hash || candidates_hash)`, every part is stored in `BlockEntropy`.
+pub fn derive_block_hash(rand: &[u8], prev_hash: &[u8], candidates_hash: &[u8]) -> Vec<u8> {
+    let mut engine = sha256::Hash::engine();
+    engine.input(rand);
+    engine.input(prev_hash);
+    engine.input(candidates_hash);
+    sha256::Hash::from_engine(engine).to_byte_array().to_vec()
+}
+
+/// Derives the hash of block `height` from the era, the previous block and its candidates and
+/// records the preimage. The era salt is hashed right before `rand`.
+pub fn next_block_hash(height: Height, prev_hash: &[u8], candidates: &[MinerCandidate]) -> Vec<u8> {
+    let (rand, from_raw_rand) = take_rand();
+    let era = era::get_era();
+    let salt = era.as_ref().map_or_else(Vec::new, |e| e.salt.clone());
+    let commit_txids = candidates
+        .iter()
+        .filter_map(|c| commit_txid(c.signed_commit_psbt.as_str()))
+        .collect::<Vec<String>>();
+    let candidates_hash = candidates_hash(commit_txids.as_slice());
+    let hash = derive_block_hash(
+        [salt.as_slice(), rand.as_slice()].concat().as_slice(),
+        prev_hash,
+        candidates_hash.as_slice(),
+    );
+    BLOCK_ENTROPY.with_borrow_mut(|v| {
+        v.insert(
+            height,
+            BlockEntropy {
+                height,
+                rand,
+                from_raw_rand,
+                prev_hash: prev_hash.to_vec(),
+                commit_txids,
+                candidates_hash,
+                era_id: era.map(|e| e.id),
+            },
+        )
+    });
+    hash
+}
+
+pub fn get_block_entropy(height: Height) -> Option<BlockEntropy> {
+    BLOCK_ENTROPY.with_borrow(|v| v.get(&height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{candidates_hash, derive_block_hash};
+
+    #[test]
+    pub fn test_derive_block_hash() {
+        let a = "aa".repeat(32);
+        let b = "bb".repeat(32);
+        assert_eq!(
+            candidates_hash(&[a.clone(), b.clone()]),
+            candidates_hash(&[b.clone(), a.clone()])
+        );
+        assert_ne!(candidates_hash(&[a.clone()]), candidates_hash(&[]));
+
+        let hash = derive_block_hash(&[1u8; 32], &[2u8; 32], &candidates_hash(&[a]));
+        assert_eq!(hash.len(), 32);
+        assert_ne!(
+            hash,
+            derive_block_hash(&[1u8; 32], &[3u8; 32], &candidates_hash(&[b]))
+        );
+    }
+}
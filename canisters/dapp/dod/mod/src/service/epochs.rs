@@ -0,0 +1,100 @@
+use crate::common::{now, self_id};
+use crate::memory::{BLOCKS, CANDIDATES, EPOCHS, NEW_BLOCK_ORDERS};
+use crate::orders::NewBlockOrders;
+use candid::Principal;
+use dod_utils::bitwork::Bitwork;
+use dod_utils::types::{EpochSummary, Height, OrderStatus};
+use std::collections::BTreeSet;
+
+pub const MAX_EPOCHS_PER_CALL: u64 = 100;
+
+/// Whether the epoch starting at `start` ends with block `height`.
+pub fn ends_epoch(start: Height, height: Height, epoch: u64) -> bool {
+    epoch > 0 && height >= start && height - start + 1 >= epoch
+}
+
+fn next_epoch() -> (u64, Height) {
+    EPOCHS.with_borrow(|v| {
+        v.last_key_value()
+            .map_or((0, 0), |(k, s)| (k + 1, s.end_height + 1))
+    })
+}
+
+/// Called once block `height` is finalized, with the difficulty of the block that follows.
+/// Writes the summary of the epoch when `height` is its last block.
+pub fn maybe_close_epoch(height: Height, difficulty_after: &Bitwork, epoch: u64) {
+    let (index, start) = next_epoch();
+    if !ends_epoch(start, height, epoch) {
+        return;
+    }
+    let mut summary = EpochSummary {
+        index,
+        start_height: start,
+        end_height: height,
+        solved_blocks: 0,
+        unsolved_blocks: 0,
+        difficulty_before: difficulty_after.clone(),
+        difficulty_after: difficulty_after.clone(),
+        dod_minted: 0,
+        dod_burned: 0,
+        cycles_burned: 0,
+        unique_miners: 0,
+        unique_stakers: 0,
+        created_at: now(),
+    };
+    BLOCKS.with_borrow(|v| {
+        if let Some(first) = v.get(&start) {
+            summary.difficulty_before = first.difficulty;
+        }
+        for (_, block) in v.range(start..=height) {
+            if block.winner.is_some() {
+                summary.solved_blocks += 1;
+            } else {
+                summary.unsolved_blocks += 1;
+            }
+            summary.dod_minted = summary.dod_minted.saturating_add(block.rewards);
+            summary.dod_burned = summary.dod_burned.saturating_add(block.dod_burned);
+            summary.cycles_burned = summary.cycles_burned.saturating_add(block.cycle_burned);
+        }
+    });
+    let miners = CANDIDATES.with_borrow(|v| {
+        v.range(start..=height)
+            .flat_map(|(_, c)| c.candidates.into_values().map(|c| c.btc_address))
+            .collect::<BTreeSet<String>>()
+    });
+    let stakers = NEW_BLOCK_ORDERS.with_borrow(|s| {
+        (start..=height)
+            .flat_map(|h| NewBlockOrders::get_orders_by_block_height(s, h))
+            .filter(|(p, o)| o.status == OrderStatus::Filled && *p != self_id())
+            .map(|(p, _)| p)
+            .collect::<BTreeSet<Principal>>()
+    });
+    summary.unique_miners = miners.len() as u64;
+    summary.unique_stakers = stakers.len() as u64;
+    EPOCHS.with_borrow_mut(|v| v.insert(index, summary));
+}
+
+/// Summaries of the epochs `from` to `to`, both included.
+pub fn get_epoch_summaries(from: u64, to: u64) -> Vec<EpochSummary> {
+    if to < from {
+        return vec![];
+    }
+    let to = to.min(from.saturating_add(MAX_EPOCHS_PER_CALL - 1));
+    EPOCHS.with_borrow(|v| v.range(from..=to).map(|(_, s)| s).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::ends_epoch;
+
+    #[test]
+    fn test_ends_epoch() {
+        assert!(!ends_epoch(0, 8, 10));
+        assert!(ends_epoch(0, 9, 10));
+        assert!(ends_epoch(10, 19, 10));
+        // the epoch was shortened while running
+        assert!(ends_epoch(10, 25, 10));
+        assert!(!ends_epoch(10, 5, 10));
+        assert!(!ends_epoch(0, 100, 0));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::common::now;
+use crate::memory::{get_btree, insert_btree};
+use crate::types::BtreeValue;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use dod_utils::types::BlockEra;
+
+const BLOCK_ERA_KEY: &str = "block_era";
+
+/// `id || started_at || salt`, kept raw as a btree value is at most 128 bytes encoded.
+pub fn encode_era(era: &BlockEra) -> Vec<u8> {
+    let mut bytes = era.id.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&era.started_at.to_le_bytes());
+    bytes.extend_from_slice(era.salt.as_slice());
+    bytes
+}
+
+pub fn decode_era(bytes: &[u8]) -> Option<BlockEra> {
+    if bytes.len() < 16 {
+        return None;
+    }
+    Some(BlockEra {
+        id: u64::from_le_bytes(bytes[0..8].try_into().ok()?),
+        started_at: u64::from_le_bytes(bytes[8..16].try_into().ok()?),
+        salt: bytes[16..].to_vec(),
+    })
+}
+
+/// The era blocks are generated in, `None` before the first bootstrap or clean up that
+/// started one.
+pub fn get_era() -> Option<BlockEra> {
+    get_btree(BLOCK_ERA_KEY.to_string()).and_then(|v| decode_era(v.value.as_slice()))
+}
+
+/// Starts a new era, its salt hashes the previous salt with the new id and start time. Kept
+/// outside the cleared maps, so the id keeps counting across clean ups, while a reinstall
+/// starts over at a later time and so with another salt.
+pub fn start_new_era() -> BlockEra {
+    let previous = get_era();
+    let id = previous.as_ref().map_or(1, |e| e.id + 1);
+    let started_at = now();
+    let mut engine = sha256::Hash::engine();
+    if let Some(previous) = previous.as_ref() {
+        engine.input(previous.salt.as_slice());
+    }
+    engine.input(&id.to_le_bytes());
+    engine.input(&started_at.to_le_bytes());
+    let era = BlockEra {
+        id,
+        started_at,
+        salt: sha256::Hash::from_engine(engine).to_byte_array().to_vec(),
+    };
+    insert_btree(
+        BLOCK_ERA_KEY.to_string(),
+        BtreeValue {
+            key: BLOCK_ERA_KEY.to_string(),
+            value: encode_era(&era),
+        },
+    );
+    era
+}
+
+/// Salt of the current era, empty before the first era so earlier hashes derive as they did.
+pub fn current_salt() -> Vec<u8> {
+    get_era().map_or_else(Vec::new, |era| era.salt)
+}
+
+/// `sha256(salt || hash)` for a hash made up without the era, such as a time seeded genesis
+/// hash. Without an era the hash is kept.
+pub fn salt_hash(hash: Vec<u8>) -> Vec<u8> {
+    let salt = current_salt();
+    if salt.is_empty() {
+        return hash;
+    }
+    let mut engine = sha256::Hash::engine();
+    engine.input(salt.as_slice());
+    engine.input(hash.as_slice());
+    sha256::Hash::from_engine(engine).to_byte_array().to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_era, encode_era};
+    use dod_utils::types::BlockEra;
+
+    #[test]
+    fn test_encode_era() {
+        let era = BlockEra {
+            id: 3,
+            started_at: 1_700_000_000_000_000_000,
+            salt: vec![7u8; 32],
+        };
+        let bytes = encode_era(&era);
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(decode_era(bytes.as_slice()), Some(era));
+        assert_eq!(decode_era(&[0u8; 8]), None);
+    }
+}
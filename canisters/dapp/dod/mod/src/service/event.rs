@@ -0,0 +1,28 @@
+use crate::memory::EVENTS;
+use candid::Principal;
+use dod_utils::types::{DodEvent, EventKind};
+
+pub fn add_event(caller: Principal, kind: EventKind) -> u64 {
+    EVENTS.with_borrow_mut(|v| {
+        let id = v.last_key_value().map_or(0, |(k, _)| k + 1);
+        v.insert(
+            id,
+            DodEvent {
+                id,
+                time: ic_cdk::api::time(),
+                caller,
+                kind,
+            },
+        );
+        id
+    })
+}
+
+pub fn get_events(from: u64, limit: u64) -> Vec<DodEvent> {
+    EVENTS.with_borrow(|v| {
+        v.range(from..)
+            .take(limit as usize)
+            .map(|(_, e)| e)
+            .collect::<Vec<DodEvent>>()
+    })
+}
@@ -0,0 +1,170 @@
+use crate::memory::{BLOCKS, NEW_BLOCK_ORDERS};
+use crate::orders::NewBlockOrders;
+use crate::service::DodService;
+use candid::Principal;
+use dod_utils::types::{BlockRange, Height, HttpRequest, HttpResponse, OrderStatus};
+use std::collections::BTreeMap;
+
+pub const ORDERS_EXPORT_PATH: &str = "/export/orders";
+/// Blocks covered by one page of an export, the next page starts at the token returned.
+pub const MAX_EXPORT_BLOCKS: u64 = 1000;
+pub const NEXT_PAGE_HEADER: &str = "x-next-page-token";
+pub const ORDERS_CSV_HEADER: &str = "block,amount,share,reward,block_time,close_time\n";
+
+/// The path of `url` and its query parameters. Values are taken as they are, principals and
+/// heights need no decoding.
+pub fn parse_url(url: &str) -> (&str, BTreeMap<&str, &str>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.split_once('=').unwrap_or((p, "")))
+        .collect();
+    (path, params)
+}
+
+/// Blocks of the page starting at `start`, up to `to` included, with the token of the next
+/// page when the range goes on.
+pub fn page_window(start: Height, to: Height) -> (Height, Height, Option<Height>) {
+    let end = to.min(start.saturating_add(MAX_EXPORT_BLOCKS - 1));
+    (start, end, (end < to).then(|| end + 1))
+}
+
+pub fn csv_row(
+    block: Height,
+    amount: u128,
+    share: f64,
+    reward: u64,
+    block_time: u64,
+    close_time: u64,
+) -> String {
+    format!(
+        "{},{},{},{},{},{}\n",
+        block, amount, share, reward, block_time, close_time
+    )
+}
+
+fn text_response(status_code: u16, body: &str) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![(
+            "content-type".to_string(),
+            "text/plain; charset=utf-8".to_string(),
+        )],
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+fn parse_height(params: &BTreeMap<&str, &str>, key: &str) -> Result<Option<Height>, String> {
+    params
+        .get(key)
+        .map(|v| v.parse::<Height>().map_err(|_| format!("Invalid {}", key)))
+        .transpose()
+}
+
+/// The settled orders of `principal` as CSV, one page of `MAX_EXPORT_BLOCKS` blocks at a
+/// time. Rewards are those the settlement credited, as computed by `get_user_block_reward`.
+fn export_orders(params: &BTreeMap<&str, &str>) -> Result<HttpResponse, String> {
+    let user = params
+        .get("principal")
+        .ok_or_else(|| "principal is needed".to_string())
+        .and_then(|p| Principal::from_text(p).map_err(|_| "Invalid principal".to_string()))?;
+    let last = DodService::get_last_block().map_or(0, |(height, _)| height);
+    let from = parse_height(params, "from")?.unwrap_or(0);
+    let to = parse_height(params, "to")?.unwrap_or(last).min(last);
+    let start = parse_height(params, "page_token")?
+        .unwrap_or(from)
+        .max(from);
+
+    let mut headers = vec![
+        (
+            "content-type".to_string(),
+            "text/csv; charset=utf-8".to_string(),
+        ),
+        (
+            "content-disposition".to_string(),
+            format!("attachment; filename=\"orders-{}.csv\"", user.to_text()),
+        ),
+    ];
+    let mut body = ORDERS_CSV_HEADER.to_string();
+    if start <= to {
+        let (start, end, next) = page_window(start, to);
+        let range = BlockRange::inclusive(start, end)?;
+        let orders = NEW_BLOCK_ORDERS.with_borrow(|v| {
+            NewBlockOrders::get_user_orders_in_range(v, user, range)
+                .filter(|(_, order)| order.status == OrderStatus::Filled)
+                .collect::<Vec<_>>()
+        });
+        for (block, order) in orders {
+            let (reward, share) =
+                DodService::get_user_block_reward(block, user).unwrap_or_default();
+            let (block_time, close_time) = BLOCKS
+                .with_borrow(|v| v.get(&block))
+                .map_or((0, 0), |b| (b.block_time, b.next_block_time));
+            body.push_str(&csv_row(
+                block,
+                order.value,
+                share,
+                reward,
+                block_time,
+                close_time,
+            ));
+        }
+        if let Some(next) = next {
+            headers.push((NEXT_PAGE_HEADER.to_string(), next.to_string()));
+        }
+    }
+    Ok(HttpResponse {
+        status_code: 200,
+        headers,
+        body: body.into_bytes(),
+    })
+}
+
+pub fn http_request(req: HttpRequest) -> HttpResponse {
+    if req.method.to_uppercase() != "GET" {
+        return text_response(405, "Method not allowed");
+    }
+    let (path, params) = parse_url(req.url.as_str());
+    match path {
+        ORDERS_EXPORT_PATH => {
+            export_orders(&params).unwrap_or_else(|e| text_response(400, e.as_str()))
+        }
+        _ => text_response(404, "Not found"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let (path, params) = parse_url("/export/orders?principal=aaaaa-aa&from=10&to=");
+        assert_eq!(path, ORDERS_EXPORT_PATH);
+        assert_eq!(params.get("principal"), Some(&"aaaaa-aa"));
+        assert_eq!(params.get("from"), Some(&"10"));
+        assert_eq!(params.get("to"), Some(&""));
+        let (path, params) = parse_url("/export/orders");
+        assert_eq!(path, ORDERS_EXPORT_PATH);
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_page_window() {
+        assert_eq!(page_window(0, 10), (0, 10, None));
+        assert_eq!(
+            page_window(0, MAX_EXPORT_BLOCKS),
+            (0, MAX_EXPORT_BLOCKS - 1, Some(MAX_EXPORT_BLOCKS))
+        );
+        assert_eq!(
+            page_window(5, 5 + MAX_EXPORT_BLOCKS - 1),
+            (5, 5 + MAX_EXPORT_BLOCKS - 1, None)
+        );
+    }
+
+    #[test]
+    fn test_csv_row() {
+        assert_eq!(csv_row(3, 100, 0.5, 50, 1, 2), "3,100,0.5,50,1,2\n");
+    }
+}
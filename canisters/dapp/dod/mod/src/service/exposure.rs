@@ -0,0 +1,60 @@
+use crate::common::self_id;
+use crate::memory::NEW_BLOCK_ORDERS;
+use crate::orders::NewBlockOrders;
+use crate::service::config;
+use dod_utils::types::{ExposureCap, Height, OrderStatus};
+
+/// The part of an order that is bet once capped at `limit`.
+pub fn capped(value: u128, limit: Option<u128>) -> u128 {
+    limit.map_or(value, |limit| value.min(limit))
+}
+
+/// The per block cap of the reward policy, resolved against the `pool` of a block.
+pub fn get_limit(pool: u128) -> Option<u128> {
+    config::get_reward_policy()
+        .and_then(|policy| policy.max_exposure)
+        .map(|cap| cap.limit(pool))
+}
+
+/// Cycles of block `height` that take part in the reward once every staker order is capped,
+/// `pool` being the stakers' deposits before the cap.
+pub fn capped_pool(height: Height, pool: u128, limit: u128) -> u128 {
+    let excess = NEW_BLOCK_ORDERS.with_borrow(|s| {
+        NewBlockOrders::get_orders_by_block_height(s, height)
+            .filter(|(p, o)| o.status != OrderStatus::Cancelled && *p != self_id())
+            .fold(0u128, |acc, (_, o)| {
+                acc.saturating_add(o.value.saturating_sub(limit))
+            })
+    });
+    pool.saturating_sub(excess)
+}
+
+/// Rejects orders over an absolute cap. A percentage of the pool is only known once the
+/// block closes and is applied at settlement.
+pub fn check_order(amount: u128) -> Result<(), String> {
+    match config::get_reward_policy().and_then(|policy| policy.max_exposure) {
+        Some(ExposureCap::Cycles(limit)) if amount > limit => Err(format!(
+            "Order of {} cycles per block is over the exposure cap of {}",
+            amount, limit
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::capped;
+    use dod_utils::types::ExposureCap;
+
+    #[test]
+    fn test_capped() {
+        assert_eq!(capped(500, None), 500);
+        assert_eq!(capped(500, Some(200)), 200);
+        assert_eq!(capped(100, Some(200)), 100);
+        assert_eq!(ExposureCap::PoolPercent(10).limit(1_005), 100);
+        assert_eq!(ExposureCap::PoolPercent(100).limit(u128::MAX), u128::MAX);
+        assert_eq!(ExposureCap::Cycles(7).limit(1_000), 7);
+        assert!(ExposureCap::PoolPercent(101).validate().is_err());
+        assert!(ExposureCap::Cycles(0).validate().is_err());
+    }
+}
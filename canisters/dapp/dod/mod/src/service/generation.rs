@@ -0,0 +1,43 @@
+use crate::common::ONE_MINUTE_NS;
+use crate::memory::GENERATION;
+
+/// A generation older than this is considered lost (e.g. a trapped ledger callback)
+/// and no longer blocks new ticks.
+const GENERATION_STALE_NS: u64 = ONE_MINUTE_NS * 10;
+
+/// Tries to start a block generation.
+/// Returns `false` and counts a skipped tick if a previous generation is still in flight.
+pub fn try_begin() -> bool {
+    let now = ic_cdk::api::time();
+    GENERATION.with_borrow_mut(|g| {
+        if g.in_flight > 0 && now.saturating_sub(g.started_at) < GENERATION_STALE_NS {
+            g.skipped_ticks += 1;
+            g.deferred = true;
+            false
+        } else {
+            g.in_flight = 1;
+            g.started_at = now;
+            g.deferred = false;
+            true
+        }
+    })
+}
+
+/// Releases one part of the current generation.
+/// Returns `true` when the generation is complete and a tick was skipped meanwhile,
+/// in which case the caller should run the deferred generation.
+pub fn release() -> bool {
+    GENERATION.with_borrow_mut(|g| {
+        g.in_flight = g.in_flight.saturating_sub(1);
+        if g.in_flight == 0 && g.deferred {
+            g.deferred = false;
+            true
+        } else {
+            false
+        }
+    })
+}
+
+pub fn get_skipped_ticks() -> u64 {
+    GENERATION.with_borrow(|g| g.skipped_ticks)
+}
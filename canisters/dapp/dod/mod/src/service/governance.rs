@@ -0,0 +1,101 @@
+use crate::memory::CONFIG;
+use crate::service::event::add_event;
+use crate::state::{owner_add, owners};
+use candid::Principal;
+use dod_utils::types::{EventKind, GovernanceMode, GovernanceStatus};
+
+pub fn get_governance_status() -> GovernanceStatus {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.governance.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// The SNS governance canister, once parameter changes are handed to it.
+pub fn get_sns_governance() -> Option<Principal> {
+    let status = get_governance_status();
+    match status.mode {
+        GovernanceMode::Sns => status.sns_governance,
+        GovernanceMode::Owners => None,
+    }
+}
+
+/// Hands parameter changes to the SNS governance canister, which can not be undone, not even by
+/// a bootstrap. The governance canister is added as an owner, the owners at the time of the
+/// migration are kept as they were and no longer pass the guards of parameter changes.
+pub fn migrate_to_sns(caller: Principal, sns_governance: Principal) -> Result<(), String> {
+    if get_sns_governance().is_some() {
+        return Err("Governance is already handed to an SNS".to_string());
+    }
+    if sns_governance == Principal::anonymous() || sns_governance == ic_cdk::id() {
+        return Err("Invalid SNS governance canister".to_string());
+    }
+    let legacy_owners = owners().map_or(vec![], |v| v.keys().cloned().collect());
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.governance = Some(GovernanceStatus {
+                    mode: GovernanceMode::Sns,
+                    sns_governance: Some(sns_governance),
+                    migrated_at: Some(ic_cdk::api::time()),
+                    legacy_owners: legacy_owners.clone(),
+                });
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })?;
+    if !legacy_owners.contains(&sns_governance) {
+        owner_add(sns_governance);
+    }
+    add_event(
+        caller,
+        EventKind::GovernanceMigrated {
+            sns_governance,
+            legacy_owners,
+        },
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::service::DodService;
+    use dod_utils::types::ClaimsHaltStatus;
+
+    fn bootstrap() {
+        DodService::new(60_000_000_000, 10, 1_000, None, vec![], None, None);
+    }
+
+    #[test]
+    fn test_bootstrap_keeps_sns_governance() {
+        bootstrap();
+        let sns_governance = Principal::from_slice(&[9; 29]);
+        CONFIG.with(|config| {
+            let mut config = config.borrow_mut();
+            let dod_service = config.dod_service.as_mut().unwrap();
+            dod_service.governance = Some(GovernanceStatus {
+                mode: GovernanceMode::Sns,
+                sns_governance: Some(sns_governance),
+                migrated_at: Some(1),
+                legacy_owners: vec![],
+            });
+            dod_service.claims_halt = Some(ClaimsHaltStatus {
+                halted: true,
+                reason: Some("incident".to_string()),
+                since: Some(1),
+            });
+        });
+
+        bootstrap();
+        assert_eq!(get_sns_governance(), Some(sns_governance));
+        assert!(DodService::get_claims_halt_status().halted);
+    }
+}
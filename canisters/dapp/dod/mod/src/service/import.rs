@@ -0,0 +1,225 @@
+use crate::memory::{BLOCKS, MINERS, SIGS};
+use crate::service::block::get_last_block;
+use crate::service::{buyback, config, entropy, psbt_store, replication, watchdog, DodService};
+use dod_utils::types::{
+    BlockData, BlockImport, BlockSigs, BlockStatus, BtcAddress, Height, MinerInfo, ScheduleAnchor,
+    StateChange,
+};
+
+/// Blocks, sigs or miners imported at once.
+pub const MAX_IMPORT_BATCH: usize = 500;
+
+/// Checks that `blocks` go on from `next_height` one height after the other and are all
+/// settled, returns the height expected after them.
+pub fn check_import_heights(next_height: Height, blocks: &[BlockData]) -> Result<Height, String> {
+    blocks.iter().try_fold(next_height, |expected, block| {
+        if block.height != expected {
+            return Err(format!(
+                "Block {} imported, block {} expected",
+                block.height, expected
+            ));
+        }
+        // blocks from before statuses were stored read as open unless they are history
+        if matches!(block.status(), BlockStatus::Open | BlockStatus::Closing) {
+            return Err(format!("Block {} is not settled", block.height));
+        }
+        Ok(expected + 1)
+    })
+}
+
+/// The running import, or a new one when no block exists yet.
+fn importing() -> Result<BlockImport, String> {
+    match config::get_block_import() {
+        Some(import) if import.sealed_at.is_some() => Err("The import is sealed".to_string()),
+        Some(import) => Ok(import),
+        None if get_last_block().is_some() => {
+            Err("Blocks are produced already, imports only go before genesis".to_string())
+        }
+        None => Ok(BlockImport::default()),
+    }
+}
+
+fn check_batch(len: usize) -> Result<(), String> {
+    if len > MAX_IMPORT_BATCH {
+        return Err(format!(
+            "At most {} entries are imported at once",
+            MAX_IMPORT_BATCH
+        ));
+    }
+    Ok(())
+}
+
+/// Errors while an import is running and not sealed, block production would settle the
+/// imported blocks again.
+pub fn check_not_importing() -> Result<(), String> {
+    match config::get_block_import() {
+        Some(import) if import.sealed_at.is_none() => {
+            Err("Blocks are being imported, seal the import first".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn import_blocks(blocks: Vec<BlockData>) -> Result<BlockImport, String> {
+    let mut import = importing()?;
+    check_batch(blocks.len())?;
+    import.next_height = check_import_heights(import.next_height, blocks.as_slice())?;
+    import.blocks += blocks.len() as u64;
+    for block in blocks {
+        BLOCKS.with_borrow_mut(|v| v.insert(block.height, block.clone()));
+        replication::record(StateChange::BlockWritten(block));
+    }
+    config::set_block_import(import.clone())?;
+    Ok(import)
+}
+
+/// Imports the winner sigs of imported blocks, with their transactions inline as
+/// `load_sigs_by_height` returns them.
+pub fn import_sigs(sigs: Vec<(Height, BlockSigs)>) -> Result<BlockImport, String> {
+    let mut import = importing()?;
+    check_batch(sigs.len())?;
+    if let Some((height, _)) = sigs
+        .iter()
+        .find(|(height, _)| *height >= import.next_height)
+    {
+        return Err(format!("Block {} is not imported", height));
+    }
+    if let Some((height, _)) = sigs
+        .iter()
+        .find(|(_, s)| s.commit_tx.is_empty() || s.reveal_tx.is_empty())
+    {
+        return Err(format!("Sigs of block {} have no transactions", height));
+    }
+    import.sigs += sigs.len() as u64;
+    for (height, s) in sigs {
+        let packed = psbt_store::pack_sigs(s.commit_tx, s.reveal_tx);
+        if let Some(replaced) = SIGS.with_borrow_mut(|v| v.insert(height, packed)) {
+            psbt_store::release_sigs(&replaced);
+        }
+    }
+    config::set_block_import(import.clone())?;
+    Ok(import)
+}
+
+pub fn import_miners(miners: Vec<MinerInfo>) -> Result<BlockImport, String> {
+    let mut import = importing()?;
+    check_batch(miners.len())?;
+    if let Some(miner) = miners
+        .iter()
+        .find(|m| MINERS.with_borrow(|v| v.contains_key(&BtcAddress(m.btc_address.clone()))))
+    {
+        return Err(format!("Miner {} exists already", miner.btc_address));
+    }
+    import.miners += miners.len() as u64;
+    for miner in miners {
+        buyback::track_miner_unclaimed(0, miner.total_dod.saturating_sub(miner.claimed_dod));
+        MINERS.with_borrow_mut(|v| v.insert(BtcAddress(miner.btc_address.clone()), miner.clone()));
+        replication::record(StateChange::MinerUpdated(miner));
+    }
+    config::set_block_import(import.clone())?;
+    Ok(import)
+}
+
+/// Locks the import and opens the block after the last imported one, block production goes
+/// on from there without `start_generate_blocks`.
+pub async fn seal_import() -> Result<BlockData, String> {
+    importing()?;
+    entropy::refresh_rand().await;
+    // checked again, the import may have changed during the call
+    let mut import = importing()?;
+    let (height, last) = get_last_block().ok_or_else(|| "No block is imported".to_string())?;
+    let interval = config::get_block_time_interval()?;
+    let grace = config::get_submission_grace();
+    let now = ic_cdk::api::time();
+    let next = BlockData {
+        height: height + 1,
+        rewards: DodService::get_block_reward_by_height(
+            height + 1,
+            DodService::get_halving_settings(),
+        )?,
+        winner: None,
+        difficulty: last.difficulty.clone(),
+        hash: entropy::next_block_hash(height + 1, last.hash.as_slice(), &[]),
+        block_time: now,
+        next_block_time: now + interval,
+        history: false,
+        cycle_burned: 0,
+        dod_burned: 0,
+        status: Some(BlockStatus::Open),
+        broadcast: None,
+        submission_cutoff: Some(now + interval + grace),
+    };
+    BLOCKS.with_borrow_mut(|v| v.insert(next.height, next.clone()));
+    replication::record(StateChange::BlockWritten(next.clone()));
+    config::set_schedule_anchor(ScheduleAnchor {
+        height: next.height,
+        time: now,
+    })?;
+    DodService::set_consider_increase(Some(next.height + config::get_difficulty_adjust_epoch()?))?;
+    import.sealed_at = Some(now);
+    config::set_block_import(import)?;
+    DodService::set_timer_delay(interval + grace, DodService::generate_blocks);
+    watchdog::start();
+    Ok(next)
+}
+
+pub fn get_block_import() -> Option<BlockImport> {
+    config::get_block_import()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dod_utils::bitwork::Bitwork;
+
+    fn block(height: Height, status: Option<BlockStatus>) -> BlockData {
+        BlockData {
+            height,
+            rewards: 0,
+            winner: None,
+            difficulty: Bitwork {
+                pre: 0,
+                post_hex: "0".to_string(),
+            },
+            hash: vec![],
+            block_time: 0,
+            next_block_time: 0,
+            history: false,
+            cycle_burned: 0,
+            dod_burned: 0,
+            status,
+            broadcast: None,
+            submission_cutoff: None,
+        }
+    }
+
+    fn legacy_block(height: Height, history: bool) -> BlockData {
+        BlockData {
+            history,
+            ..block(height, None)
+        }
+    }
+
+    #[test]
+    fn test_check_import_heights() {
+        let blocks = vec![
+            legacy_block(0, true),
+            block(1, Some(BlockStatus::Finalized)),
+            block(2, Some(BlockStatus::Settled)),
+        ];
+        assert_eq!(check_import_heights(0, &blocks), Ok(3));
+        assert_eq!(check_import_heights(3, &[]), Ok(3));
+        assert!(check_import_heights(1, &blocks).is_err());
+        assert!(check_import_heights(0, &[legacy_block(0, true), legacy_block(2, true)]).is_err());
+        assert!(check_import_heights(0, &[block(0, Some(BlockStatus::Open))]).is_err());
+    }
+
+    #[test]
+    fn test_check_import_legacy_open_block() {
+        // no stored status and not history, the block was still open
+        assert_eq!(
+            check_import_heights(0, &[legacy_block(0, false)]),
+            Err("Block 0 is not settled".to_string())
+        );
+    }
+}
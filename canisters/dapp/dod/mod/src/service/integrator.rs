@@ -0,0 +1,92 @@
+use crate::common::derive_principal;
+use crate::memory::INTEGRATORS;
+use crate::service::deposit;
+use crate::service::DodService;
+use candid::Principal;
+use dod_utils::types::Integrator;
+
+pub const MAX_INTEGRATOR_NAME_LEN: usize = 64;
+pub const MAX_END_USER_ID_LEN: usize = 64;
+
+const ACCOUNT_DOMAIN: &[u8] = b"dod-integrator";
+
+/// The account `integrator` stakes with on behalf of `end_user`. Nobody holds a key
+/// for it, so only the integrator can act on it.
+pub fn derive_account(integrator: Principal, end_user: &str) -> Principal {
+    derive_principal(ACCOUNT_DOMAIN, integrator, end_user.as_bytes())
+}
+
+pub fn get_integrator(integrator: Principal) -> Option<Integrator> {
+    INTEGRATORS.with_borrow(|v| v.get(&integrator))
+}
+
+pub fn register_integrator(integrator: Principal, name: String) -> Result<Integrator, String> {
+    if name.is_empty() || name.len() > MAX_INTEGRATOR_NAME_LEN {
+        return Err(format!(
+            "Integrator name must be 1 to {} bytes",
+            MAX_INTEGRATOR_NAME_LEN
+        ));
+    }
+    if get_integrator(integrator).is_some() {
+        return Err("Integrator already registered".to_string());
+    }
+    let record = Integrator {
+        name,
+        registered_at: ic_cdk::api::time(),
+        accounts: 0,
+        total_deposited: 0,
+    };
+    INTEGRATORS.with_borrow_mut(|v| v.insert(integrator, record.clone()));
+    Ok(record)
+}
+
+/// Resolves the account of `end_user`, failing when `integrator` is not registered.
+pub fn end_user_account(integrator: Principal, end_user: &str) -> Result<Principal, String> {
+    if get_integrator(integrator).is_none() {
+        return Err("Integrator not registered".to_string());
+    }
+    if end_user.is_empty() || end_user.len() > MAX_END_USER_ID_LEN {
+        return Err(format!(
+            "End user id must be 1 to {} bytes",
+            MAX_END_USER_ID_LEN
+        ));
+    }
+    Ok(derive_account(integrator, end_user))
+}
+
+/// Accepts the cycles attached to the call and credits them to the account of `end_user`.
+pub fn deposit_attached_cycles(integrator: Principal, end_user: &str) -> Result<u128, String> {
+    let account = end_user_account(integrator, end_user)?;
+    let new_account = DodService::get_user_detail(account).is_none();
+    let accepted = deposit::deposit_attached_cycles(account)?;
+
+    INTEGRATORS.with_borrow_mut(|v| {
+        if let Some(mut record) = v.get(&integrator) {
+            record.total_deposited = record.total_deposited.saturating_add(accepted);
+            if new_account {
+                record.accounts += 1;
+            }
+            v.insert(integrator, record);
+        }
+    });
+    Ok(accepted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_derive_account() {
+        let integrator = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+        let other = Principal::from_text("rkp4c-7iaaa-aaaaa-aaaca-cai").unwrap();
+
+        let account = derive_account(integrator, "alice");
+        assert_eq!(account, derive_account(integrator, "alice"));
+        assert_eq!(account.as_slice().len(), 29);
+        assert_ne!(account, derive_account(integrator, "bob"));
+        assert_ne!(account, derive_account(other, "alice"));
+        // a derived id, never the self-authenticating principal of a key
+        assert_eq!(account.as_slice()[28], 0x03);
+    }
+}
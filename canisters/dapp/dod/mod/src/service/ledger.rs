@@ -0,0 +1,152 @@
+use crate::log_warn;
+use crate::memory::CONFIG;
+use candid::{Nat, Principal};
+use dod_utils::types::{CanisterCheck, CanistersVerification, DodCanisters, LedgerMetadata};
+use ic_cdk::api::call::RejectionCode;
+use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue;
+
+/// What the deployed DOD ledger is created with, assumed until a ledger was queried.
+pub const DEFAULT_LEDGER_FEE: u64 = 0;
+pub const DEFAULT_LEDGER_DECIMALS: u8 = 8;
+
+pub fn get_cached_metadata() -> Option<LedgerMetadata> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.ledger_metadata.clone())
+    })
+}
+
+pub fn get_ledger_metadata() -> LedgerMetadata {
+    get_cached_metadata().unwrap_or(LedgerMetadata {
+        fee: DEFAULT_LEDGER_FEE,
+        decimals: DEFAULT_LEDGER_DECIMALS,
+        fetched_at: 0,
+    })
+}
+
+/// Reads the transfer fee and decimals of `ledger`.
+pub async fn fetch_metadata(ledger: Principal) -> Result<LedgerMetadata, String> {
+    let (fee,): (Nat,) = ic_cdk::api::call::call(ledger, "icrc1_fee", ())
+        .await
+        .map_err(|(code, msg)| format!("Unable to call icrc1_fee: {:?} {}", code, msg))?;
+    let (decimals,): (u8,) = ic_cdk::api::call::call(ledger, "icrc1_decimals", ())
+        .await
+        .map_err(|(code, msg)| format!("Unable to call icrc1_decimals: {:?} {}", code, msg))?;
+    let fee = u64::try_from(fee.0).map_err(|_| "Ledger fee does not fit in u64".to_string())?;
+    if decimals != DEFAULT_LEDGER_DECIMALS {
+        log_warn!(
+            "ledger {} has {} decimals, block rewards are expressed with {}",
+            ledger,
+            decimals,
+            DEFAULT_LEDGER_DECIMALS
+        );
+    }
+    Ok(LedgerMetadata {
+        fee,
+        decimals,
+        fetched_at: ic_cdk::api::time(),
+    })
+}
+
+fn check(method: &str, res: Result<String, String>) -> CanisterCheck {
+    let (ok, detail) = match res {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    CanisterCheck {
+        method: method.to_string(),
+        ok,
+        detail,
+        checked_at: ic_cdk::api::time(),
+    }
+}
+
+fn rejected((code, msg): (RejectionCode, String)) -> String {
+    format!("unreachable: {:?} {}", code, msg)
+}
+
+/// Calls the ledger for its symbol and metadata, the index for the ledger it indexes and the
+/// archive for its capacity, so canisters that are not the ICRC ones are found before use.
+pub async fn verify_canisters(canisters: &DodCanisters) -> CanistersVerification {
+    let symbol = ic_cdk::call::<(), (String,)>(canisters.ledger, "icrc1_symbol", ())
+        .await
+        .map(|(symbol,)| symbol)
+        .map_err(rejected);
+    let ledger = match symbol {
+        Ok(symbol) => ic_cdk::call::<(), (Vec<(String, MetadataValue)>,)>(
+            canisters.ledger,
+            "icrc1_metadata",
+            (),
+        )
+        .await
+        .map_err(rejected)
+        .and_then(|(metadata,)| {
+            if metadata.iter().any(|(key, _)| key.starts_with("icrc1:")) {
+                Ok(format!("{}, {} metadata entries", symbol, metadata.len()))
+            } else {
+                Err("icrc1_metadata has no icrc1 entries".to_string())
+            }
+        }),
+        Err(e) => Err(e),
+    };
+    let index = ic_cdk::call::<(), (Principal,)>(canisters.index, "ledger_id", ())
+        .await
+        .map_err(rejected)
+        .and_then(|(ledger_id,)| {
+            if ledger_id == canisters.ledger {
+                Ok(format!("indexes {}", ledger_id))
+            } else {
+                Err(format!("indexes another ledger {}", ledger_id))
+            }
+        });
+    let archive = ic_cdk::call::<(), (u64,)>(canisters.archive, "remaining_capacity", ())
+        .await
+        .map_err(rejected)
+        .map(|(capacity,)| format!("{} bytes remaining", capacity));
+    CanistersVerification {
+        ledger: check("icrc1_symbol, icrc1_metadata", ledger),
+        index: check("ledger_id", index),
+        archive: check("remaining_capacity", archive),
+    }
+}
+
+pub fn set_cached_metadata(metadata: LedgerMetadata) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.ledger_metadata = Some(metadata);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+/// The amount a claim leg of `amount` transfers, the ledger fee is paid out of the claim.
+pub fn amount_after_fee(amount: u64, fee: u64) -> Result<u64, String> {
+    if amount <= fee {
+        return Err(format!(
+            "Claim amount {} must be greater than the ledger fee {}",
+            amount, fee
+        ));
+    }
+    Ok(amount - fee)
+}
+
+#[cfg(test)]
+mod test {
+    use super::amount_after_fee;
+
+    #[test]
+    fn test_amount_after_fee() {
+        assert_eq!(amount_after_fee(100, 0), Ok(100));
+        assert_eq!(amount_after_fee(100, 10), Ok(90));
+        assert!(amount_after_fee(10, 10).is_err());
+        assert!(amount_after_fee(5, 10).is_err());
+    }
+}
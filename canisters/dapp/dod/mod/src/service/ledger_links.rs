@@ -0,0 +1,35 @@
+use crate::memory::BLOCK_LEDGER_LINKS;
+use candid::Nat;
+use dod_utils::types::{BlockLedgerLinks, Height};
+
+fn update(height: Height, f: impl FnOnce(&mut BlockLedgerLinks)) {
+    BLOCK_LEDGER_LINKS.with_borrow_mut(|v| {
+        let mut links = v.get(&height).unwrap_or(BlockLedgerLinks {
+            height,
+            mint_index: None,
+            burn_index: None,
+        });
+        f(&mut links);
+        v.insert(height, links);
+    })
+}
+
+/// Keeps the ledger index of the reward minted for block `height`.
+pub fn record_mint(height: Height, result: &Result<Nat, String>) {
+    if let Ok(index) = result {
+        let index = u64::try_from(index.0.clone()).ok();
+        update(height, |links| links.mint_index = index);
+    }
+}
+
+/// Keeps the ledger index of the DOD burned from the treasury for block `height`.
+pub fn record_burn(height: Height, result: &Result<Nat, String>) {
+    if let Ok(index) = result {
+        let index = u64::try_from(index.0.clone()).ok();
+        update(height, |links| links.burn_index = index);
+    }
+}
+
+pub fn get_block_ledger_links(height: Height) -> Option<BlockLedgerLinks> {
+    BLOCK_LEDGER_LINKS.with_borrow(|v| v.get(&height))
+}
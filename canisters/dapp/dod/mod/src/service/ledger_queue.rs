@@ -0,0 +1,190 @@
+use crate::common::{now, ONE_MINUTE_NS};
+use crate::memory::{LEDGER_DEAD_LETTERS, LEDGER_OP_IN_FLIGHT, LEDGER_QUEUE};
+use crate::service::{buyback, ledger_links, treasury, DodService};
+use crate::types::{LedgerDeadLetter, LedgerOp};
+use crate::{log_info, log_warn};
+use dod_utils::types::TreasuryBucket;
+use ic_cdk::spawn;
+use std::collections::BTreeMap;
+
+/// Operations of one kind queued at most, block generation waits while a kind is full.
+pub const MAX_QUEUED_PER_KIND: u64 = 20;
+/// An operation in flight for longer is considered lost (e.g. a trapped ledger callback), it is
+/// set aside as a dead letter and no longer holds the queue.
+const IN_FLIGHT_STALE_NS: u64 = ONE_MINUTE_NS * 10;
+/// Delay before block generation tries again while the queue is backlogged.
+pub const BACKLOG_RETRY_DELAY: u64 = 5_000_000_000;
+
+/// Whether a kind of operation in `ops` reached `MAX_QUEUED_PER_KIND`.
+pub fn backlogged<'a>(ops: impl IntoIterator<Item = &'a LedgerOp>) -> bool {
+    let mut queued = BTreeMap::new();
+    ops.into_iter().any(|op| {
+        let count = queued.entry(op.kind()).or_insert(0u64);
+        *count += 1;
+        *count >= MAX_QUEUED_PER_KIND
+    })
+}
+
+pub fn is_backlogged() -> bool {
+    LEDGER_QUEUE.with_borrow(|v| {
+        let ops = v.iter().map(|(_, op)| op).collect::<Vec<LedgerOp>>();
+        backlogged(ops.iter())
+    })
+}
+
+/// Queues `op` after the ones queued already and starts it when the ledger is free.
+pub fn enqueue(op: LedgerOp) {
+    LEDGER_QUEUE.with_borrow_mut(|v| {
+        let seq = v.last_key_value().map_or(0, |(k, _)| k + 1);
+        v.insert(seq, op);
+    });
+    process();
+}
+
+/// Sends the oldest queued operation, unless one is in flight already. The operation stays
+/// queued until it completes, then it is dropped, or set aside as a dead letter when it failed,
+/// and the next one is sent.
+pub fn process() {
+    let time = now();
+    match LEDGER_OP_IN_FLIGHT.with_borrow(|in_flight| *in_flight) {
+        Some((_, sent)) if time.saturating_sub(sent) < IN_FLIGHT_STALE_NS => return,
+        // the ledger may or may not have applied it, an owner has to check before requeuing
+        Some((seq, _)) => dead_letter(seq, "No reply from the ledger".to_string()),
+        None => {}
+    }
+    let Some((seq, op)) = LEDGER_QUEUE.with_borrow(|v| v.first_key_value()) else {
+        LEDGER_OP_IN_FLIGHT.with_borrow_mut(|in_flight| *in_flight = None);
+        // drained, every mint and burn queued so far reached the treasury
+        buyback::maybe_run_buyback();
+        return;
+    };
+    LEDGER_OP_IN_FLIGHT.with_borrow_mut(|in_flight| *in_flight = Some((seq, time)));
+    spawn(async move {
+        let res = run(op).await;
+        // set aside as stale while it was in flight, the queue went on without it
+        if LEDGER_OP_IN_FLIGHT.with_borrow(|in_flight| in_flight.map(|(s, _)| s)) != Some(seq) {
+            return;
+        }
+        match res {
+            Ok(()) => {
+                LEDGER_QUEUE.with_borrow_mut(|v| v.remove(&seq));
+            }
+            Err(e) => dead_letter(seq, e),
+        }
+        LEDGER_OP_IN_FLIGHT.with_borrow_mut(|in_flight| *in_flight = None);
+        process();
+    });
+}
+
+/// Moves the queued operation `seq` to the dead letters, where it waits for an owner.
+fn dead_letter(seq: u64, error: String) {
+    let Some(op) = LEDGER_QUEUE.with_borrow_mut(|v| v.remove(&seq)) else {
+        return;
+    };
+    log_warn!("ledger queue: {:?} failed: {}", op, error);
+    LEDGER_DEAD_LETTERS.with_borrow_mut(|v| {
+        v.insert(
+            seq,
+            LedgerDeadLetter {
+                op,
+                error,
+                failed_at: now(),
+            },
+        )
+    });
+}
+
+async fn run(op: LedgerOp) -> Result<(), String> {
+    log_info!("ledger queue: sending {:?}", op);
+    match op {
+        LedgerOp::MintReward { height, amount } => {
+            let res = DodService::mint_dod_award_to_treasury(amount).await;
+            ledger_links::record_mint(height, &res);
+            res?;
+            treasury::record_minted(TreasuryBucket::Emission, amount);
+            // only once the reward is minted, so a requeued reward does not mint them again
+            DodService::mint_bucket_shares(amount).await;
+            Ok(())
+        }
+        LedgerOp::Burn { height, amount } => {
+            let res = DodService::burn_dod_from_treasury(ic_cdk::id(), amount).await;
+            ledger_links::record_burn(height, &res);
+            res.map(|_| ())
+        }
+        LedgerOp::MintAssets { rewards } => {
+            // a failed asset reward stays unminted, mint_pending_asset_rewards retries it
+            DodService::mint_asset_rewards(rewards).await;
+            Ok(())
+        }
+        LedgerOp::Buyback => {
+            // a failed buyback waits for the next epoch rather than an owner
+            if let Err(e) = buyback::run_buyback().await {
+                log_warn!("buyback: {}", e);
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn get_ledger_queue() -> Vec<(u64, LedgerOp)> {
+    LEDGER_QUEUE.with_borrow(|v| v.iter().collect())
+}
+
+pub fn get_dead_letters() -> Vec<(u64, LedgerDeadLetter)> {
+    LEDGER_DEAD_LETTERS.with_borrow(|v| v.iter().collect())
+}
+
+/// Queues the dead letter `seq` again, after the operations queued already.
+pub fn requeue(seq: u64) -> Result<LedgerOp, String> {
+    let letter = LEDGER_DEAD_LETTERS
+        .with_borrow_mut(|v| v.remove(&seq))
+        .ok_or_else(|| format!("No failed ledger operation {}", seq))?;
+    enqueue(letter.op.clone());
+    Ok(letter.op)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backlogged() {
+        let burns: Vec<LedgerOp> = (0..MAX_QUEUED_PER_KIND)
+            .map(|height| LedgerOp::Burn { height, amount: 1 })
+            .collect();
+        assert!(backlogged(burns.iter()));
+        assert!(!backlogged(burns[1..].iter()));
+
+        // kinds fill up apart
+        let mixed: Vec<LedgerOp> = (0..MAX_QUEUED_PER_KIND - 1)
+            .flat_map(|height| {
+                [
+                    LedgerOp::Burn { height, amount: 1 },
+                    LedgerOp::MintReward { height, amount: 1 },
+                ]
+            })
+            .collect();
+        assert!(!backlogged(mixed.iter()));
+        assert!(!backlogged(std::iter::empty()));
+    }
+
+    #[test]
+    fn test_dead_letter() {
+        let burn = LedgerOp::Burn {
+            height: 7,
+            amount: 1,
+        };
+        LEDGER_QUEUE.with_borrow_mut(|v| v.insert(3, burn.clone()));
+        dead_letter(3, "rejected".to_string());
+        assert!(get_ledger_queue().is_empty());
+        let letters = get_dead_letters();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].0, 3);
+        assert_eq!(letters[0].1.op, burn);
+        assert_eq!(letters[0].1.error, "rejected");
+
+        // already gone from the queue, nothing more is set aside
+        dead_letter(3, "rejected".to_string());
+        assert_eq!(get_dead_letters().len(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::orders::NewUserOrders;
+use crate::service::staker::REWARD_SCALE;
+use candid::Principal;
+use dod_utils::types::LockInBonus;
+
+/// Bonus of orders committed over `blocks` blocks, growing with the range up to the maximum
+/// at `full_bonus_blocks`.
+pub fn bonus_percent(bonus: Option<&LockInBonus>, blocks: u64) -> u8 {
+    match bonus {
+        Some(bonus) if bonus.full_bonus_blocks > 0 => {
+            (bonus.max_bonus_percent as u128 * blocks.min(bonus.full_bonus_blocks) as u128
+                / bonus.full_bonus_blocks as u128) as u8
+        }
+        _ => 0,
+    }
+}
+
+/// Bonus of the order range `user` has running.
+pub fn user_bonus_percent(bonus: Option<&LockInBonus>, user: Principal) -> u8 {
+    NewUserOrders::get_user_set_range(user).map_or(0, |v| bonus_percent(bonus, v.r.len()))
+}
+
+pub fn boost(amount: u64, percent: u8) -> u64 {
+    amount.saturating_add(amount.saturating_mul(percent as u64) / 100)
+}
+
+/// A scaled reward raised by `percent`, with the DOD it adds rounded up so the treasury keeps
+/// enough to cover it.
+pub fn boost_scaled(scaled: u128, percent: u8) -> (u128, u64) {
+    let extra = scaled.saturating_mul(percent as u128) / 100;
+    let extra_dod = extra.saturating_add(REWARD_SCALE - 1) / REWARD_SCALE;
+    (
+        scaled.saturating_add(extra),
+        u64::try_from(extra_dod).unwrap_or(u64::MAX),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bonus_percent() {
+        let bonus = LockInBonus {
+            full_bonus_blocks: 1000,
+            max_bonus_percent: 20,
+        };
+        assert_eq!(bonus_percent(None, 1000), 0);
+        assert_eq!(bonus_percent(Some(&bonus), 0), 0);
+        assert_eq!(bonus_percent(Some(&bonus), 500), 10);
+        assert_eq!(bonus_percent(Some(&bonus), 1000), 20);
+        assert_eq!(bonus_percent(Some(&bonus), u64::MAX), 20);
+        assert_eq!(boost(1000, 20), 1200);
+
+        let (boosted, extra_dod) = boost_scaled(10 * REWARD_SCALE, 15);
+        assert_eq!(boosted, 11 * REWARD_SCALE + REWARD_SCALE / 2);
+        assert_eq!(extra_dod, 2);
+        assert_eq!(boost_scaled(0, 20), (0, 0));
+    }
+}
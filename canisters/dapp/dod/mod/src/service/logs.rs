@@ -0,0 +1,144 @@
+use crate::common::now;
+use crate::memory::{CONFIG, LOGS, LOG_BYTES};
+use dod_utils::types::LogEntry;
+pub use dod_utils::types::LogLevel;
+
+/// The buffer drops its oldest entries once it grows past this many bytes.
+pub const MAX_LOG_BYTES: u64 = 4 * 1024 * 1024;
+pub const MAX_LOG_MESSAGE_LEN: usize = 1024;
+pub const MAX_LOGS_PER_CALL: u64 = 500;
+// stored alongside each message: id, time and level
+const LOG_ENTRY_OVERHEAD: u64 = 32;
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::service::logs::log($crate::service::logs::LogLevel::Debug, format!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::service::logs::log($crate::service::logs::LogLevel::Info, format!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::service::logs::log($crate::service::logs::LogLevel::Warn, format!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::service::logs::log($crate::service::logs::LogLevel::Error, format!($($arg)*))
+    };
+}
+
+pub fn get_log_level() -> LogLevel {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.log_level)
+            .unwrap_or(LogLevel::Info)
+    })
+}
+
+pub fn set_log_level(level: LogLevel) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.log_level = Some(level);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+fn entry_size(message: &str) -> u64 {
+    message.len() as u64 + LOG_ENTRY_OVERHEAD
+}
+
+fn truncate(mut message: String) -> String {
+    if message.len() > MAX_LOG_MESSAGE_LEN {
+        let mut end = MAX_LOG_MESSAGE_LEN;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+    }
+    message
+}
+
+/// Prints the message and keeps it in the log buffer when `level` is at least the
+/// configured level.
+pub fn log(level: LogLevel, message: String) {
+    if level < get_log_level() {
+        return;
+    }
+    ic_cdk::println!("[{:?}] {}", level, message);
+    let message = truncate(message);
+    LOGS.with_borrow_mut(|v| {
+        let mut bytes = LOG_BYTES.with_borrow(|b| *b).unwrap_or_else(|| {
+            v.iter()
+                .fold(0, |acc, (_, e)| acc + entry_size(e.message.as_str()))
+        });
+        let id = v.last_key_value().map_or(0, |(k, _)| k + 1);
+        bytes += entry_size(message.as_str());
+        v.insert(
+            id,
+            LogEntry {
+                id,
+                time: now(),
+                level,
+                message,
+            },
+        );
+        while bytes > MAX_LOG_BYTES {
+            match v.first_key_value() {
+                Some((k, e)) => {
+                    v.remove(&k);
+                    bytes -= entry_size(e.message.as_str());
+                }
+                None => break,
+            }
+        }
+        LOG_BYTES.with_borrow_mut(|b| *b = Some(bytes));
+    })
+}
+
+/// Entries at `level` or above logged at or after `from_ts`, oldest first.
+pub fn get_logs(level: Option<LogLevel>, from_ts: Option<u64>, limit: u64) -> Vec<LogEntry> {
+    let level = level.unwrap_or(LogLevel::Debug);
+    let from_ts = from_ts.unwrap_or(0);
+    LOGS.with_borrow(|v| {
+        v.iter()
+            .map(|(_, e)| e)
+            .skip_while(|e| e.time < from_ts)
+            .filter(|e| e.level >= level)
+            .take(limit.min(MAX_LOGS_PER_CALL) as usize)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short".to_string()), "short");
+        let long = "é".repeat(MAX_LOG_MESSAGE_LEN);
+        let truncated = truncate(long);
+        assert!(truncated.len() <= MAX_LOG_MESSAGE_LEN);
+        assert!(truncated.chars().all(|c| c == 'é'));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::memory::{
+    get_blocks_memory, get_btree_memory, get_candidates_memory, get_miners_memory,
+    get_new_block_orders_memory, get_new_orders_memory, get_psbt_blobs_memory, get_stakers_memory,
+    get_upgrades_memory, BLOCKS, BTREES, CANDIDATES, MINERS, NEW_BLOCK_ORDERS, NEW_USER_ORDERS,
+    PSBT_BLOBS, SIGS, STAKERS,
+};
+use dod_utils::types::{MapUsage, MemoryReport};
+use ic_stable_structures::{Memory, StableBTreeMap, Storable};
+
+/// Entries encoded to estimate the size of a map, the report stays cheap on large maps.
+pub const MEMORY_SAMPLE_SIZE: usize = 100;
+
+/// Estimated bytes of `entries` entries, given the encoded size of the first `sampled` ones.
+pub fn approx_bytes(sampled: u64, sampled_bytes: u64, entries: u64) -> u64 {
+    if sampled == 0 {
+        return 0;
+    }
+    (sampled_bytes as u128 * entries as u128 / sampled as u128) as u64
+}
+
+fn map_usage<K, V, M>(name: &str, map: &StableBTreeMap<K, V, M>, pages: u64) -> MapUsage
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    let (sampled, sampled_bytes) =
+        map.iter()
+            .take(MEMORY_SAMPLE_SIZE)
+            .fold((0u64, 0u64), |(n, bytes), (k, v)| {
+                (
+                    n + 1,
+                    bytes + k.to_bytes().len() as u64 + v.to_bytes().len() as u64,
+                )
+            });
+    let entries = map.len();
+    MapUsage {
+        name: name.to_string(),
+        entries,
+        approx_bytes: approx_bytes(sampled, sampled_bytes, entries),
+        pages,
+    }
+}
+
+pub fn get_memory_report() -> MemoryReport {
+    let maps = vec![
+        BLOCKS.with_borrow(|v| map_usage("BLOCKS", v, get_blocks_memory().size())),
+        // shares its memory with the upgrade state
+        SIGS.with_borrow(|v| map_usage("SIGS", v, get_upgrades_memory().size())),
+        CANDIDATES.with_borrow(|v| map_usage("CANDIDATES", v, get_candidates_memory().size())),
+        PSBT_BLOBS.with_borrow(|v| map_usage("PSBT_BLOBS", v, get_psbt_blobs_memory().size())),
+        MINERS.with_borrow(|v| map_usage("MINERS", v, get_miners_memory().size())),
+        STAKERS.with_borrow(|v| map_usage("STAKERS", v, get_stakers_memory().size())),
+        NEW_BLOCK_ORDERS.with_borrow(|v| {
+            map_usage("NEW_BLOCK_ORDERS", v, get_new_block_orders_memory().size())
+        }),
+        NEW_USER_ORDERS
+            .with_borrow(|v| map_usage("NEW_USER_ORDERS", v, get_new_orders_memory().size())),
+        BTREES.with_borrow(|v| map_usage("BTREES", v, get_btree_memory().size())),
+    ];
+    MemoryReport {
+        maps,
+        stable_pages: ic_cdk::api::stable::stable64_size(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::approx_bytes;
+
+    #[test]
+    fn test_approx_bytes() {
+        assert_eq!(approx_bytes(0, 0, 10), 0);
+        assert_eq!(approx_bytes(10, 1_000, 10), 1_000);
+        assert_eq!(approx_bytes(100, 5_000, 1_000_000), 50_000_000);
+        assert_eq!(approx_bytes(3, 10, 4), 13);
+    }
+}
@@ -1,12 +1,28 @@
-use crate::memory::{BLOCKS, CANDIDATES, MINERS, SIGS};
+use crate::log_debug;
+use crate::memory::{
+    BLOCKS, CANDIDATES, MINERS, MINER_OPERATORS, MINER_REWARDS, SIGS, WINNER_ENVELOPES,
+    WINNER_PAYMENTS,
+};
 use crate::service::block::get_last_block;
-use crate::verifier::{check_signed_reveal_psbt, checked_signed_commit_psbt_b64};
+use crate::service::config::{get_bid_currency, get_btc_network, get_sigs_retention};
+use crate::service::event::add_event;
+use crate::service::{assets, buyback, psbt_store, replication};
+use crate::verifier::{
+    check_pubkey_matches_address, check_signed_reveal_psbt, checked_signed_commit_psbt_b64,
+    normalize_miner_pubkey, parse_reveal_envelope, validate_mining_address,
+};
+use base64::Engine;
 use candid::Principal;
-use dod_utils::bitwork::bitwork_match_hash;
+use dod_utils::bitwork::{bitwork_match_depth, bitwork_match_hash, bitwork_score};
+use dod_utils::block_hash::BlockHash;
+use dod_utils::tcycles::to_tcycles;
 use dod_utils::types::{
-    BlockRange, BlockSigs, BtcAddress, Height, MinerBlockData, MinerCandidate, MinerInfo,
-    MinerStatus, MinerSubmitResponse, MinterCandidates,
+    BidCurrency, BlockRange, BlockSigs, BtcAddress, EventKind, Height, MinerBlockData,
+    MinerCandidate, MinerInfo, MinerReward, MinerRewardsReconciliation, MinerStatus,
+    MinerSubmitResponse, MinersPage, MinterCandidates, StateChange, WinnerEnvelope,
+    WinnerPaymentProof,
 };
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 
 pub fn register_miner(
@@ -14,6 +30,11 @@ pub fn register_miner(
     btc_address: String,
     ecdsa_pubkey: Vec<u8>,
 ) -> Result<MinerInfo, String> {
+    let info = validate_mining_address(btc_address.as_str(), get_btc_network())
+        .map_err(|e| e.to_string())?;
+    let ecdsa_pubkey = normalize_miner_pubkey(ecdsa_pubkey.as_slice())?;
+    check_pubkey_matches_address(ecdsa_pubkey.as_slice(), &info)?;
+    let btc_address = info.address;
     match check_miner_if_existed(owner) {
         None => {
             let miner_info = MinerInfo {
@@ -30,6 +51,7 @@ pub fn register_miner(
                 v.borrow_mut()
                     .insert(BtcAddress(btc_address.clone()), miner_info.clone())
             });
+            replication::record(StateChange::MinerUpdated(miner_info.clone()));
 
             Ok(miner_info)
         }
@@ -53,7 +75,10 @@ pub fn check_miner_if_existed(caller: Principal) -> Option<MinerInfo> {
     })
 }
 
+/// Keeps the candidate with its PSBTs in the blob store, the candidate it replaces releases
+/// its own.
 pub fn add_block_candidate(height: Height, miner_candidate: MinerCandidate) {
+    let miner_candidate = psbt_store::pack_candidate(miner_candidate);
     let s = CANDIDATES.with(|v| v.borrow().get(&height));
     match s {
         None => {
@@ -66,13 +91,58 @@ pub fn add_block_candidate(height: Height, miner_candidate: MinerCandidate) {
         }
         Some(r) => {
             let mut _v = r.clone();
-            _v.candidates
-                .insert(miner_candidate.btc_address.clone(), miner_candidate.clone());
+            if let Some(replaced) = _v
+                .candidates
+                .insert(miner_candidate.btc_address.clone(), miner_candidate.clone())
+            {
+                psbt_store::release_candidate(&replaced);
+            }
             CANDIDATES.with(|v| v.borrow_mut().insert(height, _v));
         }
     }
 }
 
+/// The bid of `candidate`, as compared by `compare_bids`.
+pub fn bid_of(candidate: &MinerCandidate) -> (u128, u64) {
+    (candidate.cycles_price, candidate.dod_bid.unwrap_or(0))
+}
+
+/// Orders `(cycles_price, dod_bid)` bids under `currency`, the better bid first: the lowest
+/// cycles price, or the highest DOD bid.
+pub fn compare_bids(currency: &BidCurrency, a: (u128, u64), b: (u128, u64)) -> Ordering {
+    match currency {
+        BidCurrency::Cycles => a.0.cmp(&b.0),
+        BidCurrency::Dod => b.1.cmp(&a.1),
+    }
+}
+
+/// Sorts candidates in settlement order, the better bid first and then the earlier submission.
+pub fn sort_candidates(candidates: &mut [MinerCandidate], currency: &BidCurrency) {
+    candidates.sort_by(|a, b| {
+        compare_bids(currency, bid_of(a), bid_of(b)).then(a.submit_time.cmp(&b.submit_time))
+    });
+}
+
+/// Candidates of `candidates` competing for the reward of `asset`, in settlement order.
+pub fn competitors(
+    candidates: impl IntoIterator<Item = MinerCandidate>,
+    currency: &BidCurrency,
+    asset: &Option<String>,
+) -> Vec<MinerCandidate> {
+    let mut competitors: Vec<MinerCandidate> = candidates
+        .into_iter()
+        .filter(|c| &c.asset == asset)
+        .collect();
+    sort_candidates(&mut competitors, currency);
+    competitors
+}
+
+/// DOD the miner at `btc_address` was credited and has not claimed.
+pub fn unclaimed_dod(btc_address: &str) -> u64 {
+    get_miner_by_address(btc_address.to_string())
+        .map_or(0, |miner| miner.total_dod.saturating_sub(miner.claimed_dod))
+}
+
 pub fn get_block_candidates(height: Height) -> Vec<MinerCandidate> {
     CANDIDATES.with(|v| {
         let v = v.borrow();
@@ -83,7 +153,7 @@ pub fn get_block_candidates(height: Height) -> Vec<MinerCandidate> {
             })
             .candidates
             .iter()
-            .map(|v| v.1.clone())
+            .map(|v| psbt_store::unpack_candidate(v.1.clone()))
             .collect::<Vec<MinerCandidate>>()
     })
 }
@@ -93,7 +163,7 @@ pub fn get_mining_history_for_miners(
     block_range: BlockRange,
 ) -> Vec<MinerBlockData> {
     CANDIDATES.with_borrow(|v| {
-        v.range(block_range.0..block_range.1)
+        v.range(block_range.blocks())
             .filter(|f| f.1.candidates.get(&btc_address).is_some())
             .map(|(b, v)| {
                 let block = BLOCKS.with_borrow(|bc| bc.get(&b).unwrap());
@@ -125,7 +195,7 @@ pub fn check_if_in_candidate(btc_address: String, block: Height) -> Option<Miner
             })
             .candidates
             .get(&btc_address)
-            .map(|v| v.clone())
+            .map(|v| psbt_store::unpack_candidate(v.clone()))
     })
 }
 
@@ -152,78 +222,677 @@ pub fn miner_submit_hashes(
     signed_commit_psbt: String,
     signed_reveal_psbt: String,
     cycles_price: u128,
+    dod_bid: Option<u64>,
 ) -> Result<MinerSubmitResponse, String> {
-    match check_miner_if_existed(caller) {
-        Some(miner) => {
-            let block = get_last_block().unwrap().1;
+    match submitting_miner(caller, btc_address.as_str()) {
+        Some(miner) => submit_for_miner(
+            miner,
+            btc_address,
+            signed_commit_psbt,
+            signed_reveal_psbt,
+            cycles_price,
+            dod_bid,
+        ),
+        None => Err("Miner not found".to_string()),
+    }
+}
 
-            if block.winner.is_some() {
-                ic_cdk::println!("Block already mined {:?}", block.winner);
-                return Err("Block already mined".to_string());
+/// Checks a submission of `miner` for the open block and keeps it as the candidate of
+/// `btc_address`, which must be the miner's own address.
+pub fn submit_for_miner(
+    miner: MinerInfo,
+    btc_address: String,
+    signed_commit_psbt: String,
+    signed_reveal_psbt: String,
+    cycles_price: u128,
+    dod_bid: Option<u64>,
+) -> Result<MinerSubmitResponse, String> {
+    // candidates are kept by address, another address would replace that miner's candidate
+    if btc_address != miner.btc_address {
+        return Err("Miner can only submit for its own address".to_string());
+    }
+    let currency = get_bid_currency();
+    let (cycles_price, dod_bid) = match currency {
+        BidCurrency::Cycles => (cycles_price, None),
+        BidCurrency::Dod => {
+            let bid = dod_bid
+                .filter(|bid| *bid > 0)
+                .ok_or_else(|| "Miners bid in DOD, a DOD bid is needed".to_string())?;
+            if bid > unclaimed_dod(miner.btc_address.as_str()) {
+                return Err("DOD bid is greater than the miner's unclaimed DOD".to_string());
             }
+            (0, Some(bid))
+        }
+    };
+    let bid = (cycles_price, dod_bid.unwrap_or(0));
 
-            if block.next_block_time < ic_cdk::api::time() {
-                return Err("Not time to submit hash".to_string());
+    let block = get_last_block().unwrap().1;
+
+    if block.winner.is_some() {
+        log_debug!("Block already mined {:?}", block.winner);
+        return Err("Block already mined".to_string());
+    }
+
+    if !block.accepts_submissions(ic_cdk::api::time()) {
+        return Err("Not time to submit hash".to_string());
+    }
+
+    // a miner keeps one candidate per block, it may only be replaced by a better bid
+    let existing = check_if_in_candidate(btc_address.clone(), block.height.clone());
+    if let Some(existing) = existing.as_ref() {
+        if compare_bids(&currency, bid, bid_of(existing)) == Ordering::Greater {
+            return Err(match currency {
+                BidCurrency::Cycles => "Miner already submitted hash with a lower price",
+                BidCurrency::Dod => "Miner already submitted hash with a higher DOD bid",
             }
+            .to_string());
+        }
+    }
+
+    let block_hash = BlockHash::from(&block);
+    let (commit_txid, script_buf) = checked_signed_commit_psbt_b64(
+        signed_commit_psbt.as_str(),
+        miner.ecdsa_pubkey.clone(),
+        &block_hash,
+    )?;
+
+    // a miner keeps one candidate per block, for DMT or for one registered asset
+    let asset = assets::candidate_asset(&check_signed_reveal_psbt(
+        signed_reveal_psbt.as_str(),
+        script_buf,
+        miner.ecdsa_pubkey.clone(),
+        commit_txid.clone(),
+        miner.btc_address.clone(),
+    )?)?;
 
-            if check_if_in_candidate(btc_address.clone(), block.height.clone()).is_some() {
-                return Err("Miner already submitted hash".to_string());
+    let target = block_hash.to_display_hex();
+    let result = bitwork_match_hash(
+        commit_txid.clone(),
+        target.clone(),
+        block.difficulty.clone(),
+        false,
+    )?;
+
+    if result == false {
+        log_debug!("bitwork_match_hash  result is {:?}", result);
+        Err("Bitwork match failed".to_string())
+    } else {
+        let submit_time = match existing {
+            None => ic_cdk::api::time(),
+            Some(existing) if compare_bids(&currency, bid, bid_of(existing)) == Ordering::Less => {
+                ic_cdk::api::time()
+            }
+            Some(existing) => {
+                let (existing_txid, _) = checked_signed_commit_psbt_b64(
+                    existing.signed_commit_psbt.as_str(),
+                    miner.ecdsa_pubkey.clone(),
+                    &block_hash,
+                )?;
+                if bitwork_score(commit_txid.as_str(), target.as_str(), false)?
+                    <= bitwork_score(existing_txid.as_str(), target.as_str(), false)?
+                {
+                    return Err(
+                        "Miner already submitted hash with an equal price and work".to_string()
+                    );
+                }
+                // same bid, the candidate keeps its place in the queue
+                existing.submit_time
             }
+        };
+        let now = ic_cdk::api::time();
+        let competing_for = asset.clone();
+        // write candidate queue
+        add_block_candidate(
+            block.height.clone(),
+            MinerCandidate {
+                btc_address: btc_address.clone(),
+                cycles_price: cycles_price.clone(),
+                signed_commit_psbt,
+                submit_time,
+                signed_reveal_psbt,
+                signed_commit_psbt_hash: None,
+                signed_reveal_psbt_hash: None,
+                dod_bid,
+                match_depth: Some(bitwork_match_depth(
+                    commit_txid.as_str(),
+                    target.as_str(),
+                    &block.difficulty,
+                    false,
+                )?),
+                asset,
+            },
+        );
 
-            let mut rev = block.hash.clone();
-            rev.reverse();
-
-            let (commit_txid, script_buf) = checked_signed_commit_psbt_b64(
-                signed_commit_psbt.as_str(),
-                miner.ecdsa_pubkey.clone(),
-                rev,
-            )?;
-
-            check_signed_reveal_psbt(
-                signed_reveal_psbt.as_str(),
-                script_buf,
-                miner.ecdsa_pubkey.clone(),
-                commit_txid.clone(),
-                miner.btc_address.clone(),
-            )?;
-
-            let block_hash = hex::encode(block.hash.clone());
-            let result = bitwork_match_hash(
-                commit_txid.clone(),
-                block_hash,
-                block.difficulty.clone(),
-                false,
-            )?;
-
-            if result == false {
-                ic_cdk::println!("bitwork_match_hash  result is {:?}", result);
-                Err("Bitwork match failed".to_string())
-            } else {
-                // write candidate queue
-                add_block_candidate(
-                    block.height.clone(),
-                    MinerCandidate {
-                        btc_address: btc_address.clone(),
-                        cycles_price: cycles_price.clone(),
-                        signed_commit_psbt,
-                        submit_time: ic_cdk::api::time(),
-                        signed_reveal_psbt,
-                    },
-                );
+        // the PSBTs are not needed to rank, candidates are read as kept
+        let competitors = competitors(
+            CANDIDATES
+                .with_borrow(|v| v.get(&block.height))
+                .map_or(vec![], |v| v.candidates.into_values().collect()),
+            &currency,
+            &competing_for,
+        );
+        let best = competitors.first();
+        let best_cycles_price = best.map_or(cycles_price, |c| c.cycles_price);
+        Ok(MinerSubmitResponse {
+            block_height: block.height.clone(),
+            cycles_price: cycles_price.clone(),
+            dod_bid,
+            candidates: competitors.len() as u64,
+            best_cycles_price,
+            best_dod_bid: best.map_or(dod_bid, |c| c.dod_bid),
+            rank: competitors
+                .iter()
+                .position(|c| c.btc_address == btc_address)
+                .map_or(0, |i| i as u64 + 1),
+            time_remaining: block.submission_cutoff().saturating_sub(now),
+            cycles_price_t: to_tcycles(cycles_price),
+            best_cycles_price_t: to_tcycles(best_cycles_price),
+        })
+    }
+}
 
-                Ok(MinerSubmitResponse {
-                    block_height: block.height.clone(),
-                    cycles_price: cycles_price.clone(),
-                })
+/// Takes back up to `amount` unclaimed DOD from the miner at `btc_address`, returns how much
+/// was taken.
+pub fn debit_miner_reward(btc_address: &str, amount: u64) -> u64 {
+    MINERS.with_borrow_mut(|v| {
+        let key = BtcAddress(btc_address.to_string());
+        match v.get(&key) {
+            Some(mut miner) => {
+                let unclaimed = miner.total_dod.saturating_sub(miner.claimed_dod);
+                let debited = amount.min(unclaimed);
+                miner.total_dod -= debited;
+                buyback::track_miner_unclaimed(unclaimed, unclaimed - debited);
+                v.insert(key, miner.clone());
+                replication::record(StateChange::MinerUpdated(miner));
+                debited
             }
+            None => 0,
         }
-        None => Err("Miner not found".to_string()),
+    })
+}
+
+/// Adds DOD to the claimable total of the miner at `btc_address`.
+pub fn credit_miner_reward(btc_address: &str, amount: u64) {
+    MINERS.with_borrow_mut(|v| {
+        let key = BtcAddress(btc_address.to_string());
+        if let Some(mut miner) = v.get(&key) {
+            let unclaimed = miner.total_dod.saturating_sub(miner.claimed_dod);
+            miner.total_dod = miner.total_dod.saturating_add(amount);
+            buyback::track_miner_unclaimed(
+                unclaimed,
+                miner.total_dod.saturating_sub(miner.claimed_dod),
+            );
+            v.insert(key, miner.clone());
+            replication::record(StateChange::MinerUpdated(miner));
+        }
+    })
+}
+
+fn record_reward(height: Height, btc_address: &str, f: impl FnOnce(&mut MinerReward)) {
+    MINER_REWARDS.with_borrow_mut(|v| {
+        let mut reward = v
+            .get(&height)
+            .filter(|r| r.btc_address == btc_address)
+            .unwrap_or(MinerReward {
+                btc_address: btc_address.to_string(),
+                credited: 0,
+                debited: 0,
+                updated_at: 0,
+            });
+        f(&mut reward);
+        reward.updated_at = ic_cdk::api::time();
+        v.insert(height, reward);
+    })
+}
+
+/// Credits the DOD the winner of block `height` earns as a miner, kept apart from what its
+/// owner earns as a staker.
+pub fn credit_winner_reward(height: Height, btc_address: &str, amount: u64) {
+    credit_miner_reward(btc_address, amount);
+    record_reward(height, btc_address, |r| {
+        r.credited = r.credited.saturating_add(amount)
+    });
+}
+
+/// Takes the winning DOD bid of block `height` from the miner, returns how much was taken.
+pub fn debit_winner_bid(height: Height, btc_address: &str, amount: u64) -> u64 {
+    let debited = debit_miner_reward(btc_address, amount);
+    record_reward(height, btc_address, |r| {
+        r.debited = r.debited.saturating_add(debited)
+    });
+    debited
+}
+
+pub fn get_miner_reward(height: Height) -> Option<MinerReward> {
+    MINER_REWARDS.with_borrow(|v| v.get(&height))
+}
+
+/// Moves the miner reward of block `height` to another winner, or drops it without one.
+pub fn replace_miner_reward(height: Height, reward: Option<MinerReward>) {
+    MINER_REWARDS.with_borrow_mut(|v| match reward {
+        Some(reward) => v.insert(height, reward),
+        None => v.remove(&height),
+    });
+}
+
+/// Corrects the DOD counters of `miner` against `net`, the credits less the debits of its
+/// recorded rewards: the total is at least `net`, the claimed amount at most the total.
+/// Returns whether the total was raised and whether the claimed amount was clamped.
+pub fn reconcile_miner(miner: &mut MinerInfo, net: u64) -> (bool, bool) {
+    let raised = miner.total_dod < net;
+    if raised {
+        miner.total_dod = net;
+    }
+    let clamped = miner.claimed_dod > miner.total_dod;
+    if clamped {
+        miner.claimed_dod = miner.total_dod;
     }
+    (raised, clamped)
+}
+
+/// Reconciles the DOD counters of all miners with their recorded rewards. Rewards credited
+/// before they were recorded are kept, only totals below the recorded rewards are raised.
+pub fn reconcile_miner_rewards() -> MinerRewardsReconciliation {
+    let mut net: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    MINER_REWARDS.with_borrow(|v| {
+        for (_, reward) in v.iter() {
+            let entry = net.entry(reward.btc_address).or_insert((0, 0));
+            entry.0 = entry.0.saturating_add(reward.credited);
+            entry.1 = entry.1.saturating_add(reward.debited);
+        }
+    });
+    let mut report = MinerRewardsReconciliation::default();
+    let miners = MINERS.with_borrow(|v| v.iter().map(|(_, m)| m).collect::<Vec<_>>());
+    for mut miner in miners {
+        report.miners += 1;
+        let unclaimed = miner.total_dod.saturating_sub(miner.claimed_dod);
+        let (credited, debited) = net.get(&miner.btc_address).copied().unwrap_or((0, 0));
+        let (raised, clamped) = reconcile_miner(&mut miner, credited.saturating_sub(debited));
+        if raised {
+            report.raised += 1;
+        }
+        if clamped {
+            report.clamped += 1;
+        }
+        if raised || clamped {
+            buyback::track_miner_unclaimed(
+                unclaimed,
+                miner.total_dod.saturating_sub(miner.claimed_dod),
+            );
+            MINERS.with_borrow_mut(|v| {
+                v.insert(BtcAddress(miner.btc_address.clone()), miner.clone())
+            });
+            replication::record(StateChange::MinerUpdated(miner));
+        }
+    }
+    report
+}
+
+pub const MAX_MINER_OPERATORS: usize = 5;
+
+/// The miner a submission is made for: the miner at `btc_address` when the caller is one of
+/// its operators, otherwise the caller's own miner. Rewards follow the miner, so they always
+/// go to its owner.
+fn submitting_miner(caller: Principal, btc_address: &str) -> Option<MinerInfo> {
+    if let Some(miner) = get_miner_by_address(btc_address.to_string()) {
+        if miner.owner != caller && is_miner_operator(miner.owner, caller) {
+            return Some(miner);
+        }
+    }
+    check_miner_if_existed(caller)
+}
+
+pub fn is_miner_operator(owner: Principal, operator: Principal) -> bool {
+    MINER_OPERATORS.with_borrow(|v| v.contains_key(&(owner, operator)))
+}
+
+pub fn get_miner_operators(owner: Principal) -> Vec<Principal> {
+    MINER_OPERATORS.with_borrow(|v| {
+        v.range((owner, Principal::management_canister())..)
+            .take_while(|((o, _), _)| *o == owner)
+            .map(|((_, operator), _)| operator)
+            .collect()
+    })
+}
+
+/// Owners of the miners `operator` may submit for.
+pub fn get_operated_owners(operator: Principal) -> Vec<Principal> {
+    MINER_OPERATORS.with_borrow(|v| {
+        v.iter()
+            .filter(|((_, o), _)| *o == operator)
+            .map(|((owner, _), _)| owner)
+            .collect()
+    })
+}
+
+pub fn miner_add_operator(owner: Principal, operator: Principal) -> Result<(), String> {
+    if check_miner_if_existed(owner).is_none() {
+        return Err("Miner not found".to_string());
+    }
+    if operator == owner || operator == Principal::anonymous() {
+        return Err("Invalid operator".to_string());
+    }
+    if is_miner_operator(owner, operator) {
+        return Err("Operator already added".to_string());
+    }
+    if get_miner_operators(owner).len() >= MAX_MINER_OPERATORS {
+        return Err(format!(
+            "At most {} operators are allowed",
+            MAX_MINER_OPERATORS
+        ));
+    }
+    MINER_OPERATORS.with_borrow_mut(|v| v.insert((owner, operator), ic_cdk::api::time()));
+    Ok(())
+}
+
+pub fn miner_remove_operator(owner: Principal, operator: Principal) -> Result<(), String> {
+    MINER_OPERATORS
+        .with_borrow_mut(|v| v.remove(&(owner, operator)))
+        .map(|_| ())
+        .ok_or_else(|| "Operator not found".to_string())
 }
 
 pub fn load_sigs_by_height(height: Height) -> Option<BlockSigs> {
     SIGS.with(|v| {
         let sigs = v.borrow();
-        sigs.get(&height).map(|v| v.clone())
+        sigs.get(&height).map(psbt_store::unpack_sigs)
+    })
+}
+
+/// Keeps the signed PSBTs of `candidate` as the winner sigs of block `height`, with the
+/// payload of its reveal.
+pub fn record_winner_sigs(height: Height, candidate: &MinerCandidate) -> Result<(), String> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let commit_tx = engine
+        .decode(&candidate.signed_commit_psbt)
+        .map_err(|_| "can not decode base64".to_string())?;
+    let reveal_tx = engine
+        .decode(&candidate.signed_reveal_psbt)
+        .map_err(|_| "can not decode base64".to_string())?;
+    let sigs = psbt_store::pack_sigs(commit_tx, reveal_tx);
+    if let Some(replaced) = SIGS.with_borrow_mut(|v| v.insert(height, sigs)) {
+        psbt_store::release_sigs(&replaced);
+    }
+    record_winner_envelope(height, candidate.signed_reveal_psbt.as_str())
+}
+
+/// Forgets the sigs, payload and payment kept for the winner of block `height`.
+pub fn clear_winner(height: Height) {
+    if let Some(sigs) = SIGS.with_borrow_mut(|v| v.remove(&height)) {
+        psbt_store::release_sigs(&sigs);
+    }
+    WINNER_ENVELOPES.with_borrow_mut(|v| v.remove(&height));
+    WINNER_PAYMENTS.with_borrow_mut(|v| v.remove(&height));
+}
+
+/// Keeps the payload of the winning reveal of block `height`, so clients do not parse PSBTs.
+pub fn record_winner_envelope(height: Height, signed_reveal_psbt: &str) -> Result<(), String> {
+    let (reveal_txid, envelope) = parse_reveal_envelope(signed_reveal_psbt)?;
+    let payload = envelope.payload.unwrap_or_default();
+    let dmt = payload.dmt;
+    WINNER_ENVELOPES.with_borrow_mut(|v| {
+        v.insert(
+            height,
+            WinnerEnvelope {
+                height,
+                reveal_txid,
+                name: payload.n,
+                time: dmt.as_ref().map(|d| d.time),
+                nonce: dmt.as_ref().map(|d| d.nonce),
+                stakers: envelope.stakers.iter().map(hex::encode).collect(),
+            },
+        )
+    });
+    Ok(())
+}
+
+pub fn get_winner_envelope(height: Height) -> Option<WinnerEnvelope> {
+    WINNER_ENVELOPES.with_borrow(|v| v.get(&height))
+}
+
+/// Records the cycles credited to the winner of block `height` as an event and keeps the proof.
+pub fn record_winner_payment(height: Height, winner: &MinerInfo, cycles: u128) {
+    let event_id = add_event(
+        ic_cdk::id(),
+        EventKind::WinnerPaid {
+            height,
+            owner: winner.owner,
+            cycles,
+        },
+    );
+    WINNER_PAYMENTS.with_borrow_mut(|v| {
+        v.insert(
+            height,
+            WinnerPaymentProof {
+                height,
+                winner: winner.owner,
+                btc_address: winner.btc_address.clone(),
+                cycles_credited: cycles,
+                event_id: Some(event_id),
+                credited_at: Some(ic_cdk::api::time()),
+            },
+        )
+    });
+}
+
+/// The payment of the winner of block `height`. Blocks paid before proofs were kept are
+/// rebuilt from the block data.
+pub fn get_winner_payment_proof(height: Height) -> Option<WinnerPaymentProof> {
+    WINNER_PAYMENTS.with_borrow(|v| v.get(&height)).or_else(|| {
+        let winner = BLOCKS.with_borrow(|v| v.get(&height))?.winner?;
+        Some(WinnerPaymentProof {
+            height,
+            winner: winner.owner,
+            btc_address: winner.btc_address,
+            cycles_credited: winner.reward_cycles.unwrap_or(0),
+            event_id: None,
+            credited_at: None,
+        })
     })
 }
+
+pub const MAX_MINERS_PAGE_LIMIT: u64 = 100;
+
+/// Registered miners with `status`, in address order. Public keys are left empty unless
+/// `include_pubkey` is set.
+pub fn get_miners(
+    page: u64,
+    limit: u64,
+    status: Option<MinerStatus>,
+    include_pubkey: bool,
+) -> MinersPage {
+    let limit = limit.min(MAX_MINERS_PAGE_LIMIT);
+    MINERS.with_borrow(|v| {
+        let matches = |m: &MinerInfo| status.as_ref().map_or(true, |s| m.status == *s);
+        let total = match status {
+            Some(_) => v.iter().filter(|(_, m)| matches(m)).count() as u64,
+            None => v.len(),
+        };
+        let miners = v
+            .iter()
+            .map(|(_, m)| m)
+            .filter(|m| matches(m))
+            .skip(page.saturating_mul(limit) as usize)
+            .take(limit as usize)
+            .map(|mut m| {
+                if !include_pubkey {
+                    m.ecdsa_pubkey = vec![];
+                }
+                m
+            })
+            .collect();
+        MinersPage { miners, total }
+    })
+}
+
+pub const MAX_SIGS_PAGE_LIMIT: u64 = 100;
+/// Sigs removed per generated block, so pruning never makes block generation expensive.
+pub const PRUNE_SIGS_BATCH: u64 = 10;
+
+/// Winner sigs of the blocks won by the caller's miner address, oldest first.
+pub fn get_my_winning_sigs(
+    caller: Principal,
+    page: u64,
+    limit: u64,
+) -> Result<Vec<(Height, BlockSigs)>, String> {
+    let miner = get_miner_by_principal(caller).ok_or_else(|| "Miner not found".to_string())?;
+    let limit = limit.min(MAX_SIGS_PAGE_LIMIT);
+    Ok(SIGS.with_borrow(|sigs| {
+        BLOCKS.with_borrow(|blocks| {
+            sigs.iter()
+                .filter(|(height, _)| {
+                    blocks.get(height).map_or(false, |block| {
+                        block
+                            .winner
+                            .as_ref()
+                            .map_or(false, |w| w.btc_address == miner.btc_address)
+                    })
+                })
+                .skip(page.saturating_mul(limit) as usize)
+                .take(limit as usize)
+                .map(|(height, sigs)| (height, psbt_store::unpack_sigs(sigs)))
+                .collect()
+        })
+    }))
+}
+
+/// Removes up to `limit` sigs older than the configured retention, returns how many were removed.
+/// Nothing is pruned without a retention.
+pub fn prune_sigs(limit: u64) -> u64 {
+    let retention = match get_sigs_retention() {
+        None => return 0,
+        Some(retention) => retention,
+    };
+    let last = get_last_block().map_or(0, |(height, _)| height);
+    let cutoff = last.saturating_sub(retention);
+    let heights = SIGS.with_borrow(|v| {
+        v.range(..cutoff)
+            .take(limit as usize)
+            .map(|(height, _)| height)
+            .collect::<Vec<Height>>()
+    });
+    SIGS.with_borrow_mut(|v| {
+        for height in heights.iter() {
+            if let Some(sigs) = v.remove(height) {
+                psbt_store::release_sigs(&sigs);
+            }
+        }
+    });
+    heights.len() as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candidate(
+        btc_address: &str,
+        cycles_price: u128,
+        dod_bid: u64,
+        submit_time: u64,
+    ) -> MinerCandidate {
+        MinerCandidate {
+            btc_address: btc_address.to_string(),
+            submit_time,
+            cycles_price,
+            signed_commit_psbt: String::new(),
+            signed_reveal_psbt: String::new(),
+            signed_commit_psbt_hash: None,
+            signed_reveal_psbt_hash: None,
+            dod_bid: Some(dod_bid),
+            match_depth: None,
+            asset: None,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_miner() {
+        let mut miner = MinerInfo {
+            owner: Principal::anonymous(),
+            status: MinerStatus::Activate,
+            ecdsa_pubkey: vec![],
+            btc_address: "a".to_string(),
+            reward_cycles: None,
+            claimed_dod: 50,
+            total_dod: 40,
+        };
+        assert_eq!(reconcile_miner(&mut miner, 30), (false, true));
+        assert_eq!((miner.total_dod, miner.claimed_dod), (40, 40));
+        assert_eq!(reconcile_miner(&mut miner, 60), (true, false));
+        assert_eq!((miner.total_dod, miner.claimed_dod), (60, 40));
+        assert_eq!(reconcile_miner(&mut miner, 60), (false, false));
+    }
+
+    #[test]
+    fn test_sort_candidates() {
+        let mut candidates = vec![
+            candidate("a", 30, 5, 1),
+            candidate("b", 10, 1, 3),
+            candidate("c", 10, 9, 2),
+        ];
+        sort_candidates(&mut candidates, &BidCurrency::Cycles);
+        let order: Vec<&str> = candidates.iter().map(|c| c.btc_address.as_str()).collect();
+        assert_eq!(order, vec!["c", "b", "a"]);
+
+        sort_candidates(&mut candidates, &BidCurrency::Dod);
+        let order: Vec<&str> = candidates.iter().map(|c| c.btc_address.as_str()).collect();
+        assert_eq!(order, vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_competitors() {
+        let mut asset_candidate = candidate("d", 5, 0, 0);
+        asset_candidate.asset = Some("ABC".to_string());
+        let candidates = vec![
+            candidate("a", 30, 5, 1),
+            candidate("b", 10, 1, 3),
+            asset_candidate,
+        ];
+        let block = competitors(candidates.clone(), &BidCurrency::Cycles, &None);
+        let order: Vec<&str> = block.iter().map(|c| c.btc_address.as_str()).collect();
+        assert_eq!(order, vec!["b", "a"]);
+
+        let asset = competitors(candidates, &BidCurrency::Cycles, &Some("ABC".to_string()));
+        assert_eq!(asset.len(), 1);
+        assert_eq!(asset[0].btc_address, "d");
+    }
+
+    #[test]
+    fn test_submit_for_other_address() {
+        let miner_a = Principal::from_slice(&[1; 29]);
+        let miner_b = Principal::from_slice(&[2; 29]);
+        MINERS.with_borrow_mut(|v| {
+            for (owner, btc_address) in [(miner_a, "a"), (miner_b, "b")] {
+                v.insert(
+                    BtcAddress(btc_address.to_string()),
+                    MinerInfo {
+                        owner,
+                        status: MinerStatus::Activate,
+                        ecdsa_pubkey: vec![],
+                        btc_address: btc_address.to_string(),
+                        reward_cycles: None,
+                        claimed_dod: 0,
+                        total_dod: 0,
+                    },
+                );
+            }
+        });
+        add_block_candidate(0, candidate("a", 30, 0, 1));
+
+        // miner b is no operator of a, its submission for a leaves a's candidate in place
+        let res = miner_submit_hashes(
+            miner_b,
+            "a".to_string(),
+            String::new(),
+            String::new(),
+            10,
+            None,
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            "Miner can only submit for its own address".to_string()
+        );
+        let kept = check_if_in_candidate("a".to_string(), 0).unwrap();
+        assert_eq!((kept.cycles_price, kept.submit_time), (30, 1));
+    }
+}
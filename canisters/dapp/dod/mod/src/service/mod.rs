@@ -1,42 +1,115 @@
+pub mod amounts;
+pub mod assets;
+pub mod audit;
+pub mod blackhole;
 pub mod block;
+pub mod block_archive;
+pub mod block_json;
+pub mod boost;
+pub mod broadcast;
+pub mod build_info;
+pub mod burn_receipts;
+pub mod buyback;
+pub mod claim;
 pub mod config;
+pub mod constants;
+pub mod conversion;
+pub mod cycles;
+pub mod deploy;
+pub mod deposit;
+pub mod donations;
+pub mod entropy;
+pub mod epochs;
+pub mod era;
+pub mod event;
+pub mod export;
+pub mod exposure;
+pub mod generation;
+pub mod governance;
+pub mod import;
+pub mod integrator;
+pub mod ledger;
+pub mod ledger_links;
+pub mod ledger_queue;
+pub mod lock_in;
+pub mod logs;
+pub mod memory_report;
 pub mod miner;
+pub mod notifications;
+pub mod pool;
+pub mod pool_twap;
+pub mod priority;
+pub mod psbt_store;
+pub mod recovery;
+pub mod referral;
+pub mod replication;
+pub mod reward;
+pub mod schedule;
+pub mod settlement;
+pub mod solvency;
 pub mod staker;
-
-use crate::common::{
-    CMCClient, NotifyTopUpRequest, CMC_CAN_ID, CYCLES_BURNER_FEE, CYCLES_CREATE_FEE, ICP_CAN_ID,
-    ICP_FEE, MEMO_BURN_DOD, MEMO_TOP_UP_CANISTER, MEMO_TRANSFER, MIN_ICP_STAKE_E8S_U64,
-};
+pub mod statements;
+pub mod subscriber;
+pub mod top_up;
+pub mod transfer;
+pub mod treasury;
+pub mod watchdog;
+pub mod winner_correction;
+
+use crate::common::{now, self_id, CYCLES_BURNER_FEE, MEMO_BURN_DOD, MEMO_TRANSFER};
 use crate::management::{
     canister_add_controllers, canister_code_install, canister_code_reinstall,
     canister_code_upgrade, canister_main_create, Cycles,
 };
 use crate::memory::{
-    BLOCKS, CANDIDATES, CONFIG, MINERS, NEW_BLOCK_ORDERS, NEW_USER_ORDERS, SIGS, STAKERS, TIMER_IDS,
+    ASSET_REWARDS, BLOCKS, BLOCK_DONATIONS, BLOCK_LEDGER_LINKS, BURN_RECEIPTS, CANDIDATES,
+    CLAIM_CURSORS, CLAIM_STATEMENTS, CONFIG, EPOCHS, MINERS, MINER_REWARDS, NEW_BLOCK_ORDERS,
+    NEW_USER_ORDERS, NOTIFICATION_PREFERENCES, PENDING_CLAIMS, POOLS, POOL_ACCUMULATORS,
+    PSBT_BLOBS, REPLICATION_LOG, SIGS, STAKERS, TIMER_IDS, WATCHDOG_TIMER, WINNER_ENVELOPES,
+    WINNER_PAYMENTS,
 };
 use crate::orders::{NewBlockOrders, NewUserOrders};
 use crate::state::{info_log_add, owners};
 use crate::types::{
-    ArchiveOptions, FeatureFlags, IndexArg, IndexInitArgs, InitArgs, LedgerArgument, UpgradeArgs,
-    UserDetail,
+    ClaimLegResult, ClaimSource, ClaimStatement, Dashboard, IdentityOverview, IndexArg,
+    IndexInitArgs, LedgerArgument, LedgerDeadLetter, LedgerOp, PendingClaim, UpgradeArgs,
+    UserDetail, UserStats,
 };
-use base64::Engine;
+use crate::verifier::validate_mining_address;
+use crate::{log_debug, log_error, log_info, log_warn};
 use candid::{encode_args, CandidType, Deserialize, Encode, Nat, Principal};
 use dod_utils::bitwork::{
-    bitwork_from_height, bitwork_minus_bit_hex, bitwork_plus_bit_hex, Bitwork,
+    bitwork_from_height, bitwork_match_hash, bitwork_minus_bit_hex, bitwork_plus_bit_hex,
+    estimate_work, Bitwork,
 };
 use dod_utils::fake_32;
+use dod_utils::tcycles::to_tcycles;
 use dod_utils::types::{
-    BlockData, BlockDataFull, BlockRange, BlockSigs, BtcAddress, DodCanisters, HalvingSettings,
-    Height, MinerBlockData, MinerCandidate, MinerCandidateExt, MinerInfo, MinerSubmitResponse,
-    NewBlockOrderValue, OrderDetail, OrderStatus, UserBlockOrder, UserBlockOrderData,
+    AddressValidationError, AdminAuditEntry, AssetReward, BidCurrency, BlackholeProposal,
+    BlockArchive, BlockBundle, BlockData, BlockDataFull, BlockDataFullPage, BlockDonations,
+    BlockEntropy, BlockEra, BlockFilter, BlockImport, BlockLedgerLinks, BlockOrdersPage,
+    BlockRange, BlockSigs, BlockStatus, BroadcastInfo, BtcAddress, BtcNetwork, BuildInfo,
+    BurnReceipt, BuybackSettings, ClaimsHaltStatus, CyclesHealth, DeployPreview, DepositAccount,
+    DepositRecord, DifficultySteps, DifficultyTiers, DodCanisters, DodEvent, EfficiencyStats,
+    EmissionStatus, EpochSummary, EventKind, FailedBlockPolicy, FeeSettings, GenesisInfo,
+    GenesisParams, GovernanceStatus, HalvingSettings, HealthStatus, Height, HttpRequest,
+    HttpResponse, Integrator, InternalTransfer, LedgerMetadata, LockInBonus, LogEntry, LogLevel,
+    MaintenanceStatus, MemoryReport, MinerBlockData, MinerCandidate, MinerCandidateExt, MinerInfo,
+    MinerReward, MinerRewardsReconciliation, MinerStatus, MinerSubmitResponse, MinersPage,
+    MiningAsset, MiningAssetArgs, MiningPool, NewBlockOrderValue, NotificationPreferences,
+    OrderDetail, OrderPlacementResult, OrderPreview, OrderStatus, PendingReward, PendingRewards,
+    PoolCandidate, PoolTwap, PoolWorkerClaim, PriorityStats, ProtocolConstants, RecoverySettings,
+    RecoveryStatus, ReferralInfo, ReferralSettings, ReferrerStats, ReplicationInfo, RewardBoost,
+    RewardDustPolicy, RewardPolicy, ScheduleAnchor, ScheduledBlock, SettlementCheckpoint,
+    SettlementPhase, SolvencyReport, StakerBalance, StateChange, StateDelta, TierBonusTarget,
+    TopUpEstimate, TopUpSettings, TreasuryBucket, TreasuryBucketInfo, TreasurySplit,
+    UnbroadcastWinsPage, UserBlockOrder, UserBlockOrderData, UserBlockOrderRes, UserNotification,
+    UserOrder, WasmInfo, WinnerEnvelope, WinnerPaymentProof, WorkEstimate,
 };
 use ic_cdk::api::call::RejectionCode;
 use ic_cdk::{id, spawn};
 use ic_cdk_timers::TimerId;
-use ic_ledger_types::{
-    transfer, AccountIdentifier, Memo, Subaccount, Timestamp, Tokens, TransferArgs, TransferError,
-};
+use ic_ledger_types::{Subaccount, TransferError};
 use ic_stable_structures::storable::Blob;
 use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue;
 use icrc_ledger_types::icrc1::account::Account;
@@ -45,7 +118,17 @@ use serde::Serialize;
 use std::cmp::Ordering;
 use std::time::Duration;
 
-const DIFFICULTY_ADJUST_STEP: u8 = 1;
+const GENESIS_MESSAGE_MAX_LEN: usize = 256;
+const MAX_CLAIM_LEGS: usize = 10;
+const DASHBOARD_ORDER_BLOCKS: u64 = 20;
+// recent blocks whose pools bound the projected reward of new orders
+const PROJECTION_BLOCKS: u64 = 10;
+// open and future blocks covered by a pending rewards estimate
+const PENDING_REWARD_BLOCKS: u64 = 100;
+/// Blocks `get_orders_by_block_v2` returns at once, their candidates carry the PSBTs.
+pub const MAX_FULL_BLOCKS: u64 = 10;
+/// Orders of a block returned at once.
+pub const MAX_BLOCK_ORDERS_PAGE: u64 = 500;
 // const MIN_MINER_PRICE: u128 = 10_000_000_000u128; // 0.1T
 
 #[derive(Clone, CandidType, Debug, Serialize, Deserialize)]
@@ -64,6 +147,56 @@ pub struct DodService {
     pub archive_wasm: Option<Vec<u8>>,
     pub spv_wasm: Option<Vec<u8>>,
     pub dod_canisters: Option<DodCanisters>,
+    pub recovery_settings: Option<RecoverySettings>,
+    pub last_owner_activity: Option<u64>,
+    pub block_subscriber: Option<Principal>,
+    pub genesis: Option<GenesisInfo>,
+    pub buyback_settings: Option<BuybackSettings>,
+    pub total_buyback_burned: Option<u64>,
+    pub last_buyback_at: Option<u64>,
+    /// running totals of DOD credited but not claimed yet, seeded from a scan when `None`
+    pub staker_unclaimed: Option<u64>,
+    pub miner_unclaimed: Option<u64>,
+    pub user_pool: Option<u128>,
+    pub cycles_alert_threshold: Option<u128>,
+    pub settlement_capacity: Option<u64>,
+    pub btc_network: Option<BtcNetwork>,
+    pub maintenance: Option<MaintenanceStatus>,
+    pub failed_block_policy: Option<FailedBlockPolicy>,
+    pub submission_grace: Option<u64>,
+    pub sigs_retention: Option<u64>,
+    pub reward_dust_policy: Option<RewardDustPolicy>,
+    pub reward_policy: Option<RewardPolicy>,
+    pub fee_settings: Option<FeeSettings>,
+    pub top_up_settings: Option<TopUpSettings>,
+    pub last_top_up_at: Option<u64>,
+    /// ICP ledger block of a top-up transfer whose notification has to be retried
+    pub pending_top_up: Option<u64>,
+    pub referral_settings: Option<ReferralSettings>,
+    pub log_level: Option<LogLevel>,
+    /// progress of the latest block settlement
+    pub settlement: Option<SettlementCheckpoint>,
+    pub ledger_metadata: Option<LedgerMetadata>,
+    pub reward_boost: Option<RewardBoost>,
+    pub watchdog_factor: Option<u64>,
+    pub watchdog_restarts: Option<u64>,
+    pub last_watchdog_restart_at: Option<u64>,
+    pub cycles_per_dod: Option<u128>,
+    pub claims_halt: Option<ClaimsHaltStatus>,
+    pub difficulty_steps: Option<DifficultySteps>,
+    pub treasury_split: Option<TreasurySplit>,
+    pub treasury_buckets: Option<Vec<(TreasuryBucket, u64, u64)>>,
+    pub blackhole_proposal: Option<BlackholeProposal>,
+    pub ledger_blackholed_at: Option<u64>,
+    pub governance: Option<GovernanceStatus>,
+    pub bid_currency: Option<BidCurrency>,
+    pub difficulty_tiers: Option<DifficultyTiers>,
+    pub lock_in_bonus: Option<LockInBonus>,
+    pub claim_dedup_window: Option<u64>,
+    pub schedule_anchor: Option<ScheduleAnchor>,
+    pub block_import: Option<BlockImport>,
+    pub register_miners_as_stakers: Option<bool>,
+    pub block_archive: Option<BlockArchive>,
 }
 
 impl DodService {
@@ -144,6 +277,10 @@ impl DodService {
     ) -> Self {
         CONFIG.with(|f| {
             let mut config = f.borrow_mut();
+            // blocks start over, but neither the SNS hand-over nor a claims halt is undone
+            let previous = config.dod_service.as_ref();
+            let governance = previous.and_then(|dod_service| dod_service.governance.clone());
+            let claims_halt = previous.and_then(|dod_service| dod_service.claims_halt.clone());
             let ser = DodService {
                 block_time_interval,
                 difficulty_adjust_epoch,
@@ -160,7 +297,55 @@ impl DodService {
                 archive_wasm: None,
                 spv_wasm: None,
                 dod_canisters: None,
+                recovery_settings: None,
+                last_owner_activity: None,
+                block_subscriber: None,
+                genesis: None,
+                buyback_settings: None,
+                total_buyback_burned: None,
+                last_buyback_at: None,
+                staker_unclaimed: None,
+                miner_unclaimed: None,
+                user_pool: None,
+                cycles_alert_threshold: None,
+                settlement_capacity: None,
+                btc_network: None,
+                maintenance: None,
+                failed_block_policy: None,
+                submission_grace: None,
+                sigs_retention: None,
+                reward_dust_policy: None,
+                reward_policy: None,
+                fee_settings: None,
+                top_up_settings: None,
+                last_top_up_at: None,
+                pending_top_up: None,
+                referral_settings: None,
+                log_level: None,
+                settlement: None,
+                ledger_metadata: None,
+                reward_boost: None,
+                watchdog_factor: None,
+                watchdog_restarts: None,
+                last_watchdog_restart_at: None,
+                cycles_per_dod: None,
+                claims_halt,
+                difficulty_steps: None,
+                treasury_split: None,
+                treasury_buckets: None,
+                blackhole_proposal: None,
+                ledger_blackholed_at: None,
+                governance,
+                bid_currency: None,
+                difficulty_tiers: None,
+                lock_in_bonus: None,
+                claim_dedup_window: None,
+                schedule_anchor: None,
+                block_import: None,
+                register_miners_as_stakers: None,
+                block_archive: None,
             };
+            era::start_new_era();
             config.dod_service = Some(ser.clone());
             ser.clone()
         })
@@ -176,16 +361,42 @@ impl DodService {
         SIGS.with(|v| v.borrow_mut().clear_new());
         CANDIDATES.with(|v| v.borrow_mut().clear_new());
         STAKERS.with(|v| v.borrow_mut().clear_new());
+        buyback::reset_unclaimed();
         NEW_BLOCK_ORDERS.with(|v| v.borrow_mut().clear_new());
         NEW_USER_ORDERS.with(|v| v.borrow_mut().clear_new());
+        REPLICATION_LOG.with(|v| v.borrow_mut().clear_new());
+        EPOCHS.with(|v| v.borrow_mut().clear_new());
+        WINNER_ENVELOPES.with(|v| v.borrow_mut().clear_new());
+        WINNER_PAYMENTS.with(|v| v.borrow_mut().clear_new());
+        BLOCK_LEDGER_LINKS.with(|v| v.borrow_mut().clear_new());
+        BURN_RECEIPTS.with(|v| v.borrow_mut().clear_new());
+        NOTIFICATION_PREFERENCES.with(|v| v.borrow_mut().clear_new());
+        PSBT_BLOBS.with(|v| v.borrow_mut().clear_new());
+        POOLS.with(|v| v.borrow_mut().clear_new());
+        MINER_REWARDS.with(|v| v.borrow_mut().clear_new());
+        PENDING_CLAIMS.with(|v| v.borrow_mut().clear_new());
+        CLAIM_STATEMENTS.with(|v| v.borrow_mut().clear_new());
+        CLAIM_CURSORS.with(|v| v.borrow_mut().clear_new());
+        BLOCK_DONATIONS.with(|v| v.borrow_mut().clear_new());
+        POOL_ACCUMULATORS.with(|v| v.borrow_mut().clear_new());
+        // registered assets are kept like the config, only what they paid is cleared
+        ASSET_REWARDS.with(|v| v.borrow_mut().clear_new());
+        NewBlockOrders::invalidate_total_cycles();
+        // blocks start over, PSBTs of the blocks cleared must not match them
+        era::start_new_era();
         TIMER_IDS.with(|v| {
             if let Some(timer_id) = v.borrow_mut().pop() {
-                ic_cdk::println!("Timer canister: Stopping timer ID {timer_id:?}...");
+                log_debug!("Timer canister: Stopping timer ID {timer_id:?}...");
                 // It's safe to clear non-existent timer IDs.
                 ic_cdk_timers::clear_timer(timer_id);
             }
             v.borrow_mut().clear()
         });
+        WATCHDOG_TIMER.with(|v| {
+            if let Some(timer_id) = v.borrow_mut().take() {
+                ic_cdk_timers::clear_timer(timer_id);
+            }
+        });
     }
 
     /// Retrieves the current `DodService` instance if it exists.
@@ -279,6 +490,47 @@ impl DodService {
         });
     }
 
+    /// Retrieves the size, hash and chunking of the staged ledger wasm.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<WasmInfo>` - The wasm info, `None` if no ledger wasm is staged.
+    pub fn get_ledger_wasm_info() -> Option<WasmInfo> {
+        CONFIG.with(|config| {
+            config
+                .borrow()
+                .dod_service
+                .as_ref()
+                .and_then(|dod_service| dod_service.ledger_wasm.as_ref())
+                .map(|wasm| deploy::wasm_info(wasm))
+        })
+    }
+
+    /// Retrieves a chunk of the staged ledger wasm, a wasm too large for one reply is
+    /// fetched chunk by chunk.
+    ///
+    /// # Arguments
+    ///
+    /// * `sha256` - A `Vec<u8>` representing the hash `get_ledger_wasm_info` returned, chunks of another wasm are refused.
+    /// * `index` - A `u64` representing the chunk, from 0 to `chunks - 1`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, String>` - The bytes of the chunk, at most `WASM_CHUNK_SIZE`, or an error if the wasm changed or the chunk does not exist.
+    pub fn get_ledger_wasm_chunk(sha256: Vec<u8>, index: u64) -> Result<Vec<u8>, String> {
+        CONFIG.with(|config| {
+            match config
+                .borrow()
+                .dod_service
+                .as_ref()
+                .and_then(|dod_service| dod_service.ledger_wasm.as_ref())
+            {
+                Some(wasm) => deploy::wasm_chunk(wasm, sha256.as_slice(), index),
+                None => Err("No ledger wasm staged".to_string()),
+            }
+        })
+    }
+
     /// Retrieves the DOD canisters from the service configuration.
     ///
     /// This function accesses the global `CONFIG` to borrow the current configuration,
@@ -294,6 +546,56 @@ impl DodService {
         })
     }
 
+    /// Points the service at a DOD ledger and caches its transfer fee and decimals.
+    ///
+    /// The ledger, index and archive are called first, canisters that can not be reached or do not answer as ICRC canisters
+    /// are not configured. The checks are kept with the canisters.
+    ///
+    /// # Arguments
+    ///
+    /// * `canisters` - A `DodCanisters` representing the ledger, index and archive canisters.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LedgerMetadata, String>` - On success, returns the cached fee and decimals. On failure, returns an error message as a `String`.
+    pub async fn configure_dod_canisters(
+        canisters: DodCanisters,
+    ) -> Result<LedgerMetadata, String> {
+        let verification = ledger::verify_canisters(&canisters).await;
+        let failures = verification.failures();
+        if !failures.is_empty() {
+            return Err(format!("Canisters failed checks: {}", failures.join(", ")));
+        }
+        let metadata = ledger::fetch_metadata(canisters.ledger).await?;
+        Self::set_token_canister(canisters.ledger);
+        Self::set_dod_canisters(DodCanisters {
+            verification: Some(verification),
+            ..canisters
+        });
+        ledger::set_cached_metadata(metadata.clone())?;
+        Ok(metadata)
+    }
+
+    /// Reads the transfer fee and decimals of the configured DOD ledger again.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LedgerMetadata, String>` - On success, returns the cached fee and decimals. On failure, returns an error message as a `String`.
+    pub async fn refresh_ledger_metadata() -> Result<LedgerMetadata, String> {
+        let metadata = ledger::fetch_metadata(Self::get_token_canister()?).await?;
+        ledger::set_cached_metadata(metadata.clone())?;
+        Ok(metadata)
+    }
+
+    /// Retrieves the transfer fee and decimals claims are computed with.
+    ///
+    /// # Returns
+    ///
+    /// * `LedgerMetadata` - The cached values, or the values of the deployed ledger if none were fetched.
+    pub fn get_ledger_metadata() -> LedgerMetadata {
+        ledger::get_ledger_metadata()
+    }
+
     /// Deploys the DOD ledger canister along with its index and archive canisters.
     ///
     /// This function performs the following steps:
@@ -332,65 +634,38 @@ impl DodService {
             return Err("Archive wasm not found".to_string());
         }
 
-        let fee = CYCLES_CREATE_FEE;
+        let fee = config::get_fee_settings().cycles_create_fee;
 
         let leger_canister_id = canister_main_create(Cycles::from(fee)).await.map_err(|e| {
-            println!("Error create ledger canister: {:?}", e.msg);
+            log_error!("Error create ledger canister: {:?}", e.msg);
             e.msg
         })?;
-        println!("ledger canister: {:?} created", leger_canister_id);
+        log_info!("ledger canister: {:?} created", leger_canister_id);
 
         let index_canister_id = canister_main_create(Cycles::from(fee)).await.map_err(|e| {
-            println!("Error create index canister: {:?}", e.msg);
+            log_error!("Error create index canister: {:?}", e.msg);
             e.msg
         })?;
-        println!("index canister: {:?} created", index_canister_id);
+        log_info!("index canister: {:?} created", index_canister_id);
         let archive_canister_id = canister_main_create(Cycles::from(fee)).await.map_err(|e| {
-            println!("Error create archive canister: {:?}", e.msg);
+            log_error!("Error create archive canister: {:?}", e.msg);
             e.msg
         })?;
-        println!("archive canister: {:?} created", archive_canister_id);
+        log_info!("archive canister: {:?} created", archive_canister_id);
 
         Self::set_token_canister(leger_canister_id);
 
         let _ledger_install_result = canister_code_install(
             leger_canister_id.clone(),
             self.ledger_wasm.clone().unwrap(),
-            Encode!(&LedgerArgument::Init(InitArgs {
-                minting_account: Account {
-                    owner: dod_canister.clone(),
-                    subaccount: None
-                },
-                fee_collector_account: None,
-                initial_balances: vec![],
-                transfer_fee: Nat::from(0u64),
-                decimals: Some(8),
-                token_name: "DOD".to_string(),
-                token_symbol: "𓃡𓃡𓃡".to_string(),
-                metadata: vec![(
-                    "content-type".to_string(),
-                    MetadataValue::from("application/json")
-                ),],
-                archive_options: ArchiveOptions {
-                    trigger_threshold: 1000,
-                    num_blocks_to_archive: 2000,
-                    node_max_memory_size_bytes: None,
-                    max_message_size_bytes: None,
-                    controller_id: dod_canister.clone(),
-                    more_controller_ids: None,
-                    cycles_for_archive_creation: None,
-                    max_transactions_per_response: None,
-                },
-                max_memo_length: Some(512),
-                feature_flags: Some(FeatureFlags { icrc2: true }),
-                maximum_number_of_accounts: None,
-                accounts_overflow_trim_quantity: None,
-            }))
+            Encode!(&LedgerArgument::Init(deploy::ledger_init_args(
+                dod_canister.clone()
+            )))
             .ok(),
         )
         .await
         .map_err(|e| {
-            println!("Error installing ledger canister: {:?}", e.msg);
+            log_error!("Error installing ledger canister: {:?}", e.msg);
             e.msg
         })?;
 
@@ -404,37 +679,43 @@ impl DodService {
         )
         .await
         .map_err(|e| {
-            println!("Error installing index canister: {:?}", e.msg);
+            log_error!("Error installing index canister: {:?}", e.msg);
             e.msg
         })?;
 
         let _archive_install_result = canister_code_install(
             archive_canister_id.clone(),
             self.archive_wasm.clone().unwrap(),
-            encode_args((leger_canister_id.clone(), 2000u64, None::<u64>, None::<u64>)).ok(),
+            encode_args((
+                leger_canister_id.clone(),
+                deploy::ARCHIVE_BLOCKS,
+                None::<u64>,
+                None::<u64>,
+            ))
+            .ok(),
         )
         .await
         .map_err(|e| {
-            println!("Error installing archive canister: {:?}", e.msg);
+            log_error!("Error installing archive canister: {:?}", e.msg);
             e.msg
         })?;
 
         canister_add_controllers(leger_canister_id.clone(), all_owners.clone())
             .await
             .map_err(|e| {
-                println!("Error add controller to ledger canister: {:?}", e.msg);
+                log_error!("Error add controller to ledger canister: {:?}", e.msg);
                 e.msg
             })?;
         canister_add_controllers(index_canister_id.clone(), all_owners.clone())
             .await
             .map_err(|e| {
-                println!("Error add controller to index canister: {:?}", e.msg);
+                log_error!("Error add controller to index canister: {:?}", e.msg);
                 e.msg
             })?;
         canister_add_controllers(archive_canister_id.clone(), all_owners.clone())
             .await
             .map_err(|e| {
-                println!("Error add controller to archive canister: {:?}", e.msg);
+                log_error!("Error add controller to archive canister: {:?}", e.msg);
                 e.msg
             })?;
 
@@ -442,11 +723,25 @@ impl DodService {
             ledger: leger_canister_id,
             index: index_canister_id,
             archive: archive_canister_id,
+            verification: None,
         });
 
         Ok(leger_canister_id.clone())
     }
 
+    /// Previews `deploy_canisters` without calling anything.
+    ///
+    /// Checks that the ledger, index and archive wasms are staged and that the cycles attached
+    /// to the three canisters are available besides the user pool, and reports the init args
+    /// they would be installed with.
+    ///
+    /// # Returns
+    ///
+    /// * `DeployPreview` - The planned canisters, their cycle cost and what keeps the deploy from going ahead.
+    pub fn preview_deploy(&self) -> DeployPreview {
+        deploy::preview_deploy(self)
+    }
+
     pub async fn reset_ledgers(&self) -> Result<(), String> {
         let leger_canister_id = Self::get_dod_canisters().unwrap().ledger;
         let index_canister_id = Self::get_dod_canisters().unwrap().index;
@@ -456,41 +751,14 @@ impl DodService {
         let _ledger_install_result = canister_code_reinstall(
             leger_canister_id.clone(),
             self.ledger_wasm.clone().unwrap(),
-            Encode!(&LedgerArgument::Init(InitArgs {
-                minting_account: Account {
-                    owner: dod_canister.clone(),
-                    subaccount: None
-                },
-                fee_collector_account: None,
-                initial_balances: vec![],
-                transfer_fee: Nat::from(0u64),
-                decimals: Some(8),
-                token_name: "DOD".to_string(),
-                token_symbol: "𓃡𓃡𓃡".to_string(),
-                metadata: vec![(
-                    "content-type".to_string(),
-                    MetadataValue::from("application/json")
-                ),],
-                archive_options: ArchiveOptions {
-                    trigger_threshold: 1000,
-                    num_blocks_to_archive: 2000,
-                    node_max_memory_size_bytes: None,
-                    max_message_size_bytes: None,
-                    controller_id: dod_canister.clone(),
-                    more_controller_ids: None,
-                    cycles_for_archive_creation: None,
-                    max_transactions_per_response: None,
-                },
-                max_memo_length: Some(512),
-                feature_flags: Some(FeatureFlags { icrc2: true }),
-                maximum_number_of_accounts: None,
-                accounts_overflow_trim_quantity: None,
-            }))
+            Encode!(&LedgerArgument::Init(deploy::ledger_init_args(
+                dod_canister.clone()
+            )))
             .ok(),
         )
         .await
         .map_err(|e| {
-            println!("Error installing ledger canister: {:?}", e.msg);
+            log_error!("Error installing ledger canister: {:?}", e.msg);
             e.msg
         })?;
 
@@ -504,18 +772,24 @@ impl DodService {
         )
         .await
         .map_err(|e| {
-            println!("Error installing index canister: {:?}", e.msg);
+            log_error!("Error installing index canister: {:?}", e.msg);
             e.msg
         })?;
 
         let _archive_install_result = canister_code_reinstall(
             archive_canister_id.clone(),
             self.archive_wasm.clone().unwrap(),
-            encode_args((leger_canister_id.clone(), 2000u64, None::<u64>, None::<u64>)).ok(),
+            encode_args((
+                leger_canister_id.clone(),
+                deploy::ARCHIVE_BLOCKS,
+                None::<u64>,
+                None::<u64>,
+            ))
+            .ok(),
         )
         .await
         .map_err(|e| {
-            println!("Error installing archive canister: {:?}", e.msg);
+            log_error!("Error installing archive canister: {:?}", e.msg);
             e.msg
         })?;
 
@@ -546,29 +820,92 @@ impl DodService {
         )
         .await
         .map_err(|e| {
-            println!("Error installing index canister: {:?}", e.msg);
+            log_error!("Error installing index canister: {:?}", e.msg);
             e.msg
         })?;
 
         Ok(())
     }
 
-    pub async fn blockhole_ledger(&self) -> Result<(), String> {
+    /// Proposes to remove every controller of the ledger, index and archive.
+    ///
+    /// The ledger must answer calls, and all three canisters must be running with code installed
+    /// and enough cycles. The proposal has to be confirmed with `blockhole_ledger` before it expires.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the owner making the proposal.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlackholeProposal, String>` - On success, returns the proposal with the checks made. On failure, returns an error message as a `String`.
+    pub async fn propose_blackhole_ledger(caller: Principal) -> Result<BlackholeProposal, String> {
+        let canisters = Self::get_dod_canisters().ok_or("No ledger canisters found")?;
+        blackhole::propose(caller, &canisters).await
+    }
+
+    /// Drops the pending blackhole proposal.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the owner cancelling the proposal.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn cancel_blackhole_ledger(caller: Principal) -> Result<(), String> {
+        blackhole::cancel(caller)
+    }
+
+    /// Retrieves the pending blackhole proposal.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlackholeProposal>` - The proposal, or `None` if there is none.
+    pub fn get_blackhole_proposal() -> Option<BlackholeProposal> {
+        blackhole::get_proposal()
+    }
+
+    /// Removes every controller of the ledger, index and archive, which can not be undone.
+    ///
+    /// Confirms the proposal made by `propose_blackhole_ledger`. The checks are made again before
+    /// the first controller is removed. Each canister done is recorded in the proposal, which is
+    /// kept until all three are done, so a confirmation that failed part way is retried from
+    /// where it stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the owner confirming the proposal.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub async fn blockhole_ledger(&self, caller: Principal) -> Result<(), String> {
+        let proposal = blackhole::confirmable_proposal(now())?;
+        let canisters = Self::get_dod_canisters().ok_or("No ledger canisters found")?;
+        let done = blackhole::blackholed(&proposal);
+        // a canister without controllers can no longer be checked, they passed before it
+        if done.is_empty() {
+            blackhole::check_children(&canisters).await?;
+        }
         let DodCanisters {
             ledger,
             index,
             archive,
-        } = Self::get_dod_canisters().unwrap();
+            ..
+        } = canisters.clone();
 
-        let _ledger_exec = canister_add_controllers(ledger, vec![])
-            .await
-            .map_err(|e| e.msg)?;
-        let _index_exec = canister_add_controllers(index, vec![])
-            .await
-            .map_err(|e| e.msg)?;
-        let _archive_exec = canister_add_controllers(archive, vec![])
-            .await
-            .map_err(|e| e.msg)?;
+        for canister_id in [ledger, index, archive] {
+            if done.contains(&canister_id) {
+                continue;
+            }
+            if let Err(e) = canister_add_controllers(canister_id, vec![]).await {
+                blackhole::record_failed(caller, canister_id, e.msg.clone());
+                return Err(e.msg);
+            }
+            blackhole::record_step(canister_id)?;
+        }
+        blackhole::record_blackholed(caller, &canisters);
         Ok(())
     }
 
@@ -576,6 +913,28 @@ impl DodService {
         config::set_difficulty_adjust_epoch(epoch)
     }
 
+    /// Sets how far the difficulty moves at each adjustment.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - A `DifficultySteps` with the increase and decrease steps, each between 1 and 15.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_difficulty_steps(steps: DifficultySteps) -> Result<(), String> {
+        config::set_difficulty_steps(steps)
+    }
+
+    /// Retrieves how far the difficulty moves at each adjustment.
+    ///
+    /// # Returns
+    ///
+    /// * `DifficultySteps` - The increase and decrease steps, one sixteenth of a hex character each by default.
+    pub fn get_difficulty_steps() -> DifficultySteps {
+        config::get_difficulty_steps()
+    }
+
     /// Retrieves the token canister.
     ///
     /// # Returns
@@ -707,1478 +1066,4932 @@ impl DodService {
         config::set_consider_increase(consider_increase)
     }
 
-    // Staker Execution
-    /// Generates a subaccount from a given `Principal` identifier.
+    // Owner recovery
+    /// Hands parameter changes to an SNS, which can not be undone.
+    ///
+    /// From then on only the SNS governance canister passes the guards of parameter changes
+    /// and emergency calls. It is added as an owner, the other owners are kept as they were.
     ///
     /// # Arguments
     ///
-    /// * `id` - A `Principal` representing the identifier from which the subaccount is to be generated.
+    /// * `caller` - A `Principal` representing the owner making the migration.
+    /// * `sns_governance` - A `Principal` representing the SNS governance canister.
     ///
     /// # Returns
     ///
-    /// * `Subaccount` - The generated subaccount.
-    pub fn user_subaccount(id: Principal) -> Subaccount {
-        Subaccount::from(id)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn migrate_to_sns(caller: Principal, sns_governance: Principal) -> Result<(), String> {
+        governance::migrate_to_sns(caller, sns_governance)
     }
 
-    /// Registers a user.
+    /// Retrieves who changes parameters.
     ///
-    /// # Arguments
+    /// # Returns
     ///
-    /// * `user` - A `Principal` representing the user to be registered.
+    /// * `GovernanceStatus` - The governance mode, the SNS governance canister and the owners at the migration.
+    pub fn get_governance_status() -> GovernanceStatus {
+        governance::get_governance_status()
+    }
+
+    /// Retrieves the SNS governance canister, once parameter changes are handed to it.
     ///
     /// # Returns
     ///
-    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
-    pub fn register_user(user: Principal) -> Result<(), String> {
-        staker::register_user(user)
+    /// * `Option<Principal>` - The governance canister, or `None` while owners change parameters.
+    pub fn get_sns_governance() -> Option<Principal> {
+        governance::get_sns_governance()
     }
 
-    /// Sets the burn rate for a given user.
+    /// Records that an owner has just called a guarded update.
+    ///
+    /// The timestamp drives the inactivity window of the recovery principal.
+    pub fn record_owner_activity() {
+        recovery::record_owner_activity()
+    }
+
+    /// Configures the recovery principal and the owner inactivity window.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user whose burn rate is to be set.
-    /// * `burn_rate` - A `u128` value representing the new burn rate to be set for the user.
+    /// * `caller` - A `Principal` representing the owner configuring recovery.
+    /// * `recovery_principal` - A `Principal` allowed to claim ownership once owners are inactive.
+    /// * `inactivity_window` - A `u64` representing the inactivity window in nanoseconds.
     ///
     /// # Returns
     ///
     /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
-    pub fn user_set_burnrate(user: Principal, burn_rate: u128) -> Result<(), String> {
-        staker::user_set_burnrate(user, burn_rate)
+    pub fn set_recovery_settings(
+        caller: Principal,
+        recovery_principal: Principal,
+        inactivity_window: u64,
+    ) -> Result<(), String> {
+        recovery::set_recovery_settings(caller, recovery_principal, inactivity_window)
     }
 
-    /// Retrieves the burn rate and balance for a given user.
+    /// Removes the recovery principal, cancelling any possibility of a recovery claim.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user whose burn rate and balance are to be retrieved.
+    /// * `caller` - A `Principal` representing the owner cancelling recovery.
     ///
     /// # Returns
     ///
-    /// * `Result<(u128, Nat), String>` - On success, returns a tuple containing the burn rate as `u128` and the balance as `Nat`.
-    ///   On failure, returns an error message as a `String`.
-    pub fn get_user_burnrate(user: Principal) -> Result<(u128, Nat), String> {
-        staker::get_user_burnrate(user)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn cancel_recovery(caller: Principal) -> Result<(), String> {
+        recovery::cancel_recovery(caller)
     }
 
-    /// Places burn rate orders for a user.
-    ///
-    /// This function calculates the number of orders based on the user's burn rate and the specified burn amount.
-    /// It then places the orders for the user within the specified block range.
-    ///
-    /// # Arguments
-    ///
-    /// * `user` - A `Principal` representing the user.
-    /// * `start_height` - A `Height` representing the starting block height.
-    /// * `burn_amount` - A `u128` representing the total amount to be burned.
+    /// Retrieves the recovery settings, the last owner activity and when a claim becomes possible.
     ///
     /// # Returns
     ///
-    /// * `Result<(), String>` - Returns `Ok(())` if the orders are successfully placed, otherwise returns an error message.
-    pub fn user_put_burnrate_orders(
-        user: Principal,
-        start_height: Height,
-        burn_amount: u128,
-    ) -> Result<(), String> {
-        match Self::get_user_burnrate(user) {
-            Ok((rate, balance)) => {
-                let n_rate = Nat::from(rate);
-                let n_amount = Nat::from(burn_amount);
-
-                if balance < n_amount {
-                    return Err("Not enough balance".to_string());
-                }
-
-                if balance < n_rate {
-                    return Err("Not enough balance".to_string());
-                }
-
-                ic_cdk::println!("Burn rate: {:?}, Burn amount: {:?}", rate, burn_amount);
-
-                let times: u128 =
-                    u128::try_from((burn_amount / n_rate).0).expect("Can not convert to u128");
-
-                if times == 0 {
-                    return Err("Amount too low".to_string());
-                }
-
-                // if times > BURN_ORDERS_LIMIT {
-                //     return Err(format!(
-                //         "Burn Orders are over the limit {:?}",
-                //         BURN_ORDERS_LIMIT
-                //     ));
-                // }
-
-                // for i in 0..times {
-                //     Self::user_put_order(
-                //         user.clone(),
-                //         UserType::User,
-                //         start_height + u64::try_from(i).expect("can not convert to u64"),
-                //         rate,
-                //     )
-                //     .expect("Can not put order");
-                // }
-
-                let end_height =
-                    start_height + u64::try_from(times).expect("can not convert to u64");
-
-                Self::user_put_order_v2(user.clone(), (start_height, end_height), rate);
-
-                Ok(())
-            }
-            Err(e) => Err(e),
-        }
+    /// * `Result<RecoveryStatus, String>` - On success, returns the `RecoveryStatus`. On failure, returns an error message as a `String`.
+    pub fn get_recovery_status() -> Result<RecoveryStatus, String> {
+        recovery::get_recovery_status()
     }
 
-    /// Retrieves the current number of miners.
+    /// Adds the recovery principal as owner if no owner activity was observed within the inactivity window.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the recovery principal.
     ///
     /// # Returns
     ///
-    /// * `u32` - The current number of miners.
-    pub fn get_current_miners_length() -> u32 {
-        miner::get_current_miners_length()
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn claim_ownership(caller: Principal) -> Result<(), String> {
+        recovery::claim_ownership(caller)
     }
 
-    /// Checks if a miner exists.
+    /// Retrieves recorded events starting from a given event id.
     ///
     /// # Arguments
     ///
-    /// * `caller` - A `Principal` representing the caller.
-    /// * `btc_address` - A `String` representing the Bitcoin address.
+    /// * `from` - A `u64` representing the first event id to return.
+    /// * `limit` - A `u64` representing the maximum number of events to return.
     ///
     /// # Returns
     ///
-    /// * `Option<MinerInfo>` - Returns `Some(MinerInfo)` if the miner exists, otherwise `None`.
-    pub fn check_miner_if_existed(caller: Principal, _btc_address: String) -> Option<MinerInfo> {
-        miner::check_miner_if_existed(caller)
+    /// * `Vec<DodEvent>` - The events in ascending id order.
+    pub fn get_events(from: u64, limit: u64) -> Vec<DodEvent> {
+        event::get_events(from, limit)
     }
 
-    /// Loads signatures by block height.
+    /// Retrieves entries of the log buffer.
     ///
     /// # Arguments
     ///
-    /// * `height` - A `Height` representing the block height.
+    /// * `level` - An `Option<LogLevel>` representing the lowest level to return, `None` for all levels.
+    /// * `from_ts` - An `Option<u64>` representing the earliest log time in nanoseconds.
+    /// * `limit` - A `u64` representing the maximum number of entries to return, capped at `MAX_LOGS_PER_CALL`.
     ///
     /// # Returns
     ///
-    /// * `Option<BlockSigs>` - Returns `Some(BlockSigs)` if signatures are found, otherwise `None`.
-    pub fn load_sigs_by_height(height: Height) -> Option<BlockSigs> {
-        miner::load_sigs_by_height(height)
+    /// * `Vec<LogEntry>` - The matching entries, oldest first.
+    pub fn get_logs(level: Option<LogLevel>, from_ts: Option<u64>, limit: u64) -> Vec<LogEntry> {
+        logs::get_logs(level, from_ts, limit)
     }
 
-    /// Submits hashes for a miner.
+    /// Sets the lowest level kept in the log buffer.
     ///
     /// # Arguments
     ///
-    /// * `caller` - A `Principal` representing the caller.
-    /// * `btc_address` - A `String` representing the Bitcoin address.
-    /// * `signed_commit_psbt` - A `String` representing the signed commit PSBT.
-    /// * `signed_reveal_psbt` - A `String` representing the signed reveal PSBT.
-    /// * `cycles_price` - A `u128` representing the cycles price.
+    /// * `level` - A `LogLevel`, messages below it are only printed.
     ///
     /// # Returns
     ///
-    /// * `Result<MinerSubmitResponse, String>` - On success, returns `MinerSubmitResponse`. On failure, returns an error message as a `String`.
-    pub fn miner_submit_hashes(
-        caller: Principal,
-        btc_address: String,
-        signed_commit_psbt: String,
-        signed_reveal_psbt: String,
-        cycles_price: u128,
-    ) -> Result<MinerSubmitResponse, String> {
-        // if cycles_price < MIN_MINER_PRICE {
-        //     return Err(format!("Cycles price below {:?} cycles", MIN_MINER_PRICE));
-        // }
-        miner::miner_submit_hashes(
-            caller,
-            btc_address,
-            signed_commit_psbt,
-            signed_reveal_psbt,
-            cycles_price,
-        )
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_log_level(level: LogLevel) -> Result<(), String> {
+        logs::set_log_level(level)
     }
 
-    /// Adds a block candidate.
+    /// Retrieves the lowest level kept in the log buffer.
     ///
-    /// # Arguments
+    /// # Returns
     ///
-    /// * `height` - A `Height` representing the block height.
-    /// * `miner_candidate` - A `MinerCandidate` representing the miner candidate.
-    pub fn add_block_candidate(height: Height, miner_candidate: MinerCandidate) {
-        miner::add_block_candidate(height, miner_candidate)
+    /// * `LogLevel` - The configured level, `Info` by default.
+    pub fn get_log_level() -> LogLevel {
+        logs::get_log_level()
     }
 
-    /// Retrieves block candidates by height.
+    /// Checks the protocol version of a read replica before it starts following the state deltas.
     ///
     /// # Arguments
     ///
-    /// * `height` - A `Height` representing the block height.
+    /// * `replica_version` - A `u32` representing the replication protocol version the replica speaks.
     ///
     /// # Returns
     ///
-    /// * `Vec<MinerCandidate>` - A vector of `MinerCandidate` for the given height.
-    pub fn get_block_candidates(height: Height) -> Vec<MinerCandidate> {
-        miner::get_block_candidates(height)
+    /// * `Result<ReplicationInfo, String>` - On success, returns the protocol version and the sequence range of the delta log. On failure, returns an error message as a `String`.
+    pub fn replication_handshake(replica_version: u32) -> Result<ReplicationInfo, String> {
+        replication::handshake(replica_version)
     }
 
-    /// Checks if a Bitcoin address is in the candidate list for a given block.
+    /// Retrieves the state deltas recorded after a given sequence number.
     ///
     /// # Arguments
     ///
-    /// * `btc_address` - A `String` representing the Bitcoin address.
-    /// * `block` - A `Height` representing the block height.
+    /// * `since_seq` - An `Option<u64>` representing the last sequence number the replica applied, `None` to start from the oldest delta.
+    /// * `limit` - A `u64` representing the maximum number of deltas to return, capped at `MAX_DELTAS_PER_CALL`. Fewer are returned once they reach `MAX_DELTA_BYTES_PER_CALL` encoded bytes.
     ///
     /// # Returns
     ///
-    /// * `Option<MinerCandidate>` - Returns `Some(MinerCandidate)` if the address is in the candidate list, otherwise `None`.
-    pub fn check_if_in_candidate(btc_address: String, block: Height) -> Option<MinerCandidate> {
-        miner::check_if_in_candidate(btc_address, block)
+    /// * `Result<Vec<StateDelta>, String>` - On success, returns the deltas in ascending sequence order. On failure, returns an error message as a `String`.
+    pub fn get_state_deltas(since_seq: Option<u64>, limit: u64) -> Result<Vec<StateDelta>, String> {
+        replication::get_deltas(since_seq, limit)
     }
 
-    /// Retrieves miner information by principal.
+    /// Sets the canister notified through `on_block(BlockData)` after each block is finalized.
     ///
     /// # Arguments
     ///
-    /// * `principal` - A `Principal` representing the principal.
+    /// * `subscriber` - An `Option<Principal>` representing the subscriber canister, `None` disables notifications.
     ///
     /// # Returns
     ///
-    /// * `Option<MinerInfo>` - Returns `Some(MinerInfo)` if the miner is found, otherwise `None`.
-    pub fn get_miner_by_principal(principal: Principal) -> Option<MinerInfo> {
-        miner::get_miner_by_principal(principal)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_block_subscriber(subscriber: Option<Principal>) -> Result<(), String> {
+        config::set_block_subscriber(subscriber)
     }
 
-    /// Registers a miner.
+    /// Retrieves the canister notified after each block is finalized.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Principal>` - The subscriber canister if one is registered, otherwise `None`.
+    pub fn get_block_subscriber() -> Option<Principal> {
+        config::get_block_subscriber()
+    }
+
+    /// Reports that the winner of a block broadcast its transactions to Bitcoin.
     ///
     /// # Arguments
     ///
-    /// * `owner` - A `Principal` representing the owner.
-    /// * `btc_address` - A `String` representing the Bitcoin address.
-    /// * `ecdsa_pubkey` - A `Vec<u8>` representing the ECDSA public key.
+    /// * `caller` - A `Principal` representing the owner of the winning miner.
+    /// * `height` - A `Height` representing the won block.
+    /// * `txid` - A `String` representing the broadcast reveal transaction id in hex.
     ///
     /// # Returns
     ///
-    /// * `Result<MinerInfo, String>` - On success, returns `MinerInfo`. On failure, returns an error message as a `String`.
-    pub fn register_miner(
-        owner: Principal,
-        btc_address: String,
-        ecdsa_pubkey: Vec<u8>,
-    ) -> Result<MinerInfo, String> {
-        miner::register_miner(owner, btc_address, ecdsa_pubkey)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn report_broadcast(caller: Principal, height: Height, txid: String) -> Result<(), String> {
+        broadcast::report_broadcast(caller, height, txid)
     }
 
-    /// Retrieves miner information by address.
+    /// Marks the broadcast of a won block as confirmed on Bitcoin.
     ///
     /// # Arguments
     ///
-    /// * `address` - A `String` representing the address.
+    /// * `caller` - A `Principal` representing the owner confirming the transaction.
+    /// * `height` - A `Height` representing the won block.
+    /// * `txid` - A `String` representing the confirmed transaction id in hex.
     ///
     /// # Returns
     ///
-    /// * `Option<MinerInfo>` - Returns `Some(MinerInfo)` if the miner is found, otherwise `None`.
-    pub fn get_miner_by_address(address: String) -> Option<MinerInfo> {
-        miner::get_miner_by_address(address)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn confirm_broadcast(
+        caller: Principal,
+        height: Height,
+        txid: String,
+    ) -> Result<(), String> {
+        broadcast::confirm_broadcast(caller, height, txid)
     }
 
-    /// Retrieves the mining history for a given Bitcoin address within a specified block range.
+    /// Retrieves the broadcast information of a won block.
     ///
-    /// This function calls the `get_mining_history_for_miners` function from the `miner` module
-    /// to obtain the mining history for the specified Bitcoin address and block range.
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BroadcastInfo>` - The reported or confirmed broadcast, or `None` if nothing was reported.
+    pub fn get_broadcast_info(height: Height) -> Option<BroadcastInfo> {
+        broadcast::get_broadcast_info(height)
+    }
+
+    /// Retrieves the heights won by a miner whose broadcast was not reported yet, one page of
+    /// stored winner sigs at a time.
     ///
     /// # Arguments
     ///
-    /// * `btc_address` - A `String` representing the Bitcoin address.
-    /// * `block_range` - A `BlockRange` representing the range of blocks to retrieve the mining history for.
+    /// * `btc_address` - A `String` representing the miner's Bitcoin address.
+    /// * `from` - A `Height` representing the first height of the page.
+    /// * `limit` - A `u64` representing the number of heights with winner sigs visited, capped at 500.
     ///
     /// # Returns
     ///
-    /// * `Vec<MinerBlockData>` - A vector of `MinerBlockData` containing the mining history for the specified address and block range.
-    pub fn get_mining_history_for_miners(
+    /// * `UnbroadcastWinsPage` - The won heights without a reported broadcast and where the next page starts.
+    pub fn get_unbroadcast_wins(
         btc_address: String,
-        block_range: BlockRange,
-    ) -> Vec<MinerBlockData> {
-        miner::get_mining_history_for_miners(btc_address, block_range)
+        from: Height,
+        limit: u64,
+    ) -> UnbroadcastWinsPage {
+        broadcast::get_unbroadcast_wins(btc_address, from, limit)
     }
 
-    //  Blocks Execution
+    /// Sets the operational cycles threshold below which an alert is logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - An `Option<u128>` representing the minimum operational headroom in cycles, `None` disables the alert.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_cycles_alert_threshold(threshold: Option<u128>) -> Result<(), String> {
+        cycles::set_cycles_alert_threshold(threshold)
+    }
 
-    /// Retrieves all blocks.
+    /// Retrieves the canister cycles split into the user pool and the operational headroom.
     ///
     /// # Returns
     ///
-    /// * `Vec<BlockData>` - A vector of `BlockData` representing all blocks.
-    pub fn get_blocks() -> Vec<BlockData> {
-        block::get_blocks()
+    /// * `CyclesHealth` - The canister balance, the user pool, the operational headroom and the alert state.
+    pub fn get_cycles_health() -> CyclesHealth {
+        cycles::get_cycles_health()
     }
 
-    /// Retrieves blocks within a specified range.
+    /// Configures the automatic top-up of the canister from ICP held in one of its subaccounts.
     ///
     /// # Arguments
     ///
-    /// * `from` - A `Height` representing the starting height.
-    /// * `to` - A `Height` representing the ending height.
+    /// * `caller` - A `Principal` representing the owner making the change.
+    /// * `settings` - An `Option<TopUpSettings>` with the funding subaccount, threshold, amount and interval, `None` disables the top-up.
     ///
     /// # Returns
     ///
-    /// * `Vec<BlockData>` - A vector of `BlockData` representing the blocks within the specified range.
-    pub fn get_blocks_range(from: Height, to: Height) -> Vec<BlockData> {
-        block::get_blocks_range(from, to)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_top_up_settings(
+        caller: Principal,
+        settings: Option<TopUpSettings>,
+    ) -> Result<(), String> {
+        top_up::set_top_up_settings(caller, settings)
     }
 
-    /// Retrieves the count of failed blocks in the last epoch.
+    /// Retrieves the automatic top-up settings.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<TopUpSettings>` - The configured settings, or `None` when the top-up is disabled.
+    pub fn get_top_up_settings() -> Option<TopUpSettings> {
+        top_up::get_top_up_settings()
+    }
+
+    /// Estimates the next automatic top-up without transferring anything.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<TopUpEstimate, String>` - On success, returns the funding balance, the cycles the CMC would mint and whether a top-up would start now. On failure, returns an error message as a `String`.
+    pub async fn estimate_top_up() -> Result<TopUpEstimate, String> {
+        top_up::estimate_top_up().await
+    }
+
+    /// Turns maintenance mode on or off.
+    ///
+    /// While enabled, all state-mutating endpoints except the owner recovery paths are rejected
+    /// with the given reason, and queries keep working.
     ///
     /// # Arguments
     ///
-    /// * `start_height` - A `Height` representing the starting height.
+    /// * `caller` - A `Principal` representing the owner changing the mode.
+    /// * `enabled` - A `bool` indicating whether maintenance mode is enabled.
+    /// * `reason` - An `Option<String>` representing the reason shown in rejection messages.
     ///
     /// # Returns
     ///
-    /// * `(u64, u64, f64)` - A tuple containing the count of failed blocks, the total number of blocks, and the failure rate.
-    pub fn get_last_epoch_failed_blocks_count(start_height: Height) -> (u64, u64, f64) {
-        block::get_last_epoch_failed_blocks_count(start_height)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_maintenance(
+        caller: Principal,
+        enabled: bool,
+        reason: Option<String>,
+    ) -> Result<(), String> {
+        config::set_maintenance_status(MaintenanceStatus {
+            enabled,
+            reason: reason.clone(),
+            since: if enabled {
+                Some(ic_cdk::api::time())
+            } else {
+                None
+            },
+        })?;
+        event::add_event(caller, EventKind::MaintenanceChanged { enabled, reason });
+        Ok(())
     }
 
-    /// Starts the process of generating blocks asynchronously.
+    /// Retrieves the maintenance mode status.
     ///
-    /// This function initiates the block generation process and sets a timer to
-    /// periodically generate blocks based on the block time interval.
+    /// # Returns
+    ///
+    /// * `MaintenanceStatus` - Whether maintenance mode is enabled, with its reason and start time.
+    pub fn get_maintenance_status() -> MaintenanceStatus {
+        config::get_maintenance_status()
+    }
+
+    /// Halts every reward claim, for when the ledger or the balances can not be trusted.
+    ///
+    /// Claims stay halted until `resume_claims`, whatever the maintenance mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the owner halting claims.
+    /// * `reason` - A `String` shown in the rejection of claims.
     ///
     /// # Returns
     ///
     /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
-    pub async fn start_generate_blocks() -> Result<(), String> {
-        Self::generate_blocks();
-        let block_time_interval = Self::get_block_time_interval()?;
-        Self::set_timer(block_time_interval, Self::generate_blocks);
+    pub fn halt_claims(caller: Principal, reason: String) -> Result<(), String> {
+        config::set_claims_halt_status(ClaimsHaltStatus {
+            halted: true,
+            reason: Some(reason.clone()),
+            since: Some(ic_cdk::api::time()),
+        })?;
+        event::add_event(
+            caller,
+            EventKind::ClaimsHaltChanged {
+                halted: true,
+                reason: Some(reason),
+            },
+        );
         Ok(())
     }
 
-    /// Sets a timer to execute a callback function at a specified interval.
+    /// Lets reward claims go through again.
     ///
     /// # Arguments
     ///
-    /// * `interval` - A `u64` representing the interval in nanoseconds.
-    /// * `callback` - A function pointer to the callback function to be executed.
+    /// * `caller` - A `Principal` representing the owner resuming claims.
     ///
     /// # Returns
     ///
-    /// * `TimerId` - The ID of the created timer.
-    pub fn set_timer(interval: u64, callback: fn()) -> TimerId {
-        let secs = Duration::from_nanos(interval);
-        ic_cdk::println!("Timer canister: Starting a new timer with {secs:?} interval...");
-        // Schedule a new periodic task to increment the counter.
-        let timer_id = ic_cdk_timers::set_timer_interval(secs, callback);
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn resume_claims(caller: Principal) -> Result<(), String> {
+        config::set_claims_halt_status(ClaimsHaltStatus::default())?;
+        event::add_event(
+            caller,
+            EventKind::ClaimsHaltChanged {
+                halted: false,
+                reason: None,
+            },
+        );
+        Ok(())
+    }
 
-        // Add the timer ID to the global vector.
-        TIMER_IDS.with(|timer_ids| timer_ids.borrow_mut().push(timer_id));
-        timer_id
+    /// Retrieves whether reward claims are halted.
+    ///
+    /// # Returns
+    ///
+    /// * `ClaimsHaltStatus` - Whether claims are halted, with the reason and the time of the halt.
+    pub fn get_claims_halt_status() -> ClaimsHaltStatus {
+        config::get_claims_halt_status()
     }
 
-    pub fn timer_stop() {
-        TIMER_IDS.with(|timer_ids| {
-            if let Some(timer_id) = timer_ids.borrow_mut().pop() {
-                ic_cdk::println!("Timer canister: Stopping timer ID {timer_id:?}...");
-                // It's safe to clear non-existent timer IDs.
-                ic_cdk_timers::clear_timer(timer_id);
+    /// Sets how cycles of blocks without a winner are handled.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - A `FailedBlockPolicy` deciding whether debited cycles are burned or refunded.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_failed_block_policy(policy: FailedBlockPolicy) -> Result<(), String> {
+        if let FailedBlockPolicy::RefundPercent(p) = policy {
+            if p > 100 {
+                return Err("Refund percent can not be greater than 100".to_string());
             }
-        });
+        }
+        config::set_failed_block_policy(policy)
     }
 
-    pub fn set_timer_delay(interval: u64, callback: fn()) -> TimerId {
-        let secs = Duration::from_nanos(interval);
-        ic_cdk::println!("Timer canister: Starting a new timer with {secs:?} interval...");
-        // Schedule a new periodic task to increment the counter.
-        let timer_id = ic_cdk_timers::set_timer(secs, callback);
-
-        // Add the timer ID to the global vector.
-        TIMER_IDS.with(|timer_ids| timer_ids.borrow_mut().push(timer_id));
-        timer_id
+    /// Sets the grace window after `next_block_time` during which submissions are still accepted.
+    ///
+    /// The cutoff is recorded on each block when it opens, so a change only applies to the next block.
+    ///
+    /// # Arguments
+    ///
+    /// * `grace` - A `u64` representing the grace window in nanoseconds, it must be shorter than the block time interval.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_submission_grace(grace: u64) -> Result<(), String> {
+        if grace >= Self::get_block_time_interval()? {
+            return Err("Grace window must be shorter than the block time interval".to_string());
+        }
+        // the slots change length, the schedule goes on from the cutoff of the open block
+        if let Some((height, open)) = Self::get_last_block() {
+            config::set_schedule_anchor(ScheduleAnchor {
+                height: height + 1,
+                time: open.submission_cutoff(),
+            })?;
+        }
+        config::set_submission_grace(grace)
     }
 
-    pub fn generate_blocks() {
-        let block_time_interval = Self::get_block_time_interval().unwrap();
-        let difficulty_adjust_epoch = Self::get_difficulty_adjust_epoch().unwrap();
-        let default_rewards = Self::get_default_rewards().unwrap();
-        let start_difficulty = Self::get_start_difficulty().unwrap();
-        let halving_settings = Self::get_halving_settings();
-        match Self::get_last_block() {
-            None => {
-                let mut random_32 = fake_32();
-                random_32.reverse();
-                // genesis block
-                let time = ic_cdk::api::time();
-                let bitwork = start_difficulty.clone();
-
-                Self::set_consider_increase(Some(0 + difficulty_adjust_epoch))
-                    .expect("Can not set consider increase height");
-
-                let block_data = BlockData {
-                    height: 0,
-                    rewards: default_rewards,
-                    winner: None,
-                    difficulty: bitwork,
-                    hash: random_32,
-                    block_time: time,
-                    next_block_time: time + block_time_interval,
-                    history: false,
-                    cycle_burned: 0,
-                    dod_burned: 0,
-                };
-                BLOCKS.with(|v| v.borrow_mut().insert(0, block_data.clone()));
+    /// Retrieves the submission grace window.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The grace window in nanoseconds, `0` by default.
+    pub fn get_submission_grace() -> u64 {
+        config::get_submission_grace()
+    }
 
-                // Ok(block_data.clone());
+    /// Sets what miners bid for a block, cycles or DOD.
+    ///
+    /// Candidates of the open block were submitted in the current currency, so the currency can
+    /// only change while the open block has none.
+    ///
+    /// # Arguments
+    ///
+    /// * `currency` - A `BidCurrency`, `Cycles` for the lowest cycles price to win or `Dod` for the highest DOD bid.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_bid_currency(currency: BidCurrency) -> Result<(), String> {
+        if let Some((height, _)) = Self::get_last_block() {
+            if !Self::get_block_candidates(height).is_empty() {
+                return Err(
+                    "The bid currency can only change while the open block has no candidates"
+                        .to_string(),
+                );
             }
-            Some(r) => {
-                Self::timer_stop();
-
-                let last_block = r.1;
+        }
+        config::set_bid_currency(currency)
+    }
 
-                let last_block_reward =
-                    Self::get_block_reward_by_height(last_block.height, halving_settings.clone())
-                        .unwrap();
+    /// Retrieves what miners bid for a block.
+    ///
+    /// # Returns
+    ///
+    /// * `BidCurrency` - The configured currency, `Cycles` by default.
+    pub fn get_bid_currency() -> BidCurrency {
+        config::get_bid_currency()
+    }
 
-                // temporally comment out the burn DOD from treasury
-                spawn(async move {
-                    let _ = Self::mint_dod_award_to_treasury(last_block_reward).await;
-                    //.expect("Can not mint DOD award to treasury");
-                });
+    /// Sets the bonus tiers for blocks solved at a higher difficulty than required.
+    ///
+    /// A winning commit txid matching more hex digits of the block hash than the difficulty asks
+    /// for raises its cycles price, or its DOD winner share, by the bonus of the highest tier it
+    /// reaches. Tiers apply to candidates submitted after they are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `tiers` - An `Option<DifficultyTiers>`, ordered by extra digits with increasing bonuses, or `None` to pay no bonus.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_difficulty_tiers(tiers: Option<DifficultyTiers>) -> Result<(), String> {
+        if let Some(tiers) = tiers.as_ref() {
+            tiers.validate()?;
+        }
+        config::set_difficulty_tiers(tiers)
+    }
 
-                // 1. handle candidates sorting, price lowest first, submit time first
-                let mut candidates = Self::get_block_candidates(last_block.height);
-                candidates.sort();
-                let winner_address = if candidates.len() > 0 {
-                    Some(candidates[0].btc_address.clone())
-                } else {
-                    None
-                };
-                let cycle_price = if candidates.len() > 0 {
-                    Some(candidates[0].cycles_price.clone())
-                } else {
-                    None
-                };
+    /// Retrieves the bonus tiers for blocks solved at a higher difficulty than required.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<DifficultyTiers>` - The configured tiers, `None` when no bonus is paid.
+    pub fn get_difficulty_tiers() -> Option<DifficultyTiers> {
+        config::get_difficulty_tiers()
+    }
 
-                // 1.1 should get current block total cycles to see the price if winner can win.
-                let cycle_deposit = Self::get_block_total_cycles(last_block.height, false);
+    /// Sets the lock-in bonus, raising the reward share of stakers whose order range is long.
+    ///
+    /// The bonus grows with the length of the running order range and is paid out of the
+    /// treasury share of each block.
+    ///
+    /// # Arguments
+    ///
+    /// * `bonus` - An `Option<LockInBonus>` with the maximum bonus and the range length earning it, or `None` to pay no bonus.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_lock_in_bonus(bonus: Option<LockInBonus>) -> Result<(), String> {
+        if let Some(bonus) = bonus.as_ref() {
+            bonus.validate()?;
+        }
+        config::set_lock_in_bonus(bonus)
+    }
 
-                ic_cdk::println!("cycle_deposit is {:?}", cycle_deposit);
+    /// Retrieves the lock-in bonus.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<LockInBonus>` - The configured bonus, `None` when no bonus is paid.
+    pub fn get_lock_in_bonus() -> Option<LockInBonus> {
+        config::get_lock_in_bonus()
+    }
 
-                let mut _miner = None;
-                #[allow(unused_assignments)]
-                let mut treasury_revinvest = 0u128;
-                #[allow(unused_assignments)]
-                let mut to_burn = 0u128;
+    /// Retrieves how cycles of blocks without a winner are handled.
+    ///
+    /// # Returns
+    ///
+    /// * `FailedBlockPolicy` - The configured policy, `BurnAll` by default.
+    pub fn get_failed_block_policy() -> FailedBlockPolicy {
+        config::get_failed_block_policy()
+    }
 
-                if winner_address.is_some()
-                    && cycle_price.is_some()
-                    && cycle_deposit > cycle_price.unwrap()
-                {
-                    let miner_info = Self::get_miner_by_address(winner_address.unwrap()).unwrap();
-                    _miner = Some(MinerInfo {
-                        reward_cycles: Some(cycle_price.unwrap()),
-                        ..miner_info.clone()
-                    });
+    /// Sets how participants with a reward share below one DOD unit are handled.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - A `RewardDustPolicy` deciding whether the fraction is dropped, refunded or accumulated.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_reward_dust_policy(policy: RewardDustPolicy) -> Result<(), String> {
+        config::set_reward_dust_policy(policy)
+    }
 
-                    treasury_revinvest = (cycle_deposit - cycle_price.unwrap()) / 2;
+    /// Retrieves how participants with a reward share below one DOD unit are handled.
+    ///
+    /// # Returns
+    ///
+    /// * `RewardDustPolicy` - The configured policy, `Floor` by default.
+    pub fn get_reward_dust_policy() -> RewardDustPolicy {
+        config::get_reward_dust_policy()
+    }
 
-                    // because we have miner meanwhile owner as staker,
-                    // we increase the balance from cycle price for miners
-                    Self::increase_user_cycle_balance(
-                        miner_info.owner.clone(),
-                        Nat::from(cycle_price.unwrap()),
-                    )
-                    .unwrap();
-                } else {
-                    treasury_revinvest = cycle_deposit / 2;
-                }
+    /// Sets how the DOD reward of a block is allocated between the winner, stakers and treasury.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - An `Option<RewardPolicy>` with percentages summing to 100 and an optional per block cap on each staker's bet, `None` restores the cycles based split.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_reward_policy(policy: Option<RewardPolicy>) -> Result<(), String> {
+        if let Some(policy) = policy.as_ref() {
+            policy.validate()?;
+        }
+        config::set_reward_policy(policy)
+    }
 
-                // to burn equals to treasury_revinvest
+    /// Retrieves how the DOD reward of a block is allocated.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<RewardPolicy>` - The configured policy, or `None` when the treasury gets the share of its own cycle orders.
+    pub fn get_reward_policy() -> Option<RewardPolicy> {
+        config::get_reward_policy()
+    }
 
-                to_burn = treasury_revinvest.clone();
-                Self::user_put_order_v2(
-                    id(),
-                    (last_block.height + 1, last_block.height + 2),
-                    treasury_revinvest,
-                );
+    /// Sets the ICP transfer fee, the minimum ICP deposit and the cycles attached to created canisters.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - A `FeeSettings` within the bounds checked by `FeeSettings::validate`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_fee_settings(settings: FeeSettings) -> Result<(), String> {
+        settings.validate()?;
+        config::set_fee_settings(settings)
+    }
 
-                // 2. write block data and update winner to storage
+    /// Retrieves the fee settings in effect.
+    ///
+    /// # Returns
+    ///
+    /// * `FeeSettings` - The configured settings, or the compiled-in defaults when none were set.
+    pub fn get_fee_settings() -> FeeSettings {
+        config::get_fee_settings()
+    }
 
-                let mut _block = last_block.clone();
-
-                _block.winner = _miner.clone();
-                _block.history = true;
-
-                // 3. write winner sigs to storage
-                if _block.winner.is_some() {
-                    let commit_tx = base64::engine::general_purpose::STANDARD
-                        .decode(candidates[0].signed_commit_psbt.clone())
-                        .map_err(|_| "can not decode base64".to_string())
-                        .unwrap();
-                    let reveal_tx = base64::engine::general_purpose::STANDARD
-                        .decode(candidates[0].signed_reveal_psbt.clone())
-                        .map_err(|_| "can not decode base64".to_string())
-                        .unwrap();
-
-                    SIGS.with(|v| {
-                        v.borrow_mut().insert(
-                            _block.height.clone(),
-                            BlockSigs {
-                                commit_tx,
-                                reveal_tx,
-                            },
-                        )
-                    });
-                }
-
-                // 3.3 update all user balances
-
-                Self::update_users_balance_v2(last_block.height, cycle_deposit);
-
-                // 4. burn  cycles here
-                ic_cdk::println!(
-                    "{}",
-                    format!(
-                        "execute_cycles_on_block_data, cycle_deposit is {:?}, expect to burn {:?}",
-                        cycle_deposit, to_burn
-                    )
-                );
-
-                info_log_add(
-                    format!(
-                        "execute_cycles_on_block_data, cycle_deposit is {:?}, expect to burn {:?}",
-                        cycle_deposit, to_burn
-                    )
-                    .as_str(),
-                );
-
-                // temporally comment out execute_cycles_on_block_data
-                Self::execute_cycles_on_block_data(to_burn.clone()).unwrap();
-
-                // 4.1 burn DOD
-
-                _block.cycle_burned = to_burn.clone();
-                ic_cdk::println!("block.winner is {:?}", _miner.clone());
-                let _id = id();
-                let (total_burn, _) = Self::get_user_block_reward(_block.height.clone(), _id);
-                ic_cdk::println!("dod total burn is {:?}", total_burn);
-
-                if total_burn == Self::get_default_rewards().unwrap() {
-                    ic_cdk::println!("No one deposit cycles in this block, we should stop here");
-                    return;
-                }
-
-                // temporally comment out the burn DOD from treasury
-                spawn(async move {
-                    let _ = Self::burn_dod_from_treasury(_id, total_burn).await;
-                    // .expect("Can not burn DOD from treasury");
-                });
-
-                _block.dod_burned = total_burn.clone();
-                BLOCKS.with(|v| v.borrow_mut().insert(_block.height.clone(), _block.clone()));
-
-                // 5. create new block
-                let mut random_32 = fake_32();
-                random_32.reverse();
-
-                // 6. difficulty adjust
-                let mut bitwork;
-
-                bitwork = last_block.difficulty.clone();
-
-                if _block.winner.is_none() {
-                    let considered = Self::get_consider_decrease().unwrap();
-
-                    match considered {
-                        None => {
-                            Self::set_consider_decrease(Some(
-                                _block.height + difficulty_adjust_epoch,
-                            ))
-                            .expect("Can not set consider decrease height");
-
-                            Self::set_consider_increase(None)
-                                .expect("Can not set consider increase height");
-                        }
-                        Some(i) => {
-                            if _block.height + 1 == i {
-                                let decreased = bitwork_minus_bit_hex(
-                                    last_block.difficulty.clone(),
-                                    DIFFICULTY_ADJUST_STEP,
-                                )
-                                .unwrap();
-
-                                if decreased.cmp(&start_difficulty) == Ordering::Less {
-                                    bitwork = start_difficulty.clone();
-                                } else {
-                                    bitwork = decreased;
-                                }
-
-                                Self::set_consider_decrease(Some(i + difficulty_adjust_epoch))
-                                    .expect("Can not set consider decrease height");
-                            }
-                        }
-                    }
-                } else {
-                    let considered = Self::get_consider_increase().unwrap();
-                    match considered {
-                        None => {
-                            Self::set_consider_increase(Some(
-                                _block.height + difficulty_adjust_epoch,
-                            ))
-                            .expect("Can not set consider increase height");
-
-                            Self::set_consider_decrease(None)
-                                .expect("Can not set consider decrease height");
-                        }
-                        Some(i) => {
-                            if _block.height + 1 == i {
-                                bitwork = bitwork_plus_bit_hex(
-                                    last_block.difficulty.clone(),
-                                    DIFFICULTY_ADJUST_STEP,
-                                )
-                                .unwrap();
-                                Self::set_consider_increase(Some(i + difficulty_adjust_epoch))
-                                    .expect("Can not set consider increase height");
-                            }
-                        }
-                    }
-                }
-
-                let current_time = ic_cdk::api::time();
-                let block_data = BlockData {
-                    height: last_block.height + 1,
-                    rewards: Self::get_block_reward_by_height(
-                        last_block.height + 1,
-                        halving_settings.clone(),
-                    )
-                    .unwrap(),
-                    winner: None,
-                    difficulty: bitwork,
-                    hash: random_32,
-                    block_time: current_time,
-                    next_block_time: current_time + block_time_interval,
-                    history: false,
-                    cycle_burned: 0,
-                    dod_burned: 0,
-                };
-                BLOCKS.with(|v| v.borrow_mut().insert(block_data.height, block_data.clone()));
-                Self::set_timer_delay(block_time_interval, Self::generate_blocks);
-                // Ok(block_data.clone());
-            }
-        }
+    /// Checks a hash against a target hash and bitwork with the same rule used for submissions.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_hash` - A `String` representing the hex hash to check, e.g. a commit txid.
+    /// * `target_hash` - A `String` representing the hex block hash to match.
+    /// * `bitwork` - A `Bitwork` representing the difficulty.
+    /// * `reverse` - A `bool` indicating whether the target hash bytes are reversed before matching.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, String>` - On success, returns whether the hash matches. On failure, returns an error message as a `String`.
+    pub fn bitwork_check(
+        current_hash: String,
+        target_hash: String,
+        bitwork: Bitwork,
+        reverse: bool,
+    ) -> Result<bool, String> {
+        bitwork_match_hash(current_hash, target_hash, bitwork, reverse)
     }
 
-    /// Retrieves the last block.
+    /// Retrieves the bitwork a block must be mined with.
+    ///
+    /// Produced blocks return their recorded difficulty, which includes the epoch adjustments.
+    /// Future heights return the base schedule derived from the difficulty adjust epoch.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
     ///
     /// # Returns
     ///
-    /// * `Option<(u64, BlockData)>` - Returns `Some((u64, BlockData))` if the last block exists, otherwise `None`.
-    pub fn get_last_block() -> Option<(u64, BlockData)> {
-        block::get_last_block()
+    /// * `Result<Bitwork, String>` - On success, returns the `Bitwork`. On failure, returns an error message as a `String`.
+    pub fn bitwork_for_height(height: Height) -> Result<Bitwork, String> {
+        match Self::get_block_by_height(height) {
+            Some(block) => Ok(block.difficulty),
+            None => bitwork_from_height(height, Self::get_difficulty_adjust_epoch()?),
+        }
     }
 
-    /// Retrieves a block by its height.
+    /// Retrieves the admin audit entries recorded between two timestamps.
     ///
     /// # Arguments
     ///
-    /// * `height` - A `u64` representing the height of the block.
+    /// * `from_ts` - A `u64` representing the first timestamp in nanoseconds, inclusive.
+    /// * `to_ts` - A `u64` representing the last timestamp in nanoseconds, inclusive.
     ///
     /// # Returns
     ///
-    /// * `Option<BlockData>` - Returns `Some(BlockData)` if the block exists, otherwise `None`.
-    pub fn get_block_by_height(height: u64) -> Option<BlockData> {
-        block::get_block_by_height(height)
+    /// * `Vec<AdminAuditEntry>` - The entries in time order, at most 1000 per call.
+    pub fn get_admin_audit(from_ts: u64, to_ts: u64) -> Vec<AdminAuditEntry> {
+        audit::get_admin_audit(from_ts, to_ts)
     }
 
-    /// Deposits cycles from ICP.
+    /// Sets or clears the treasury buyback-and-burn schedule.
     ///
-    /// This function transfers ICP to the CMC canister and notifies the top-up, converting the ICP to cycles.
+    /// Once set, every time the ledger queue drains the canister checks whether the configured interval
+    /// has elapsed since the last buyback and, if so, burns treasury DOD that is not owed to stakers or miners.
     ///
     /// # Arguments
     ///
-    /// * `from` - A `Principal` representing the sender.
-    /// * `qty_e8s_u64` - A `u64` representing the quantity of ICP in e8s.
+    /// * `caller` - A `Principal` representing the owner changing the schedule.
+    /// * `settings` - An `Option<BuybackSettings>` with the burn policy and interval, `None` disables buybacks.
     ///
-    /// # Panics
+    /// # Returns
     ///
-    /// This function will panic if the quantity of ICP is less than the minimum required stake.
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_buyback_settings(
+        caller: Principal,
+        settings: Option<BuybackSettings>,
+    ) -> Result<(), String> {
+        buyback::set_buyback_settings(caller, settings)
+    }
+
+    /// Retrieves the treasury buyback-and-burn schedule.
     ///
-    /// # Steps
+    /// # Returns
     ///
-    /// 1. Transfers ICP to the CMC canister.
-    /// 2. Notifies the top-up to convert ICP to cycles.
-    /// 3. Updates the user's balance with the new cycles.
-    pub async fn deposit_cycles_from_icp(from: Principal, qty_e8s_u64: u64) {
-        if qty_e8s_u64 < MIN_ICP_STAKE_E8S_U64 {
-            panic!(
-                "At least 0.5 ICP is required to fuel the furnace, but got {}",
-                qty_e8s_u64
-            );
-        }
-        let caller_subaccount = Subaccount::from(from.clone());
-        let icp_can_id = Principal::from_text(ICP_CAN_ID).unwrap();
-        let cmc_can_id = Principal::from_text(CMC_CAN_ID).unwrap();
-        let canister_id = id();
-        let subaccount = Subaccount::from(canister_id);
-
-        let transfer_args = TransferArgs {
-            amount: Tokens::from_e8s(qty_e8s_u64),
-            to: AccountIdentifier::new(&cmc_can_id, &subaccount),
-            memo: Memo(MEMO_TOP_UP_CANISTER),
-            fee: Tokens::from_e8s(ICP_FEE),
-            from_subaccount: Some(caller_subaccount),
-            created_at_time: Some(Timestamp {
-                timestamp_nanos: ic_cdk::api::time(),
-            }),
-        };
+    /// * `Option<BuybackSettings>` - The configured schedule, or `None` if buybacks are disabled.
+    pub fn get_buyback_settings() -> Option<BuybackSettings> {
+        buyback::get_buyback_settings()
+    }
 
-        let block_index = transfer(icp_can_id, transfer_args)
-            .await
-            .expect("Unable to call ICP canister")
-            .expect("Unable to transfer ICP");
+    /// Retrieves the emission status of the DOD token.
+    ///
+    /// This combines the current block reward after halving with the buyback schedule and the
+    /// amount of DOD burned by buybacks so far.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<EmissionStatus, String>` - On success, returns the `EmissionStatus`. On failure, returns an error message as a `String`.
+    pub fn get_emission_status() -> Result<EmissionStatus, String> {
+        let height = Self::get_last_block().map(|r| r.0);
+        let halving_settings = Self::get_halving_settings();
+        let block_reward =
+            Self::get_block_reward_by_height(height.unwrap_or(0), halving_settings.clone())?;
+        Ok(EmissionStatus {
+            height,
+            block_reward,
+            halving_settings,
+            buyback_settings: buyback::get_buyback_settings(),
+            total_buyback_burned: buyback::get_total_buyback_burned(),
+            last_buyback_at: buyback::get_last_buyback_at(),
+        })
+    }
 
-        let cmc = CMCClient(cmc_can_id);
+    // Staker Execution
+    /// Generates a subaccount from a given `Principal` identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A `Principal` representing the identifier from which the subaccount is to be generated.
+    ///
+    /// # Returns
+    ///
+    /// * `Subaccount` - The generated subaccount.
+    pub fn user_subaccount(id: Principal) -> Subaccount {
+        Subaccount::from(id)
+    }
 
-        let notify_args = NotifyTopUpRequest {
-            block_index,
-            canister_id,
-        };
+    /// Retrieves the account a user deposits ICP to before staking.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the depositing user.
+    ///
+    /// # Returns
+    ///
+    /// * `DepositAccount` - The account identifier, the ICRC-1 textual account and the subaccount bytes.
+    pub fn get_deposit_account(user: Principal) -> DepositAccount {
+        staker::deposit_account(self_id(), user)
+    }
 
-        let cycles = cmc
-            .notify_top_up(notify_args)
-            .await
-            .expect("Unable to call cycle canister")
-            .0
-            .expect("Unable to deposit cycles");
-
-        let blob29 = Blob::<29>::try_from(from.clone().as_slice()).expect("error transformation");
-        let user = Self::get_user_detail(from.clone());
-
-        if user.is_some() {
-            let user = user.unwrap();
-
-            STAKERS.with(|v| {
-                v.borrow_mut().insert(
-                    blob29,
-                    UserDetail {
-                        balance: user.balance + cycles,
-                        ..user
-                    },
-                );
-            })
-        } else {
-            STAKERS.with(|v| {
-                v.borrow_mut().insert(
-                    blob29,
-                    UserDetail {
-                        principal: from.clone(),
-                        subaccount,
-                        balance: cycles,
-                        claimed_dod: 0,
-                        total_dod: 0,
-                        cycle_burning_rate: 0,
-                    },
-                );
-            })
-        }
+    /// Registers a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user to be registered.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn register_user(user: Principal) -> Result<(), String> {
+        staker::register_user(user)
     }
 
-    /// Retrieves the details of a user.
+    /// Registers a new user referred by the owner of a referral code.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user.
+    /// * `user` - A `Principal` representing the user to register.
+    /// * `code` - A `String` representing the referral code of the referrer.
     ///
     /// # Returns
     ///
-    /// * `Option<UserDetail>` - Returns `Some(UserDetail)` if the user exists, otherwise `None`.
-    pub fn get_user_detail(user: Principal) -> Option<UserDetail> {
-        let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
-        STAKERS.with(|v| v.borrow().get(&blob29).map(|v| v.clone()))
+    /// * `Result<ReferralInfo, String>` - On success, returns the recorded referral. On failure, returns an error message as a `String`.
+    pub fn register_with_referral(user: Principal, code: String) -> Result<ReferralInfo, String> {
+        referral::register_with_referral(user, code)
     }
-    /// Writes the cycle balance for a user.
+
+    /// Publishes the referral code of a registered user, returning the existing one if already published.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user.
-    /// * `balance` - A `Nat` representing the new balance to be set.
+    /// * `user` - A `Principal` representing the referrer.
     ///
     /// # Returns
     ///
-    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
-    pub fn increase_user_cycle_balance(
-        user: Principal,
-        increase_balance: Nat,
-    ) -> Result<(), String> {
-        match Self::get_user_detail(user) {
-            None => Err("No user found".to_string()),
-            Some(r) => {
-                let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
-                STAKERS.with(|v| {
-                    v.borrow_mut().insert(
-                        blob29,
-                        UserDetail {
-                            balance: r.balance + increase_balance,
-                            ..r
-                        },
-                    );
-                });
-                Ok(())
-            }
-        }
+    /// * `Result<String, String>` - On success, returns the referral code. On failure, returns an error message as a `String`.
+    pub fn create_referral_code(user: Principal) -> Result<String, String> {
+        referral::create_referral_code(user)
     }
 
-    pub fn decrease_user_cycle_balance(
-        user: Principal,
-        decreace_balance: Nat,
-    ) -> Result<(), String> {
-        match Self::get_user_detail(user) {
-            None => Err("No user found".to_string()),
-            Some(r) => {
-                let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
-                if r.clone().balance - decreace_balance.clone() < Nat::from(0u128) {
-                    return Err("Not enough balance".to_string());
-                }
-                STAKERS.with(|v| {
-                    v.borrow_mut().insert(
-                        blob29,
-                        UserDetail {
-                            balance: r.clone().balance - decreace_balance.clone(),
-                            ..r.clone()
-                        },
-                    );
-                });
-                Ok(())
-            }
-        }
+    /// Retrieves the referral code and rebates of a referrer.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the referrer.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ReferrerStats>` - Returns `Some(ReferrerStats)` if the user published a code, otherwise `None`.
+    pub fn get_referrer_stats(user: Principal) -> Option<ReferrerStats> {
+        referral::get_referrer_stats(user)
     }
 
-    /// Writes the claimed reward for a user.
+    /// Retrieves the referral a user registered with.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user.
-    /// * `claimed_dod` - A `u64` representing the claimed reward to be set.
+    /// * `user` - A `Principal` representing the referred user.
     ///
     /// # Returns
     ///
-    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
-    pub fn write_user_claimed_dod(user: Principal, claimed_dod: u64) -> Result<(), String> {
-        match Self::get_user_detail(user) {
-            None => Err("No user found".to_string()),
-            Some(r) => {
-                let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
-                STAKERS.with(|v| {
-                    v.borrow_mut()
-                        .insert(blob29, UserDetail { claimed_dod, ..r });
-                });
-                Ok(())
-            }
-        }
+    /// * `Option<ReferralInfo>` - Returns `Some(ReferralInfo)` if the user registered with a code, otherwise `None`.
+    pub fn get_referral(user: Principal) -> Option<ReferralInfo> {
+        referral::get_referral(user)
     }
 
-    /// Writes the claimed reward for a miner.
+    /// Sets the referral rebate and whether the program is running.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the miner.
-    /// * `claimed_dod` - A `u64` representing the claimed reward to be set.
+    /// * `settings` - A `ReferralSettings` with a rebate percent of at most `MAX_REBATE_PERCENT`.
     ///
     /// # Returns
     ///
     /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
-    pub fn write_miner_claimed_dod(user: Principal, claimed_dod: u64) -> Result<(), String> {
-        match Self::get_miner_by_principal(user) {
-            None => Err("No miner found".to_string()),
-            Some(r) => {
-                MINERS.with(|v| {
-                    v.borrow_mut().insert(
-                        BtcAddress(r.btc_address.clone()),
-                        MinerInfo { claimed_dod, ..r },
-                    );
-                });
-                Ok(())
-            }
-        }
+    pub fn set_referral_settings(settings: ReferralSettings) -> Result<(), String> {
+        referral::set_referral_settings(settings)
     }
 
-    /// Executes cycles on block data by burning the specified amount of cycles.
+    /// Starts or stops the referral program, keeping the configured rebate.
     ///
     /// # Arguments
     ///
-    /// * `to_burn` - A `u128` representing the amount of cycles to burn.
+    /// * `enabled` - A `bool` indicating whether registrations and rebates are accepted.
     ///
     /// # Returns
     ///
     /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
-    pub fn execute_cycles_on_block_data(to_burn: u128) -> Result<(), String> {
-        let current_balance = ic_cdk::api::canister_balance128();
-        if current_balance < to_burn {
-            ic_cdk::println!(
-                "{}",
-                format!(
-                    "Insufficient cycles, current {:?}, expect to burn {:?}",
-                    current_balance, to_burn
-                )
-            );
-            Ok(())
-        } else {
-            ic_cdk::api::cycles_burn(to_burn.saturating_sub(CYCLES_BURNER_FEE));
-            Ok(())
-        }
+    pub fn set_referral_enabled(enabled: bool) -> Result<(), String> {
+        referral::set_referral_enabled(enabled)
     }
 
-    /// Places an order for a user over a range of blocks.
+    /// Retrieves the referral program settings.
     ///
-    /// This function updates the new user orders and new block orders with the specified range and amount.
+    /// # Returns
+    ///
+    /// * `Option<ReferralSettings>` - The configured settings, or `None` when the program was never configured.
+    pub fn get_referral_settings() -> Option<ReferralSettings> {
+        referral::get_referral_settings()
+    }
+
+    /// Sets the burn rate for a given user.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user placing the order.
-    /// * `range` - A `BlockRange` representing the range of blocks for the order.
-    /// * `amount` - A `u128` representing the amount for the order.
-    pub fn user_put_order_v2(user: Principal, range: BlockRange, amount: u128) {
-        // Update the new user orders with the specified range and amount.
+    /// * `user` - A `Principal` representing the user whose burn rate is to be set.
+    /// * `burn_rate` - A `u128` value representing the new burn rate to be set for the user.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn user_set_burnrate(user: Principal, burn_rate: u128) -> Result<(), String> {
+        staker::user_set_burnrate(user, burn_rate)
+    }
 
-        let old = NEW_USER_ORDERS.with_borrow(|v| v.get(&user));
+    /// Retrieves the burn rate and balance for a given user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user whose burn rate and balance are to be retrieved.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(u128, Nat), String>` - On success, returns a tuple containing the burn rate as `u128` and the balance as `Nat`.
+    ///   On failure, returns an error message as a `String`.
+    pub fn get_user_burnrate(user: Principal) -> Result<(u128, Nat), String> {
+        staker::get_user_burnrate(user)
+    }
 
-        NEW_USER_ORDERS.with_borrow_mut(|v| {
-            NewUserOrders::update_order(v, user, range, amount);
-        });
+    /// Computes the order range and the per block amount for a burn amount.
+    ///
+    /// The number of blocks covered is the burn amount divided by the user's burn rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `start_height` - A `Height` representing the starting block height.
+    /// * `burn_amount` - A `u128` representing the total amount to be burned.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(BlockRange, u128), String>` - On success, returns the block range and the burn rate per block.
+    ///   On failure, returns an error message as a `String`.
+    pub fn burnrate_orders_range(
+        user: Principal,
+        start_height: Height,
+        burn_amount: u128,
+    ) -> Result<(BlockRange, u128), String> {
+        let (rate, balance) = Self::get_user_burnrate(user)?;
+        let n_rate = Nat::from(rate);
+        let n_amount = Nat::from(burn_amount);
 
-        // Update the new block orders for each block in the specified range.
-        NEW_BLOCK_ORDERS.with_borrow_mut(|v| {
-            for block in range.0..range.1 {
-                NewBlockOrders::write_order_by_block_height(
-                    v,
-                    block,
-                    user,
-                    amount,
-                    OrderStatus::Pending,
-                );
-            }
+        if balance < n_amount {
+            return Err("Not enough balance".to_string());
+        }
 
-            if old.is_some() {
-                let _old = old.unwrap();
-                if _old.r.1 >= range.1 {
-                    for block in range.1..=_old.r.1 {
-                        NewBlockOrders::write_order_by_block_height(
-                            v,
-                            block,
-                            user,
-                            0,
-                            OrderStatus::Cancelled,
-                        );
-                    }
-                }
-            }
-        });
+        if balance < n_rate {
+            return Err("Not enough balance".to_string());
+        }
+
+        log_debug!("Burn rate: {:?}, Burn amount: {:?}", rate, burn_amount);
+
+        // if times > BURN_ORDERS_LIMIT {
+        //     return Err(format!(
+        //         "Burn Orders are over the limit {:?}",
+        //         BURN_ORDERS_LIMIT
+        //     ));
+        // }
+
+        let range =
+            amounts::orders_range(start_height, burn_amount, rate).map_err(|e| e.to_string())?;
+
+        Ok((range, rate))
     }
 
-    pub fn user_put_order_instant(user: Principal, range: BlockRange, amount: u128) {
-        // Update the new user orders with the specified range and amount.
+    /// Previews which of the user's existing orders would be kept, extended or cancelled
+    /// by placing burn rate orders, without writing anything.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `start_height` - A `Height` representing the starting block height.
+    /// * `burn_amount` - A `u128` representing the total amount to be burned.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<OrderPreview, String>` - On success, returns the `OrderPreview`. On failure, returns an error message as a `String`.
+    pub fn preview_put_orders(
+        user: Principal,
+        start_height: Height,
+        burn_amount: u128,
+    ) -> Result<OrderPreview, String> {
+        let (range, rate) = Self::burnrate_orders_range(user, start_height, burn_amount)?;
+        Ok(NewUserOrders::preview_update(
+            Self::get_user_range(user),
+            range,
+            rate,
+        ))
+    }
 
-        let old = NEW_USER_ORDERS.with_borrow(|v| v.get(&user));
+    /// Places burn rate orders for a user.
+    ///
+    /// This function calculates the number of orders based on the user's burn rate and the specified burn amount.
+    /// It then places the orders for the user within the specified block range.
+    /// A user's running order range is only overwritten when `replace_existing` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `start_height` - A `Height` representing the starting block height.
+    /// * `burn_amount` - A `u128` representing the total amount to be burned.
+    /// * `replace_existing` - A `bool` confirming that a running order range may be replaced.
+    /// * `priority_fee` - An `Option<u128>` of cycles paid per block on top of the order, burned at settlement.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<OrderPlacementResult, String>` - The blocks covered, the cycles committed and the projected reward if the orders are successfully placed, otherwise returns an error message.
+    pub fn user_put_burnrate_orders(
+        user: Principal,
+        start_height: Height,
+        burn_amount: u128,
+        replace_existing: bool,
+        priority_fee: Option<u128>,
+    ) -> Result<OrderPlacementResult, String> {
+        let (range, rate) = Self::burnrate_orders_range(user, start_height, burn_amount)?;
+        exposure::check_order(rate)?;
+
+        let last_height = Self::get_last_block().map_or(0, |(h, _)| h);
+        let running = Self::get_user_range(user).map_or(false, |v| v.r.end() > last_height);
+        if running && !replace_existing {
+            return Err(
+                "Existing orders are still running, set replace_existing to replace them"
+                    .to_string(),
+            );
+        }
 
-        NEW_USER_ORDERS.with_borrow_mut(|v| {
-            NewUserOrders::update_order(v, user, range, amount);
+        let blocks_covered = range.len();
+        let projected_reward_range =
+            Self::project_order_reward(range.start(), rate, blocks_covered);
+        Self::user_put_order_v2(user.clone(), range, rate);
+        priority::set_priority_fee(user, priority_fee);
+
+        Ok(OrderPlacementResult {
+            blocks_covered,
+            total_committed: rate.saturating_mul(blocks_covered as u128),
+            projected_reward_range,
+            replaced_previous: running,
+        })
+    }
+
+    /// Projects the DOD earned by orders of `rate` cycles per block, with the stakers' share of
+    /// the reward at `start_height` and the pools of the recent finalized blocks.
+    fn project_order_reward(start_height: Height, rate: u128, blocks: u64) -> (u64, u64) {
+        let reward = Self::get_block_reward_by_height(start_height, Self::get_halving_settings())
+            .unwrap_or(0);
+        let reward = match Self::get_reward_policy() {
+            Some(policy) => reward::split_reward(&policy, reward).1,
+            None => reward,
+        };
+        let pools = Self::get_last_block().map_or(vec![], |(height, _)| {
+            (height.saturating_sub(PROJECTION_BLOCKS)..height)
+                .map(|h| Self::get_block_total_cycles(h, false))
+                .collect::<Vec<u128>>()
         });
+        let bet = exposure::capped(
+            rate,
+            exposure::get_limit(pools.iter().max().copied().unwrap_or(0)),
+        );
+        let (low, high) = staker::projected_reward_range(reward, bet, blocks, &pools);
+        let bonus = lock_in::bonus_percent(Self::get_lock_in_bonus().as_ref(), blocks);
+        (lock_in::boost(low, bonus), lock_in::boost(high, bonus))
+    }
 
-        // Update the new block orders for each block in the specified range.
-        NEW_BLOCK_ORDERS.with_borrow_mut(|v| {
-            for block in range.0..range.1 {
-                NewBlockOrders::write_order_by_block_height(
-                    v,
-                    block,
-                    user,
-                    amount,
-                    OrderStatus::Pending,
-                );
-            }
+    /// Sets the reminders a user wants to be polled for.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `preferences` - An `Option<NotificationPreferences>` with the reminders, `None` removes them.
+    pub fn set_notification_preferences(
+        user: Principal,
+        preferences: Option<NotificationPreferences>,
+    ) {
+        notifications::set_preferences(user, preferences)
+    }
 
-            if old.is_some() {
-                let _old = old.unwrap();
-                if _old.r.1 > range.1 {
-                    for block in range.1.._old.r.1 {
-                        NewBlockOrders::write_order_by_block_height(
-                            v,
-                            block,
-                            user,
-                            0,
-                            OrderStatus::Cancelled,
-                        );
-                    }
-                }
+    /// Retrieves the reminders a user wants to be polled for.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<NotificationPreferences>` - The preferences, or `None` if the user set none.
+    pub fn get_notification_preferences(user: Principal) -> Option<NotificationPreferences> {
+        notifications::get_preferences(user)
+    }
+
+    /// Retrieves the reminders currently due for a user, computed from their preferences.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `since` - An `Option<Height>` representing the last block the client was notified of, wins up to it are left out.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<UserNotification>` - The wins, expiring order range and claimable balance to remind the user of.
+    pub fn get_notifications(user: Principal, since: Option<Height>) -> Vec<UserNotification> {
+        notifications::get_notifications(user, since)
+    }
+
+    /// Estimates the DOD a user's unsettled orders would earn, in the open block and the ordered
+    /// blocks after it, if no more cycles were deposited in them.
+    ///
+    /// The estimates use the reward policy and the exposure cap in force now, and change as
+    /// other users place orders.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `PendingRewards` - The estimate of every block, at most 100, and their sum.
+    pub fn get_pending_rewards(user: Principal) -> PendingRewards {
+        let mut pending = PendingRewards {
+            blocks: vec![],
+            estimated_total_dod: 0,
+        };
+        let (Some((open, _)), Some(range)) = (Self::get_last_block(), Self::get_user_range(user))
+        else {
+            return pending;
+        };
+        let halving_settings = Self::get_halving_settings();
+        let reward_policy = Self::get_reward_policy();
+        let Some(window) = BlockRange::with_len(open, PENDING_REWARD_BLOCKS)
+            .and_then(|window| window.intersect(&range.r))
+        else {
+            return pending;
+        };
+        for height in window.blocks() {
+            let order = Self::get_user_block_order(user, height);
+            if order.status != OrderStatus::Pending || order.value == 0 {
+                continue;
             }
-        });
+            let reward =
+                Self::get_block_reward_by_height(height, halving_settings.clone()).unwrap_or(0);
+            let pool = Self::get_block_total_cycles(height, false);
+            let (reward, pool) = match reward_policy.as_ref() {
+                Some(policy) => (
+                    reward::split_reward(policy, reward).1,
+                    pool.saturating_sub(Self::get_user_block_order(self_id(), height).value),
+                ),
+                None => (reward, pool),
+            };
+            let (bet, pool) = match exposure::get_limit(pool) {
+                Some(limit) => (
+                    exposure::capped(order.value, Some(limit)),
+                    exposure::capped_pool(height, pool, limit),
+                ),
+                None => (order.value, pool),
+            };
+            let estimated_dod =
+                u64::try_from(staker::scaled_reward(reward, bet, pool) / staker::REWARD_SCALE)
+                    .unwrap_or(u64::MAX);
+            pending.estimated_total_dod = pending.estimated_total_dod.saturating_add(estimated_dod);
+            pending.blocks.push(PendingReward {
+                height,
+                bet,
+                pool,
+                estimated_dod,
+            });
+        }
+        pending
     }
 
-    /// Updates the balances of users based on block orders.
+    /// Retrieves the priority fee paid per block by a user's running orders.
     ///
-    /// This function iterates through the block orders and updates the balance of each user.
-    /// If the user's balance is greater than the order amount and the user has a bet in the range,
-    /// it subtracts the order amount from the balance. Otherwise, the balance remains unchanged.
-    /// It also calculates the user's share of the total cycles and updates their total DOD reward.
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `u128` - The priority fee in cycles, `0` without one.
+    pub fn get_priority_fee(user: Principal) -> u128 {
+        priority::get_priority_fee(user)
+    }
+
+    /// Sets the number of orders settled per block at most. Orders are settled highest priority
+    /// fee first, the ones past the capacity miss the block and keep their cycles.
     ///
     /// # Arguments
     ///
-    /// * `block` - A `Height` representing the block height.
-    /// * `total_cycles` - A `u128` representing the total cycles for the block.
-    pub fn update_users_balance_v2(block: Height, total_cycles: u128) {
-        NEW_BLOCK_ORDERS.with_borrow_mut(|s| {
-            let orders: Vec<_> = NewBlockOrders::get_orders_by_block_height(s, block).collect();
-            for (p, v) in orders {
-                match Self::get_user_detail(p) {
-                    None => {
-                        continue;
-                    }
-                    Some(user) => {
-                        // Check if the user has a bet in the range.
-                        let is_range = NewUserOrders::get_user_bet(user.principal, block).is_some();
-                        let OrderDetail {
-                            value: user_bet,
-                            status,
-                        } = v;
-                        // Calculate the new balance.
-                        let mut actual_bet = user_bet;
-                        let new_balance = if user.balance >= user_bet
-                            && is_range
-                            && status != OrderStatus::Cancelled
-                            && status != OrderStatus::Filled
-                        {
-                            user.balance - user_bet
-                        } else {
-                            actual_bet = 0;
-                            user.balance
-                        };
-                        let blob29 =
-                            Blob::<29>::try_from(p.as_slice()).expect("error transformation");
+    /// * `capacity` - An `Option<u64>` representing the maximum settled orders per block, `None` settles every order.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_settlement_capacity(capacity: Option<u64>) -> Result<(), String> {
+        priority::set_settlement_capacity(capacity)
+    }
 
-                        // Calculate the user's share and reward.
+    /// Retrieves the number of orders settled per block at most.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u64>` - The settlement capacity, or `None` if every order is settled.
+    pub fn get_settlement_capacity() -> Option<u64> {
+        priority::get_settlement_capacity()
+    }
 
-                        let share = actual_bet as f64 / total_cycles as f64;
-                        let halving_settings =
-                            Self::get_halving_settings().expect("Can not get halving settings");
-                        let reward =
-                            Self::get_block_reward_by_height(block, Some(halving_settings.clone()))
-                                .expect("Can not get block reward by height");
-                        let r = (reward as f64 * share).floor() as u64;
+    /// Retrieves the priority fees paid in a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<PriorityStats>` - The stats of the block, or `None` if no priority fee was paid in it.
+    pub fn get_priority_stats(height: Height) -> Option<PriorityStats> {
+        priority::get_priority_stats(height)
+    }
 
-                        if status == OrderStatus::Pending {
-                            NewBlockOrders::write_order_by_block_height(
-                                s,
-                                block,
-                                p,
-                                user_bet,
-                                OrderStatus::Filled,
-                            );
-                        }
+    /// Retrieves the checkpoint of the latest block settlement.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SettlementCheckpoint>` - The checkpoint, or `None` if no block was settled since checkpoints were introduced.
+    pub fn get_settlement_checkpoint() -> Option<SettlementCheckpoint> {
+        settlement::get_checkpoint()
+    }
 
-                        // Update the user's details in the STAKERS map.
-                        STAKERS.with(|v| {
-                            v.borrow_mut().insert(
-                                blob29,
-                                UserDetail {
-                                    balance: new_balance,
-                                    total_dod: user.total_dod + r,
-                                    ..user
-                                },
-                            );
-                        });
-                    }
-                }
-            }
-        })
+    /// Retrieves the summaries of a range of difficulty epochs.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - A `u64` representing the first epoch index.
+    /// * `to` - A `u64` representing the last epoch index, included.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<EpochSummary>` - The summaries of the closed epochs in the range, at most 100.
+    pub fn get_epoch_summaries(from: u64, to: u64) -> Vec<EpochSummary> {
+        epochs::get_epoch_summaries(from, to)
     }
 
-    /// Retrieves the range of blocks for a given user.
+    /// Retrieves the current number of miners.
     ///
-    /// This function fetches the range of blocks that a user has set orders for.
+    /// # Returns
+    ///
+    /// * `u32` - The current number of miners.
+    pub fn get_current_miners_length() -> u32 {
+        miner::get_current_miners_length()
+    }
+
+    /// Checks if a miner exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the caller.
+    /// * `btc_address` - A `String` representing the Bitcoin address.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MinerInfo>` - Returns `Some(MinerInfo)` if the miner exists, otherwise `None`.
+    pub fn check_miner_if_existed(caller: Principal, _btc_address: String) -> Option<MinerInfo> {
+        miner::check_miner_if_existed(caller)
+    }
+
+    /// Loads signatures by block height.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlockSigs>` - Returns `Some(BlockSigs)` if signatures are found, otherwise `None`.
+    pub fn load_sigs_by_height(height: Height) -> Option<BlockSigs> {
+        miner::load_sigs_by_height(height)
+    }
+
+    /// Retrieves the parsed reveal payload of a winning block.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<WinnerEnvelope>` - The payload, or `None` if the block has no winner or was settled before payloads were kept.
+    pub fn get_winner_envelope(height: Height) -> Option<WinnerEnvelope> {
+        miner::get_winner_envelope(height)
+    }
+
+    /// Retrieves what the winner of a block was paid.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<WinnerPaymentProof>` - The winner, the cycles credited and the id of the `WinnerPaid` event, or `None` if the block has no winner.
+    pub fn get_winner_payment_proof(height: Height) -> Option<WinnerPaymentProof> {
+        miner::get_winner_payment_proof(height)
+    }
+
+    /// Renders a block, its settlement and its winner envelope as canonical JSON.
+    ///
+    /// Keys are sorted, integers are decimal strings and bytes are lowercase hex, so the
+    /// rendering of a finalized block never changes and can be hashed for archival.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String, String>` - On success, returns the JSON. On failure, returns an error message as a `String`.
+    pub fn get_block_json(height: Height) -> Result<String, String> {
+        block_json::get_block_json(height)
+    }
+
+    /// Sets the shares of the minted DOD that go to the team and ecosystem treasury buckets.
+    ///
+    /// The block reward is still minted in full to the emission bucket, the shares are minted
+    /// on top of it so that emission keeps the rest of the percentages.
+    ///
+    /// # Arguments
+    ///
+    /// * `split` - An `Option<TreasurySplit>` with the team and ecosystem percentages, `None` mints to emission only.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_treasury_split(split: Option<TreasurySplit>) -> Result<(), String> {
+        treasury::set_treasury_split(split)
+    }
+
+    /// Retrieves the shares of the minted DOD that go to the team and ecosystem treasury buckets.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<TreasurySplit>` - The split, or `None` if everything is minted to emission.
+    pub fn get_treasury_split() -> Option<TreasurySplit> {
+        treasury::get_treasury_split()
+    }
+
+    /// Retrieves the treasury buckets with their subaccounts and the DOD minted and transferred.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<TreasuryBucketInfo>, String>` - On success, returns every bucket. On failure, returns an error message as a `String`.
+    pub fn get_treasury_buckets() -> Result<Vec<TreasuryBucketInfo>, String> {
+        treasury::get_treasury_buckets()
+    }
+
+    /// Transfers DOD out of the team or ecosystem treasury bucket.
+    ///
+    /// The ledger fee is paid by the bucket on top of the amount.
+    ///
+    /// # Arguments
+    ///
+    /// * `bucket` - A `TreasuryBucket` to transfer from, the emission bucket is reserved for block rewards.
+    /// * `to` - An `Account` receiving the DOD.
+    /// * `amount` - A `u64` representing the DOD to transfer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Nat, String>` - On success, returns the ledger block index. On failure, returns an error message as a `String`.
+    pub async fn transfer_from_treasury_bucket(
+        bucket: TreasuryBucket,
+        to: Account,
+        amount: u64,
+    ) -> Result<Nat, String> {
+        if bucket == TreasuryBucket::Emission {
+            return Err("The emission bucket is reserved for block rewards".to_string());
+        }
+        if amount == 0 {
+            return Err("Transfer amount is zero".to_string());
+        }
+        let fee = match ledger::get_cached_metadata() {
+            Some(metadata) => metadata.fee,
+            None => Self::refresh_ledger_metadata().await?.fee,
+        };
+        let available = treasury::get_treasury_buckets()?
+            .into_iter()
+            .find(|b| b.bucket == bucket)
+            .map_or(0, |b| b.minted.saturating_sub(b.transferred));
+        if amount.saturating_add(fee) > available {
+            return Err(format!(
+                "Not enough DOD in the {:?} bucket, {} available",
+                bucket, available
+            ));
+        }
+        let from_subaccount = treasury::get_bucket_subaccount(&bucket)?;
+        let token_canister = Self::get_token_canister()?;
+        let res = Self::transfer_claimed_dod(
+            token_canister,
+            from_subaccount,
+            to,
+            amount,
+            fee,
+            ic_cdk::api::time(),
+        )
+        .await
+        .into_result()?;
+        treasury::record_transferred(bucket, amount + fee);
+        Ok(res)
+    }
+
+    /// Retrieves the receipts of the cycles burned for a range of blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - A `BlockRange` of the blocks, at most the first 100 are returned.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<BurnReceipt>` - The receipts, blocks that burned nothing are left out.
+    pub fn get_burn_receipts(range: BlockRange) -> Vec<BurnReceipt> {
+        burn_receipts::get_burn_receipts(range)
+    }
+
+    /// Retrieves the ledger block indices of the mint and burn made for a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlockLedgerLinks>` - The indices, or `None` if no transfer of the block reached the ledger.
+    pub fn get_block_ledger_links(height: Height) -> Option<BlockLedgerLinks> {
+        ledger_links::get_block_ledger_links(height)
+    }
+
+    /// Retrieves the winner sigs of the blocks won by the caller's miner.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the miner owner.
+    /// * `page` - A `u64` representing the zero based page number.
+    /// * `limit` - A `u64` representing the page size, capped at 100.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<(Height, BlockSigs)>, String>` - On success, returns the heights and sigs of the page. On failure, returns an error message as a `String`.
+    pub fn get_my_winning_sigs(
+        caller: Principal,
+        page: u64,
+        limit: u64,
+    ) -> Result<Vec<(Height, BlockSigs)>, String> {
+        miner::get_my_winning_sigs(caller, page, limit)
+    }
+
+    /// Sets how many blocks winner sigs are kept for, older sigs are pruned while blocks are generated.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention` - An `Option<u64>` representing the number of blocks, `None` keeps sigs forever.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_sigs_retention(retention: Option<u64>) -> Result<(), String> {
+        if retention == Some(0) {
+            return Err("Sigs retention can not be zero".to_string());
+        }
+        config::set_sigs_retention(retention)
+    }
+
+    /// Retrieves the number of blocks winner sigs are kept for.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u64>` - The retention in blocks, or `None` if sigs are kept forever.
+    pub fn get_sigs_retention() -> Option<u64> {
+        config::get_sigs_retention()
+    }
+
+    /// Prunes winner sigs older than the retention.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - A `u64` representing the maximum number of sigs to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The number of sigs removed.
+    pub fn prune_sigs(limit: u64) -> u64 {
+        miner::prune_sigs(limit)
+    }
+
+    /// Submits hashes for a miner.
+    ///
+    /// A miner that already submitted for the block may submit again while it is open, the new
+    /// candidate replaces the previous one when its price is lower, or equal with better work.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the caller, the miner owner or one of its operators.
+    /// * `btc_address` - A `String` representing the Bitcoin address.
+    /// * `signed_commit_psbt` - A `String` representing the signed commit PSBT.
+    /// * `signed_reveal_psbt` - A `String` representing the signed reveal PSBT.
+    /// * `cycles_price` - A `u128` representing the cycles price, ignored when miners bid in DOD.
+    /// * `dod_bid` - An `Option<u64>` representing the DOD bid, needed when miners bid in DOD.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MinerSubmitResponse, String>` - On success, returns `MinerSubmitResponse`. On failure, returns an error message as a `String`.
+    pub fn miner_submit_hashes(
+        caller: Principal,
+        btc_address: String,
+        signed_commit_psbt: String,
+        signed_reveal_psbt: String,
+        cycles_price: u128,
+        dod_bid: Option<u64>,
+    ) -> Result<MinerSubmitResponse, String> {
+        // if cycles_price < MIN_MINER_PRICE {
+        //     return Err(format!("Cycles price below {:?} cycles", MIN_MINER_PRICE));
+        // }
+        miner::miner_submit_hashes(
+            caller,
+            btc_address,
+            signed_commit_psbt,
+            signed_reveal_psbt,
+            cycles_price,
+            dod_bid,
+        )
+    }
+
+    /// Adds a block candidate.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    /// * `miner_candidate` - A `MinerCandidate` representing the miner candidate.
+    pub fn add_block_candidate(height: Height, miner_candidate: MinerCandidate) {
+        miner::add_block_candidate(height, miner_candidate)
+    }
+
+    /// Retrieves block candidates by height.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<MinerCandidate>` - A vector of `MinerCandidate` for the given height.
+    pub fn get_block_candidates(height: Height) -> Vec<MinerCandidate> {
+        miner::get_block_candidates(height)
+    }
+
+    /// Checks if a Bitcoin address is in the candidate list for a given block.
+    ///
+    /// # Arguments
+    ///
+    /// * `btc_address` - A `String` representing the Bitcoin address.
+    /// * `block` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MinerCandidate>` - Returns `Some(MinerCandidate)` if the address is in the candidate list, otherwise `None`.
+    pub fn check_if_in_candidate(btc_address: String, block: Height) -> Option<MinerCandidate> {
+        miner::check_if_in_candidate(btc_address, block)
+    }
+
+    /// Retrieves miner information by principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - A `Principal` representing the principal.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MinerInfo>` - Returns `Some(MinerInfo)` if the miner is found, otherwise `None`.
+    pub fn get_miner_by_principal(principal: Principal) -> Option<MinerInfo> {
+        miner::get_miner_by_principal(principal)
+    }
+
+    /// Registers a miner.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - A `Principal` representing the owner.
+    /// * `btc_address` - A `String` representing the Bitcoin address.
+    /// * `ecdsa_pubkey` - A `Vec<u8>` representing the ECDSA public key.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MinerInfo, String>` - On success, returns `MinerInfo`. On failure, returns an error message as a `String`.
+    pub fn register_miner(
+        owner: Principal,
+        btc_address: String,
+        ecdsa_pubkey: Vec<u8>,
+    ) -> Result<MinerInfo, String> {
+        miner::register_miner(owner, btc_address, ecdsa_pubkey)
+    }
+
+    /// Authorizes an operator to submit hashes for the owner's miner.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - A `Principal` representing the miner owner.
+    /// * `operator` - A `Principal` representing the operator.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn miner_add_operator(owner: Principal, operator: Principal) -> Result<(), String> {
+        miner::miner_add_operator(owner, operator)
+    }
+
+    /// Revokes an operator of the owner's miner.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - A `Principal` representing the miner owner.
+    /// * `operator` - A `Principal` representing the operator.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn miner_remove_operator(owner: Principal, operator: Principal) -> Result<(), String> {
+        miner::miner_remove_operator(owner, operator)
+    }
+
+    /// Retrieves the operators of the owner's miner.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - A `Principal` representing the miner owner.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Principal>` - The operators allowed to submit hashes for the miner.
+    pub fn get_miner_operators(owner: Principal) -> Vec<Principal> {
+        miner::get_miner_operators(owner)
+    }
+
+    /// Sets the Bitcoin network miner addresses must belong to.
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - An `Option<BtcNetwork>` representing the expected network, `None` accepts any network.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_btc_network(network: Option<BtcNetwork>) -> Result<(), String> {
+        config::set_btc_network(network)
+    }
+
+    /// Retrieves the Bitcoin network miner addresses must belong to.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BtcNetwork>` - The expected network, or `None` if any network is accepted.
+    pub fn get_btc_network() -> Option<BtcNetwork> {
+        config::get_btc_network()
+    }
+
+    /// Validates a Bitcoin address for mining against the configured network.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - A `String` representing the Bitcoin address.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), AddressValidationError>` - On success, returns `Ok(())`. On failure, returns why the address is rejected.
+    pub fn validate_btc_address(address: String) -> Result<(), AddressValidationError> {
+        validate_mining_address(address.as_str(), config::get_btc_network()).map(|_| ())
+    }
+
+    /// Retrieves miner information by address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - A `String` representing the address.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MinerInfo>` - Returns `Some(MinerInfo)` if the miner is found, otherwise `None`.
+    pub fn get_miner_by_address(address: String) -> Option<MinerInfo> {
+        miner::get_miner_by_address(address)
+    }
+
+    /// Demotes the winner of a finalized block whose PSBTs turn out to be invalid.
+    ///
+    /// The cycles and DOD credited to the demoted winner are taken back as far as they are
+    /// unspent and unclaimed. The next valid candidate can be promoted, it is paid at most what
+    /// was taken back. The correction is recorded as a `WinnerInvalidated` event.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - A `Principal` representing the owner making the correction.
+    /// * `height` - A `Height` representing the block height.
+    /// * `reason` - A `String` explaining why the winner is invalid.
+    /// * `promote_next` - A `bool` indicating whether the next valid candidate becomes the winner.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn invalidate_winner(
+        caller: Principal,
+        height: Height,
+        reason: String,
+        promote_next: bool,
+    ) -> Result<(), String> {
+        winner_correction::invalidate_winner(caller, height, reason, promote_next)
+    }
+
+    /// Retrieves a page of the registered miners.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - A `u64` representing the zero based page number.
+    /// * `limit` - A `u64` representing the page size, capped at 100.
+    /// * `status` - An `Option<MinerStatus>` to only list miners with that status.
+    /// * `include_pubkey` - A `bool`, the ECDSA public keys are left empty unless it is set.
+    ///
+    /// # Returns
+    ///
+    /// * `MinersPage` - The miners of the page and the number of miners matching the status.
+    pub fn get_miners(
+        page: u64,
+        limit: u64,
+        status: Option<MinerStatus>,
+        include_pubkey: bool,
+    ) -> MinersPage {
+        miner::get_miners(page, limit, status, include_pubkey)
+    }
+
+    /// Retrieves the mining history for a given Bitcoin address within a specified block range.
+    ///
+    /// This function calls the `get_mining_history_for_miners` function from the `miner` module
+    /// to obtain the mining history for the specified Bitcoin address and block range.
+    ///
+    /// # Arguments
+    ///
+    /// * `btc_address` - A `String` representing the Bitcoin address.
+    /// * `block_range` - A `BlockRange` representing the range of blocks to retrieve the mining history for.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<MinerBlockData>` - A vector of `MinerBlockData` containing the mining history for the specified address and block range.
+    pub fn get_mining_history_for_miners(
+        btc_address: String,
+        block_range: BlockRange,
+    ) -> Vec<MinerBlockData> {
+        miner::get_mining_history_for_miners(btc_address, block_range)
+    }
+
+    //  Blocks Execution
+
+    /// Records the running build in the upgrade history.
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - A `&str` representing the crate version of the canister.
+    /// * `git_commit` - A `&str` representing the git commit the canister was built from.
+    pub fn record_upgrade(version: &str, git_commit: &str) {
+        build_info::record_upgrade(version, git_commit)
+    }
+
+    /// Retrieves the build information of the running canister.
+    ///
+    /// The version and git commit are baked into the canister at compile time, so they are passed in by the actor.
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - A `&str` representing the crate version of the canister.
+    /// * `git_commit` - A `&str` representing the git commit the canister was built from.
+    /// * `candid` - A `&str` representing the candid interface of the canister.
+    ///
+    /// # Returns
+    ///
+    /// * `BuildInfo` - The version, git commit, candid interface hash and the upgrade history.
+    pub fn get_build_info(version: &str, git_commit: &str, candid: &str) -> BuildInfo {
+        build_info::get_build_info(version, git_commit, candid)
+    }
+
+    /// Retrieves the preimage of a block hash, so anyone can recompute its derivation.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlockEntropy>` - The entropy sources of the block, or `None` for the genesis block and blocks created before entropy mixing.
+    pub fn get_block_entropy(height: Height) -> Option<BlockEntropy> {
+        entropy::get_block_entropy(height)
+    }
+
+    /// Estimates the work to find a txid matching a difficulty.
+    ///
+    /// # Arguments
+    ///
+    /// * `difficulty` - A `Bitwork` representing the difficulty to match, usually the one of the current block.
+    /// * `hashrate` - A `u64` representing the hashes per second of the rig.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<WorkEstimate, String>` - The chance of one attempt, the expected attempts and the expected seconds. On failure, returns an error message as a `String`.
+    pub fn estimate_work(difficulty: Bitwork, hashrate: u64) -> Result<WorkEstimate, String> {
+        estimate_work(difficulty, hashrate)
+    }
+
+    /// Retrieves the DOD minted per cycle burned over the most recent blocks.
+    ///
+    /// Blocks that are not finalized yet are skipped, so the open block is never counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_blocks` - A `u64` representing the number of most recent blocks to look at, capped at 1000.
+    ///
+    /// # Returns
+    ///
+    /// * `EfficiencyStats` - The per block values and the aggregate over the window.
+    pub fn get_efficiency_stats(window_blocks: u64) -> EfficiencyStats {
+        block::get_efficiency_stats(window_blocks)
+    }
+
+    /// Retrieves a page of blocks matching a filter.
+    ///
+    /// Blocks are visited in height order within the filter's height range and the page is cut
+    /// after filtering, so `page` counts matching blocks only.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - A `BlockFilter` with the optional height range, winner and minimum burn conditions.
+    /// * `page` - A `u64` representing the zero based page number.
+    /// * `limit` - A `u64` representing the page size, capped at 100.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<BlockData>` - A vector of `BlockData` representing the matching blocks of the page.
+    pub fn get_blocks_filtered(filter: BlockFilter, page: u64, limit: u64) -> Vec<BlockData> {
+        block::get_blocks_filtered(filter, page, limit)
+    }
+
+    /// Serves the HTTP routes of the canister.
+    ///
+    /// `/export/orders?principal=...&from=&to=` returns the settled orders of a principal as CSV,
+    /// a page of `MAX_EXPORT_BLOCKS` blocks at a time. When the range goes on, the
+    /// `x-next-page-token` header holds the `page_token` of the next page.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - An `HttpRequest` from the HTTP gateway.
+    ///
+    /// # Returns
+    ///
+    /// * `HttpResponse` - The CSV export, or a plain text error with its status code.
+    pub fn http_request(req: HttpRequest) -> HttpResponse {
+        export::http_request(req)
+    }
+
+    /// Retrieves blocks within a specified range.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - A `BlockRange` of the blocks to retrieve.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<BlockData>` - A vector of `BlockData` representing the blocks within the specified range.
+    pub fn get_blocks_range(range: BlockRange) -> Vec<BlockData> {
+        block::get_blocks_range(range)
+    }
+
+    /// Retrieves the count of failed blocks in the last epoch.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_height` - A `Height` representing the starting height.
+    ///
+    /// # Returns
+    ///
+    /// * `(u64, u64, f64)` - A tuple containing the count of failed blocks, the total number of blocks, and the failure rate.
+    pub fn get_last_epoch_failed_blocks_count(start_height: Height) -> (u64, u64, f64) {
+        block::get_last_epoch_failed_blocks_count(start_height)
+    }
+
+    /// Starts the process of generating blocks asynchronously.
+    ///
+    /// This function initiates the block generation process and sets a timer to
+    /// periodically generate blocks based on the block time interval.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub async fn start_generate_blocks() -> Result<(), String> {
+        import::check_not_importing()?;
+        entropy::refresh_rand().await;
+        Self::generate_blocks();
+        let block_time_interval = Self::get_block_time_interval()?;
+        Self::set_timer(
+            block_time_interval + Self::get_submission_grace(),
+            Self::generate_blocks,
+        );
+        watchdog::start();
+        Ok(())
+    }
+
+    /// Sends the ledger operations queued before an upgrade, from a timer since calls can not be made while upgrading.
+    pub fn resume_ledger_queue() {
+        ic_cdk_timers::set_timer(Duration::ZERO, ledger_queue::process);
+    }
+
+    /// Retrieves the mints and burns waiting for the ledger, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(u64, LedgerOp)>` - The queued operations with their sequence numbers.
+    pub fn get_ledger_queue() -> Vec<(u64, LedgerOp)> {
+        ledger_queue::get_ledger_queue()
+    }
+
+    /// Retrieves the queued mints and burns that failed or got no reply from the ledger.
+    ///
+    /// They are not sent again on their own, the ledger may have applied one that got no reply.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(u64, LedgerDeadLetter)>` - The failed operations with the sequence numbers they were queued under.
+    pub fn get_ledger_dead_letters() -> Vec<(u64, LedgerDeadLetter)> {
+        ledger_queue::get_dead_letters()
+    }
+
+    /// Queues a failed ledger operation again, after the ones queued already.
+    ///
+    /// # Arguments
+    ///
+    /// * `seq` - A `u64` representing the sequence number of the failed operation.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LedgerOp, String>` - On success, returns the requeued operation. On failure, returns an error message as a `String`.
+    pub fn requeue_ledger_op(seq: u64) -> Result<LedgerOp, String> {
+        ledger_queue::requeue(seq)
+    }
+
+    /// Arms the block production watchdog if production was started, used after an upgrade.
+    pub fn start_watchdog() {
+        if Self::get_current_service().is_some() && Self::get_last_block().is_some() {
+            watchdog::start();
+        }
+    }
+
+    /// Sets how overdue block production may get before the watchdog restarts it.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - A `u64` representing the number of block intervals past the submission cutoff, at least 2.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_watchdog_factor(factor: u64) -> Result<(), String> {
+        watchdog::set_watchdog_factor(factor)
+    }
+
+    /// Retrieves the block production and cycles status.
+    ///
+    /// # Returns
+    ///
+    /// * `HealthStatus` - The last block, how overdue it is, the watchdog restarts and the cycles health.
+    pub fn health() -> HealthStatus {
+        watchdog::health()
+    }
+
+    /// Sets or removes the reward boost for blocks with few deposits.
+    ///
+    /// # Arguments
+    ///
+    /// * `boost` - An `Option<RewardBoost>` with the deposit threshold and the boost of an empty block, `None` to remove it.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_reward_boost(boost: Option<RewardBoost>) -> Result<(), String> {
+        boost::set_reward_boost(boost)
+    }
+
+    /// Retrieves the reward boost.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<RewardBoost>` - The boost, or `None` if block rewards are not boosted.
+    pub fn get_reward_boost() -> Option<RewardBoost> {
+        boost::get_reward_boost()
+    }
+
+    /// Sets the rate at which unclaimed DOD is converted back to cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles_per_dod` - An `Option<u128>` of cycles credited per whole DOD, `None` disables the conversion.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_cycles_per_dod(cycles_per_dod: Option<u128>) -> Result<(), String> {
+        conversion::set_cycles_per_dod(cycles_per_dod)
+    }
+
+    /// Retrieves the rate at which unclaimed DOD is converted back to cycles.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u128>` - The cycles credited per whole DOD, or `None` if the conversion is disabled.
+    pub fn get_cycles_per_dod() -> Option<u128> {
+        conversion::get_cycles_per_dod()
+    }
+
+    /// Retrieves the entry counts and approximate sizes of the main stable maps.
+    ///
+    /// # Returns
+    ///
+    /// * `MemoryReport` - The usage of every map and the stable memory pages allocated in total.
+    pub fn get_memory_report() -> MemoryReport {
+        memory_report::get_memory_report()
+    }
+
+    /// Sets a timer to execute a callback function at a specified interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - A `u64` representing the interval in nanoseconds.
+    /// * `callback` - A function pointer to the callback function to be executed.
+    ///
+    /// # Returns
+    ///
+    /// * `TimerId` - The ID of the created timer.
+    pub fn set_timer(interval: u64, callback: fn()) -> TimerId {
+        let secs = Duration::from_nanos(interval);
+        log_debug!("Timer canister: Starting a new timer with {secs:?} interval...");
+        // Schedule a new periodic task to increment the counter.
+        let timer_id = ic_cdk_timers::set_timer_interval(secs, callback);
+
+        // Add the timer ID to the global vector.
+        TIMER_IDS.with(|timer_ids| timer_ids.borrow_mut().push(timer_id));
+        timer_id
+    }
+
+    pub fn timer_stop() {
+        TIMER_IDS.with(|timer_ids| {
+            if let Some(timer_id) = timer_ids.borrow_mut().pop() {
+                log_debug!("Timer canister: Stopping timer ID {timer_id:?}...");
+                // It's safe to clear non-existent timer IDs.
+                ic_cdk_timers::clear_timer(timer_id);
+            }
+        });
+    }
+
+    pub fn set_timer_delay(interval: u64, callback: fn()) -> TimerId {
+        let secs = Duration::from_nanos(interval);
+        log_debug!("Timer canister: Starting a new timer with {secs:?} interval...");
+        // Schedule a new periodic task to increment the counter.
+        let timer_id = ic_cdk_timers::set_timer(secs, callback);
+
+        // Add the timer ID to the global vector.
+        TIMER_IDS.with(|timer_ids| timer_ids.borrow_mut().push(timer_id));
+        timer_id
+    }
+
+    /// Generates the next block, unless the previous generation is still in flight.
+    ///
+    /// A generation stays in flight until its synchronous settlement has completed, its
+    /// mints and burns wait in the ledger queue. Overlapping ticks are skipped, counted
+    /// and collapsed into a single deferred generation once the running one completes.
+    /// Generation waits while the ledger queue is backlogged.
+    pub fn generate_blocks() {
+        // mints and burns of settled blocks go first, settling more would only queue more
+        if ledger_queue::is_backlogged() {
+            log_warn!("ledger queue is backlogged, block generation waits");
+            Self::timer_stop();
+            Self::set_timer_delay(ledger_queue::BACKLOG_RETRY_DELAY, Self::generate_blocks);
+            return;
+        }
+        if !generation::try_begin() {
+            info_log_add("generate_blocks: previous generation still in flight, tick skipped");
+            return;
+        }
+        Self::run_generate_blocks();
+        Self::release_generation();
+    }
+
+    /// Releases one part of the current generation and runs the deferred tick if any.
+    fn release_generation() {
+        if generation::release() {
+            Self::generate_blocks();
+        }
+    }
+
+    /// Retrieves the number of timer ticks skipped because a generation was still in flight.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The number of skipped ticks since the last upgrade.
+    pub fn get_skipped_generation_ticks() -> u64 {
+        generation::get_skipped_ticks()
+    }
+
+    fn run_generate_blocks() {
+        let block_time_interval = Self::get_block_time_interval().unwrap();
+        let difficulty_adjust_epoch = Self::get_difficulty_adjust_epoch().unwrap();
+        let start_difficulty = Self::get_start_difficulty().unwrap();
+        let halving_settings = Self::get_halving_settings();
+        match Self::get_last_block() {
+            None => {
+                let mut random_32 = fake_32();
+                random_32.reverse();
+                // genesis block
+                if let Err(e) =
+                    Self::write_genesis_block(era::salt_hash(random_32), ic_cdk::api::time())
+                {
+                    log_error!("can not write genesis block: {}", e);
+                }
+            }
+            Some(r) => {
+                Self::timer_stop();
+
+                let mut last_block = r.1;
+                // 1. handle candidates sorting, best bid first, submit time first
+                let bid_currency = Self::get_bid_currency();
+                let mut candidates = Self::get_block_candidates(last_block.height);
+                miner::sort_candidates(&mut candidates, &bid_currency);
+                // candidates of registered assets only compete for their asset's reward
+                let asset_candidates = candidates
+                    .iter()
+                    .filter(|c| !assets::is_block_candidate(c))
+                    .cloned()
+                    .collect::<Vec<MinerCandidate>>();
+                candidates.retain(|c| assets::is_block_candidate(c));
+
+                // production stopped after a block nobody deposited in, it goes on from the next block
+                if last_block.status() == BlockStatus::Finalized {
+                    Self::open_next_block(
+                        &last_block,
+                        candidates.as_slice(),
+                        block_time_interval,
+                        difficulty_adjust_epoch,
+                        &start_difficulty,
+                        halving_settings,
+                    );
+                    return;
+                }
+
+                let mut checkpoint = match settlement::pending(last_block.height) {
+                    // the block was closed, minted and its winner paid when the settlement started
+                    Some(checkpoint) => {
+                        log_info!(
+                            "resuming settlement of block {} after {:?}",
+                            checkpoint.height,
+                            checkpoint.last_key
+                        );
+                        checkpoint
+                    }
+                    None => {
+                        // the deposits are final now, a boosted reward replaces the estimate
+                        // the block was opened with
+                        let last_block_reward = Self::get_block_reward_by_height(
+                            last_block.height,
+                            halving_settings.clone(),
+                        )
+                        .unwrap();
+                        last_block.rewards = last_block_reward;
+
+                        // close the block before settling it, submissions are only accepted while open
+                        if let Err(e) = last_block.transition(BlockStatus::Closing) {
+                            log_error!("can not close block {}: {}", last_block.height, e);
+                            return;
+                        }
+                        BLOCKS
+                            .with(|v| v.borrow_mut().insert(last_block.height, last_block.clone()));
+                        replication::record(StateChange::BlockWritten(last_block.clone()));
+
+                        let height = last_block.height;
+                        ledger_queue::enqueue(LedgerOp::MintReward {
+                            height,
+                            amount: last_block_reward,
+                        });
+
+                        let asset_rewards =
+                            assets::settle_assets(height, asset_candidates.as_slice());
+                        if !asset_rewards.is_empty() {
+                            ledger_queue::enqueue(LedgerOp::MintAssets {
+                                rewards: asset_rewards,
+                            });
+                        }
+
+                        if bid_currency == BidCurrency::Dod {
+                            // a miner that claimed its DOD since it bid can no longer pay the bid
+                            candidates.retain(|c| {
+                                miner::unclaimed_dod(c.btc_address.as_str())
+                                    >= c.dod_bid.unwrap_or(0)
+                            });
+                        }
+                        let winner_address = if candidates.len() > 0 {
+                            Some(candidates[0].btc_address.clone())
+                        } else {
+                            None
+                        };
+                        // the winner of a DOD auction is paid no cycles
+                        let cycle_price = if candidates.len() > 0 {
+                            match bid_currency {
+                                BidCurrency::Cycles => Some(candidates[0].cycles_price.clone()),
+                                BidCurrency::Dod => Some(0),
+                            }
+                        } else {
+                            None
+                        };
+                        let mut winner_dod_bid = None;
+
+                        // 1.1 should get current block total cycles to see the price if winner can win.
+                        let cycle_deposit = Self::get_block_total_cycles(last_block.height, false);
+
+                        log_debug!("cycle_deposit is {:?}", cycle_deposit);
+
+                        let mut _miner = None;
+                        if winner_address.is_some()
+                            && cycle_price.is_some()
+                            && cycle_deposit > cycle_price.unwrap()
+                        {
+                            let miner_info =
+                                Self::get_miner_by_address(winner_address.unwrap()).unwrap();
+                            // a hash beyond the difficulty raises the price paid, up to the
+                            // cycles deposited in the block
+                            let paid = reward::apply_bonus(
+                                cycle_price.unwrap(),
+                                reward::tier_bonus_percent(
+                                    Self::get_difficulty_tiers().as_ref(),
+                                    TierBonusTarget::Cycles,
+                                    candidates[0].match_depth,
+                                ),
+                            )
+                            .min(cycle_deposit);
+                            _miner = Some(MinerInfo {
+                                reward_cycles: Some(paid),
+                                ..miner_info.clone()
+                            });
+
+                            // because we have miner meanwhile owner as staker,
+                            // we increase the balance from cycle price for miners,
+                            // the owner becomes a staker when its miner first wins
+                            staker::register_user(miner_info.owner.clone()).unwrap();
+                            cycles::add_to_user_pool(paid);
+                            Self::increase_user_cycle_balance(
+                                miner_info.owner.clone(),
+                                Nat::from(paid),
+                            )
+                            .unwrap();
+                            miner::record_winner_payment(last_block.height, &miner_info, paid);
+                            if bid_currency == BidCurrency::Dod {
+                                winner_dod_bid = Some(miner::debit_winner_bid(
+                                    last_block.height,
+                                    miner_info.btc_address.as_str(),
+                                    candidates[0].dod_bid.unwrap_or(0),
+                                ));
+                            }
+                        }
+
+                        // blocks without a winner may refund part of the debited cycles
+                        // according to the failed block policy
+                        let refund_percent = if _miner.is_none() {
+                            Self::get_failed_block_policy().refund_percent()
+                        } else {
+                            0
+                        };
+                        SettlementCheckpoint {
+                            winner_dod_bid,
+                            ..settlement::begin(
+                                last_block.height,
+                                cycle_deposit,
+                                refund_percent,
+                                _miner,
+                            )
+                        }
+                    }
+                };
+
+                // 1.2 update user balances, a large block is settled over several ticks
+                let settled =
+                    Self::settle_users_balance_batch(&mut checkpoint, settlement::SETTLEMENT_BATCH);
+                settlement::save_checkpoint(checkpoint.clone());
+                if !settled {
+                    Self::set_timer_delay(
+                        settlement::SETTLEMENT_RESUME_DELAY,
+                        Self::generate_blocks,
+                    );
+                    return;
+                }
+
+                let cycle_deposit = checkpoint.cycle_deposit;
+                let _miner = checkpoint.winner.clone();
+                let treasury_revinvest = amounts::treasury_reinvest(
+                    cycle_deposit,
+                    _miner.as_ref().and_then(|m| m.reward_cycles),
+                    checkpoint.refunded,
+                );
+
+                // to burn equals to treasury_revinvest, plus the priority fees paid in the block
+
+                let to_burn = treasury_revinvest.saturating_add(checkpoint.stats.total_fees);
+                Self::user_put_order_v2(
+                    id(),
+                    BlockRange::inclusive(last_block.height + 1, last_block.height + 1)
+                        .expect("a single block range is never inverted"),
+                    treasury_revinvest,
+                );
+
+                // 2. write block data and update winner to storage
+
+                let mut _block = last_block.clone();
+
+                _block.winner = _miner.clone();
+
+                // 3. write winner sigs to storage
+                // candidates that could not pay their DOD bid were left out when the winner was
+                // decided, so the winner is looked up by address
+                let winner_candidate = _block
+                    .winner
+                    .as_ref()
+                    .and_then(|w| candidates.iter().find(|c| c.btc_address == w.btc_address));
+                if let Some(candidate) = winner_candidate {
+                    if let Err(e) = miner::record_winner_sigs(_block.height, candidate) {
+                        log_error!("can not keep winner sigs of {}: {}", _block.height, e);
+                    }
+                }
+
+                if let Err(e) = _block.transition(BlockStatus::Settled) {
+                    log_error!("can not settle block {}: {}", _block.height, e);
+                    return;
+                }
+
+                // 4. burn  cycles here
+                log_debug!(
+                    "{}",
+                    format!(
+                        "execute_cycles_on_block_data, cycle_deposit is {:?}, expect to burn {:?}",
+                        cycle_deposit, to_burn
+                    )
+                );
+
+                info_log_add(
+                    format!(
+                        "execute_cycles_on_block_data, cycle_deposit is {:?}, expect to burn {:?}",
+                        cycle_deposit, to_burn
+                    )
+                    .as_str(),
+                );
+
+                // temporally comment out execute_cycles_on_block_data
+                Self::execute_cycles_on_block_data(_block.height, to_burn.clone()).unwrap();
+
+                // 4.1 burn DOD
+
+                _block.cycle_burned = to_burn.clone();
+                log_debug!("block.winner is {:?}", _miner.clone());
+                let _id = id();
+                let (total_burn, no_deposits) = match Self::get_reward_policy() {
+                    None => {
+                        let (total_burn, _) =
+                            Self::get_user_block_reward(_block.height.clone(), _id)
+                                .unwrap_or_default();
+                        (
+                            total_burn,
+                            total_burn == Self::get_default_rewards().unwrap(),
+                        )
+                    }
+                    Some(policy) => {
+                        let block_reward = Self::get_block_reward_by_height(
+                            _block.height,
+                            halving_settings.clone(),
+                        )
+                        .unwrap();
+                        let stakers_cycles = cycle_deposit
+                            .saturating_sub(Self::get_user_block_order(_id, _block.height).value)
+                            .saturating_sub(donations::block_total(_block.height));
+                        let winner_bonus = winner_candidate.map_or(0, |c| {
+                            reward::tier_bonus_percent(
+                                Self::get_difficulty_tiers().as_ref(),
+                                TierBonusTarget::Dod,
+                                c.match_depth,
+                            )
+                        });
+                        let total_burn = reward::allocate(
+                            _block.height,
+                            &policy,
+                            block_reward,
+                            _miner.as_ref().map(|m| m.btc_address.as_str()),
+                            winner_bonus,
+                            stakers_cycles > 0,
+                        );
+                        (total_burn, stakers_cycles == 0)
+                    }
+                };
+                // DOD rebates to referrers and lock-in bonuses are paid out of the treasury share
+                let total_burn = total_burn
+                    .saturating_sub(checkpoint.dod_rebates.unwrap_or(0))
+                    .saturating_sub(checkpoint.lock_in_bonuses.unwrap_or(0));
+                // the winning DOD bid was taken from the winner's balance when the block closed
+                let total_burn = total_burn.saturating_add(checkpoint.winner_dod_bid.unwrap_or(0));
+                // a block nobody deposited in is finalized without a burn
+                let total_burn = if no_deposits { 0 } else { total_burn };
+                log_debug!("dod total burn is {:?}", total_burn);
+
+                // nothing reaches the ledger for an empty burn
+                if total_burn > 0 {
+                    ledger_queue::enqueue(LedgerOp::Burn {
+                        height: _block.height,
+                        amount: total_burn,
+                    });
+                }
+
+                _block.dod_burned = total_burn.clone();
+                if let Err(e) = _block.transition(BlockStatus::Finalized) {
+                    log_error!("can not finalize block {}: {}", _block.height, e);
+                    return;
+                }
+                BLOCKS.with(|v| v.borrow_mut().insert(_block.height.clone(), _block.clone()));
+                replication::record(StateChange::BlockWritten(_block.clone()));
+                pool_twap::record(&_block, Self::get_block_total_cycles(_block.height, false));
+                block_archive::maybe_archive();
+                subscriber::notify_block_finalized(_block.clone());
+                top_up::maybe_run_top_up();
+                miner::prune_sigs(miner::PRUNE_SIGS_BATCH);
+
+                if no_deposits {
+                    log_info!(
+                        "No one deposit cycles in block {}, production stops until start_generate_blocks",
+                        _block.height
+                    );
+                    return;
+                }
+
+                if !settlement::is_complete(last_block.height) {
+                    log_error!(
+                        "settlement of block {} is not complete, next block not started",
+                        last_block.height
+                    );
+                    return;
+                }
+                Self::open_next_block(
+                    &_block,
+                    candidates.as_slice(),
+                    block_time_interval,
+                    difficulty_adjust_epoch,
+                    &start_difficulty,
+                    halving_settings,
+                );
+            }
+        }
+    }
+
+    /// Opens the block after the finalized `block` and arms the timer settling it.
+    fn open_next_block(
+        block: &BlockData,
+        candidates: &[MinerCandidate],
+        block_time_interval: u64,
+        difficulty_adjust_epoch: u64,
+        start_difficulty: &Bitwork,
+        halving_settings: Option<HalvingSettings>,
+    ) {
+        // 5. create new block, the hash mixes raw_rand, the last hash and the candidates
+        let random_32 =
+            entropy::next_block_hash(block.height + 1, block.hash.as_slice(), candidates);
+        spawn(entropy::refresh_rand());
+
+        // 6. difficulty adjust
+        let bitwork = Self::adjust_difficulty(
+            block.height,
+            block.difficulty.clone(),
+            start_difficulty,
+            block.winner.is_some(),
+            difficulty_adjust_epoch,
+        );
+        epochs::maybe_close_epoch(block.height, &bitwork, difficulty_adjust_epoch);
+
+        let current_time = ic_cdk::api::time();
+        let submission_grace = Self::get_submission_grace();
+        let anchor = schedule::get_schedule_anchor();
+        let (next_block_time, next_anchor) = schedule::next_block_close(
+            anchor,
+            block.height + 1,
+            current_time,
+            block_time_interval,
+            submission_grace,
+        );
+        if next_anchor != anchor {
+            log_warn!(
+                "block {} is more than {} slots late, schedule anchored at it",
+                block.height + 1,
+                schedule::MAX_CATCH_UP_SLOTS
+            );
+            config::set_schedule_anchor(next_anchor).ok();
+        }
+        let rewards = match Self::get_block_reward_by_height(block.height + 1, halving_settings) {
+            Ok(rewards) => rewards,
+            Err(e) => {
+                log_error!("can not open block {}: {}", block.height + 1, e);
+                return;
+            }
+        };
+        let block_data = BlockData {
+            height: block.height + 1,
+            rewards,
+            winner: None,
+            difficulty: bitwork,
+            hash: random_32,
+            block_time: current_time,
+            next_block_time,
+            history: false,
+            cycle_burned: 0,
+            dod_burned: 0,
+            status: Some(BlockStatus::Open),
+            broadcast: None,
+            submission_cutoff: Some(next_block_time + submission_grace),
+        };
+        BLOCKS.with(|v| v.borrow_mut().insert(block_data.height, block_data.clone()));
+        replication::record(StateChange::BlockWritten(block_data.clone()));
+        // settle only after the cutoff, so grace window submissions still count
+        Self::set_timer_delay(
+            (next_block_time + submission_grace).saturating_sub(current_time),
+            Self::generate_blocks,
+        );
+    }
+
+    /// Writes the genesis block at height 0 and arms the first difficulty increase.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - A `Vec<u8>` representing the genesis block hash.
+    /// * `time` - A `u64` representing the genesis block time in nanoseconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlockData, String>` - On success, returns the genesis `BlockData`. On failure, returns an error message as a `String`.
+    fn write_genesis_block(hash: Vec<u8>, time: u64) -> Result<BlockData, String> {
+        let block_time_interval = Self::get_block_time_interval()?;
+        let difficulty_adjust_epoch = Self::get_difficulty_adjust_epoch()?;
+
+        Self::set_consider_increase(Some(0 + difficulty_adjust_epoch))?;
+
+        let block_data = BlockData {
+            height: 0,
+            rewards: Self::get_default_rewards()?,
+            winner: None,
+            difficulty: Self::get_start_difficulty()?,
+            hash,
+            block_time: time,
+            next_block_time: time + block_time_interval,
+            history: false,
+            cycle_burned: 0,
+            dod_burned: 0,
+            status: Some(BlockStatus::Open),
+            broadcast: None,
+            submission_cutoff: Some(time + block_time_interval + Self::get_submission_grace()),
+        };
+        BLOCKS.with(|v| v.borrow_mut().insert(0, block_data.clone()));
+        replication::record(StateChange::BlockWritten(block_data.clone()));
+        config::set_schedule_anchor(ScheduleAnchor { height: 0, time })?;
+        Ok(block_data)
+    }
+
+    /// Initializes the genesis block with explicit parameters instead of a random hash and the current time.
+    ///
+    /// This is rejected once a genesis block exists. Block production still starts with `start_generate_blocks`.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - A `GenesisParams` holding the optional hash, timestamp and message of the genesis block.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlockData, String>` - On success, returns the genesis `BlockData`. On failure, returns an error message as a `String`.
+    pub fn init_genesis(params: GenesisParams) -> Result<BlockData, String> {
+        if Self::get_last_block().is_some() {
+            return Err("Genesis block already exists".to_string());
+        }
+        import::check_not_importing()?;
+        if let Some(hash) = params.hash.as_ref() {
+            if hash.len() != 32 {
+                return Err("Genesis hash must be 32 bytes".to_string());
+            }
+        }
+        if let Some(message) = params.message.as_ref() {
+            if message.len() > GENESIS_MESSAGE_MAX_LEN {
+                return Err(format!(
+                    "Genesis message is longer than {} bytes",
+                    GENESIS_MESSAGE_MAX_LEN
+                ));
+            }
+        }
+
+        let time = now();
+        if let Some(timestamp) = params.timestamp {
+            if timestamp == 0 || timestamp > time {
+                return Err("Genesis timestamp must be set and not in the future".to_string());
+            }
+        }
+        let time = params.timestamp.unwrap_or(time);
+        let hash = params.hash.unwrap_or_else(|| {
+            let mut random_32 = fake_32();
+            random_32.reverse();
+            era::salt_hash(random_32)
+        });
+
+        config::set_genesis_info(GenesisInfo {
+            hash: hash.clone(),
+            timestamp: time,
+            message: params.message,
+        })?;
+        Self::write_genesis_block(hash, time)
+    }
+
+    /// Retrieves the parameters the genesis block was initialized with through `init_genesis`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<GenesisInfo>` - The genesis information if the genesis was explicitly initialized, otherwise `None`.
+    pub fn get_genesis_info() -> Option<GenesisInfo> {
+        config::get_genesis_info()
+    }
+
+    /// Retrieves the last block.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(u64, BlockData)>` - Returns `Some((u64, BlockData))` if the last block exists, otherwise `None`.
+    pub fn get_last_block() -> Option<(u64, BlockData)> {
+        block::get_last_block()
+    }
+
+    /// Retrieves the expected schedule of the open block and the blocks after it.
+    ///
+    /// Times follow the current block interval and submission grace, rewards the halving and
+    /// boost settings. The difficulty is the current one and may be adjusted along the way.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - A `u64` representing the number of blocks, capped at 100.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ScheduledBlock>, String>` - On success, returns the blocks in height order. On failure, returns an error message as a `String`.
+    pub fn get_block_schedule(n: u64) -> Result<Vec<ScheduledBlock>, String> {
+        schedule::get_block_schedule(n)
+    }
+
+    /// Retrieves a block by its height.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `u64` representing the height of the block.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlockData>` - Returns `Some(BlockData)` if the block exists, otherwise `None`.
+    pub fn get_block_by_height(height: u64) -> Option<BlockData> {
+        block::get_block_by_height(height)
+    }
+
+    /// Retrieves a block together with its winner sigs and candidate summaries.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlockBundle>` - Returns `Some(BlockBundle)` if the block exists, otherwise `None`.
+    pub fn get_block_bundle(height: Height) -> Option<BlockBundle> {
+        block::get_block_bundle(height)
+    }
+
+    /// Retrieves the bundles of several blocks at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `heights` - A `Vec<Height>` representing the block heights, at most `MAX_BLOCK_BUNDLES`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<BlockBundle>, String>` - On success, returns the bundles of the existing blocks in the order asked. On failure, returns an error message as a `String`.
+    pub fn get_block_bundles(heights: Vec<Height>) -> Result<Vec<BlockBundle>, String> {
+        block::get_block_bundles(heights)
+    }
+
+    /// Deposits cycles from ICP.
+    ///
+    /// This function records the deposit, transfers ICP to the CMC canister and notifies the top-up, converting the ICP to cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - A `Principal` representing the sender.
+    /// * `qty_e8s_u64` - A `u64` representing the quantity of ICP in e8s.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DepositRecord, String>` - The deposit record, a failed notification leaves it `Transferred` with the error.
+    ///
+    /// # Steps
+    ///
+    /// 1. Records a `Pending` deposit.
+    /// 2. Transfers ICP to the CMC canister.
+    /// 3. Notifies the top-up to convert ICP to cycles.
+    /// 4. Updates the user's balance with the new cycles.
+    pub async fn deposit_cycles_from_icp(
+        from: Principal,
+        qty_e8s_u64: u64,
+    ) -> Result<DepositRecord, String> {
+        deposit::begin_deposit(from, qty_e8s_u64).await
+    }
+
+    /// Retries the top-up notification of a deposit stuck after its ICP transfer.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the owner of the deposit.
+    /// * `deposit_id` - A `u64` representing the deposit id.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DepositRecord, String>` - The updated deposit, or an error if it is not `Transferred` or still notifying.
+    pub async fn retry_deposit(user: Principal, deposit_id: u64) -> Result<DepositRecord, String> {
+        deposit::retry_deposit(user, deposit_id).await
+    }
+
+    /// Retrieves the deposits of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<DepositRecord>` - The deposits of the user, newest first.
+    pub fn get_deposits(user: Principal) -> Vec<DepositRecord> {
+        deposit::get_deposits(user)
+    }
+
+    /// Deposits the cycles attached to the call.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user to credit.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u128, String>` - The accepted cycles, or an error if no cycles were attached.
+    pub fn deposit_attached_cycles(user: Principal) -> Result<u128, String> {
+        deposit::deposit_attached_cycles(user)
+    }
+
+    /// Registers an integrator staking on behalf of its end users.
+    ///
+    /// # Arguments
+    ///
+    /// * `integrator` - A `Principal` representing the integrating canister.
+    /// * `name` - A `String` naming the integrator.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Integrator, String>` - The registered integrator, or an error if it is already registered.
+    pub fn register_integrator(integrator: Principal, name: String) -> Result<Integrator, String> {
+        integrator::register_integrator(integrator, name)
+    }
+
+    /// Registers a mining pool, or updates its name and limit when it is registered already.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - A `Principal` representing the pool, the only caller allowed to submit for its workers.
+    /// * `name` - A `String` naming the pool.
+    /// * `max_candidates_per_block` - An `Option<u64>` limiting the entries the pool submits per block, `DEFAULT_POOL_CANDIDATES_PER_BLOCK` by default.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MiningPool, String>` - The registered pool, or an error if the name is invalid.
+    pub fn register_pool(
+        pool: Principal,
+        name: String,
+        max_candidates_per_block: Option<u64>,
+    ) -> Result<MiningPool, String> {
+        pool::register_pool(pool, name, max_candidates_per_block)
+    }
+
+    /// Retrieves the era blocks are generated in.
+    ///
+    /// A new era starts with each bootstrap and clean up, its salt is mixed into every block hash.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlockEra>` - The current era, or `None` before the first one.
+    pub fn get_block_era() -> Option<BlockEra> {
+        era::get_era()
+    }
+
+    /// Retrieves a registered mining pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - A `Principal` representing the pool.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MiningPool>` - The pool if registered, otherwise `None`.
+    pub fn get_pool(pool: Principal) -> Option<MiningPool> {
+        pool::get_pool(pool)
+    }
+
+    /// Submits worker solutions of a pool for the open block.
+    ///
+    /// Each entry is checked as a submission of the worker's miner, which is registered for the
+    /// pool on the first entry of the worker. Entries beyond the pool's limit for the block fail.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - A `Principal` representing the calling pool.
+    /// * `candidates` - A `Vec<PoolCandidate>` of at most `MAX_POOL_BATCH` worker solutions.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<Result<MinerSubmitResponse, String>>, String>` - The result of each entry, in order, or an error if the pool is not registered or the batch is too large.
+    pub fn pool_submit_candidates(
+        pool: Principal,
+        candidates: Vec<PoolCandidate>,
+    ) -> Result<Vec<Result<MinerSubmitResponse, String>>, String> {
+        pool::submit_candidates(pool, candidates)
+    }
+
+    /// Claims what a worker of a pool won for the pool.
+    ///
+    /// The cycles and miner DOD of the worker's account move to the pool's own staker account.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - A `Principal` representing the calling pool.
+    /// * `btc_address` - A `&str` holding the worker's bitcoin address.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PoolWorkerClaim, String>` - On success, returns the cycles and DOD moved. On failure, returns an error message as a `String`.
+    pub fn pool_claim_worker_rewards(
+        pool: Principal,
+        btc_address: &str,
+    ) -> Result<PoolWorkerClaim, String> {
+        pool::claim_worker_rewards(pool, btc_address)
+    }
+
+    /// Retrieves a registered integrator.
+    ///
+    /// # Arguments
+    ///
+    /// * `integrator` - A `Principal` representing the integrating canister.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Integrator>` - The integrator if registered, otherwise `None`.
+    pub fn get_integrator(integrator: Principal) -> Option<Integrator> {
+        integrator::get_integrator(integrator)
+    }
+
+    /// Credits the cycles attached to the call to the account of an end user of the integrator.
+    ///
+    /// # Arguments
+    ///
+    /// * `integrator` - A `Principal` representing the integrating canister.
+    /// * `end_user` - A `&str` identifying the end user within the integrator.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u128, String>` - The accepted cycles, or an error if the integrator is not registered.
+    pub fn integrator_deposit_cycles(
+        integrator: Principal,
+        end_user: &str,
+    ) -> Result<u128, String> {
+        integrator::deposit_attached_cycles(integrator, end_user)
+    }
+
+    /// Places burn rate orders for an end user of the integrator.
+    ///
+    /// # Arguments
+    ///
+    /// * `integrator` - A `Principal` representing the integrating canister.
+    /// * `end_user` - A `&str` identifying the end user within the integrator.
+    /// * `start_height` - A `Height` representing the first block of the orders.
+    /// * `burn_amount` - A `u128` representing the cycles to burn.
+    /// * `replace_existing` - A `bool` allowing to replace running orders.
+    /// * `priority_fee` - An `Option<u128>` representing the priority fee per order.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if the orders were placed, otherwise an error.
+    pub fn integrator_put_orders(
+        integrator: Principal,
+        end_user: &str,
+        start_height: Height,
+        burn_amount: u128,
+        replace_existing: bool,
+        priority_fee: Option<u128>,
+    ) -> Result<(), String> {
+        let account = integrator::end_user_account(integrator, end_user)?;
+        Self::user_put_burnrate_orders(
+            account,
+            start_height,
+            burn_amount,
+            replace_existing,
+            priority_fee,
+        )
+        .map(|_| ())
+    }
+
+    /// Claims DOD rewards of an end user of the integrator.
+    ///
+    /// # Arguments
+    ///
+    /// * `integrator` - A `Principal` representing the integrating canister.
+    /// * `end_user` - A `&str` identifying the end user within the integrator.
+    /// * `to` - An `Account` receiving the DOD.
+    /// * `amount` - A `u64` representing the DOD to claim.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ClaimLegResult>, String>` - The result of the transfer, or an error.
+    pub async fn integrator_claim_reward(
+        integrator: Principal,
+        end_user: &str,
+        to: Account,
+        amount: u64,
+    ) -> Result<Vec<ClaimLegResult>, String> {
+        let account = integrator::end_user_account(integrator, end_user)?;
+        Self::claim_reward_split(account, vec![(to, amount)]).await
+    }
+
+    /// Retrieves the account detail of an end user of the integrator.
+    ///
+    /// # Arguments
+    ///
+    /// * `integrator` - A `Principal` representing the integrating canister.
+    /// * `end_user` - A `&str` identifying the end user within the integrator.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<UserDetail>, String>` - The detail if the account was funded, or an error if the integrator is not registered.
+    pub fn integrator_get_user_detail(
+        integrator: Principal,
+        end_user: &str,
+    ) -> Result<Option<UserDetail>, String> {
+        let account = integrator::end_user_account(integrator, end_user)?;
+        Ok(Self::get_user_detail(account))
+    }
+
+    /// Assembles the data a front-end loads on page load in a single response.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user, user fields are left empty for the anonymous principal.
+    ///
+    /// # Returns
+    ///
+    /// * `Dashboard` - The last block, halving settings, canisters and the user's detail, burning range and recent orders.
+    pub fn get_dashboard(user: Principal) -> Dashboard {
+        let last_block = Self::get_last_block().map(|(_, block)| block);
+        let mut dashboard = Dashboard {
+            last_block: last_block.clone(),
+            halving_settings: Self::get_halving_settings(),
+            canisters: Self::get_current_service().and_then(|service| service.dod_canisters),
+            user: None,
+            burning_range: None,
+            orders: None,
+        };
+        if user == Principal::anonymous() {
+            return dashboard;
+        }
+        dashboard.user = Self::get_user_detail(user);
+        dashboard.burning_range = Self::get_user_range(user);
+        if let Some(block) = last_block {
+            let from = block.height.saturating_sub(DASHBOARD_ORDER_BLOCKS);
+            if let Ok(range) = BlockRange::inclusive(from, block.height) {
+                let (data, total) =
+                    Self::get_user_orders_by_blocks(user, range, OrderStatus::Filled);
+                dashboard.orders = Some(UserBlockOrderRes {
+                    total,
+                    from,
+                    to: block.height,
+                    data,
+                });
+            }
+        }
+        dashboard
+    }
+
+    /// Assembles everything linked to a principal in a single response.
+    ///
+    /// A principal can own a miner, stake cycles, operate miners of others and integrate end
+    /// users at once, and the winner cycles of its miner are credited to its staker balance.
+    /// The overview puts these side by side with what is left to claim and any claim in progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - A `Principal` representing the identity to look up.
+    ///
+    /// # Returns
+    ///
+    /// * `IdentityOverview` - The linked miner, staker detail, order range, operators, integrator and referral records, unclaimed DOD, claim lock and role flags.
+    pub fn get_identity_overview(principal: Principal) -> IdentityOverview {
+        let miner = Self::get_miner_by_principal(principal);
+        let staker = Self::get_user_detail(principal);
+        let burning_range = Self::get_user_range(principal);
+        let operates_for = miner::get_operated_owners(principal);
+        let integrator = Self::get_integrator(principal);
+        IdentityOverview {
+            principal,
+            is_miner: miner.is_some(),
+            // a staker record alone may come from a miner registration or a win
+            is_staker: burning_range.is_some()
+                || staker::get_user_stats(principal).cycles_deposited > 0,
+            is_miner_operator: !operates_for.is_empty(),
+            is_integrator: integrator.is_some(),
+            unclaimed_staker_dod: staker
+                .as_ref()
+                .map_or(0, |s| s.total_dod.saturating_sub(s.claimed_dod)),
+            unclaimed_miner_dod: miner
+                .as_ref()
+                .map_or(0, |m| m.total_dod.saturating_sub(m.claimed_dod)),
+            miner,
+            staker,
+            burning_range,
+            miner_operators: miner::get_miner_operators(principal),
+            operates_for,
+            integrator,
+            referral: Self::get_referral(principal),
+            claim_locked_at: claim::locked_at(principal),
+        }
+    }
+
+    /// Retrieves the lifetime aggregates of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `UserStats` - The cycles deposited and burned, blocks participated and first activity of the user.
+    pub fn get_user_stats(user: Principal) -> UserStats {
+        staker::get_user_stats(user)
+    }
+
+    /// Retrieves the details of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<UserDetail>` - Returns `Some(UserDetail)` if the user exists, otherwise `None`.
+    pub fn get_user_detail(user: Principal) -> Option<UserDetail> {
+        let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
+        STAKERS.with(|v| v.borrow().get(&blob29).map(|v| v.clone()))
+    }
+    /// Writes the cycle balance for a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `balance` - A `Nat` representing the new balance to be set.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn increase_user_cycle_balance(
+        user: Principal,
+        increase_balance: Nat,
+    ) -> Result<(), String> {
+        match Self::get_user_detail(user) {
+            None => Err("No user found".to_string()),
+            Some(r) => {
+                let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
+                let detail = UserDetail {
+                    balance: r.balance + increase_balance,
+                    ..r
+                };
+                replication::record_staker(&detail);
+                STAKERS.with(|v| {
+                    v.borrow_mut().insert(blob29, detail);
+                });
+                Ok(())
+            }
+        }
+    }
+
+    pub fn decrease_user_cycle_balance(
+        user: Principal,
+        decreace_balance: Nat,
+    ) -> Result<(), String> {
+        match Self::get_user_detail(user) {
+            None => Err("No user found".to_string()),
+            Some(r) => {
+                let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
+                if r.clone().balance - decreace_balance.clone() < Nat::from(0u128) {
+                    return Err("Not enough balance".to_string());
+                }
+                let detail = UserDetail {
+                    balance: r.clone().balance - decreace_balance.clone(),
+                    ..r.clone()
+                };
+                replication::record_staker(&detail);
+                STAKERS.with(|v| {
+                    v.borrow_mut().insert(blob29, detail);
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the claimed reward for a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `claimed_dod` - A `u64` representing the claimed reward to be set.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn write_user_claimed_dod(user: Principal, claimed_dod: u64) -> Result<(), String> {
+        match Self::get_user_detail(user) {
+            None => Err("No user found".to_string()),
+            Some(r) => {
+                let blob29 = Blob::<29>::try_from(user.as_slice()).expect("error transformation");
+                buyback::track_staker_unclaimed(
+                    r.total_dod.saturating_sub(r.claimed_dod),
+                    r.total_dod.saturating_sub(claimed_dod),
+                );
+                let detail = UserDetail { claimed_dod, ..r };
+                replication::record_staker(&detail);
+                STAKERS.with(|v| {
+                    v.borrow_mut().insert(blob29, detail);
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the claimed reward for a miner.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the miner.
+    /// * `claimed_dod` - A `u64` representing the claimed reward to be set.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn write_miner_claimed_dod(user: Principal, claimed_dod: u64) -> Result<(), String> {
+        match Self::get_miner_by_principal(user) {
+            None => Err("No miner found".to_string()),
+            Some(r) => {
+                buyback::track_miner_unclaimed(
+                    r.total_dod.saturating_sub(r.claimed_dod),
+                    r.total_dod.saturating_sub(claimed_dod),
+                );
+                let miner = MinerInfo { claimed_dod, ..r };
+                MINERS.with(|v| {
+                    v.borrow_mut()
+                        .insert(BtcAddress(miner.btc_address.clone()), miner.clone());
+                });
+                replication::record(StateChange::MinerUpdated(miner));
+                Ok(())
+            }
+        }
+    }
+
+    /// Claims DOD a miner earned winning blocks, kept apart from what its owner earns as a
+    /// staker.
+    ///
+    /// The claim shares the claim lock of the owner, so it can not run alongside a staker claim
+    /// of the same principal. The ledger fee is paid out of the amount.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - A `Principal` representing the owner of the miner.
+    /// * `to` - An `Option<Account>` receiving the DOD, the owner's account by default.
+    /// * `amount` - A `u64` representing the DOD to claim, in base units.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Nat, String>` - On success, returns the ledger block index of the transfer. On failure, returns an error message as a `String`.
+    pub async fn miner_claim_reward(
+        owner: Principal,
+        to: Option<Account>,
+        amount: u64,
+    ) -> Result<Nat, String> {
+        claim::check_not_halted()?;
+        claim::lock(owner)?;
+        let res = Self::miner_claim_reward_locked(owner, to, amount).await;
+        claim::unlock(owner);
+        res
+    }
+
+    async fn miner_claim_reward_locked(
+        owner: Principal,
+        to: Option<Account>,
+        amount: u64,
+    ) -> Result<Nat, String> {
+        let miner =
+            Self::get_miner_by_principal(owner).ok_or_else(|| "No miner found".to_string())?;
+        let to = to.unwrap_or(Account {
+            owner,
+            subaccount: None,
+        });
+        // DOD of other pending claims may have left already
+        let reserved = claim::reserved(owner, &ClaimSource::Miner, &[(to.clone(), amount)]);
+        if amount
+            > miner
+                .total_dod
+                .saturating_sub(miner.claimed_dod)
+                .saturating_sub(reserved)
+        {
+            return Err("Claim amount is greater than unclaimed amount".to_string());
+        }
+        let fee = match ledger::get_cached_metadata() {
+            Some(metadata) => metadata.fee,
+            None => Self::refresh_ledger_metadata().await?.fee,
+        };
+        ledger::amount_after_fee(amount, fee)?;
+        let res = Self::transfer_claim(
+            owner,
+            ClaimSource::Miner,
+            Self::get_dod_block_account()?,
+            to,
+            amount,
+            fee,
+        )
+        .await?;
+        // read again, settlement may have credited the miner during the await
+        let claimed_dod = Self::get_miner_by_principal(owner).map_or(0, |m| m.claimed_dod);
+        Self::write_miner_claimed_dod(owner, claimed_dod.saturating_add(amount))?;
+        Ok(res)
+    }
+
+    /// Retrieves the DOD the winner of a block was credited and debited as a miner.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MinerReward>` - The miner reward of the block, `None` for blocks settled before miner rewards were recorded or without a winner share.
+    pub fn get_miner_reward(height: Height) -> Option<MinerReward> {
+        miner::get_miner_reward(height)
+    }
+
+    /// Reconciles the DOD counters of all miners with their recorded rewards.
+    ///
+    /// Totals below what the recorded rewards add up to are raised, claimed amounts above the
+    /// total are clamped to it. Rewards credited before they were recorded are kept.
+    ///
+    /// # Returns
+    ///
+    /// * `MinerRewardsReconciliation` - The number of miners checked, raised and clamped.
+    pub fn reconcile_miner_rewards() -> MinerRewardsReconciliation {
+        miner::reconcile_miner_rewards()
+    }
+
+    /// Registers an asset mined next to DMT, or updates a registered one.
+    ///
+    /// Reveals mining a registered and active asset are taken as candidates of that asset. The
+    /// best of them in a block wins the asset's reward, minted to the asset's treasury subaccount.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - A `MiningAssetArgs` with the asset id, its rewards, halving settings and state.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MiningAsset, String>` - The registered asset, or an error when the arguments are invalid or too many assets are registered.
+    pub fn register_mining_asset(args: MiningAssetArgs) -> Result<MiningAsset, String> {
+        assets::register_asset(args)
+    }
+
+    /// Retrieves the registered mining assets.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<MiningAsset>` - The registered assets, active or not.
+    pub fn get_mining_assets() -> Vec<MiningAsset> {
+        assets::get_assets()
+    }
+
+    /// Retrieves the rewards an asset paid in a range of blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `asset` - A `String` representing the asset id.
+    /// * `range` - A `BlockRange` of the blocks, at most the first `MAX_ASSET_REWARD_BLOCKS` are looked up.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<AssetReward>` - The rewards of the blocks the asset had a winner in.
+    pub fn get_asset_rewards(asset: String, range: BlockRange) -> Vec<AssetReward> {
+        assets::get_asset_rewards(asset.as_str(), range)
+    }
+
+    /// Mints asset rewards to the treasury subaccounts of their assets.
+    ///
+    /// A failed mint is logged and the reward stays unminted, `mint_pending_asset_rewards` retries it.
+    ///
+    /// # Arguments
+    ///
+    /// * `rewards` - A `Vec<AssetReward>` representing the rewards to mint.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The number of rewards minted.
+    async fn mint_asset_rewards(rewards: Vec<AssetReward>) -> u64 {
+        let mut minted = 0;
+        for reward in rewards {
+            let res = match assets::get_asset(reward.asset.as_str())
+                .and_then(|asset| <[u8; 32]>::try_from(asset.treasury_subaccount.as_slice()).ok())
+            {
+                Some(subaccount) => {
+                    Self::mint_dod_to_treasury_subaccount(subaccount, reward.reward).await
+                }
+                None => Err(format!("Asset {} has no treasury subaccount", reward.asset)),
+            };
+            match res {
+                Ok(_) => {
+                    assets::mark_minted(reward.asset.as_str(), reward.height);
+                    minted += 1;
+                }
+                Err(e) => log_error!(
+                    "can not mint the {} reward of block {}: {}",
+                    reward.asset,
+                    reward.height,
+                    e
+                ),
+            }
+        }
+        minted
+    }
+
+    /// Mints the asset rewards whose mint failed.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The number of rewards minted.
+    pub async fn mint_pending_asset_rewards() -> u64 {
+        Self::mint_asset_rewards(assets::unminted_rewards()).await
+    }
+
+    /// Claims an asset reward the miner of `owner` won, from the asset's treasury subaccount.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - A `Principal` representing the miner owner.
+    /// * `asset` - A `String` representing the asset id.
+    /// * `height` - A `Height` representing the block the reward was won in.
+    /// * `to` - An `Option<Account>` to send the reward to, the owner's account when `None`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Nat, String>` - On success, the block index of the transfer. On failure, an error when the reward was not won by the miner, is not minted or is claimed already.
+    pub async fn miner_claim_asset_reward(
+        owner: Principal,
+        asset: String,
+        height: Height,
+        to: Option<Account>,
+    ) -> Result<Nat, String> {
+        claim::check_not_halted()?;
+        claim::lock(owner)?;
+        let res = Self::miner_claim_asset_reward_locked(owner, asset, height, to).await;
+        claim::unlock(owner);
+        res
+    }
+
+    async fn miner_claim_asset_reward_locked(
+        owner: Principal,
+        asset: String,
+        height: Height,
+        to: Option<Account>,
+    ) -> Result<Nat, String> {
+        let miner =
+            Self::get_miner_by_principal(owner).ok_or_else(|| "No miner found".to_string())?;
+        let reward = assets::claimable_reward(asset.as_str(), height, miner.btc_address.as_str())?;
+        let subaccount = assets::get_asset(asset.as_str())
+            .and_then(|a| <[u8; 32]>::try_from(a.treasury_subaccount.as_slice()).ok())
+            .ok_or_else(|| format!("Asset {} has no treasury subaccount", asset))?;
+        let fee = match ledger::get_cached_metadata() {
+            Some(metadata) => metadata.fee,
+            None => Self::refresh_ledger_metadata().await?.fee,
+        };
+        ledger::amount_after_fee(reward.reward, fee)?;
+        let to = to.unwrap_or(Account {
+            owner,
+            subaccount: None,
+        });
+        let res = Self::transfer_claim(
+            owner,
+            ClaimSource::Asset {
+                asset: asset.clone(),
+                height,
+            },
+            subaccount,
+            to,
+            reward.reward,
+            fee,
+        )
+        .await?;
+        assets::mark_claimed(asset.as_str(), height);
+        Ok(res)
+    }
+
+    /// Executes cycles on block data by burning the specified amount of cycles.
+    ///
+    /// Cycles still held in user balances are never burned, a burn is capped by the cycles the
+    /// canister holds beyond the user pool. An alert is logged when the operational headroom
+    /// falls below the threshold.
+    /// Each burn leaves a receipt with the canister balance before and after it.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block the cycles are burned for.
+    /// * `to_burn` - A `u128` representing the amount of cycles to burn.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn execute_cycles_on_block_data(height: Height, to_burn: u128) -> Result<(), String> {
+        let current_balance = ic_cdk::api::canister_balance128();
+        if current_balance < to_burn {
+            log_warn!(
+                "{}",
+                format!(
+                    "Insufficient cycles, current {:?}, expect to burn {:?}",
+                    current_balance, to_burn
+                )
+            );
+            Ok(())
+        } else {
+            let to_burn = to_burn.min(current_balance.saturating_sub(cycles::get_user_pool()));
+            let balance_before = ic_cdk::api::canister_balance128();
+            let burned = ic_cdk::api::cycles_burn(to_burn.saturating_sub(CYCLES_BURNER_FEE));
+            if burned > 0 {
+                burn_receipts::record(
+                    height,
+                    burned,
+                    balance_before,
+                    ic_cdk::api::canister_balance128(),
+                );
+            }
+            cycles::check_cycles_health();
+            Ok(())
+        }
+    }
+
+    /// Places an order for a user over a range of blocks.
+    ///
+    /// This function updates the new user orders and new block orders with the specified range and amount.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user placing the order.
+    /// * `range` - A `BlockRange` representing the range of blocks for the order.
+    /// * `amount` - A `u128` representing the amount for the order.
+    pub fn user_put_order_v2(user: Principal, range: BlockRange, amount: u128) {
+        // Update the new user orders with the specified range and amount.
+
+        let old = NEW_USER_ORDERS.with_borrow(|v| v.get(&user));
+
+        NEW_USER_ORDERS.with_borrow_mut(|v| {
+            NewUserOrders::update_order(v, user, range, amount);
+        });
+
+        // Update the new block orders for each block in the specified range.
+        NEW_BLOCK_ORDERS.with_borrow_mut(|v| {
+            for block in range.blocks() {
+                NewBlockOrders::write_order_by_block_height(
+                    v,
+                    block,
+                    user,
+                    amount,
+                    OrderStatus::Pending,
+                );
+            }
+
+            // cancel the blocks of the previous range after the new one
+            if let Some(cancelled) =
+                old.and_then(|old| BlockRange::new(range.end(), old.r.end()).ok())
+            {
+                for block in cancelled.blocks() {
+                    NewBlockOrders::write_order_by_block_height(
+                        v,
+                        block,
+                        user,
+                        0,
+                        OrderStatus::Cancelled,
+                    );
+                }
+            }
+        });
+    }
+
+    pub fn user_put_order_instant(user: Principal, range: BlockRange, amount: u128) {
+        // Update the new user orders with the specified range and amount.
+
+        let old = NEW_USER_ORDERS.with_borrow(|v| v.get(&user));
+
+        NEW_USER_ORDERS.with_borrow_mut(|v| {
+            NewUserOrders::update_order(v, user, range, amount);
+        });
+
+        // Update the new block orders for each block in the specified range.
+        NEW_BLOCK_ORDERS.with_borrow_mut(|v| {
+            for block in range.blocks() {
+                NewBlockOrders::write_order_by_block_height(
+                    v,
+                    block,
+                    user,
+                    amount,
+                    OrderStatus::Pending,
+                );
+            }
+
+            // cancel the blocks of the previous range after the new one
+            if let Some(cancelled) =
+                old.and_then(|old| BlockRange::new(range.end(), old.r.end()).ok())
+            {
+                for block in cancelled.blocks() {
+                    NewBlockOrders::write_order_by_block_height(
+                        v,
+                        block,
+                        user,
+                        0,
+                        OrderStatus::Cancelled,
+                    );
+                }
+            }
+        });
+    }
+
+    /// Updates the balances of users based on block orders.
+    ///
+    /// This function iterates through the block orders and updates the balance of each user.
+    /// If the user's balance is greater than the order amount and the user has a bet in the range,
+    /// it subtracts the order amount from the balance. Otherwise, the balance remains unchanged.
+    /// It also calculates the user's share of the total cycles in integers and updates their total DOD reward,
+    /// shares below one DOD unit are handled by the reward dust policy.
+    /// Orders are settled highest priority fee first, a fee is only paid when the balance covers it on top of the order
+    /// and never on a refunded block. Past the settlement capacity the remaining orders miss the block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - A `Height` representing the block height.
+    /// * `total_cycles` - A `u128` representing the total cycles for the block.
+    /// * `refund_percent` - A `u8` representing the percentage of each debited order returned to the user.
+    ///
+    /// # Returns
+    ///
+    /// * `(u128, u128)` - The total amount of cycles refunded to users, dust refunds included, and the total priority fees paid.
+    pub fn update_users_balance_v2(
+        block: Height,
+        total_cycles: u128,
+        refund_percent: u8,
+    ) -> (u128, u128) {
+        let mut checkpoint = settlement::begin(block, total_cycles, refund_percent, None);
+        while !Self::settle_users_balance_batch(&mut checkpoint, settlement::SETTLEMENT_BATCH) {}
+        (checkpoint.refunded, checkpoint.stats.total_fees)
+    }
+
+    /// Settles the next pending orders of a checkpointed settlement, see `update_users_balance_v2`.
+    ///
+    /// Settled orders are marked filled, so a batch interrupted by a trap is rolled back and a
+    /// later batch never settles an order twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - A mutable reference to the `SettlementCheckpoint` of the block, updated with the batch.
+    /// * `max_orders` - A `usize` representing the maximum number of orders settled in this batch.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` when every order of the block is settled and the checkpoint is complete.
+    pub fn settle_users_balance_batch(
+        checkpoint: &mut SettlementCheckpoint,
+        max_orders: usize,
+    ) -> bool {
+        let block = checkpoint.height;
+        let total_cycles = checkpoint.cycle_deposit;
+        let refund_percent = checkpoint.refund_percent;
+        let mut refunded = 0u128;
+        // read before any balance changes, the batch moves it by what it debits and credits
+        let user_pool = cycles::get_user_pool();
+        let mut debited = 0u128;
+        let mut credited = 0u128;
+        let mut dod_rebates = 0u64;
+        let mut lock_in_bonuses = 0u64;
+        let stats = &mut checkpoint.stats;
+        let mut last_key = None;
+        let reward = Self::get_block_reward_by_height(block, Self::get_halving_settings())
+            .expect("Can not get block reward by height");
+        let dust_policy = Self::get_reward_dust_policy();
+        let lock_in_bonus = Self::get_lock_in_bonus();
+        let capacity = priority::get_settlement_capacity();
+        // with a reward policy stakers split their share among themselves, the treasury
+        // order does not take part
+        let reward_policy = Self::get_reward_policy();
+        let mut settled_stakers = vec![];
+        let (reward, total_cycles) = match reward_policy.as_ref() {
+            Some(policy) => (
+                reward::split_reward(policy, reward).1,
+                total_cycles.saturating_sub(Self::get_user_block_order(self_id(), block).value),
+            ),
+            None => (reward, total_cycles),
+        };
+        // the excess of an order over the cap is not bet, the share is of the capped pool
+        let exposure_limit = exposure::get_limit(total_cycles);
+        let total_cycles = exposure_limit.map_or(total_cycles, |limit| {
+            exposure::capped_pool(block, total_cycles, limit)
+        });
+        let done = NEW_BLOCK_ORDERS.with_borrow_mut(|s| {
+            // settled orders are no longer pending, what is left is the rest of the settlement
+            let mut orders: Vec<_> = NewBlockOrders::get_orders_by_block_height(s, block)
+                .filter(|(_, v)| v.status == OrderStatus::Pending)
+                .map(|(p, v)| (p, v, priority::get_priority_fee(p)))
+                .collect();
+            // stable sort, equal fees keep the principal order
+            orders.sort_by(|a, b| b.2.cmp(&a.2));
+            let done = orders.len() <= max_orders;
+            for (p, v, priority_fee) in orders.into_iter().take(max_orders) {
+                last_key = Some(p);
+                match Self::get_user_detail(p) {
+                    None => {
+                        // nothing to settle, but the order must not stay pending. Filled keeps
+                        // the block total, and the reward boost computed from it, unchanged
+                        NewBlockOrders::write_order_by_block_height(
+                            s,
+                            block,
+                            p,
+                            v.value,
+                            OrderStatus::Filled,
+                        );
+                        continue;
+                    }
+                    Some(user) => {
+                        // Check if the user has a bet in the range.
+                        let is_range = NewUserOrders::get_user_bet(user.principal, block).is_some();
+                        let OrderDetail {
+                            value: order_value,
+                            status,
+                        } = v;
+                        let user_bet = if p == self_id() {
+                            order_value
+                        } else {
+                            exposure::capped(order_value, exposure_limit)
+                        };
+                        let settles = user.balance >= user_bet
+                            && is_range
+                            && status != OrderStatus::Cancelled
+                            && status != OrderStatus::Filled;
+                        // orders come highest fee first, past the capacity the rest miss the
+                        // block. The treasury order is not held back
+                        let missed = settles
+                            && p != self_id()
+                            && priority::misses_block(capacity, stats.settled_orders);
+                        if missed {
+                            stats.missed_orders = Some(stats.missed_orders.unwrap_or(0) + 1);
+                        }
+                        let settles = settles && !missed;
+                        // Calculate the user's reward in integers, scaled below one DOD unit.
+                        let scaled = if settles && !(reward_policy.is_some() && p == self_id()) {
+                            staker::scaled_reward(reward, user_bet, total_cycles)
+                        } else {
+                            0
+                        };
+                        // a long order range earns the lock-in bonus, out of the treasury share
+                        let scaled = if scaled > 0 && p != self_id() {
+                            let (boosted, extra_dod) = lock_in::boost_scaled(
+                                scaled,
+                                lock_in::user_bonus_percent(lock_in_bonus.as_ref(), p),
+                            );
+                            lock_in_bonuses = lock_in_bonuses.saturating_add(extra_dod);
+                            boosted
+                        } else {
+                            scaled
+                        };
+                        let dust_refund = settles
+                            && scaled < staker::REWARD_SCALE
+                            && dust_policy == RewardDustPolicy::Refund;
+                        if dust_refund {
+                            refunded = refunded.saturating_add(user_bet);
+                        }
+                        let (r, reward_remainder) = match dust_policy {
+                            RewardDustPolicy::Accumulate => staker::settle_scaled_reward(
+                                scaled,
+                                user.reward_remainder.unwrap_or(0),
+                            ),
+                            _ => staker::settle_scaled_reward(scaled, 0),
+                        };
+
+                        // Calculate the new balance.
+                        let mut actual_bet = user_bet;
+                        let mut paid_fee = 0u128;
+                        let new_balance = if settles && !dust_refund {
+                            // a refunded block buys no priority, nothing is charged for it
+                            let fee = if refund_percent == 0
+                                && user.balance >= user_bet.saturating_add(priority_fee)
+                            {
+                                priority_fee
+                            } else {
+                                0
+                            };
+                            stats.settled_orders += 1;
+                            if fee > 0 {
+                                stats.prioritized_orders += 1;
+                                stats.total_fees = stats.total_fees.saturating_add(fee);
+                                stats.max_fee = stats.max_fee.max(fee);
+                            }
+                            paid_fee = fee;
+                            user.balance - user_bet - fee
+                        } else {
+                            actual_bet = 0;
+                            user.balance
+                        };
+                        let refund = amounts::percent_of(actual_bet, refund_percent);
+                        refunded = refunded.saturating_add(refund);
+                        if actual_bet > 0 {
+                            let burned = (actual_bet - refund).saturating_add(paid_fee);
+                            staker::record_participation(p, block, burned);
+                            debited = debited.saturating_add(burned);
+                            let (rebate, rebate_dod) =
+                                referral::rebate(p, actual_bet - refund, reward, total_cycles);
+                            credited = credited.saturating_add(rebate);
+                            refunded = refunded.saturating_add(rebate);
+                            dod_rebates = dod_rebates.saturating_add(rebate_dod);
+                        }
+                        let new_balance = new_balance + Nat::from(refund);
+                        let blob29 =
+                            Blob::<29>::try_from(p.as_slice()).expect("error transformation");
+
+                        if status == OrderStatus::Pending {
+                            NewBlockOrders::write_order_by_block_height(
+                                s,
+                                block,
+                                p,
+                                order_value,
+                                OrderStatus::Filled,
+                            );
+                        }
+
+                        let unclaimed = user.total_dod.saturating_sub(user.claimed_dod);
+                        let user = UserDetail {
+                            balance: new_balance,
+                            total_dod: user.total_dod.saturating_add(r),
+                            reward_remainder: match dust_policy {
+                                RewardDustPolicy::Accumulate => Some(reward_remainder),
+                                _ => user.reward_remainder,
+                            },
+                            ..user
+                        };
+                        buyback::track_staker_unclaimed(
+                            unclaimed,
+                            user.total_dod.saturating_sub(user.claimed_dod),
+                        );
+                        settled_stakers.push(p);
+
+                        // Update the user's details in the STAKERS map.
+                        STAKERS.with(|v| {
+                            v.borrow_mut().insert(blob29, user);
+                        });
+                    }
+                }
+            }
+            done
+        });
+        if !settled_stakers.is_empty() {
+            // read back once the batch is done, a rebate may credit a staker settled before it
+            let stakers = settled_stakers
+                .into_iter()
+                .filter_map(Self::get_user_detail)
+                .map(|user| StakerBalance {
+                    principal: user.principal,
+                    balance: u128::try_from(user.balance.0).unwrap_or(u128::MAX),
+                    claimed_dod: user.claimed_dod,
+                    total_dod: user.total_dod,
+                })
+                .collect();
+            replication::record(StateChange::Settled {
+                height: block,
+                stakers,
+            });
+        }
+        cycles::set_user_pool(user_pool.saturating_sub(debited).saturating_add(credited));
+        checkpoint.refunded = checkpoint.refunded.saturating_add(refunded);
+        // kept in the checkpoint, the burn may come several ticks and an upgrade later
+        checkpoint.dod_rebates = Some(
+            checkpoint
+                .dod_rebates
+                .unwrap_or(0)
+                .saturating_add(dod_rebates),
+        );
+        checkpoint.lock_in_bonuses = Some(
+            checkpoint
+                .lock_in_bonuses
+                .unwrap_or(0)
+                .saturating_add(lock_in_bonuses),
+        );
+        if last_key.is_some() {
+            checkpoint.last_key = last_key;
+        }
+        checkpoint.updated_at = now();
+        if done {
+            checkpoint.phase = SettlementPhase::Complete;
+            priority::write_priority_stats(checkpoint.stats.clone());
+        }
+        done
+    }
+
+    /// Computes the difficulty of the block following `height`.
+    ///
+    /// A run of `difficulty_adjust_epoch` blocks with a winner raises the difficulty by the
+    /// increase step, a run without a winner lowers it by the decrease step, never below the
+    /// start difficulty.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block just finalized.
+    /// * `last_difficulty` - A `Bitwork` representing the difficulty of that block.
+    /// * `start_difficulty` - A `&Bitwork` representing the lowest allowed difficulty.
+    /// * `has_winner` - A `bool` indicating whether the block had a winner.
+    /// * `difficulty_adjust_epoch` - A `u64` representing the length of a run.
+    ///
+    /// # Returns
+    ///
+    /// * `Bitwork` - The difficulty of the next block.
+    pub fn adjust_difficulty(
+        height: Height,
+        last_difficulty: Bitwork,
+        start_difficulty: &Bitwork,
+        has_winner: bool,
+        difficulty_adjust_epoch: u64,
+    ) -> Bitwork {
+        let mut bitwork = last_difficulty.clone();
+
+        if !has_winner {
+            let considered = Self::get_consider_decrease().unwrap();
+
+            match considered {
+                None => {
+                    Self::set_consider_decrease(Some(height + difficulty_adjust_epoch))
+                        .expect("Can not set consider decrease height");
+
+                    Self::set_consider_increase(None)
+                        .expect("Can not set consider increase height");
+                }
+                Some(i) => {
+                    if height + 1 == i {
+                        let decreased = bitwork_minus_bit_hex(
+                            last_difficulty,
+                            config::get_difficulty_steps().decrease,
+                        )
+                        .unwrap();
+
+                        if decreased.cmp(start_difficulty) == Ordering::Less {
+                            bitwork = start_difficulty.clone();
+                        } else {
+                            bitwork = decreased;
+                        }
+
+                        Self::set_consider_decrease(Some(i + difficulty_adjust_epoch))
+                            .expect("Can not set consider decrease height");
+                    }
+                }
+            }
+        } else {
+            let considered = Self::get_consider_increase().unwrap();
+            match considered {
+                None => {
+                    Self::set_consider_increase(Some(height + difficulty_adjust_epoch))
+                        .expect("Can not set consider increase height");
+
+                    Self::set_consider_decrease(None)
+                        .expect("Can not set consider decrease height");
+                }
+                Some(i) => {
+                    if height + 1 == i {
+                        bitwork = bitwork_plus_bit_hex(
+                            last_difficulty,
+                            config::get_difficulty_steps().increase,
+                        )
+                        .unwrap();
+                        Self::set_consider_increase(Some(i + difficulty_adjust_epoch))
+                            .expect("Can not set consider increase height");
+                    }
+                }
+            }
+        }
+        bitwork
+    }
+
+    /// Retrieves the range of blocks for a given user.
+    ///
+    /// This function fetches the range of blocks that a user has set orders for.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user whose block range is to be retrieved.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<NewBlockOrderValue>` - Returns `Some(NewBlockOrderValue)` if the user has set a range, otherwise `None`.
+    pub fn get_user_range(user: Principal) -> Option<NewBlockOrderValue> {
+        NewUserOrders::get_user_set_range(user)
+    }
+
+    /// Retrieves the share of a user in a specific block.
+    ///
+    /// This function calculates the share of a user in a specific block based on the total cycles and the user's block order.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - A `u64` representing the block height.
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `f64` - The share of the user in the block.
+    pub fn get_user_block_share(block: u64, user: Principal) -> f64 {
+        let total_cycles = Self::get_block_total_cycles(block, false);
+        let user_order = Self::get_user_block_order(user, block);
+
+        if user.ne(&id())
+            && (user_order.status == OrderStatus::Pending
+                || user_order.status == OrderStatus::Cancelled)
+        {
+            0f64
+        } else {
+            user_order.value as f64 / total_cycles as f64
+        }
+    }
+
+    pub fn get_user_block_share_v2(block: u64, user: Principal) -> f64 {
+        let total_cycles = Self::get_block_total_cycles_v2(block, true);
+        let user_order = Self::get_user_block_order(user, block);
+
+        if user.ne(&id())
+            && (user_order.status == OrderStatus::Pending
+                || user_order.status == OrderStatus::Cancelled)
+        {
+            0f64
+        } else {
+            user_order.value as f64 / total_cycles as f64
+        }
+    }
+
+    /// Retrieves the user's block reward and share.
+    ///
+    /// This function calculates the user's reward and share for a specific block based on the total cycles and the user's block order.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - A `u64` representing the block height.
+    /// * `user` - A `Principal` representing the user.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(u64, f64), String>` - A tuple containing the user's reward as `u64` and the share as `f64`, or an error if the service is not set up.
+    ///
+    /// The reward is split in fixed point with `staker::scaled_reward`, as staker credits are,
+    /// the share is informative only.
+    pub fn get_user_block_reward(block: u64, user: Principal) -> Result<(u64, f64), String> {
+        let share = Self::get_user_block_share(block, user);
+        let reward = Self::get_block_reward_by_height(block, Self::get_halving_settings())?;
+        let bet = if share > 0f64 {
+            Self::get_user_block_order(user, block).value
+        } else {
+            0
+        };
+        let scaled = staker::scaled_reward(reward, bet, Self::get_block_total_cycles(block, false));
+        Ok((
+            u64::try_from(scaled / staker::REWARD_SCALE).unwrap_or(u64::MAX),
+            share,
+        ))
+    }
+
+    pub fn get_user_block_reward_v2(block: u64, user: Principal) -> Result<(u64, f64), String> {
+        let share = Self::get_user_block_share_v2(block, user);
+        let reward = Self::get_block_reward_by_height(block, Self::get_halving_settings())?;
+        Ok(((reward as f64 * share).floor() as u64, share))
+    }
+
+    /// Retrieves the total cycles for a specific block.
+    ///
+    /// This function calculates the total cycles for a given block by summing up the cycles from all orders
+    /// and the cycles donated to the block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - A `u64` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `u128` - The total cycles for the block.
+    pub fn get_block_total_cycles(block: u64, with_filled: bool) -> u128 {
+        NewBlockOrders::cached_total_cycles(block, with_filled, || {
+            NEW_BLOCK_ORDERS.with_borrow(|v| {
+                NewBlockOrders::get_orders_by_block_height(v, block).fold(
+                    donations::block_total(block),
+                    |acc, (_, x)| match (with_filled, x.status) {
+                        (true, OrderStatus::Filled) | (_, OrderStatus::Cancelled) => acc,
+                        _ => acc.saturating_add(x.value),
+                    },
+                )
+            })
+        })
+    }
+
+    pub fn get_block_total_cycles_v2(block: u64, _with_filled: bool) -> u128 {
+        // NEW_BLOCK_ORDERS.with_borrow(|v| {
+        //     NewBlockOrders::get_orders_by_block_height(v, block).fold(0, |acc, (_, x)| {
+        //         match x.status {
+        //             OrderStatus::Pending => acc + x.value,
+        //             OrderStatus::Filled => acc,
+        //             OrderStatus::Cancelled => acc + x.value,
+        //         }
+        //     })
+        // })
+
+        BLOCKS.with_borrow(|v| {
+            v.get(&block).map_or(0, |x| {
+                x.cycle_burned
+                    .saturating_mul(2)
+                    .saturating_add(x.winner.as_ref().and_then(|x| x.reward_cycles).unwrap_or(0))
+            })
+        })
+    }
+
+    /// Retrieves the block order for a specific user and block.
+    ///
+    /// This function fetches the order details for a given user and block height.
+    /// It first attempts to retrieve the order from the new block orders. If no order is found,
+    /// it returns a default `OrderDetail` with a value of 0 and a status of `Pending`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user whose order is to be retrieved.
+    /// * `block` - A `u64` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `OrderDetail` - The order details for the specified user and block.
+    pub fn get_user_block_order(user: Principal, block: u64) -> OrderDetail {
+        NEW_BLOCK_ORDERS.with_borrow(|v| {
+            NewBlockOrders::get_orders_by_block_height(v, block)
+                .filter(|(k, _)| k == &user)
+                .map(|(_, v)| v)
+                .next()
+                .unwrap_or(OrderDetail {
+                    value: 0,
+                    status: OrderStatus::Pending,
+                })
+        })
+    }
+
+    /// Retrieves the user's orders within a specified block range.
+    ///
+    /// This function fetches the orders for a given user within the specified block range.
+    /// It accesses the `NEW_BLOCK_ORDERS` to get the user's orders in the range and collects them into a vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user whose orders are to be retrieved.
+    /// * `range` - A `BlockRange` representing the range of blocks to fetch orders from.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(u64, OrderDetail)>` - A vector of tuples where each tuple contains a block height (`u64`) and the corresponding `OrderDetail`.
+    pub fn get_user_orders_in_range(user: Principal, range: BlockRange) -> Vec<(u64, OrderDetail)> {
+        NEW_BLOCK_ORDERS.with_borrow(|v| {
+            NewBlockOrders::get_user_orders_in_range(v, user, range)
+                .collect::<Vec<(u64, OrderDetail)>>()
+        })
+    }
+
+    /// Retrieves the user's orders within a specified block range and filters them by status.
+    ///
+    /// This function fetches the orders for a given user within the specified block range and filters them by the provided status.
+    /// It accesses the `NEW_BLOCK_ORDERS` to get the user's orders in the range, calculates the reward and share for each order,
+    /// and collects them into a vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user whose orders are to be retrieved.
+    /// * `range` - A `BlockRange` of the blocks to fetch orders from.
+    /// * `status` - An `OrderStatus` representing the status to filter orders by.
+    ///
+    /// # Returns
+    ///
+    /// * `(Vec<UserBlockOrder>, u64)` - A tuple where the first element is a vector of `UserBlockOrder` and the second element is the total number of orders.
+    pub fn get_user_orders_by_blocks(
+        user: Principal,
+        range: BlockRange,
+        status: OrderStatus,
+    ) -> (Vec<UserBlockOrder>, u64) {
+        NEW_BLOCK_ORDERS.with_borrow(|v| {
+            let data = NewBlockOrders::get_user_orders_in_range(v, user, range)
+                .filter(|(_, v)| v.status == status)
+                .map(|(a, b)| {
+                    let (reward, share) =
+                        Self::get_user_block_reward(a.clone(), user.clone()).unwrap_or_default();
+                    UserBlockOrder {
+                        block: a.clone(),
+                        amount: b.value.clone(),
+                        share,
+                        reward,
+                    }
+                })
+                .collect::<Vec<UserBlockOrder>>();
+            let total = data.len() as u64;
+            (data, total)
+        })
+    }
+
+    /// Retrieves the user's orders within a block range, in every status or in one.
+    ///
+    /// Unlike `get_user_orders_by_blocks`, pending and cancelled orders are included, each with
+    /// the open and close times of its block. Blocks after the open one have their times
+    /// estimated from the current interval and submission grace.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user whose orders are to be retrieved.
+    /// * `range` - A `BlockRange` of the blocks to fetch orders from.
+    /// * `status` - An `Option<OrderStatus>` to keep only the orders in that status, all orders when `None`.
+    ///
+    /// # Returns
+    ///
+    /// * `(Vec<UserOrder>, u64)` - A tuple of the orders in block order and their number.
+    pub fn get_user_orders(
+        user: Principal,
+        range: BlockRange,
+        status: Option<OrderStatus>,
+    ) -> (Vec<UserOrder>, u64) {
+        let open = Self::get_last_block().map(|(_, block)| block);
+        let interval = Self::get_block_time_interval().unwrap_or(0);
+        let grace = Self::get_submission_grace();
+        NEW_BLOCK_ORDERS.with_borrow(|v| {
+            let data = NewBlockOrders::get_user_orders_in_range(v, user, range)
+                .filter(|(_, order)| status.as_ref().map_or(true, |s| order.status == *s))
+                .map(|(block, order)| {
+                    let (reward, share) = match order.status {
+                        OrderStatus::Filled => {
+                            Self::get_user_block_reward(block, user).unwrap_or_default()
+                        }
+                        _ => (0, 0.0),
+                    };
+                    let (open_at, close_at, estimated) = open
+                        .as_ref()
+                        .and_then(|open| schedule::block_times(block, open, interval, grace))
+                        .unwrap_or((0, 0, false));
+                    UserOrder {
+                        block,
+                        amount: order.value,
+                        status: order.status,
+                        share,
+                        reward,
+                        open_at,
+                        close_at,
+                        estimated,
+                    }
+                })
+                .collect::<Vec<UserOrder>>();
+            let total = data.len() as u64;
+            (data, total)
+        })
+    }
+
+    /// Retrieves orders by block range.
+    ///
+    /// This function fetches the orders for a specified block range and collects them into a vector of `BlockDataFull`.
+    /// It accesses the `NEW_BLOCK_ORDERS` to get the orders for each block in the range, filters the filled orders,
+    /// and collects the user data and miner candidates for each block.
+    /// At most `MAX_FULL_BLOCKS` blocks are returned with at most `MAX_BLOCK_ORDERS_PAGE` orders each,
+    /// `get_block_orders` pages through the rest of the orders of a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - A `BlockRange` of the blocks to retrieve, stopping at the first block not generated yet.
+    ///
+    /// # Returns
+    ///
+    /// * `BlockDataFullPage` - The block data, user data, and miner candidates of each block returned, and the first block left out.
+    pub fn get_orders_by_block_v2(range: BlockRange) -> BlockDataFullPage {
+        let page = range.limit(MAX_FULL_BLOCKS);
+        let mut data: Vec<BlockDataFull> = vec![];
+        for i in page.blocks() {
+            let Some(block) = BLOCKS.with_borrow(|v| v.get(&i)) else {
+                return BlockDataFullPage {
+                    data,
+                    max_blocks: MAX_FULL_BLOCKS,
+                    next: None,
+                };
+            };
+            let miners = CANDIDATES.with_borrow(|v| {
+                v.get(&i).map_or_else(Vec::new, |v| {
+                    v.candidates
+                        .into_values()
+                        .map(psbt_store::unpack_candidate)
+                        .map(|k| {
+                            let principal = MINERS.with_borrow(|s| {
+                                let info = s.get(&BtcAddress(k.btc_address.clone())).unwrap();
+                                info.owner.clone()
+                            });
+                            MinerCandidateExt {
+                                miner_principal: principal,
+                                btc_address: k.btc_address.clone(),
+                                submit_time: k.submit_time.clone(),
+                                cycles_price: k.cycles_price.clone(),
+                                signed_commit_psbt: k.signed_commit_psbt.clone(),
+                                signed_reveal_psbt: k.signed_reveal_psbt.clone(),
+                                cycles_price_t: to_tcycles(k.cycles_price),
+                            }
+                        })
+                        .collect()
+                })
+            });
+            let orders = Self::get_block_orders(i, 0, MAX_BLOCK_ORDERS_PAGE);
+            data.push(BlockDataFull {
+                block,
+                user_data: orders.data,
+                user_data_total: Some(orders.total),
+                miners,
+            });
+        }
+        BlockDataFullPage {
+            data,
+            max_blocks: MAX_FULL_BLOCKS,
+            next: (page.end() < range.end()).then(|| page.end()),
+        }
+    }
+
+    /// Retrieves a page of the filled orders of a block, with the treasury order.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    /// * `offset` - A `u64` representing the number of orders to skip.
+    /// * `limit` - A `u64` representing the number of orders to return, at most `MAX_BLOCK_ORDERS_PAGE`.
+    ///
+    /// # Returns
+    ///
+    /// * `BlockOrdersPage` - The orders of the page with their rewards and shares, and the number of orders of the block.
+    pub fn get_block_orders(height: Height, offset: u64, limit: u64) -> BlockOrdersPage {
+        let limit = limit.min(MAX_BLOCK_ORDERS_PAGE);
+        let orders: Vec<(Principal, OrderDetail)> = NEW_BLOCK_ORDERS.with_borrow(|v| {
+            NewBlockOrders::get_orders_by_block_height(v, height)
+                .filter(|(k, v)| *k == id() || v.status == OrderStatus::Filled)
+                .collect()
+        });
+        let data = orders
+            .iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|(user, order)| {
+                let (reward, share) =
+                    Self::get_user_block_reward(height, *user).unwrap_or_default();
+                UserBlockOrderData {
+                    height,
+                    amount: order.value,
+                    share,
+                    reward,
+                    user: *user,
+                }
+            })
+            .collect();
+        BlockOrdersPage {
+            height,
+            total: orders.len() as u64,
+            offset,
+            limit,
+            data,
+        }
+    }
+
+    /// Claims the reward for a user.
+    ///
+    /// This asynchronous function calculates the amount of DOD tokens to be claimed by the user,
+    /// updates the user's claimed DOD amount, and transfers the tokens to the user's account.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user claiming the reward.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Nat, String>` - On success, returns the amount of tokens claimed as `Nat`. On failure, returns an error message as a `String`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * The user details cannot be retrieved.
+    /// * The claimed DOD amount cannot be written.
+    /// * The token canister cannot be retrieved.
+    /// * The transfer call to the token canister fails.
+    pub async fn claim_reward(
+        user: Principal,
+        to: Option<Account>,
+        claim_amount: Option<u64>,
+    ) -> Result<Nat, String> {
+        log_debug!("claim_amount {:?}", claim_amount);
+        log_debug!("to {:?}", to);
+        let claim_amount = claim_amount.ok_or_else(|| "Claim amount is none".to_string())?;
+        let to = to.unwrap_or(Account {
+            owner: user.clone(),
+            subaccount: None,
+        });
+
+        Self::claim_reward_split(user, vec![(to, claim_amount)])
+            .await?
+            .pop()
+            .map_or_else(|| Err("No claim result".to_string()), |leg| leg.result)
+    }
+
+    /// Claims the reward for a user and splits it between several accounts.
+    ///
+    /// A user runs one claim at a time, a second claim is rejected while the first one holds the
+    /// claim lock. Legs are transferred one after another, and only a successful leg is added to the
+    /// claimed amount, so a failed leg leaves its amount unclaimed without touching the other legs.
+    /// The ledger fee is paid out of each leg, an account receives its amount minus the fee.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user claiming the reward.
+    /// * `payouts` - A `Vec<(Account, u64)>` of destination accounts and the amount each one receives.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ClaimLegResult>, String>` - On success, returns the result of every leg in order. On failure, returns an error message as a `String`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * No payouts, too many payouts, or duplicated destinations are given.
+    /// * Any leg amount is zero or not above the ledger fee, or the total is greater than the unclaimed amount.
+    /// * Another claim of the user is in progress.
+    /// * The user details cannot be retrieved.
+    /// * The claimed DOD amount cannot be written.
+    pub async fn claim_reward_split(
+        user: Principal,
+        payouts: Vec<(Account, u64)>,
+    ) -> Result<Vec<ClaimLegResult>, String> {
+        claim::check_not_halted()?;
+        claim::lock(user)?;
+        let res = Self::claim_reward_split_locked(user, payouts).await;
+        claim::unlock(user);
+        res
+    }
+
+    async fn claim_reward_split_locked(
+        user: Principal,
+        payouts: Vec<(Account, u64)>,
+    ) -> Result<Vec<ClaimLegResult>, String> {
+        if payouts.is_empty() {
+            return Err("No payouts given".to_string());
+        }
+        if payouts.len() > MAX_CLAIM_LEGS {
+            return Err(format!("At most {} payouts are allowed", MAX_CLAIM_LEGS));
+        }
+        if payouts.iter().any(|(_, amount)| *amount == 0) {
+            return Err("Claim amount is zero ".to_string());
+        }
+        if payouts
+            .iter()
+            .enumerate()
+            .any(|(i, (to, _))| payouts[..i].iter().any(|(other, _)| other == to))
+        {
+            return Err("Duplicated payout account".to_string());
+        }
+        let total = payouts
+            .iter()
+            .try_fold(0u64, |acc, (_, amount)| acc.checked_add(*amount))
+            .ok_or_else(|| "Claim amount overflow".to_string())?;
+        // ledgers configured before the fee was cached are read once here
+        let fee = match ledger::get_cached_metadata() {
+            Some(metadata) => metadata.fee,
+            None => Self::refresh_ledger_metadata().await?.fee,
+        };
+        for (_, amount) in payouts.iter() {
+            ledger::amount_after_fee(*amount, fee)?;
+        }
+
+        let user_detail = Self::get_user_detail(user).ok_or_else(|| "No user found".to_string())?;
+        let from_subaccount = Self::get_dod_block_account()?;
+        let unclaimed = if user_detail.total_dod > user_detail.claimed_dod {
+            user_detail.total_dod - user_detail.claimed_dod
+        } else {
+            0
+        };
+        // DOD of other pending claims may have left already
+        let unclaimed =
+            unclaimed.saturating_sub(claim::reserved(user, &ClaimSource::Staker, &payouts));
+        if total > unclaimed {
+            return Err("Claim amount is greater than unclaimed amount ".to_string());
+        }
+
+        let mut results = Vec::with_capacity(payouts.len());
+        for (to, amount) in payouts {
+            let result = Self::transfer_claim(
+                user,
+                ClaimSource::Staker,
+                from_subaccount,
+                to.clone(),
+                amount,
+                fee,
+            )
+            .await;
+            if result.is_ok() {
+                // read again, settlement may have updated the user during the await
+                let claimed_dod = Self::get_user_detail(user).map_or(0, |r| r.claimed_dod);
+                Self::write_user_claimed_dod(user, claimed_dod + amount)?;
+            }
+            results.push(ClaimLegResult { to, amount, result });
+        }
+        Ok(results)
+    }
+
+    /// Converts unclaimed DOD of a user back to cycles.
+    ///
+    /// The DOD is burned from the treasury, by a transfer to the minting account, and the cycles
+    /// are credited to the user's cycle balance at the configured rate. The conversion shares the
+    /// claim lock, so it can not run alongside a claim of the same user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user converting the reward.
+    /// * `amount_dod` - A `u64` representing the DOD to convert, in base units.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u128, String>` - On success, returns the cycles credited. On failure, returns an error message as a `String`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * The conversion is disabled or the amount converts to no cycles.
+    /// * The amount is greater than the unclaimed amount.
+    /// * The canister does not hold enough cycles of its own to credit.
+    /// * Another claim of the user is in progress, or the burn fails.
+    pub async fn claim_rewards_as_cycles(user: Principal, amount_dod: u64) -> Result<u128, String> {
+        claim::check_not_halted()?;
+        claim::lock(user)?;
+        let res = Self::claim_rewards_as_cycles_locked(user, amount_dod).await;
+        claim::unlock(user);
+        res
+    }
+
+    async fn claim_rewards_as_cycles_locked(
+        user: Principal,
+        amount_dod: u64,
+    ) -> Result<u128, String> {
+        let cycles_per_dod = conversion::get_cycles_per_dod()
+            .ok_or_else(|| "Cycles conversion is not enabled".to_string())?;
+        if amount_dod == 0 {
+            return Err("Claim amount is zero ".to_string());
+        }
+        let decimals = match ledger::get_cached_metadata() {
+            Some(metadata) => metadata.decimals,
+            None => Self::refresh_ledger_metadata().await?.decimals,
+        };
+        let credited = conversion::cycles_for(amount_dod, cycles_per_dod, decimals)?;
+
+        let user_detail = Self::get_user_detail(user).ok_or_else(|| "No user found".to_string())?;
+        if amount_dod
+            > user_detail
+                .total_dod
+                .saturating_sub(user_detail.claimed_dod)
+        {
+            return Err("Claim amount is greater than unclaimed amount ".to_string());
+        }
+        // credited cycles come out of the canister's own cycles, never out of other users'
+        if cycles::get_cycles_health().operational < credited {
+            return Err("Not enough cycles to convert the reward".to_string());
+        }
+
+        Self::burn_dod_from_treasury(self_id(), amount_dod).await?;
+        // read again, settlement may have updated the user during the await
+        let claimed_dod = Self::get_user_detail(user).map_or(0, |r| r.claimed_dod);
+        Self::write_user_claimed_dod(user, claimed_dod + amount_dod)?;
+        Self::increase_user_cycle_balance(user, Nat::from(credited))?;
+        cycles::add_to_user_pool(credited);
+        log_info!(
+            "{} converted {} DOD to {} cycles",
+            user.to_text(),
+            amount_dod,
+            credited
+        );
+        Ok(credited)
+    }
+
+    async fn transfer_claimed_dod(
+        token_canister: Principal,
+        from_subaccount: [u8; 32],
+        to: Account,
+        amount: u64,
+        fee: u64,
+        created_at_time: u64,
+    ) -> claim::ClaimOutcome {
+        let arg = TransferArg {
+            from_subaccount: Some(from_subaccount),
+            to,
+            fee: Some(Nat::from(fee)),
+            created_at_time: Some(created_at_time),
+            memo: Some(icrc_ledger_types::icrc1::transfer::Memo::from(
+                MEMO_TRANSFER,
+            )),
+            amount: NumTokens::from(amount),
+        };
+        let call_result = ic_cdk::api::call::call(token_canister, "icrc1_transfer", (arg.clone(),))
+            .await
+            as Result<
+                (Result<Nat, icrc_ledger_types::icrc1::transfer::TransferError>,),
+                (RejectionCode, String),
+            >;
+        claim::classify_transfer(call_result.map(|resp| resp.0))
+    }
+
+    /// Transfers a claim of `user`, `amount` minus the fee reaching `to`.
+    ///
+    /// The claim stays pending until the ledger answers, a retry of the same claim is sent with the
+    /// `created_at_time` of the first attempt, so a transfer that landed is found as a duplicate
+    /// and taken as done. A claim older than the deduplication window is not sent again.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user claiming.
+    /// * `source` - A `ClaimSource` representing the balance the claim is paid from.
+    /// * `from_subaccount` - A `[u8; 32]` representing the treasury subaccount to transfer from.
+    /// * `to` - An `Account` representing the destination.
+    /// * `amount` - A `u64` representing the DOD debited, the fee included.
+    /// * `fee` - A `u64` representing the ledger fee.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Nat, String>` - On success, the block index of the transfer. On failure, an error message as a `String`.
+    async fn transfer_claim(
+        user: Principal,
+        source: ClaimSource,
+        from_subaccount: [u8; 32],
+        to: Account,
+        amount: u64,
+        fee: u64,
+    ) -> Result<Nat, String> {
+        let token_canister = Self::get_token_canister()?;
+        let created_at_time =
+            claim::begin_transfer(user, &source, &to, amount, ic_cdk::api::time())?;
+        let outcome = Self::transfer_claimed_dod(
+            token_canister,
+            from_subaccount,
+            to.clone(),
+            amount.saturating_sub(fee),
+            fee,
+            created_at_time,
+        )
+        .await;
+        claim::end_transfer(user, &source, &to, amount, &outcome);
+        let res = outcome.into_result();
+        if let Ok(tx_index) = res.as_ref() {
+            statements::record_claim(tx_index, user, &source, &to, amount, fee);
+        }
+        res
+    }
+
+    /// Checks that the block treasury holds enough DOD to pay every staker and miner their
+    /// unclaimed rewards. Anyone can call it, a check younger than ten minutes is returned
+    /// again instead of asking the ledger.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SolvencyReport, String>` - The treasury balance, what it owes and the surplus or deficit, or an error if the ledger could not be reached.
+    pub async fn verify_solvency() -> Result<SolvencyReport, String> {
+        solvency::verify_solvency().await
+    }
+
+    /// Donates cycles to the pool of a block without expecting any reward for them.
+    ///
+    /// The donation raises the block total the stakers' shares are computed from and is burned
+    /// or paid to the winner with the block, it is not refunded when the block has no winner.
+    /// The cycles attached to the call are taken when there are any, the donor's staker balance
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `donor` - A `Principal` representing the donor.
+    /// * `height` - A `Height` representing the block, the open one or one of the next `MAX_DONATION_BLOCKS_AHEAD`.
+    /// * `amount` - A `u128` representing the cycles donated, at least `MIN_DONATION`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlockDonations, String>` - The donations of the block, or an error if the block can not take them or the cycles are missing.
+    pub fn donate_cycles_to_block(
+        donor: Principal,
+        height: Height,
+        amount: u128,
+    ) -> Result<BlockDonations, String> {
+        donations::donate(donor, height, amount)
+    }
+
+    /// Retrieves the cycles donated to a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - A `Height` representing the block height.
+    ///
+    /// # Returns
+    ///
+    /// * `BlockDonations` - The total donated and each donor's part.
+    pub fn get_block_donations(height: Height) -> BlockDonations {
+        donations::get_block_donations(height)
+    }
+
+    /// Retrieves the average total cycles per block over the last settled blocks.
+    ///
+    /// The averages come from running totals kept as blocks are finalized, so a single block's pool moves them by its share of the window only.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_blocks` - A `u64` representing the number of blocks to average, at most `MAX_TWAP_WINDOW`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PoolTwap, String>` - On success, returns the block weighted and time weighted averages. On failure, returns an error message as a `String`.
+    pub fn get_pool_twap(window_blocks: u64) -> Result<PoolTwap, String> {
+        pool_twap::get_pool_twap(window_blocks)
+    }
+
+    /// Retrieves the protocol constants with the currently configured limits.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ProtocolConstants, String>` - On success, returns the constants, whose `revision` changes whenever a value does. On failure, returns an error message as a `String`.
+    pub fn get_protocol_constants() -> Result<ProtocolConstants, String> {
+        constants::get_protocol_constants()
+    }
+
+    /// Imports a batch of blocks of a previous deployment, before genesis.
+    ///
+    /// Heights must go on from the last imported block one after the other, starting at 0, and every block must be settled.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - A `Vec<BlockData>` holding at most `MAX_IMPORT_BATCH` blocks.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlockImport, String>` - On success, returns the import progress. On failure, returns an error message as a `String`.
+    pub fn import_blocks(blocks: Vec<BlockData>) -> Result<BlockImport, String> {
+        import::import_blocks(blocks)
+    }
+
+    /// Imports the winner sigs of imported blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `sigs` - A `Vec<(Height, BlockSigs)>` holding at most `MAX_IMPORT_BATCH` sigs with their transactions inline.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlockImport, String>` - On success, returns the import progress. On failure, returns an error message as a `String`.
+    pub fn import_sigs(sigs: Vec<(Height, BlockSigs)>) -> Result<BlockImport, String> {
+        import::import_sigs(sigs)
+    }
+
+    /// Imports miners of a previous deployment, none of them may be registered already.
+    ///
+    /// # Arguments
+    ///
+    /// * `miners` - A `Vec<MinerInfo>` holding at most `MAX_IMPORT_BATCH` miners.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlockImport, String>` - On success, returns the import progress. On failure, returns an error message as a `String`.
+    pub fn import_miners(miners: Vec<MinerInfo>) -> Result<BlockImport, String> {
+        import::import_miners(miners)
+    }
+
+    /// Locks further imports and opens the block after the last imported one, block production goes on from there.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlockData, String>` - On success, returns the opened block. On failure, returns an error message as a `String`.
+    pub async fn seal_import() -> Result<BlockData, String> {
+        import::seal_import().await
+    }
+
+    /// Retrieves the progress of the block import.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BlockImport>` - The import progress, `None` when nothing was imported.
+    pub fn get_block_import() -> Option<BlockImport> {
+        import::get_block_import()
+    }
+
+    /// Retrieves the last treasury solvency check.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SolvencyReport>` - The report with the time it was made, `None` if no check ran since the last upgrade.
+    pub fn get_solvency_report() -> Option<SolvencyReport> {
+        solvency::get_solvency_report()
+    }
+
+    /// Retrieves the statement of a claim transfer, the blocks whose rewards it paid out.
+    ///
+    /// Claims take the oldest rewards of their source that no earlier claim took, asset claims
+    /// the reward of their block.
+    ///
+    /// # Arguments
+    ///
+    /// * `claim_tx_index` - A `u64` representing the ledger block index of the claim transfer.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ClaimStatement>` - The statement, `None` for transfers that are not claims or were made before statements were kept.
+    pub fn get_claim_statement(claim_tx_index: u64) -> Option<ClaimStatement> {
+        statements::get_claim_statement(claim_tx_index)
+    }
+
+    /// Retrieves the claims of a user whose outcome is not known yet.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user whose block range is to be retrieved.
+    /// * `user` - A `Principal` representing the user.
     ///
     /// # Returns
     ///
-    /// * `Option<NewBlockOrderValue>` - Returns `Some(NewBlockOrderValue)` if the user has set a range, otherwise `None`.
-    pub fn get_user_range(user: Principal) -> Option<NewBlockOrderValue> {
-        NewUserOrders::get_user_set_range(user)
+    /// * `Vec<PendingClaim>` - The pending claims, with the time they were first sent and the last error.
+    pub fn get_pending_claims(user: Principal) -> Vec<PendingClaim> {
+        claim::get_pending_claims(user)
     }
 
-    /// Retrieves the share of a user in a specific block.
+    /// Resolves a pending claim that can no longer be retried.
     ///
-    /// This function calculates the share of a user in a specific block based on the total cycles and the user's block order.
+    /// Owners check on the ledger whether the claim landed. A landed claim is added to the claimed
+    /// amount of its source, otherwise it is dropped and its DOD can be claimed again.
     ///
     /// # Arguments
     ///
-    /// * `block` - A `u64` representing the block height.
-    /// * `user` - A `Principal` representing the user.
+    /// * `user` - A `Principal` representing the user of the claim.
+    /// * `index` - A `u64` representing the position of the claim in `get_pending_claims`.
+    /// * `landed` - A `bool`, whether the transfer was found on the ledger.
     ///
     /// # Returns
     ///
-    /// * `f64` - The share of the user in the block.
-    pub fn get_user_block_share(block: u64, user: Principal) -> f64 {
-        let total_cycles = Self::get_block_total_cycles(block, false);
-        let user_order = Self::get_user_block_order(user, block);
-
-        if user.ne(&id())
-            && (user_order.status == OrderStatus::Pending
-                || user_order.status == OrderStatus::Cancelled)
-        {
-            0f64
-        } else {
-            user_order.value as f64 / total_cycles as f64
-        }
-    }
-
-    pub fn get_user_block_share_v2(block: u64, user: Principal) -> f64 {
-        let total_cycles = Self::get_block_total_cycles_v2(block, true);
-        let user_order = Self::get_user_block_order(user, block);
-
-        if user.ne(&id())
-            && (user_order.status == OrderStatus::Pending
-                || user_order.status == OrderStatus::Cancelled)
-        {
-            0f64
-        } else {
-            user_order.value as f64 / total_cycles as f64
+    /// * `Result<PendingClaim, String>` - The resolved claim, or an error when there is no claim at `index`.
+    pub fn resolve_pending_claim(
+        user: Principal,
+        index: u64,
+        landed: bool,
+    ) -> Result<PendingClaim, String> {
+        let claim = claim::get_pending_claims(user)
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| "No pending claim found".to_string())?;
+        if landed {
+            match &claim.source {
+                ClaimSource::Staker => {
+                    let claimed_dod = Self::get_user_detail(user).map_or(0, |r| r.claimed_dod);
+                    Self::write_user_claimed_dod(user, claimed_dod.saturating_add(claim.amount))?;
+                }
+                ClaimSource::Miner => {
+                    let claimed_dod =
+                        Self::get_miner_by_principal(user).map_or(0, |m| m.claimed_dod);
+                    Self::write_miner_claimed_dod(user, claimed_dod.saturating_add(claim.amount))?;
+                }
+                ClaimSource::Asset { asset, height } => assets::mark_claimed(asset, *height),
+            }
         }
+        claim::take_pending_claim(user, index as usize);
+        Ok(claim)
     }
 
-    /// Retrieves the user's block reward and share.
-    ///
-    /// This function calculates the user's reward and share for a specific block based on the total cycles and the user's block order.
+    /// Sets how long retries of a pending claim reuse the time it was first sent with.
     ///
     /// # Arguments
     ///
-    /// * `block` - A `u64` representing the block height.
-    /// * `user` - A `Principal` representing the user.
+    /// * `window` - A `u64` in nanoseconds, at most the day the ledger deduplicates transactions for.
     ///
     /// # Returns
     ///
-    /// * `(u64, f64)` - A tuple containing the user's reward as `u64` and the share as `f64`.
-    pub fn get_user_block_reward(block: u64, user: Principal) -> (u64, f64) {
-        let share = Self::get_user_block_share(block, user);
-        let halving_settings = Self::get_halving_settings().expect("Can not get halving settings");
-        let reward = Self::get_block_reward_by_height(block, Some(halving_settings))
-            .expect("Can not get block reward by height");
-        ((reward as f64 * share).floor() as u64, share)
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_claim_dedup_window(window: u64) -> Result<(), String> {
+        if window == 0 || window > claim::MAX_CLAIM_DEDUP_WINDOW {
+            return Err(format!(
+                "Claim deduplication window must be 1 to {} nanoseconds",
+                claim::MAX_CLAIM_DEDUP_WINDOW
+            ));
+        }
+        config::set_claim_dedup_window(window)
     }
 
-    pub fn get_user_block_reward_v2(block: u64, user: Principal) -> (u64, f64) {
-        let share = Self::get_user_block_share_v2(block, user);
-        let halving_settings = Self::get_halving_settings().expect("Can not get halving settings");
-        let reward = Self::get_block_reward_by_height(block, Some(halving_settings))
-            .expect("Can not get block reward by height");
-        ((reward as f64 * share).floor() as u64, share)
+    /// Retrieves the claim deduplication window.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The window in nanoseconds, the default when it was never set.
+    pub fn get_claim_dedup_window() -> u64 {
+        config::get_claim_dedup_window()
     }
 
-    /// Retrieves the total cycles for a specific block.
-    ///
-    /// This function calculates the total cycles for a given block by summing up the cycles from all orders.
+    /// Sets whether registering a miner registers its owner as a staker too, as it always did before stakers were created on first deposit.
     ///
     /// # Arguments
     ///
-    /// * `block` - A `u64` representing the block height.
+    /// * `enabled` - A `bool`, `true` for the previous behavior.
     ///
     /// # Returns
     ///
-    /// * `u128` - The total cycles for the block.
-    pub fn get_block_total_cycles(block: u64, with_filled: bool) -> u128 {
-        NEW_BLOCK_ORDERS.with_borrow(|v| {
-            NewBlockOrders::get_orders_by_block_height(v, block).fold(0, |acc, (_, x)| {
-                match (with_filled, x.status) {
-                    (true, OrderStatus::Filled) | (_, OrderStatus::Cancelled) => acc,
-                    _ => acc + x.value,
-                }
-            })
-        })
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    pub fn set_register_miners_as_stakers(enabled: bool) -> Result<(), String> {
+        config::set_register_miners_as_stakers(enabled)
     }
 
-    pub fn get_block_total_cycles_v2(block: u64, _with_filled: bool) -> u128 {
-        // NEW_BLOCK_ORDERS.with_borrow(|v| {
-        //     NewBlockOrders::get_orders_by_block_height(v, block).fold(0, |acc, (_, x)| {
-        //         match x.status {
-        //             OrderStatus::Pending => acc + x.value,
-        //             OrderStatus::Filled => acc,
-        //             OrderStatus::Cancelled => acc + x.value,
-        //         }
-        //     })
-        // })
-
-        BLOCKS.with_borrow(|v| {
-            v.get(&block).map_or(0, |x| {
-                x.cycle_burned * 2 + x.winner.as_ref().map_or(0, |x| x.reward_cycles.unwrap())
-            })
-        })
+    /// Retrieves whether registering a miner registers its owner as a staker too.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `false` by default, stakers are created on their first deposit.
+    pub fn get_register_miners_as_stakers() -> bool {
+        config::get_register_miners_as_stakers()
     }
 
-    /// Retrieves the block order for a specific user and block.
+    /// Sets the `dod_archive` canister blocks older than `retain_blocks` are moved to as blocks are finalized.
     ///
-    /// This function fetches the order details for a given user and block height.
-    /// It first attempts to retrieve the order from the new block orders. If no order is found,
-    /// it returns a default `OrderDetail` with a value of 0 and a status of `Pending`.
+    /// The archive canister can not be replaced once blocks were moved to it.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user whose order is to be retrieved.
-    /// * `block` - A `u64` representing the block height.
+    /// * `canister` - A `Principal` representing the archive canister, installed with this canister as its DOD canister.
+    /// * `retain_blocks` - A `u64` representing the blocks kept here, at least `MIN_RETAIN_BLOCKS` and the difficulty epoch.
     ///
     /// # Returns
     ///
-    /// * `OrderDetail` - The order details for the specified user and block.
-    pub fn get_user_block_order(user: Principal, block: u64) -> OrderDetail {
-        NEW_BLOCK_ORDERS.with_borrow(|v| {
-            NewBlockOrders::get_orders_by_block_height(v, block)
-                .filter(|(k, _)| k == &user)
-                .map(|(_, v)| v)
-                .next()
-                .unwrap_or(OrderDetail {
-                    value: 0,
-                    status: OrderStatus::Pending,
-                })
-        })
+    /// * `Result<BlockArchive, String>` - On success, returns the archive settings. On failure, returns an error message as a `String`.
+    pub fn set_block_archive(
+        canister: Principal,
+        retain_blocks: u64,
+    ) -> Result<BlockArchive, String> {
+        block_archive::set_block_archive(canister, retain_blocks)
     }
 
-    /// Retrieves the user's orders within a specified block range.
+    /// Retrieves the block archive settings and progress.
     ///
-    /// This function fetches the orders for a given user within the specified block range.
-    /// It accesses the `NEW_BLOCK_ORDERS` to get the user's orders in the range and collects them into a vector.
+    /// # Returns
+    ///
+    /// * `Option<BlockArchive>` - The archive settings, `None` when no archive is set.
+    pub fn get_block_archive() -> Option<BlockArchive> {
+        config::get_block_archive()
+    }
+
+    /// Moves the next batch of old blocks to the block archive, as done after each finalized block.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64, String>` - On success, returns the number of blocks moved. On failure, returns an error message as a `String`.
+    pub async fn archive_blocks() -> Result<u64, String> {
+        block_archive::archive_blocks().await
+    }
+
+    /// Retrieves a block, from the block archive when it was moved there.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user whose orders are to be retrieved.
-    /// * `range` - A `BlockRange` representing the range of blocks to fetch orders from.
+    /// * `height` - A `Height` representing the block height.
     ///
     /// # Returns
     ///
-    /// * `Vec<(u64, OrderDetail)>` - A vector of tuples where each tuple contains a block height (`u64`) and the corresponding `OrderDetail`.
-    pub fn get_user_orders(user: Principal, range: BlockRange) -> Vec<(u64, OrderDetail)> {
-        NEW_BLOCK_ORDERS.with_borrow(|v| {
-            NewBlockOrders::get_user_orders_in_range(v, user, range)
-                .collect::<Vec<(u64, OrderDetail)>>()
-        })
+    /// * `Result<Option<BlockData>, String>` - On success, returns the block if it exists. On failure, returns an error message as a `String`.
+    pub async fn get_archived_block_by_height(height: Height) -> Result<Option<BlockData>, String> {
+        block_archive::get_block_by_height(height).await
     }
 
-    /// Retrieves the user's orders within a specified block range and filters them by status.
-    ///
-    /// This function fetches the orders for a given user within the specified block range and filters them by the provided status.
-    /// It accesses the `NEW_BLOCK_ORDERS` to get the user's orders in the range, calculates the reward and share for each order,
-    /// and collects them into a vector.
+    /// Retrieves the blocks within a range, the ones moved to the block archive included.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user whose orders are to be retrieved.
-    /// * `from` - A `u64` representing the starting block height.
-    /// * `to` - A `u64` representing the ending block height.
-    /// * `status` - An `OrderStatus` representing the status to filter orders by.
+    /// * `range` - A `BlockRange` representing the blocks to return.
     ///
     /// # Returns
     ///
-    /// * `(Vec<UserBlockOrder>, u64)` - A tuple where the first element is a vector of `UserBlockOrder` and the second element is the total number of orders.
-    pub fn get_user_orders_by_blocks(
-        user: Principal,
-        from: u64,
-        to: u64,
-        status: OrderStatus,
-    ) -> (Vec<UserBlockOrder>, u64) {
-        NEW_BLOCK_ORDERS.with_borrow(|v| {
-            let data = NewBlockOrders::get_user_orders_in_range(v, user, (from, to))
-                .filter(|(_, v)| v.status == status)
-                .map(|(a, b)| {
-                    let (reward, share) = Self::get_user_block_reward(a.clone(), user.clone());
-                    UserBlockOrder {
-                        block: a.clone(),
-                        amount: b.value.clone(),
-                        share,
-                        reward,
-                    }
-                })
-                .collect::<Vec<UserBlockOrder>>();
-            let total = data.len() as u64;
-            (data, total)
-        })
+    /// * `Result<Vec<BlockData>, String>` - On success, returns the blocks in height order, ending before the first archived block the archive does not return. On failure, returns an error message as a `String`.
+    pub async fn get_archived_blocks_range(range: BlockRange) -> Result<Vec<BlockData>, String> {
+        block_archive::get_blocks_range(range).await
     }
 
-    /// Retrieves orders by block range.
+    /// Checks that a block is still kept here, not moved to the block archive.
     ///
-    /// This function fetches the orders for a specified block range and collects them into a vector of `BlockDataFull`.
-    /// It accesses the `NEW_BLOCK_ORDERS` to get the orders for each block in the range, filters the filled orders,
-    /// and collects the user data and miner candidates for each block.
+    /// Reads of local blocks reject archived heights rather than return nothing for them.
     ///
     /// # Arguments
     ///
-    /// * `from` - A `u64` representing the starting block height.
-    /// * `to` - A `u64` representing the ending block height.
+    /// * `height` - A `Height` representing the block height.
     ///
     /// # Returns
     ///
-    /// * `Vec<BlockDataFull>` - A vector of `BlockDataFull` containing the block data, user data, and miner candidates for each block in the range.
-    pub fn get_orders_by_block_v2(from: u64, to: u64) -> Vec<BlockDataFull> {
-        let mut data: Vec<BlockDataFull> = vec![];
-        NEW_BLOCK_ORDERS.with_borrow(|v| {
-            for i in from..to {
-                if let Some(block) = BLOCKS.with_borrow(|v| v.get(&i)) {
-                    let miners = CANDIDATES.with_borrow(|v| {
-                        v.get(&i).map_or_else(Vec::new, |v| {
-                            v.candidates
-                                .iter()
-                                .map(|(_, k)| {
-                                    let principal = MINERS.with_borrow(|s| {
-                                        let info =
-                                            s.get(&BtcAddress(k.btc_address.clone())).unwrap();
-                                        info.owner.clone()
-                                    });
-                                    MinerCandidateExt {
-                                        miner_principal: principal,
-                                        btc_address: k.btc_address.clone(),
-                                        submit_time: k.submit_time.clone(),
-                                        cycles_price: k.cycles_price.clone(),
-                                        signed_commit_psbt: k.signed_commit_psbt.clone(),
-                                        signed_reveal_psbt: k.signed_reveal_psbt.clone(),
-                                    }
-                                })
-                                .collect()
-                        })
-                    });
-
-                    let orders = NewBlockOrders::get_orders_by_block_height(v, i);
-                    let user_data: Vec<UserBlockOrderData> = orders
-                        .into_iter()
-                        .filter(|(k, v)| {
-                            if k.clone() == id() {
-                                return true;
-                            } else {
-                                v.status == OrderStatus::Filled
-                            }
-                        })
-                        .map(|(user, amount)| {
-                            let (reward, share) = Self::get_user_block_reward(i, user);
-                            UserBlockOrderData {
-                                height: i,
-                                amount: amount.value,
-                                share,
-                                reward,
-                                user,
-                            }
-                        })
-                        .collect();
-
-                    data.push(BlockDataFull {
-                        block,
-                        user_data,
-                        miners,
-                    });
-                } else {
-                    break;
-                }
-            }
-        });
-        data
+    /// * `Result<(), String>` - On success, returns an empty tuple. On failure, returns where the block was archived as a `String`.
+    pub fn check_block_kept(height: Height) -> Result<(), String> {
+        block_archive::check_kept(height)
     }
 
-    /// Claims the reward for a user.
+    /// Moves cycles balance from the caller to other users inside the canister.
     ///
-    /// This asynchronous function calculates the amount of DOD tokens to be claimed by the user,
-    /// updates the user's claimed DOD amount, and transfers the tokens to the user's account.
+    /// Every leg that is credited is recorded with the shared memo and timestamp, see `get_internal_transfers`.
     ///
     /// # Arguments
     ///
-    /// * `user` - A `Principal` representing the user claiming the reward.
+    /// * `caller` - A `Principal` representing the user sending cycles.
+    /// * `to` - A `Vec<(Principal, u128)>` of receivers and amounts.
+    /// * `memo` - An `Option<Vec<u8>>` of at most 32 bytes recorded with each transfer.
     ///
     /// # Returns
     ///
-    /// * `Result<Nat, String>` - On success, returns the amount of tokens claimed as `Nat`. On failure, returns an error message as a `String`.
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if:
-    /// * The user details cannot be retrieved.
-    /// * The claimed DOD amount cannot be written.
-    /// * The token canister cannot be retrieved.
-    /// * The transfer call to the token canister fails.
-    pub async fn claim_reward(
-        user: Principal,
-        to: Option<Account>,
-        claim_amount: Option<u64>,
-    ) -> Result<Nat, String> {
-        ic_cdk::println!("\n claim_amount {:?}", claim_amount);
-        ic_cdk::println!("\n to {:?}", to);
-        let user_detail = Self::get_user_detail(user).unwrap();
-        let from_subaccount = Self::get_dod_block_account()?;
-        let unclaimed = if user_detail.total_dod > user_detail.claimed_dod {
-            user_detail.total_dod - user_detail.claimed_dod
-        } else {
-            0
-        };
-
-        if claim_amount.is_none() {
-            return Err("Claim amount is none".to_string());
-        }
-
-        if claim_amount.is_some() {
-            if claim_amount.unwrap() > unclaimed {
-                return Err("Claim amount is greater than unclaimed amount ".to_string());
-            }
-            if claim_amount.unwrap() == 0 {
-                return Err("Claim amount is zero ".to_string());
-            }
-        }
-
-        Self::write_user_claimed_dod(
-            user_detail.principal,
-            user_detail.claimed_dod + claim_amount.unwrap_or(0),
-        )?;
-
-        let token_canister = Self::get_token_canister()?;
-
-        let amount = NumTokens::from(claim_amount.unwrap_or(0));
-        let arg = TransferArg {
-            from_subaccount: Some(from_subaccount),
-            to: to.unwrap_or(Account {
-                owner: user.clone(),
-                subaccount: None,
-            }),
-            fee: None,
-            created_at_time: Some(ic_cdk::api::time()),
-            memo: Some(icrc_ledger_types::icrc1::transfer::Memo::from(
-                MEMO_TRANSFER,
-            )),
-            amount: amount.clone(),
-        };
-        let call_result = ic_cdk::api::call::call(token_canister, "icrc1_transfer", (arg.clone(),))
-            .await
-            as Result<(Result<Nat, TransferError>,), (RejectionCode, String)>;
-
-        match call_result {
-            Ok(resp) => match resp.0 {
-                Ok(_resp) => Ok(_resp),
-                Err(msg) => Err(format!(
-                    "Error calling claim_reward::icrc1_transfer msg: {}",
-                    msg
-                )),
-            },
-            Err((code, msg)) => {
-                let code = code as u16;
-                Err(format!(
-                    "Error calling claim_reward::icrc1_transfer code: {}, msg: {}",
-                    code, msg
-                ))
-            }
-        }
-    }
-
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
     pub fn inner_transfer_cycles(
         caller: Principal,
         to: Vec<(Principal, u128)>,
+        memo: Option<Vec<u8>>,
     ) -> Result<(), String> {
+        transfer::check_memo(&memo)?;
         let range = Self::get_user_range(caller);
         let last_block = Self::get_last_block();
         if last_block.is_none() {
             return Err("No last block found".to_string());
         }
 
-        if range.is_some() && range.unwrap().r.1 > last_block.unwrap().0 {
+        if range.is_some() && range.unwrap().r.end() > last_block.unwrap().0 {
             Err("Can not transfer cycles when user has orders running".to_string())
         } else {
             let mut total_amount = 0;
@@ -2190,10 +6003,12 @@ impl DodService {
                 Err("Not enough balance".to_string())
             } else {
                 let mut total_amount_actual = 0u128;
+                let now = ic_cdk::api::time();
                 for (to, amount) in to {
                     let s = Self::increase_user_cycle_balance(to, Nat::from(amount));
                     if s.is_ok() {
                         total_amount_actual += amount;
+                        transfer::record_internal_transfer(caller, to, amount, memo.clone(), now);
                     }
                 }
                 Self::decrease_user_cycle_balance(caller, Nat::from(total_amount_actual))
@@ -2201,10 +6016,26 @@ impl DodService {
         }
     }
 
+    /// Retrieves a page of the internal cycles transfers sent or received by a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - A `Principal` representing the user.
+    /// * `page` - A `u64` representing the zero based page number.
+    /// * `limit` - A `u64` representing the page size, capped at 100.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<InternalTransfer>` - The transfers of the page, oldest first.
+    pub fn get_internal_transfers(user: Principal, page: u64, limit: u64) -> Vec<InternalTransfer> {
+        transfer::get_internal_transfers(user, page, limit)
+    }
+
     /// Retrieves the block reward for a given block height, considering halving settings.
     ///
     /// This function calculates the block reward based on the default rewards and the halving ratio
-    /// if the halving settings are provided. The reward is adjusted according to the current halving ratio.
+    /// if the halving settings are provided. The reward is adjusted according to the current halving ratio,
+    /// then boosted when a reward boost is configured and the block has few deposits.
     ///
     /// # Arguments
     ///
@@ -2218,13 +6049,8 @@ impl DodService {
         height: Height,
         halving_settings: Option<HalvingSettings>,
     ) -> Result<u64, String> {
-        let default_reward = Self::get_default_rewards()?;
-        let mut reward = default_reward;
-        if halving_settings.is_some() {
-            let ratio = config::get_current_halving_ratio(height, halving_settings.unwrap());
-            reward = (reward as f64 * ratio).floor() as u64;
-        }
-        Ok(reward)
+        let reward = config::halved_reward(Self::get_default_rewards()?, height, halving_settings);
+        Ok(boost::apply(height, reward))
     }
 
     /// Mints DOD award to the treasury.
@@ -2248,6 +6074,49 @@ impl DodService {
     /// * The transfer call to the token canister fails.
     pub async fn mint_dod_award_to_treasury(reward: u64) -> Result<Nat, String> {
         let to_subaccount = Self::get_dod_block_account()?;
+        Self::mint_dod_to_treasury_subaccount(to_subaccount, reward).await
+    }
+
+    /// Mints the team and ecosystem shares of a block reward to their treasury buckets.
+    ///
+    /// A failed mint is logged and not retried, the bucket only counts what was minted.
+    ///
+    /// # Arguments
+    ///
+    /// * `reward` - A `u64` representing the block reward minted to the emission bucket.
+    async fn mint_bucket_shares(reward: u64) {
+        let split = match treasury::get_treasury_split() {
+            Some(split) => split,
+            None => return,
+        };
+        let (team, ecosystem) = treasury::bucket_shares(&split, reward);
+        for (bucket, amount) in [
+            (TreasuryBucket::Team, team),
+            (TreasuryBucket::Ecosystem, ecosystem),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            let res = match treasury::get_bucket_subaccount(&bucket) {
+                Ok(subaccount) => Self::mint_dod_to_treasury_subaccount(subaccount, amount).await,
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(_) => treasury::record_minted(bucket, amount),
+                Err(e) => log_error!(
+                    "can not mint {} DOD to the {:?} bucket: {}",
+                    amount,
+                    bucket,
+                    e
+                ),
+            }
+        }
+    }
+
+    async fn mint_dod_to_treasury_subaccount(
+        to_subaccount: [u8; 32],
+        reward: u64,
+    ) -> Result<Nat, String> {
         let token_canister = Self::get_token_canister()?;
         let amount = NumTokens::from(reward);
         let arg = TransferArg {
@@ -2264,7 +6133,7 @@ impl DodService {
             amount: amount.clone(),
         };
 
-        ic_cdk::println!(
+        log_debug!(
             "mint_dod_award_to_treasury::icrc1_transfer arg:{}",
             format!(" {:?}", arg)
         );
@@ -2276,14 +6145,14 @@ impl DodService {
         match call_result {
             Ok(resp) => match resp.0 {
                 Ok(_resp) => {
-                    ic_cdk::println!(
+                    log_debug!(
                         "mint_dod_award_to_treasury::icrc1_transfer resp:{}",
                         format!(" {:?}", _resp)
                     );
                     Ok(_resp)
                 }
                 Err(msg) => {
-                    ic_cdk::println!(
+                    log_error!(
                         "mint_dod_award_to_treasury::icrc1_transfer msg:{}",
                         format!(" {:?}", msg)
                     );
@@ -2295,7 +6164,7 @@ impl DodService {
             },
             Err((code, msg)) => {
                 let code = code as u16;
-                ic_cdk::println!(
+                log_error!(
                     "Error calling mint_dod_award_to_treasury::icrc1_transfer msg:{}",
                     format!(" {:?}", msg)
                 );
@@ -2346,7 +6215,7 @@ impl DodService {
                 amount: amount.clone(),
             };
 
-            ic_cdk::println!(
+            log_debug!(
                 "burn_dod_from_treasury::icrc1_transfer arg:{}",
                 format!(" {:?}", arg)
             );
@@ -2358,14 +6227,14 @@ impl DodService {
             match call_result {
                 Ok(resp) => match resp.0 {
                     Ok(_resp) => {
-                        ic_cdk::println!(
+                        log_debug!(
                             "burn_dod_from_treasury::icrc1_transfer resp:{}",
                             format!(" {:?}", _resp)
                         );
                         Ok(_resp)
                     }
                     Err(msg) => {
-                        ic_cdk::println!(
+                        log_error!(
                             "Error calling  burn_dod_from_treasury::icrc1_transfer msg:{}",
                             format!(" {:?}", msg)
                         );
@@ -2377,7 +6246,7 @@ impl DodService {
                 },
                 Err((code, msg)) => {
                     let code = code as u16;
-                    ic_cdk::println!(
+                    log_error!(
                         "Error calling burn_dod_from_treasury::icrc1_transfer msg:{}",
                         format!(" {:?}", msg)
                     );
@@ -2395,7 +6264,9 @@ impl DodService {
 
 #[cfg(test)]
 mod test {
-    use dod_utils::types::HalvingSettings;
+    use crate::common::set_native_time;
+    use crate::service::DodService;
+    use dod_utils::types::{GenesisParams, HalvingSettings};
 
     #[test]
     pub fn test_halving() {
@@ -2409,4 +6280,17 @@ mod test {
         );
         println!("{:?}", d);
     }
+
+    #[test]
+    pub fn test_init_genesis_timestamp() {
+        set_native_time(1_000);
+        let params = |timestamp| GenesisParams {
+            hash: None,
+            timestamp: Some(timestamp),
+            message: None,
+        };
+        assert!(DodService::init_genesis(params(0)).is_err());
+        assert!(DodService::init_genesis(params(1_001)).is_err());
+        assert!(DodService::get_genesis_info().is_none());
+    }
 }
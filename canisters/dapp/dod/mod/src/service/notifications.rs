@@ -0,0 +1,90 @@
+use crate::memory::{BLOCKS, NOTIFICATION_PREFERENCES};
+use crate::service::DodService;
+use candid::Principal;
+use dod_utils::types::{Height, NotificationPreferences, UserNotification};
+
+/// Blocks searched for wins by one poll, a client that polls less often passes `since`.
+pub const MAX_WIN_LOOKBACK: u64 = 100;
+
+pub fn get_preferences(user: Principal) -> Option<NotificationPreferences> {
+    NOTIFICATION_PREFERENCES.with_borrow(|v| v.get(&user))
+}
+
+pub fn set_preferences(user: Principal, preferences: Option<NotificationPreferences>) {
+    NOTIFICATION_PREFERENCES.with_borrow_mut(|v| match preferences {
+        Some(preferences) => v.insert(user, preferences),
+        None => v.remove(&user),
+    });
+}
+
+/// Blocks left before an order range ending at `range_end` (excluded) runs out, when that is
+/// at most `within` blocks after the open block.
+pub fn expiring_in(range_end: Height, open: Height, within: u64) -> Option<u64> {
+    let blocks_left = range_end.checked_sub(open).filter(|left| *left > 0)?;
+    (blocks_left <= within).then_some(blocks_left)
+}
+
+/// The reminders due for `user` under their preferences. Wins are the blocks after `since`
+/// mined by one of the user's miners, at most `MAX_WIN_LOOKBACK` blocks back.
+pub fn get_notifications(user: Principal, since: Option<Height>) -> Vec<UserNotification> {
+    let preferences = match get_preferences(user) {
+        Some(preferences) => preferences,
+        None => return vec![],
+    };
+    let open = match DodService::get_last_block() {
+        Some((open, _)) => open,
+        None => return vec![],
+    };
+    let mut notifications = vec![];
+    if preferences.notify_on_win {
+        let from = since
+            .map_or(0, |h| h.saturating_add(1))
+            .max(open.saturating_sub(MAX_WIN_LOOKBACK));
+        BLOCKS.with_borrow(|v| {
+            for (height, block) in v.range(from..open) {
+                if let Some(winner) = block.winner.filter(|w| w.owner == user) {
+                    notifications.push(UserNotification::Won {
+                        height,
+                        btc_address: winner.btc_address,
+                    });
+                }
+            }
+        });
+    }
+    if let Some(within) = preferences.order_expiry_blocks {
+        if let Some(range) = DodService::get_user_range(user) {
+            if let (Some(last_height), Some(blocks_left)) =
+                (range.r.last(), expiring_in(range.r.end(), open, within))
+            {
+                notifications.push(UserNotification::OrderExpiring {
+                    last_height,
+                    blocks_left,
+                });
+            }
+        }
+    }
+    if let Some(threshold) = preferences.claimable_above {
+        if let Some(detail) = DodService::get_user_detail(user) {
+            let amount = detail.total_dod.saturating_sub(detail.claimed_dod);
+            if amount > threshold {
+                notifications.push(UserNotification::Claimable { amount });
+            }
+        }
+    }
+    notifications
+}
+
+#[cfg(test)]
+mod test {
+    use super::expiring_in;
+
+    #[test]
+    fn test_expiring_in() {
+        assert_eq!(expiring_in(110, 100, 10), Some(10));
+        assert_eq!(expiring_in(111, 100, 10), None);
+        assert_eq!(expiring_in(101, 100, 10), Some(1));
+        // the range is over
+        assert_eq!(expiring_in(100, 100, 10), None);
+        assert_eq!(expiring_in(90, 100, 10), None);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::common::derive_principal;
+use crate::memory::{POOLS, STAKERS};
+use crate::service::block::get_last_block;
+use crate::service::{buyback, miner, replication, staker};
+use candid::{Nat, Principal};
+use dod_utils::types::{
+    Height, MinerInfo, MinerSubmitResponse, MiningPool, PoolCandidate, PoolWorkerClaim,
+};
+use ic_stable_structures::storable::Blob;
+
+pub const MAX_POOL_NAME_LEN: usize = 64;
+/// Entries taken by one call, a pool splits larger batches.
+pub const MAX_POOL_BATCH: usize = 50;
+/// Entries a pool may submit per block unless the owners set another limit.
+pub const DEFAULT_POOL_CANDIDATES_PER_BLOCK: u64 = 200;
+
+const WORKER_DOMAIN: &[u8] = b"dod-pool-worker";
+
+/// The account owning the miner of worker `btc_address` in `pool`. Nobody holds a key for it,
+/// so workers need no IC identity, only the pool submits for them and claims what they win.
+pub fn derive_worker_account(pool: Principal, btc_address: &str) -> Principal {
+    derive_principal(WORKER_DOMAIN, pool, btc_address.as_bytes())
+}
+
+pub fn get_pool(pool: Principal) -> Option<MiningPool> {
+    POOLS.with_borrow(|v| v.get(&pool))
+}
+
+/// Registers `pool`, or renames it and changes its limit when it is registered already.
+pub fn register_pool(
+    pool: Principal,
+    name: String,
+    max_candidates_per_block: Option<u64>,
+) -> Result<MiningPool, String> {
+    if name.is_empty() || name.len() > MAX_POOL_NAME_LEN {
+        return Err(format!(
+            "Pool name must be 1 to {} bytes",
+            MAX_POOL_NAME_LEN
+        ));
+    }
+    let max_candidates_per_block =
+        max_candidates_per_block.unwrap_or(DEFAULT_POOL_CANDIDATES_PER_BLOCK);
+    let record = match get_pool(pool) {
+        Some(record) => MiningPool {
+            name,
+            max_candidates_per_block,
+            ..record
+        },
+        None => MiningPool {
+            name,
+            registered_at: ic_cdk::api::time(),
+            max_candidates_per_block,
+            workers: 0,
+            total_submitted: 0,
+            window_height: 0,
+            window_count: 0,
+        },
+    };
+    POOLS.with_borrow_mut(|v| v.insert(pool, record.clone()));
+    Ok(record)
+}
+
+/// Counts one entry against the limit of `pool` for block `height`, false once it is reached.
+pub fn take_quota(pool: &mut MiningPool, height: Height) -> bool {
+    if pool.window_height != height {
+        pool.window_height = height;
+        pool.window_count = 0;
+    }
+    if pool.window_count >= pool.max_candidates_per_block {
+        return false;
+    }
+    pool.window_count += 1;
+    true
+}
+
+/// The miner of the worker in `candidate`, registered for the pool on its first entry. Returns
+/// whether it was registered by this entry.
+fn worker_miner(pool: Principal, candidate: &PoolCandidate) -> Result<(MinerInfo, bool), String> {
+    let owner = derive_worker_account(pool, candidate.btc_address.as_str());
+    match miner::get_miner_by_address(candidate.btc_address.clone()) {
+        Some(miner) if miner.owner == owner => Ok((miner, false)),
+        Some(_) => Err("Worker address is registered to another owner".to_string()),
+        None => {
+            let ecdsa_pubkey = candidate
+                .ecdsa_pubkey
+                .clone()
+                .ok_or_else(|| "Worker not registered, its ecdsa_pubkey is needed".to_string())?;
+            let miner = miner::register_miner(owner, candidate.btc_address.clone(), ecdsa_pubkey)?;
+            Ok((miner, true))
+        }
+    }
+}
+
+/// Checks each entry of `pool` as a submission of its worker for the open block. Entries are
+/// independent, one failing does not fail the others.
+pub fn submit_candidates(
+    pool: Principal,
+    candidates: Vec<PoolCandidate>,
+) -> Result<Vec<Result<MinerSubmitResponse, String>>, String> {
+    let mut record = get_pool(pool).ok_or_else(|| "Pool not registered".to_string())?;
+    if candidates.len() > MAX_POOL_BATCH {
+        return Err(format!(
+            "At most {} candidates can be submitted at once",
+            MAX_POOL_BATCH
+        ));
+    }
+    let (height, _) = get_last_block().ok_or_else(|| "No block found".to_string())?;
+    let mut results = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        if !take_quota(&mut record, height) {
+            results.push(Err(
+                "Pool reached its candidate limit for this block".to_string()
+            ));
+            continue;
+        }
+        let res = worker_miner(pool, &candidate).and_then(|(miner, registered)| {
+            if registered {
+                record.workers += 1;
+            }
+            miner::submit_for_miner(
+                miner,
+                candidate.btc_address,
+                candidate.signed_commit_psbt,
+                candidate.signed_reveal_psbt,
+                candidate.cycles_price,
+                candidate.dod_bid,
+            )
+        });
+        if res.is_ok() {
+            record.total_submitted += 1;
+        }
+        results.push(res);
+    }
+    POOLS.with_borrow_mut(|v| v.insert(pool, record));
+    Ok(results)
+}
+
+/// Moves the cycles and miner DOD the worker at `btc_address` won to the staker account of
+/// `pool`, from where the pool burns or claims them like any staker.
+pub fn claim_worker_rewards(pool: Principal, btc_address: &str) -> Result<PoolWorkerClaim, String> {
+    get_pool(pool).ok_or_else(|| "Pool not registered".to_string())?;
+    let worker = derive_worker_account(pool, btc_address);
+    let miner = miner::get_miner_by_address(btc_address.to_string())
+        .filter(|miner| miner.owner == worker)
+        .ok_or_else(|| "Worker not registered for the pool".to_string())?;
+
+    let dod = miner::debit_miner_reward(miner.btc_address.as_str(), u64::MAX);
+    let worker_key = Blob::<29>::try_from(worker.as_slice()).expect("error transformation");
+    let cycles = STAKERS.with_borrow_mut(|v| match v.get(&worker_key) {
+        Some(mut detail) => {
+            let cycles = detail.balance.clone();
+            detail.balance = Nat::from(0u128);
+            replication::record_staker(&detail);
+            v.insert(worker_key, detail);
+            cycles
+        }
+        None => Nat::from(0u128),
+    });
+
+    staker::register_user(pool)?;
+    let pool_key = Blob::<29>::try_from(pool.as_slice()).expect("error transformation");
+    STAKERS.with_borrow_mut(|v| {
+        let mut detail = v.get(&pool_key).expect("pool registered as user");
+        detail.balance = detail.balance + cycles.clone();
+        let unclaimed = detail.total_dod.saturating_sub(detail.claimed_dod);
+        detail.total_dod = detail.total_dod.saturating_add(dod);
+        buyback::track_staker_unclaimed(
+            unclaimed,
+            detail.total_dod.saturating_sub(detail.claimed_dod),
+        );
+        replication::record_staker(&detail);
+        v.insert(pool_key, detail);
+    });
+    Ok(PoolWorkerClaim {
+        btc_address: miner.btc_address,
+        cycles: u128::try_from(cycles.0).unwrap_or(u128::MAX),
+        dod,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::MINERS;
+    use dod_utils::types::{BtcAddress, MinerStatus};
+
+    #[test]
+    fn test_take_quota() {
+        let mut pool = MiningPool {
+            name: "pool".to_string(),
+            registered_at: 0,
+            max_candidates_per_block: 2,
+            workers: 0,
+            total_submitted: 0,
+            window_height: 0,
+            window_count: 0,
+        };
+        assert!(take_quota(&mut pool, 10));
+        assert!(take_quota(&mut pool, 10));
+        assert!(!take_quota(&mut pool, 10));
+        // the limit starts over with the next block
+        assert!(take_quota(&mut pool, 11));
+        assert_eq!(pool.window_count, 1);
+    }
+
+    #[test]
+    fn test_derive_worker_account() {
+        let pool = Principal::anonymous();
+        assert_eq!(
+            derive_worker_account(pool, "bc1qa"),
+            derive_worker_account(pool, "bc1qa")
+        );
+        assert_ne!(
+            derive_worker_account(pool, "bc1qa"),
+            derive_worker_account(pool, "bc1qb")
+        );
+    }
+
+    #[test]
+    fn test_claim_worker_rewards() {
+        let pool = Principal::from_slice(&[7; 29]);
+        let worker = derive_worker_account(pool, "bc1qworker");
+        POOLS.with_borrow_mut(|v| {
+            v.insert(
+                pool,
+                MiningPool {
+                    name: "pool".to_string(),
+                    registered_at: 0,
+                    max_candidates_per_block: 2,
+                    workers: 1,
+                    total_submitted: 1,
+                    window_height: 0,
+                    window_count: 0,
+                },
+            )
+        });
+        MINERS.with_borrow_mut(|v| {
+            v.insert(
+                BtcAddress("bc1qworker".to_string()),
+                MinerInfo {
+                    owner: worker,
+                    status: MinerStatus::Activate,
+                    ecdsa_pubkey: vec![],
+                    btc_address: "bc1qworker".to_string(),
+                    reward_cycles: None,
+                    claimed_dod: 0,
+                    total_dod: 0,
+                },
+            )
+        });
+        // the worker wins a block, credited the way block generation credits a winner
+        staker::register_user(worker).unwrap();
+        crate::service::DodService::increase_user_cycle_balance(worker, Nat::from(300u128))
+            .unwrap();
+        miner::credit_miner_reward("bc1qworker", 1_000);
+
+        // only the pool the worker belongs to claims
+        assert!(claim_worker_rewards(Principal::anonymous(), "bc1qworker").is_err());
+        let claim = claim_worker_rewards(pool, "bc1qworker").unwrap();
+        assert_eq!((claim.cycles, claim.dod), (300, 1_000));
+
+        let pool_detail = crate::service::DodService::get_user_detail(pool).unwrap();
+        assert_eq!(pool_detail.balance, Nat::from(300u128));
+        assert_eq!(pool_detail.total_dod, 1_000);
+        assert_eq!(miner::unclaimed_dod("bc1qworker"), 0);
+        let worker_detail = crate::service::DodService::get_user_detail(worker).unwrap();
+        assert_eq!(worker_detail.balance, Nat::from(0u128));
+
+        // a second claim finds nothing left
+        let claim = claim_worker_rewards(pool, "bc1qworker").unwrap();
+        assert_eq!((claim.cycles, claim.dod), (0, 0));
+    }
+}
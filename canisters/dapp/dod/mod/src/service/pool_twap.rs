@@ -0,0 +1,117 @@
+use crate::memory::POOL_ACCUMULATORS;
+use crate::types::PoolAccumulator;
+use dod_utils::tcycles::to_tcycles;
+use dod_utils::types::{BlockData, Height, PoolTwap};
+
+/// Blocks a pool average spans at most.
+pub const MAX_TWAP_WINDOW: u64 = 10_000;
+
+/// The totals after block `height`, whose pool was `cycles` while it was open for `duration`.
+pub fn accumulate(
+    prev: &PoolAccumulator,
+    height: Height,
+    cycles: u128,
+    duration: u64,
+) -> PoolAccumulator {
+    PoolAccumulator {
+        height,
+        blocks: prev.blocks + 1,
+        cycles: prev.cycles.saturating_add(cycles),
+        weighted_cycles: prev
+            .weighted_cycles
+            .saturating_add(cycles.saturating_mul(duration as u128)),
+        duration: prev.duration.saturating_add(duration),
+    }
+}
+
+/// Pool averages of the blocks after `start` up to `end`.
+pub fn twap(window_blocks: u64, start: &PoolAccumulator, end: &PoolAccumulator) -> PoolTwap {
+    let blocks = end.blocks.saturating_sub(start.blocks);
+    let duration = end.duration.saturating_sub(start.duration);
+    let block_weighted = end
+        .cycles
+        .saturating_sub(start.cycles)
+        .checked_div(blocks as u128)
+        .unwrap_or(0);
+    let time_weighted = end
+        .weighted_cycles
+        .saturating_sub(start.weighted_cycles)
+        .checked_div(duration as u128)
+        .unwrap_or(0);
+    PoolTwap {
+        window_blocks,
+        from_height: if start.blocks == 0 {
+            0
+        } else {
+            start.height + 1
+        },
+        to_height: end.height,
+        blocks,
+        duration,
+        block_weighted,
+        time_weighted,
+        block_weighted_t: to_tcycles(block_weighted),
+        time_weighted_t: to_tcycles(time_weighted),
+    }
+}
+
+/// Adds the pool of the finalized `block` to the totals, once per block.
+pub fn record(block: &BlockData, cycles: u128) {
+    POOL_ACCUMULATORS.with_borrow_mut(|v| {
+        let prev = v.last_key_value().map(|(_, acc)| acc).unwrap_or_default();
+        if prev.blocks > 0 && prev.height >= block.height {
+            return;
+        }
+        let duration = block.next_block_time.saturating_sub(block.block_time);
+        v.insert(
+            block.height,
+            accumulate(&prev, block.height, cycles, duration),
+        );
+    })
+}
+
+/// Averages of the pools of the last `window_blocks` settled blocks, fewer when there are
+/// not as many.
+pub fn get_pool_twap(window_blocks: u64) -> Result<PoolTwap, String> {
+    if window_blocks == 0 || window_blocks > MAX_TWAP_WINDOW {
+        return Err(format!("Window must be 1 to {} blocks", MAX_TWAP_WINDOW));
+    }
+    POOL_ACCUMULATORS.with_borrow(|v| {
+        let (_, end) = v
+            .last_key_value()
+            .ok_or_else(|| "No block is settled".to_string())?;
+        let start = end
+            .height
+            .checked_sub(window_blocks)
+            .and_then(|height| v.range(..=height).next_back())
+            .map(|(_, acc)| acc)
+            .unwrap_or_default();
+        Ok(twap(window_blocks, &start, &end))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_twap() {
+        let mut accs = vec![PoolAccumulator::default()];
+        for (height, cycles, duration) in [(0, 100, 10), (1, 300, 10), (2, 1000, 30)] {
+            let next = accumulate(accs.last().unwrap(), height, cycles, duration);
+            accs.push(next);
+        }
+        let all = twap(3, &accs[0], &accs[3]);
+        assert_eq!((all.from_height, all.to_height, all.blocks), (0, 2, 3));
+        assert_eq!(all.block_weighted, 1400 / 3);
+        // 100 * 10 + 300 * 10 + 1000 * 30 over 50
+        assert_eq!(all.time_weighted, 680);
+
+        let last = twap(2, &accs[1], &accs[3]);
+        assert_eq!((last.from_height, last.blocks, last.duration), (1, 2, 40));
+        assert_eq!(last.block_weighted, 650);
+        assert_eq!(last.time_weighted, 33000 / 40);
+
+        assert_eq!(twap(1, &accs[3], &accs[3]).block_weighted, 0);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::memory::{CONFIG, PRIORITY_FEES, PRIORITY_STATS};
+use candid::Principal;
+use dod_utils::types::{Height, PriorityStats};
+
+/// Priority fee paid per block by the user's running order range, `0` without one.
+pub fn get_priority_fee(user: Principal) -> u128 {
+    PRIORITY_FEES.with_borrow(|v| v.get(&user).unwrap_or(0))
+}
+
+/// The fee follows the order range, so placing orders without a fee clears it.
+pub fn set_priority_fee(user: Principal, fee: Option<u128>) {
+    PRIORITY_FEES.with_borrow_mut(|v| match fee {
+        Some(fee) if fee > 0 => {
+            v.insert(user, fee);
+        }
+        _ => {
+            v.remove(&user);
+        }
+    })
+}
+
+/// Orders settled per block at most, `None` settles every order.
+pub fn get_settlement_capacity() -> Option<u64> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.settlement_capacity)
+    })
+}
+
+pub fn set_settlement_capacity(capacity: Option<u64>) -> Result<(), String> {
+    if capacity == Some(0) {
+        return Err("Settlement capacity can not be zero".to_string());
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.settlement_capacity = capacity;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+/// Whether an order misses the block, the capacity being taken by `settled` orders already.
+pub fn misses_block(capacity: Option<u64>, settled: u64) -> bool {
+    capacity.map_or(false, |capacity| settled >= capacity)
+}
+
+/// Only blocks with at least one paid priority fee or missed order are stored.
+pub fn write_priority_stats(stats: PriorityStats) {
+    if stats.prioritized_orders > 0 || stats.missed_orders.unwrap_or(0) > 0 {
+        PRIORITY_STATS.with_borrow_mut(|v| v.insert(stats.height, stats));
+    }
+}
+
+pub fn get_priority_stats(height: Height) -> Option<PriorityStats> {
+    PRIORITY_STATS.with_borrow(|v| v.get(&height))
+}
@@ -0,0 +1,126 @@
+use crate::memory::PSBT_BLOBS;
+use base64::Engine;
+use bitcoin::hashes::{sha256, Hash};
+use dod_utils::types::{BlockSigs, MinerCandidate, PsbtBlob};
+use ic_stable_structures::storable::Blob;
+
+fn blob_key(hash: &[u8]) -> Option<Blob<32>> {
+    Blob::<32>::try_from(hash).ok()
+}
+
+pub fn psbt_hash(bytes: &[u8]) -> Vec<u8> {
+    sha256::Hash::hash(bytes).to_byte_array().to_vec()
+}
+
+/// Keeps `bytes` under their sha256 and returns it. Bytes kept already only gain a reference.
+pub fn put(bytes: Vec<u8>) -> Vec<u8> {
+    let hash = psbt_hash(&bytes);
+    let key = blob_key(&hash).expect("sha256 is 32 bytes");
+    PSBT_BLOBS.with_borrow_mut(|v| {
+        let blob = match v.get(&key) {
+            Some(blob) => PsbtBlob {
+                refs: blob.refs + 1,
+                ..blob
+            },
+            None => PsbtBlob { bytes, refs: 1 },
+        };
+        v.insert(key, blob);
+    });
+    hash
+}
+
+pub fn get(hash: &[u8]) -> Option<Vec<u8>> {
+    let key = blob_key(hash)?;
+    PSBT_BLOBS.with_borrow(|v| v.get(&key).map(|blob| blob.bytes))
+}
+
+/// Drops a reference to the bytes under `hash`, they are removed with their last reference.
+pub fn release(hash: &[u8]) {
+    let key = match blob_key(hash) {
+        Some(key) => key,
+        None => return,
+    };
+    PSBT_BLOBS.with_borrow_mut(|v| match v.get(&key) {
+        Some(blob) if blob.refs > 1 => {
+            v.insert(
+                key,
+                PsbtBlob {
+                    refs: blob.refs - 1,
+                    ..blob
+                },
+            );
+        }
+        Some(_) => {
+            v.remove(&key);
+        }
+        None => {}
+    });
+}
+
+/// The candidate as kept in `CANDIDATES`: its PSBTs move to the blob store and only their
+/// hashes stay. PSBTs that are not base64 stay inline.
+pub fn pack_candidate(mut candidate: MinerCandidate) -> MinerCandidate {
+    let engine = base64::engine::general_purpose::STANDARD;
+    if let (Ok(commit), Ok(reveal)) = (
+        engine.decode(&candidate.signed_commit_psbt),
+        engine.decode(&candidate.signed_reveal_psbt),
+    ) {
+        candidate.signed_commit_psbt_hash = Some(put(commit));
+        candidate.signed_reveal_psbt_hash = Some(put(reveal));
+        candidate.signed_commit_psbt = String::new();
+        candidate.signed_reveal_psbt = String::new();
+    }
+    candidate
+}
+
+/// The candidate with its PSBTs back in base64. Candidates kept before the blob store are
+/// returned as they are.
+pub fn unpack_candidate(mut candidate: MinerCandidate) -> MinerCandidate {
+    let engine = base64::engine::general_purpose::STANDARD;
+    if let Some(commit) = candidate.signed_commit_psbt_hash.take() {
+        candidate.signed_commit_psbt = get(&commit).map_or_else(String::new, |b| engine.encode(b));
+    }
+    if let Some(reveal) = candidate.signed_reveal_psbt_hash.take() {
+        candidate.signed_reveal_psbt = get(&reveal).map_or_else(String::new, |b| engine.encode(b));
+    }
+    candidate
+}
+
+pub fn release_candidate(candidate: &MinerCandidate) {
+    for hash in [
+        &candidate.signed_commit_psbt_hash,
+        &candidate.signed_reveal_psbt_hash,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        release(hash);
+    }
+}
+
+/// The sigs as kept in `SIGS`, referencing the blob store.
+pub fn pack_sigs(commit_tx: Vec<u8>, reveal_tx: Vec<u8>) -> BlockSigs {
+    BlockSigs {
+        commit_tx: vec![],
+        reveal_tx: vec![],
+        commit_hash: Some(put(commit_tx)),
+        reveal_hash: Some(put(reveal_tx)),
+    }
+}
+
+/// The sigs with their transactions inline, sigs kept before the blob store already are.
+pub fn unpack_sigs(mut sigs: BlockSigs) -> BlockSigs {
+    if let Some(commit) = sigs.commit_hash.take() {
+        sigs.commit_tx = get(&commit).unwrap_or_default();
+    }
+    if let Some(reveal) = sigs.reveal_hash.take() {
+        sigs.reveal_tx = get(&reveal).unwrap_or_default();
+    }
+    sigs
+}
+
+pub fn release_sigs(sigs: &BlockSigs) {
+    for hash in [&sigs.commit_hash, &sigs.reveal_hash].into_iter().flatten() {
+        release(hash);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::memory::CONFIG;
+use crate::service::event::add_event;
+use crate::state::{info_log_add, owner_add, owners};
+use candid::Principal;
+use dod_utils::types::{EventKind, RecoverySettings, RecoveryStatus};
+
+pub fn record_owner_activity() {
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.last_owner_activity = Some(ic_cdk::api::time());
+        }
+    })
+}
+
+pub fn set_recovery_settings(
+    caller: Principal,
+    recovery_principal: Principal,
+    inactivity_window: u64,
+) -> Result<(), String> {
+    if recovery_principal == Principal::anonymous() {
+        return Err("Recovery principal can not be anonymous".to_string());
+    }
+    if inactivity_window == 0 {
+        return Err("Inactivity window can not be zero".to_string());
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.recovery_settings = Some(RecoverySettings {
+                    recovery_principal,
+                    inactivity_window,
+                });
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })?;
+    add_event(
+        caller,
+        EventKind::RecoveryConfigured {
+            recovery_principal,
+            inactivity_window,
+        },
+    );
+    Ok(())
+}
+
+pub fn cancel_recovery(caller: Principal) -> Result<(), String> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.recovery_settings = None;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })?;
+    add_event(caller, EventKind::RecoveryCancelled);
+    Ok(())
+}
+
+pub fn get_recovery_status() -> Result<RecoveryStatus, String> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .map(|dod_service| {
+                let claimable_at = match (
+                    dod_service.recovery_settings.as_ref(),
+                    dod_service.last_owner_activity,
+                ) {
+                    (Some(settings), Some(last)) => {
+                        Some(last.saturating_add(settings.inactivity_window))
+                    }
+                    _ => None,
+                };
+                RecoveryStatus {
+                    settings: dod_service.recovery_settings.clone(),
+                    last_owner_activity: dod_service.last_owner_activity,
+                    claimable_at,
+                }
+            })
+            .ok_or_else(|| "No service found".to_string())
+    })
+}
+
+/// The recovery principal becomes an owner only after no owner update has been
+/// observed for the whole inactivity window. Any owner activity resets the clock,
+/// and owners can revoke the recovery principal with `cancel_recovery`.
+pub fn claim_ownership(caller: Principal) -> Result<(), String> {
+    let status = get_recovery_status()?;
+    let settings = status
+        .settings
+        .ok_or_else(|| "Recovery is not configured".to_string())?;
+
+    if settings.recovery_principal != caller {
+        return Err("Caller is not the recovery principal".to_string());
+    }
+
+    let last_owner_activity = status
+        .last_owner_activity
+        .ok_or_else(|| "No owner activity recorded yet".to_string())?;
+    let claimable_at = status.claimable_at.unwrap_or(u64::MAX);
+    if ic_cdk::api::time() < claimable_at {
+        return Err(format!(
+            "Owners are still active, ownership can be claimed after {}",
+            claimable_at
+        ));
+    }
+
+    let already_owner = owners().map_or(false, |v| v.contains_key(&caller));
+    if !already_owner {
+        owner_add(caller);
+    }
+    info_log_add(format!("dod: ownership claimed by recovery principal {}", caller).as_str());
+    add_event(
+        caller,
+        EventKind::OwnershipClaimed {
+            last_owner_activity,
+        },
+    );
+    // the claim itself counts as owner activity, so the window starts over
+    record_owner_activity();
+    Ok(())
+}
@@ -0,0 +1,220 @@
+use crate::common::now;
+use crate::memory::{CONFIG, REFERRALS, REFERRAL_CODES, REFERRERS, STAKERS};
+use crate::service::{buyback, replication, staker, DodService};
+use bitcoin::hashes::{sha256, Hash};
+use candid::{Nat, Principal};
+use dod_utils::types::{RebateKind, ReferralInfo, ReferralSettings, ReferrerStats};
+use ic_stable_structures::storable::Blob;
+
+pub const MAX_REBATE_PERCENT: u8 = 50;
+const CODE_DOMAIN: &[u8] = b"dod-referral";
+const CODE_BYTES: usize = 8;
+
+pub fn get_referral_settings() -> Option<ReferralSettings> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.referral_settings.clone())
+    })
+}
+
+pub fn set_referral_settings(settings: ReferralSettings) -> Result<(), String> {
+    if settings.rebate_percent > MAX_REBATE_PERCENT {
+        return Err(format!(
+            "Rebate percent must be at most {}",
+            MAX_REBATE_PERCENT
+        ));
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.referral_settings = Some(settings);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+/// Starts or stops the program, keeping the configured rebate.
+pub fn set_referral_enabled(enabled: bool) -> Result<(), String> {
+    let settings =
+        get_referral_settings().ok_or_else(|| "Referral program is not configured".to_string())?;
+    set_referral_settings(ReferralSettings {
+        enabled,
+        ..settings
+    })
+}
+
+fn active_settings() -> Option<ReferralSettings> {
+    get_referral_settings().filter(|s| s.enabled)
+}
+
+/// The code of `user`, the same every time it is derived.
+pub fn referral_code(user: Principal) -> String {
+    let mut seed = CODE_DOMAIN.to_vec();
+    seed.extend_from_slice(user.as_slice());
+    hex::encode_upper(&sha256::Hash::hash(seed.as_slice()).to_byte_array()[..CODE_BYTES])
+}
+
+pub fn get_referrer_stats(user: Principal) -> Option<ReferrerStats> {
+    REFERRERS.with_borrow(|v| v.get(&user))
+}
+
+pub fn get_referral(user: Principal) -> Option<ReferralInfo> {
+    REFERRALS.with_borrow(|v| v.get(&user))
+}
+
+/// Publishes the referral code of a registered user.
+pub fn create_referral_code(user: Principal) -> Result<String, String> {
+    if let Some(stats) = get_referrer_stats(user) {
+        return Ok(stats.code);
+    }
+    if DodService::get_user_detail(user).is_none() {
+        return Err("Only registered users can refer".to_string());
+    }
+    let code = referral_code(user);
+    if REFERRAL_CODES.with_borrow(|v| v.contains_key(&code)) {
+        return Err("Referral code already taken".to_string());
+    }
+    REFERRAL_CODES.with_borrow_mut(|v| v.insert(code.clone(), user));
+    REFERRERS.with_borrow_mut(|v| {
+        v.insert(
+            user,
+            ReferrerStats {
+                code: code.clone(),
+                referred_users: 0,
+                rebate_cycles: 0,
+                rebate_dod: 0,
+            },
+        )
+    });
+    Ok(code)
+}
+
+/// Registers a new user referred by the owner of `code`.
+pub fn register_with_referral(user: Principal, code: String) -> Result<ReferralInfo, String> {
+    if active_settings().is_none() {
+        return Err("Referral program is not running".to_string());
+    }
+    if DodService::get_user_detail(user).is_some() {
+        return Err("User already registered".to_string());
+    }
+    let referrer = REFERRAL_CODES
+        .with_borrow(|v| v.get(&code))
+        .ok_or_else(|| "Referral code not found".to_string())?;
+    if referrer == user {
+        return Err("Can not refer yourself".to_string());
+    }
+    // the referrer was referred by this user, the two would rebate each other
+    if get_referral(referrer).map_or(false, |r| r.referrer == user) {
+        return Err("Can not refer your own referrer".to_string());
+    }
+
+    staker::register_user(user)?;
+    let referral = ReferralInfo {
+        referrer,
+        code,
+        registered_at: now(),
+    };
+    REFERRALS.with_borrow_mut(|v| v.insert(user, referral.clone()));
+    REFERRERS.with_borrow_mut(|v| {
+        if let Some(mut stats) = v.get(&referrer) {
+            stats.referred_users += 1;
+            v.insert(referrer, stats);
+        }
+    });
+    Ok(referral)
+}
+
+/// Credits the referrer of `user` with the rebate on `burned` cycles. `reward` and
+/// `total_cycles` are the stakers reward and cycles of the block, to value a DOD rebate.
+/// Returns the cycles rebated, which are not burned, and the DOD rebated, which the treasury
+/// does not burn.
+pub fn rebate(user: Principal, burned: u128, reward: u64, total_cycles: u128) -> (u128, u64) {
+    let settings = match active_settings() {
+        Some(settings) if settings.rebate_percent > 0 => settings,
+        _ => return (0, 0),
+    };
+    let referrer = match get_referral(user) {
+        Some(referral) => referral.referrer,
+        None => return (0, 0),
+    };
+    let cycles = burned * settings.rebate_percent as u128 / 100;
+    if cycles == 0 {
+        return (0, 0);
+    }
+    let referrer_detail = match DodService::get_user_detail(referrer) {
+        Some(detail) => detail,
+        None => return (0, 0),
+    };
+    let blob29 = Blob::<29>::try_from(referrer.as_slice()).expect("error transformation");
+
+    match settings.kind {
+        RebateKind::Cycles => {
+            STAKERS.with_borrow_mut(|v| {
+                let mut detail = referrer_detail;
+                detail.balance = detail.balance + Nat::from(cycles);
+                replication::record_staker(&detail);
+                v.insert(blob29, detail);
+            });
+            update_stats(referrer, |s| {
+                s.rebate_cycles = s.rebate_cycles.saturating_add(cycles)
+            });
+            (cycles, 0)
+        }
+        RebateKind::Dod => {
+            let dod =
+                (staker::scaled_reward(reward, cycles, total_cycles) / staker::REWARD_SCALE) as u64;
+            if dod > 0 {
+                STAKERS.with_borrow_mut(|v| {
+                    let mut detail = referrer_detail;
+                    let unclaimed = detail.total_dod.saturating_sub(detail.claimed_dod);
+                    detail.total_dod = detail.total_dod.saturating_add(dod);
+                    buyback::track_staker_unclaimed(
+                        unclaimed,
+                        detail.total_dod.saturating_sub(detail.claimed_dod),
+                    );
+                    replication::record_staker(&detail);
+                    v.insert(blob29, detail);
+                });
+                update_stats(referrer, |s| {
+                    s.rebate_dod = s.rebate_dod.saturating_add(dod)
+                });
+            }
+            (0, dod)
+        }
+    }
+}
+
+fn update_stats(referrer: Principal, f: impl FnOnce(&mut ReferrerStats)) {
+    REFERRERS.with_borrow_mut(|v| {
+        if let Some(mut stats) = v.get(&referrer) {
+            f(&mut stats);
+            v.insert(referrer, stats);
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_referral_code() {
+        let alice = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+        let bob = Principal::from_text("rkp4c-7iaaa-aaaaa-aaaca-cai").unwrap();
+
+        let code = referral_code(alice);
+        assert_eq!(code, referral_code(alice));
+        assert_eq!(code.len(), CODE_BYTES * 2);
+        assert!(code
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase()));
+        assert_ne!(code, referral_code(bob));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::common::now;
+use crate::memory::REPLICATION_LOG;
+use crate::service::config;
+use dod_utils::types::{ReplicationInfo, StakerBalance, StateChange, StateDelta, UserDetail};
+use ic_stable_structures::Storable;
+
+/// Bumped whenever `StateChange` changes in a way an older replica can not apply.
+pub const REPLICATION_PROTOCOL_VERSION: u32 = 2;
+pub const MAX_DELTAS_PER_CALL: u64 = 500;
+/// Encoded size of the deltas returned by one call, well under the reply limit.
+pub const MAX_DELTA_BYTES_PER_CALL: usize = 1_500_000;
+/// Deltas kept in the log, older ones are pruned as new ones are written.
+pub const REPLICATION_LOG_RETENTION: u64 = 200_000;
+const PRUNE_BATCH: usize = 16;
+
+pub fn record(change: StateChange) -> u64 {
+    REPLICATION_LOG.with_borrow_mut(|v| {
+        let seq = v.last_key_value().map_or(0, |(k, _)| k + 1);
+        v.insert(
+            seq,
+            StateDelta {
+                seq,
+                time: now(),
+                change,
+            },
+        );
+        // bounded work per write, the log shrinks back under the retention over a few writes
+        for _ in 0..PRUNE_BATCH {
+            if v.len() <= REPLICATION_LOG_RETENTION {
+                break;
+            }
+            match v.first_key_value() {
+                Some((k, _)) => v.remove(&k),
+                None => break,
+            };
+        }
+        seq
+    })
+}
+
+/// Records the balances of `user` as they were just written.
+pub fn record_staker(user: &UserDetail) -> u64 {
+    record(StateChange::StakerUpdated(StakerBalance {
+        principal: user.principal,
+        balance: u128::try_from(user.balance.0.clone()).unwrap_or(u128::MAX),
+        claimed_dod: user.claimed_dod,
+        total_dod: user.total_dod,
+    }))
+}
+
+pub fn replication_info() -> ReplicationInfo {
+    REPLICATION_LOG.with_borrow(|v| ReplicationInfo {
+        protocol_version: REPLICATION_PROTOCOL_VERSION,
+        first_seq: v.first_key_value().map(|(k, _)| k),
+        last_seq: v.last_key_value().map(|(k, _)| k),
+        genesis: config::get_genesis_info(),
+    })
+}
+
+/// Checks the protocol version of a replica, only replicas speaking the same version may sync.
+pub fn handshake(replica_version: u32) -> Result<ReplicationInfo, String> {
+    if replica_version != REPLICATION_PROTOCOL_VERSION {
+        return Err(format!(
+            "Replication protocol version {} is not supported, expected {}",
+            replica_version, REPLICATION_PROTOCOL_VERSION
+        ));
+    }
+    Ok(replication_info())
+}
+
+/// Deltas after `since_seq`, oldest first, as many as fit in `MAX_DELTA_BYTES_PER_CALL` but
+/// at least one. Fails when deltas the replica has not seen were already pruned, the replica
+/// then has to resync before following the log again.
+pub fn get_deltas(since_seq: Option<u64>, limit: u64) -> Result<Vec<StateDelta>, String> {
+    let from = since_seq.map_or(0, |s| s.saturating_add(1));
+    REPLICATION_LOG.with_borrow(|v| {
+        if let Some((first, _)) = v.first_key_value() {
+            if from < first {
+                return Err(format!(
+                    "Deltas before {} were pruned, resync required",
+                    first
+                ));
+            }
+        }
+        let mut bytes = 0usize;
+        let mut deltas = vec![];
+        for (_, delta) in v
+            .range(from..)
+            .take(limit.min(MAX_DELTAS_PER_CALL) as usize)
+        {
+            bytes = bytes.saturating_add(delta.to_bytes().len());
+            if bytes > MAX_DELTA_BYTES_PER_CALL && !deltas.is_empty() {
+                break;
+            }
+            deltas.push(delta);
+        }
+        Ok(deltas)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use candid::Principal;
+
+    #[test]
+    fn test_get_deltas_capped_by_bytes() {
+        let stakers: Vec<StakerBalance> = (0..1_000u32)
+            .map(|i| StakerBalance {
+                principal: Principal::from_slice(&i.to_be_bytes()),
+                balance: u128::MAX,
+                claimed_dod: u64::MAX,
+                total_dod: u64::MAX,
+            })
+            .collect();
+        for height in 0..40 {
+            record(StateChange::Settled {
+                height,
+                stakers: stakers.clone(),
+            });
+        }
+        let deltas = get_deltas(None, MAX_DELTAS_PER_CALL).unwrap();
+        assert!(!deltas.is_empty() && deltas.len() < 40);
+        let bytes: usize = deltas.iter().map(|d| d.to_bytes().len()).sum();
+        assert!(bytes <= MAX_DELTA_BYTES_PER_CALL);
+        // the next page goes on where this one stopped
+        let next = get_deltas(Some(deltas.last().unwrap().seq), MAX_DELTAS_PER_CALL).unwrap();
+        assert_eq!(next[0].seq, deltas.last().unwrap().seq + 1);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::service::miner;
+use dod_utils::types::{DifficultyTiers, Height, RewardPolicy, TierBonusTarget};
+
+/// Splits `reward` into the (winner, stakers, treasury) shares of the policy, the rounding
+/// dust goes to the treasury.
+pub fn split_reward(policy: &RewardPolicy, reward: u64) -> (u64, u64, u64) {
+    let share = |percent: u8| (reward as u128 * percent as u128 / 100) as u64;
+    let winner = share(policy.winner_percent);
+    let stakers = share(policy.stakers_percent);
+    (winner, stakers, reward - winner - stakers)
+}
+
+/// Bonus of the difficulty tier the winning hash reached, when the tiers pay it on `target`.
+pub fn tier_bonus_percent(
+    tiers: Option<&DifficultyTiers>,
+    target: TierBonusTarget,
+    match_depth: Option<u64>,
+) -> u16 {
+    tiers
+        .filter(|t| t.target == target)
+        .map_or(0, |t| t.bonus_percent(match_depth.unwrap_or(0)))
+}
+
+pub fn apply_bonus(amount: u128, bonus_percent: u16) -> u128 {
+    amount.saturating_add(amount.saturating_mul(bonus_percent as u128) / 100)
+}
+
+/// Credits the winner share of a finalized block to the winning miner and returns the DOD to
+/// burn from the treasury, which also takes the shares nobody was there to receive. A winner
+/// bonus is paid out of the treasury share and never exceeds it.
+pub fn allocate(
+    height: Height,
+    policy: &RewardPolicy,
+    block_reward: u64,
+    winner: Option<&str>,
+    winner_bonus_percent: u16,
+    has_stakers: bool,
+) -> u64 {
+    let (winner_share, stakers_share, mut burn) = split_reward(policy, block_reward);
+    match winner {
+        Some(btc_address) => {
+            let bonus = (apply_bonus(winner_share as u128, winner_bonus_percent) as u64
+                - winner_share)
+                .min(burn);
+            burn -= bonus;
+            miner::credit_winner_reward(height, btc_address, winner_share + bonus)
+        }
+        None => burn += winner_share,
+    }
+    if !has_stakers {
+        burn += stakers_share;
+    }
+    burn
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dod_utils::types::DifficultyTier;
+
+    #[test]
+    fn test_split_reward() {
+        let policy = RewardPolicy {
+            winner_percent: 10,
+            stakers_percent: 60,
+            treasury_percent: 30,
+            max_exposure: None,
+        };
+        assert_eq!(split_reward(&policy, 1000), (100, 600, 300));
+        assert_eq!(split_reward(&policy, 999), (99, 599, 301));
+        assert!(policy.validate().is_ok());
+
+        let policy = RewardPolicy {
+            winner_percent: 10,
+            stakers_percent: 60,
+            treasury_percent: 31,
+            max_exposure: None,
+        };
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn test_tier_bonus_percent() {
+        let tiers = DifficultyTiers {
+            tiers: vec![
+                DifficultyTier {
+                    extra_digits: 1,
+                    bonus_percent: 10,
+                },
+                DifficultyTier {
+                    extra_digits: 3,
+                    bonus_percent: 50,
+                },
+            ],
+            target: TierBonusTarget::Cycles,
+        };
+        assert!(tiers.validate().is_ok());
+        let bonus = |depth| tier_bonus_percent(Some(&tiers), TierBonusTarget::Cycles, depth);
+        assert_eq!(bonus(None), 0);
+        assert_eq!(bonus(Some(0)), 0);
+        assert_eq!(bonus(Some(2)), 10);
+        assert_eq!(bonus(Some(7)), 50);
+        assert_eq!(
+            tier_bonus_percent(Some(&tiers), TierBonusTarget::Dod, Some(7)),
+            0
+        );
+        assert_eq!(apply_bonus(1000, 50), 1500);
+        assert_eq!(apply_bonus(1000, 0), 1000);
+
+        let unordered = DifficultyTiers {
+            tiers: tiers.tiers.iter().rev().cloned().collect(),
+            target: TierBonusTarget::Dod,
+        };
+        assert!(unordered.validate().is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::memory::BLOCKS;
+use crate::service::{config, DodService};
+use dod_utils::types::{BlockData, Height, ScheduleAnchor, ScheduledBlock};
+
+pub const MAX_SCHEDULE_BLOCKS: u64 = 100;
+/// Slots a block can open late and still catch up with the schedule, later the schedule
+/// starts over from it.
+pub const MAX_CATCH_UP_SLOTS: u64 = 10;
+
+/// Open, close and cutoff times of the block `offset` (at least 1) blocks after the open one, whose
+/// submissions stop at `cutoff`. A block is opened when the one before it is settled, right
+/// after its cutoff.
+pub fn slot_times(cutoff: u64, offset: u64, interval: u64, grace: u64) -> (u64, u64, u64) {
+    let open_at = cutoff + (offset - 1) * (interval + grace);
+    (open_at, open_at + interval, open_at + interval + grace)
+}
+
+/// Time block `height` opens at on the schedule of `anchor`, one block every `slot`.
+pub fn scheduled_open(anchor: ScheduleAnchor, height: Height, slot: u64) -> u64 {
+    anchor
+        .time
+        .saturating_add(height.saturating_sub(anchor.height).saturating_mul(slot))
+}
+
+/// Close time of block `height` opening at `now`, and the anchor of the schedule after it. A
+/// late block still closes at its scheduled time, with at least half an interval to mine, so
+/// the blocks after it catch up with the schedule instead of shifting it. A block more than
+/// `MAX_CATCH_UP_SLOTS` slots late is anchored at `now`.
+pub fn next_block_close(
+    anchor: ScheduleAnchor,
+    height: Height,
+    now: u64,
+    interval: u64,
+    grace: u64,
+) -> (u64, ScheduleAnchor) {
+    let slot = interval + grace;
+    let open_at = scheduled_open(anchor, height, slot);
+    if now.saturating_sub(open_at) > MAX_CATCH_UP_SLOTS.saturating_mul(slot) {
+        return (now + interval, ScheduleAnchor { height, time: now });
+    }
+    ((open_at + interval).max(now + interval / 2), anchor)
+}
+
+/// Anchor of the block schedule, the genesis block unless the schedule was anchored since.
+pub fn get_schedule_anchor() -> ScheduleAnchor {
+    config::get_schedule_anchor().unwrap_or_else(|| ScheduleAnchor {
+        height: 0,
+        time: BLOCKS.with_borrow(|v| v.get(&0).map_or(0, |block| block.block_time)),
+    })
+}
+
+/// The open block and the `n - 1` blocks expected after it, with the current interval, grace
+/// and reward schedule. Blocks after the open one use its difficulty, which may be adjusted
+/// as blocks are solved or not.
+pub fn get_block_schedule(n: u64) -> Result<Vec<ScheduledBlock>, String> {
+    let (height, open) = DodService::get_last_block().ok_or("No block found")?;
+    let interval = DodService::get_block_time_interval()?;
+    let grace = DodService::get_submission_grace();
+    let halving_settings = DodService::get_halving_settings();
+    (0..n.min(MAX_SCHEDULE_BLOCKS))
+        .map(|offset| {
+            let ((open_at, close_at, submission_cutoff), reward) = if offset == 0 {
+                (
+                    (
+                        open.block_time,
+                        open.next_block_time,
+                        open.submission_cutoff(),
+                    ),
+                    open.rewards,
+                )
+            } else {
+                (
+                    slot_times(open.submission_cutoff(), offset, interval, grace),
+                    DodService::get_block_reward_by_height(
+                        height + offset,
+                        halving_settings.clone(),
+                    )?,
+                )
+            };
+            Ok(ScheduledBlock {
+                height: height + offset,
+                open_at,
+                close_at,
+                submission_cutoff,
+                difficulty: open.difficulty.clone(),
+                reward,
+            })
+        })
+        .collect()
+}
+
+/// Open and close times of block `height`, estimated with `slot_times` when it comes after the
+/// open block `open`. `None` for a block that is not kept.
+pub fn block_times(
+    height: Height,
+    open: &BlockData,
+    interval: u64,
+    grace: u64,
+) -> Option<(u64, u64, bool)> {
+    if height > open.height {
+        let (open_at, close_at, _) = slot_times(
+            open.submission_cutoff(),
+            height - open.height,
+            interval,
+            grace,
+        );
+        return Some((open_at, close_at, true));
+    }
+    BLOCKS
+        .with_borrow(|v| v.get(&height))
+        .map(|block| (block.block_time, block.next_block_time, false))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slot_times() {
+        // the open block stops taking submissions at 170
+        assert_eq!(slot_times(170, 1, 60, 10), (170, 230, 240));
+        assert_eq!(slot_times(170, 2, 60, 10), (240, 300, 310));
+        assert_eq!(slot_times(160, 2, 60, 0), (220, 280, 280));
+    }
+
+    #[test]
+    fn test_next_block_close() {
+        let anchor = ScheduleAnchor {
+            height: 0,
+            time: 1000,
+        };
+        assert_eq!(scheduled_open(anchor, 3, 70), 1210);
+        // on time, the block gets the full interval
+        assert_eq!(next_block_close(anchor, 3, 1210, 60, 10), (1270, anchor));
+        // late, it still closes on the schedule
+        assert_eq!(next_block_close(anchor, 3, 1225, 60, 10), (1270, anchor));
+        // later, it keeps half an interval and the next blocks catch up
+        assert_eq!(next_block_close(anchor, 3, 1260, 60, 10), (1290, anchor));
+        assert_eq!(next_block_close(anchor, 4, 1300, 60, 10), (1340, anchor));
+        // too late to catch up, the schedule starts over from it
+        assert_eq!(
+            next_block_close(anchor, 3, 1211 + 700, 60, 10),
+            (
+                1971,
+                ScheduleAnchor {
+                    height: 3,
+                    time: 1911
+                }
+            )
+        );
+        assert_eq!(next_block_close(anchor, 3, 1210 + 700, 60, 10).1, anchor);
+    }
+}
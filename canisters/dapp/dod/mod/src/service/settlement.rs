@@ -0,0 +1,64 @@
+use crate::common::now;
+use crate::memory::CONFIG;
+use dod_utils::types::{Height, MinerInfo, PriorityStats, SettlementCheckpoint, SettlementPhase};
+
+/// Orders settled per timer tick, a larger block is settled over several ticks.
+pub const SETTLEMENT_BATCH: usize = 1_000;
+/// Delay before the next batch of an unfinished settlement.
+pub const SETTLEMENT_RESUME_DELAY: u64 = 1_000_000_000;
+
+pub fn get_checkpoint() -> Option<SettlementCheckpoint> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.settlement.clone())
+    })
+}
+
+pub fn save_checkpoint(checkpoint: SettlementCheckpoint) {
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.settlement = Some(checkpoint);
+        }
+    })
+}
+
+pub fn begin(
+    height: Height,
+    cycle_deposit: u128,
+    refund_percent: u8,
+    winner: Option<MinerInfo>,
+) -> SettlementCheckpoint {
+    let now = now();
+    SettlementCheckpoint {
+        height,
+        phase: SettlementPhase::Balances,
+        cycle_deposit,
+        refund_percent,
+        winner,
+        winner_dod_bid: None,
+        last_key: None,
+        refunded: 0,
+        stats: PriorityStats {
+            height,
+            ..Default::default()
+        },
+        started_at: now,
+        updated_at: now,
+        dod_rebates: None,
+        lock_in_bonuses: None,
+    }
+}
+
+/// The unfinished settlement of `height`, if it was started in an earlier tick.
+pub fn pending(height: Height) -> Option<SettlementCheckpoint> {
+    get_checkpoint().filter(|c| c.height == height && c.phase == SettlementPhase::Balances)
+}
+
+/// Whether block `height` may be followed by the next one. Blocks settled before
+/// checkpoints existed have none and count as complete.
+pub fn is_complete(height: Height) -> bool {
+    pending(height).is_none()
+}
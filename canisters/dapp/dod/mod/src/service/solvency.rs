@@ -0,0 +1,102 @@
+use crate::memory::{PENDING_CLAIMS, SOLVENCY_REPORT};
+use crate::service::buyback::{miner_unclaimed, staker_unclaimed};
+use crate::service::config::{get_dod_block_account, get_token_canister};
+use crate::types::ClaimSource;
+use candid::Nat;
+use dod_utils::types::SolvencyReport;
+use ic_cdk::id;
+use icrc_ledger_types::icrc1::account::Account;
+
+/// A report younger than this is returned again instead of asking the ledger, so anyone can
+/// call `verify_solvency` without flooding it.
+pub const SOLVENCY_CHECK_INTERVAL: u64 = 10 * 60 * 1_000_000_000;
+
+/// Compares `treasury_balance` with the unclaimed rewards of stakers and miners.
+pub fn solvency_report(
+    treasury_balance: u64,
+    staker_unclaimed: u64,
+    miner_unclaimed: u64,
+    pending_claims: u64,
+    checked_at: u64,
+) -> SolvencyReport {
+    let owed = staker_unclaimed.saturating_add(miner_unclaimed);
+    SolvencyReport {
+        checked_at,
+        treasury_balance,
+        staker_unclaimed,
+        miner_unclaimed,
+        pending_claims,
+        surplus: treasury_balance.saturating_sub(owed),
+        deficit: owed.saturating_sub(treasury_balance),
+        solvent: treasury_balance >= owed,
+    }
+}
+
+/// DOD held by pending staker and miner claims, asset claims are paid from their own subaccount.
+pub fn pending_claims() -> u64 {
+    PENDING_CLAIMS.with_borrow(|v| {
+        v.iter()
+            .flat_map(|(_, pending)| pending.claims)
+            .filter(|c| !matches!(c.source, ClaimSource::Asset { .. }))
+            .fold(0u64, |acc, c| acc.saturating_add(c.amount))
+    })
+}
+
+pub fn get_solvency_report() -> Option<SolvencyReport> {
+    SOLVENCY_REPORT.with_borrow(|v| v.clone())
+}
+
+/// Checks the treasury balance on the ledger against what it owes, unless the last check is
+/// younger than `SOLVENCY_CHECK_INTERVAL`.
+pub async fn verify_solvency() -> Result<SolvencyReport, String> {
+    let now = ic_cdk::api::time();
+    if let Some(report) = get_solvency_report() {
+        if now < report.checked_at.saturating_add(SOLVENCY_CHECK_INTERVAL) {
+            return Ok(report);
+        }
+    }
+    let token_canister = get_token_canister()?;
+    let (balance,): (Nat,) = ic_cdk::call(
+        token_canister,
+        "icrc1_balance_of",
+        (Account {
+            owner: id(),
+            subaccount: Some(get_dod_block_account()?),
+        },),
+    )
+    .await
+    .map_err(|(code, msg)| format!("icrc1_balance_of code: {:?}, msg: {}", code, msg))?;
+    // balances and claims are read after the call, the ones read before may have moved meanwhile
+    let report = solvency_report(
+        u64::try_from(balance.0).unwrap_or(u64::MAX),
+        staker_unclaimed(),
+        miner_unclaimed(),
+        pending_claims(),
+        ic_cdk::api::time(),
+    );
+    SOLVENCY_REPORT.with_borrow_mut(|v| *v = Some(report.clone()));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_solvency_report() {
+        let report = solvency_report(1000, 600, 300, 0, 7);
+        assert!(report.solvent);
+        assert_eq!(report.surplus, 100);
+        assert_eq!(report.deficit, 0);
+        assert_eq!(report.checked_at, 7);
+
+        let report = solvency_report(800, 600, 300, 100, 7);
+        assert!(!report.solvent);
+        assert_eq!(report.surplus, 0);
+        assert_eq!(report.deficit, 100);
+
+        let report = solvency_report(0, u64::MAX, u64::MAX, 0, 0);
+        assert_eq!(report.deficit, u64::MAX);
+        assert!(solvency_report(900, 600, 300, 0, 0).solvent);
+    }
+}
@@ -1,9 +1,11 @@
-use crate::common::CYCLES_BURNER_FEE;
-use crate::memory::STAKERS;
-use crate::types::UserDetail;
+use crate::common::{now, CYCLES_BURNER_FEE};
+use crate::memory::{STAKERS, USER_STATS};
+use crate::types::{UserDetail, UserStats};
 use candid::{Nat, Principal};
-use ic_ledger_types::Subaccount;
+use dod_utils::types::DepositAccount;
+use ic_ledger_types::{AccountIdentifier, Subaccount};
 use ic_stable_structures::storable::Blob;
+use icrc_ledger_types::icrc1::account::Account;
 
 pub fn user_set_burnrate(user: Principal, burn_rate: u128) -> Result<(), String> {
     if burn_rate < CYCLES_BURNER_FEE {
@@ -56,9 +58,136 @@ pub fn register_user(user: Principal) -> Result<(), String> {
                     claimed_dod: 0,
                     total_dod: 0,
                     cycle_burning_rate: 0,
+                    reward_remainder: None,
                 },
             );
         });
     }
     Ok(())
 }
+
+/// The account of `canister` under the subaccount derived from `user`, deposits are staked from it.
+pub fn deposit_account(canister: Principal, user: Principal) -> DepositAccount {
+    let subaccount = Subaccount::from(user);
+    DepositAccount {
+        account_id: AccountIdentifier::new(&canister, &subaccount).to_string(),
+        icrc1_account: Account {
+            owner: canister,
+            subaccount: Some(subaccount.0),
+        }
+        .to_string(),
+        subaccount: subaccount.0,
+    }
+}
+
+pub fn get_user_stats(user: Principal) -> UserStats {
+    USER_STATS.with_borrow(|v| v.get(&user).unwrap_or_default())
+}
+
+fn update_user_stats(user: Principal, f: impl FnOnce(&mut UserStats)) {
+    USER_STATS.with_borrow_mut(|v| {
+        let mut stats = v.get(&user).unwrap_or_default();
+        stats.first_activity.get_or_insert(now());
+        f(&mut stats);
+        v.insert(user, stats);
+    })
+}
+
+pub fn record_deposit(user: Principal, cycles: u128) {
+    update_user_stats(user, |stats| {
+        stats.cycles_deposited = stats.cycles_deposited.saturating_add(cycles);
+    })
+}
+
+/// Called once per settled order, `burned` is what the block finally kept from the user.
+pub fn record_participation(user: Principal, block: u64, burned: u128) {
+    update_user_stats(user, |stats| {
+        stats.cycles_burned = stats.cycles_burned.saturating_add(burned);
+        stats.blocks_participated += 1;
+        stats.last_block = Some(block);
+    })
+}
+
+/// Rewards are split in e8s of a DOD unit, so shares below one unit are not lost to rounding.
+pub const REWARD_SCALE: u128 = 100_000_000;
+
+/// `reward * bet / total_cycles` scaled by `REWARD_SCALE`, computed without floats.
+pub fn scaled_reward(reward: u64, bet: u128, total_cycles: u128) -> u128 {
+    if bet == 0 || total_cycles == 0 {
+        return 0;
+    }
+    let scaled =
+        Nat::from(reward) * Nat::from(bet) * Nat::from(REWARD_SCALE) / Nat::from(total_cycles);
+    u128::try_from(scaled.0).unwrap_or(u128::MAX)
+}
+
+/// Splits a scaled reward plus the `remainder` carried from earlier blocks into the whole
+/// units to pay and the new remainder.
+pub fn settle_scaled_reward(scaled: u128, remainder: u64) -> (u64, u64) {
+    let total = scaled + remainder as u128;
+    (
+        u64::try_from(total / REWARD_SCALE).unwrap_or(u64::MAX),
+        (total % REWARD_SCALE) as u64,
+    )
+}
+
+/// DOD earned by `bet` cycles per block over `blocks` blocks of `reward`, from the largest
+/// to the smallest of the `pools` deposited by the other stakers.
+pub fn projected_reward_range(reward: u64, bet: u128, blocks: u64, pools: &[u128]) -> (u64, u64) {
+    let per_block = |pool: u128| {
+        u64::try_from(scaled_reward(reward, bet, bet.saturating_add(pool)) / REWARD_SCALE)
+            .unwrap_or(u64::MAX)
+    };
+    let low = per_block(pools.iter().max().copied().unwrap_or(0));
+    let high = per_block(pools.iter().min().copied().unwrap_or(0));
+    (low.saturating_mul(blocks), high.saturating_mul(blocks))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_projected_reward_range() {
+        assert_eq!(projected_reward_range(1000, 100, 10, &[]), (10_000, 10_000));
+        assert_eq!(
+            projected_reward_range(1000, 100, 10, &[100, 300, 900]),
+            (1_000, 5_000)
+        );
+        assert_eq!(projected_reward_range(1000, 0, 10, &[100]), (0, 0));
+    }
+
+    #[test]
+    fn test_deposit_account() {
+        let canister = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+        let user = Principal::from_text("rkp4c-7iaaa-aaaaa-aaaca-cai").unwrap();
+
+        let account = deposit_account(canister, user);
+        assert_eq!(account.subaccount, Subaccount::from(user).0);
+        assert_eq!(account.account_id.len(), 64);
+        assert_eq!(
+            account.account_id,
+            AccountIdentifier::new(&canister, &Subaccount::from(user)).to_string()
+        );
+        let parsed: Account = account.icrc1_account.parse().unwrap();
+        assert_eq!(parsed.owner, canister);
+        assert_eq!(parsed.subaccount, Some(account.subaccount));
+        assert_ne!(account, deposit_account(canister, canister));
+    }
+
+    #[test]
+    fn test_scaled_reward() {
+        assert_eq!(scaled_reward(1000, 1, 3), 33_333_333_333);
+        assert_eq!(scaled_reward(1000, 0, 3), 0);
+        assert_eq!(scaled_reward(1000, 1, 0), 0);
+        // a share worth a third of a unit
+        assert_eq!(scaled_reward(1, 1, 3), 33_333_333);
+
+        assert_eq!(settle_scaled_reward(33_333_333_333, 0), (333, 33_333_333));
+        assert_eq!(
+            settle_scaled_reward(33_333_333, 33_333_333),
+            (0, 66_666_666)
+        );
+        assert_eq!(settle_scaled_reward(33_333_333, 66_666_667), (1, 0));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::memory::{CLAIM_CURSORS, CLAIM_STATEMENTS, MINER_REWARDS, NEW_BLOCK_ORDERS};
+use crate::orders::NewBlockOrders;
+use crate::service::DodService;
+use crate::types::{ClaimCursor, ClaimSource, ClaimStatement, ClaimedBlock};
+use candid::{Nat, Principal};
+use dod_utils::types::{BlockRange, Height, OrderStatus};
+use icrc_ledger_types::icrc1::account::Account;
+
+/// Blocks a claim is attributed over at most, from where the previous claim stopped. What the
+/// rewards of these blocks do not cover is left unattributed.
+pub const MAX_STATEMENT_BLOCKS: u64 = 1000;
+
+/// Spreads `amount` over block `rewards` in block order, starting at `cursor`. Returns the
+/// blocks covered, the cursor after them and the part of `amount` no reward covered.
+pub fn attribute(
+    amount: u64,
+    cursor: ClaimCursor,
+    rewards: impl IntoIterator<Item = (Height, u64)>,
+) -> (Vec<ClaimedBlock>, ClaimCursor, u64) {
+    let mut left = amount;
+    let mut blocks = vec![];
+    let mut cursor = cursor;
+    for (block, reward) in rewards {
+        if left == 0 {
+            break;
+        }
+        if block < cursor.block {
+            continue;
+        }
+        let consumed = if block == cursor.block {
+            cursor.consumed
+        } else {
+            0
+        };
+        let taken = reward.saturating_sub(consumed).min(left);
+        if taken > 0 {
+            blocks.push(ClaimedBlock {
+                block,
+                amount: taken,
+            });
+            left -= taken;
+        }
+        cursor = if consumed + taken >= reward {
+            ClaimCursor {
+                block: block + 1,
+                consumed: 0,
+            }
+        } else {
+            ClaimCursor {
+                block,
+                consumed: consumed + taken,
+            }
+        };
+    }
+    (blocks, cursor, left)
+}
+
+/// Block rewards of `user` as a source, from `from` on.
+fn source_rewards(user: Principal, source: &ClaimSource, from: Height) -> Vec<(Height, u64)> {
+    let last = DodService::get_last_block().map_or(0, |(height, _)| height);
+    let Ok(range) = BlockRange::inclusive(from, last) else {
+        return vec![];
+    };
+    let range = range.limit(MAX_STATEMENT_BLOCKS);
+    match source {
+        ClaimSource::Staker => NEW_BLOCK_ORDERS.with_borrow(|v| {
+            NewBlockOrders::get_user_orders_in_range(v, user, range)
+                .filter(|(_, order)| order.status == OrderStatus::Filled)
+                .map(|(block, _)| {
+                    let reward = DodService::get_user_block_reward(block, user).map_or(0, |r| r.0);
+                    (block, reward)
+                })
+                .collect()
+        }),
+        ClaimSource::Miner => match DodService::get_miner_by_principal(user) {
+            Some(miner) => MINER_REWARDS.with_borrow(|v| {
+                v.range(range.blocks())
+                    .filter(|(_, reward)| reward.btc_address == miner.btc_address)
+                    .map(|(height, reward)| {
+                        (height, reward.credited.saturating_sub(reward.debited))
+                    })
+                    .collect()
+            }),
+            None => vec![],
+        },
+        ClaimSource::Asset { .. } => vec![],
+    }
+}
+
+fn get_cursor(user: Principal, source: &ClaimSource) -> ClaimCursor {
+    let cursors = CLAIM_CURSORS.with_borrow(|v| v.get(&user).unwrap_or_default());
+    match source {
+        ClaimSource::Staker => cursors.staker,
+        ClaimSource::Miner => cursors.miner,
+        ClaimSource::Asset { .. } => None,
+    }
+    .unwrap_or_default()
+}
+
+fn put_cursor(user: Principal, source: &ClaimSource, cursor: ClaimCursor) {
+    CLAIM_CURSORS.with_borrow_mut(|v| {
+        let mut cursors = v.get(&user).unwrap_or_default();
+        match source {
+            ClaimSource::Staker => cursors.staker = Some(cursor),
+            ClaimSource::Miner => cursors.miner = Some(cursor),
+            ClaimSource::Asset { .. } => return,
+        }
+        v.insert(user, cursors);
+    })
+}
+
+/// Keeps the statement of a claim transfer, attributing `amount` to the oldest rewards of the
+/// source no earlier claim took. A transfer the ledger found as a duplicate keeps the
+/// statement recorded when it first landed.
+pub fn record_claim(
+    tx_index: &Nat,
+    user: Principal,
+    source: &ClaimSource,
+    to: &Account,
+    amount: u64,
+    fee: u64,
+) {
+    let Ok(tx_index) = u64::try_from(tx_index.0.clone()) else {
+        return;
+    };
+    if CLAIM_STATEMENTS.with_borrow(|v| v.contains_key(&tx_index)) {
+        return;
+    }
+    let (blocks, unattributed) = match source {
+        ClaimSource::Asset { height, .. } => (
+            vec![ClaimedBlock {
+                block: *height,
+                amount,
+            }],
+            0,
+        ),
+        _ => {
+            let cursor = get_cursor(user, source);
+            let rewards = source_rewards(user, source, cursor.block);
+            let (blocks, cursor, unattributed) = attribute(amount, cursor, rewards);
+            put_cursor(user, source, cursor);
+            (blocks, unattributed)
+        }
+    };
+    CLAIM_STATEMENTS.with_borrow_mut(|v| {
+        v.insert(
+            tx_index,
+            ClaimStatement {
+                tx_index,
+                user,
+                source: source.clone(),
+                to: to.clone(),
+                amount,
+                fee,
+                recorded_at: ic_cdk::api::time(),
+                blocks,
+                unattributed,
+            },
+        )
+    });
+}
+
+pub fn get_claim_statement(tx_index: u64) -> Option<ClaimStatement> {
+    CLAIM_STATEMENTS.with_borrow(|v| v.get(&tx_index))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_attribute() {
+        let rewards = vec![(3, 100), (5, 50), (8, 200)];
+        let (blocks, cursor, left) = attribute(120, ClaimCursor::default(), rewards.clone());
+        assert_eq!(
+            blocks,
+            vec![
+                ClaimedBlock {
+                    block: 3,
+                    amount: 100
+                },
+                ClaimedBlock {
+                    block: 5,
+                    amount: 20
+                }
+            ]
+        );
+        assert_eq!(
+            cursor,
+            ClaimCursor {
+                block: 5,
+                consumed: 20
+            }
+        );
+        assert_eq!(left, 0);
+
+        // the next claim goes on with what is left of block 5
+        let (blocks, cursor, left) = attribute(300, cursor, rewards);
+        assert_eq!(
+            blocks,
+            vec![
+                ClaimedBlock {
+                    block: 5,
+                    amount: 30
+                },
+                ClaimedBlock {
+                    block: 8,
+                    amount: 200
+                }
+            ]
+        );
+        assert_eq!(
+            cursor,
+            ClaimCursor {
+                block: 9,
+                consumed: 0
+            }
+        );
+        assert_eq!(left, 70);
+
+        let (blocks, cursor, left) = attribute(10, cursor.clone(), vec![]);
+        assert!(blocks.is_empty());
+        assert_eq!(cursor.block, 9);
+        assert_eq!(left, 10);
+    }
+}
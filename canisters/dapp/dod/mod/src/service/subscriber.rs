@@ -0,0 +1,38 @@
+use crate::service::config::get_block_subscriber;
+use crate::state::info_log_add;
+use candid::Principal;
+use dod_utils::types::BlockData;
+use ic_cdk::api::call::CallResult;
+use ic_cdk::spawn;
+use std::time::Duration;
+
+const MAX_NOTIFY_ATTEMPTS: u32 = 5;
+const NOTIFY_BASE_DELAY_NS: u64 = 2_000_000_000;
+
+/// Pushes a finalized block to the registered subscriber, if any.
+pub fn notify_block_finalized(block: BlockData) {
+    if let Some(subscriber) = get_block_subscriber() {
+        notify(subscriber, block, 0);
+    }
+}
+
+/// Calls `on_block` on the subscriber, retrying with exponential backoff on failure.
+fn notify(subscriber: Principal, block: BlockData, attempt: u32) {
+    spawn(async move {
+        let res: CallResult<()> = ic_cdk::call(subscriber, "on_block", (block.clone(),)).await;
+        if let Err((code, msg)) = res {
+            if attempt + 1 < MAX_NOTIFY_ATTEMPTS {
+                let delay = Duration::from_nanos(NOTIFY_BASE_DELAY_NS << attempt);
+                ic_cdk_timers::set_timer(delay, move || notify(subscriber, block, attempt + 1));
+            } else {
+                info_log_add(
+                    format!(
+                        "notify_block_finalized: giving up on block {} for {}, code: {:?}, msg: {}",
+                        block.height, subscriber, code, msg
+                    )
+                    .as_str(),
+                );
+            }
+        }
+    });
+}
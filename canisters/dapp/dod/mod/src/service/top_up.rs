@@ -0,0 +1,273 @@
+use crate::common::{
+    CMCClient, NotifyTopUpError, NotifyTopUpRequest, CMC_CAN_ID, ICP_CAN_ID, MEMO_TOP_UP_CANISTER,
+};
+use crate::memory::CONFIG;
+use crate::service::event::add_event;
+use crate::service::{config, cycles};
+use crate::state::info_log_add;
+use candid::Principal;
+use dod_utils::types::{EventKind, TopUpEstimate, TopUpSettings};
+use ic_cdk::{id, spawn};
+use ic_ledger_types::{
+    account_balance, transfer, AccountBalanceArgs, AccountIdentifier, Memo, Subaccount, Timestamp,
+    Tokens, TransferArgs,
+};
+
+pub fn get_top_up_settings() -> Option<TopUpSettings> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.top_up_settings.clone())
+    })
+}
+
+/// Whether `subaccount` has the shape of `Subaccount::from(principal)`, under which users
+/// deposit ICP.
+fn is_principal_subaccount(subaccount: &[u8; 32]) -> bool {
+    let len = subaccount[0] as usize;
+    len > 0 && len <= 29 && subaccount[1 + len..].iter().all(|b| *b == 0)
+}
+
+pub fn set_top_up_settings(
+    caller: Principal,
+    settings: Option<TopUpSettings>,
+) -> Result<(), String> {
+    if let Some(settings) = settings.as_ref() {
+        if settings.interval == 0 {
+            return Err("Top-up interval can not be zero".to_string());
+        }
+        if settings.threshold == 0 {
+            return Err("Top-up threshold can not be zero".to_string());
+        }
+        // a top-up must never spend the ICP a user deposited
+        if is_principal_subaccount(&settings.funding_subaccount) {
+            return Err("Funding subaccount can not be a principal subaccount".to_string());
+        }
+        let fees = config::get_fee_settings();
+        if settings.amount_e8s < fees.min_icp_stake_e8s {
+            return Err(format!(
+                "Top-up amount must be at least {} e8s",
+                fees.min_icp_stake_e8s
+            ));
+        }
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.top_up_settings = settings.clone();
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })?;
+    add_event(caller, EventKind::TopUpConfigured { settings });
+    Ok(())
+}
+
+pub fn get_last_top_up_at() -> Option<u64> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.last_top_up_at)
+    })
+}
+
+fn take_pending_top_up() -> Option<u64> {
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .and_then(|dod_service| dod_service.pending_top_up.take())
+    })
+}
+
+fn set_pending_top_up(block_index: Option<u64>) {
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.pending_top_up = block_index;
+        }
+    })
+}
+
+/// Cycles minted for `amount_e8s` at the CMC rate, one XDR buys one trillion cycles.
+pub fn estimated_cycles(amount_e8s: u64, xdr_permyriad_per_icp: u64) -> u128 {
+    // e8s * (permyriad / 10_000 XDR per ICP) * 1e12 cycles per XDR / 1e8 e8s per ICP
+    amount_e8s as u128 * xdr_permyriad_per_icp as u128
+}
+
+fn next_allowed_at(settings: &TopUpSettings) -> Option<u64> {
+    get_last_top_up_at().map(|last| last.saturating_add(settings.interval))
+}
+
+/// Called once per generated block. A top-up whose notification failed is retried first,
+/// otherwise a new one starts when the operational cycles are below the threshold.
+///
+/// The interval is marked as consumed before the ledger calls, so a failed transfer waits
+/// for the next interval instead of being retried on every block.
+pub fn maybe_run_top_up() {
+    let settings = match get_top_up_settings() {
+        None => return,
+        Some(settings) => settings,
+    };
+    if let Some(block_index) = take_pending_top_up() {
+        spawn(notify_top_up(settings.amount_e8s, block_index));
+        return;
+    }
+    let now = ic_cdk::api::time();
+    if next_allowed_at(&settings).map_or(false, |next| now < next) {
+        return;
+    }
+    if cycles::get_cycles_health().operational >= settings.threshold {
+        return;
+    }
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.last_top_up_at = Some(now);
+        }
+    });
+    spawn(run_top_up(settings));
+}
+
+async fn run_top_up(settings: TopUpSettings) {
+    let cmc_can_id = Principal::from_text(CMC_CAN_ID).unwrap();
+    let transfer_args = TransferArgs {
+        amount: Tokens::from_e8s(settings.amount_e8s),
+        to: AccountIdentifier::new(&cmc_can_id, &Subaccount::from(id())),
+        memo: Memo(MEMO_TOP_UP_CANISTER),
+        fee: Tokens::from_e8s(config::get_fee_settings().icp_fee),
+        from_subaccount: Some(Subaccount(settings.funding_subaccount)),
+        created_at_time: Some(Timestamp {
+            timestamp_nanos: ic_cdk::api::time(),
+        }),
+    };
+    let error = match transfer(Principal::from_text(ICP_CAN_ID).unwrap(), transfer_args).await {
+        Ok(Ok(block_index)) => return notify_top_up(settings.amount_e8s, block_index).await,
+        Ok(Err(e)) => format!("Unable to transfer ICP: {:?}", e),
+        Err((code, msg)) => format!("Unable to call ICP canister: {:?} {}", code, msg),
+    };
+    top_up_failed(settings.amount_e8s, None, error);
+}
+
+/// Notifies the CMC of a top-up transfer. Errors the CMC may recover from keep the
+/// transfer pending, so the next block retries the notification.
+async fn notify_top_up(amount_e8s: u64, block_index: u64) {
+    let cmc = CMCClient(Principal::from_text(CMC_CAN_ID).unwrap());
+    let res = cmc
+        .notify_top_up(NotifyTopUpRequest {
+            block_index,
+            canister_id: id(),
+        })
+        .await;
+    match res {
+        Ok((Ok(minted),)) => {
+            add_event(
+                id(),
+                EventKind::TopUpCompleted {
+                    amount_e8s,
+                    block_index,
+                    cycles: u128::try_from(minted.0).unwrap_or(u128::MAX),
+                },
+            );
+        }
+        Ok((Err(
+            e @ (NotifyTopUpError::Refunded { .. }
+            | NotifyTopUpError::InvalidTransaction(_)
+            | NotifyTopUpError::TransactionTooOld(_)),
+        ),)) => top_up_failed(
+            amount_e8s,
+            Some(block_index),
+            format!("Unable to top up: {:?}", e),
+        ),
+        Ok((Err(e),)) => {
+            set_pending_top_up(Some(block_index));
+            info_log_add(format!("top up: notification will be retried: {:?}", e).as_str());
+        }
+        Err((code, msg)) => {
+            set_pending_top_up(Some(block_index));
+            info_log_add(
+                format!("top up: notification will be retried: {:?} {}", code, msg).as_str(),
+            );
+        }
+    }
+}
+
+fn top_up_failed(amount_e8s: u64, block_index: Option<u64>, error: String) {
+    info_log_add(format!("top up: {}", error).as_str());
+    add_event(
+        id(),
+        EventKind::TopUpFailed {
+            amount_e8s,
+            block_index,
+            error,
+        },
+    );
+}
+
+/// What the next top-up would do, without transferring anything.
+pub async fn estimate_top_up() -> Result<TopUpEstimate, String> {
+    let settings = get_top_up_settings().ok_or_else(|| "Top-up is not configured".to_string())?;
+    let funding_balance = account_balance(
+        Principal::from_text(ICP_CAN_ID).unwrap(),
+        AccountBalanceArgs {
+            account: AccountIdentifier::new(&id(), &Subaccount(settings.funding_subaccount)),
+        },
+    )
+    .await
+    .map_err(|(code, msg)| format!("Unable to call ICP canister: {:?} {}", code, msg))?;
+    let (rate,) = CMCClient(Principal::from_text(CMC_CAN_ID).unwrap())
+        .get_icp_xdr_conversion_rate()
+        .await
+        .map_err(|(code, msg)| format!("Unable to call cycle canister: {:?} {}", code, msg))?;
+
+    let operational = cycles::get_cycles_health().operational;
+    let next_allowed_at = next_allowed_at(&settings);
+    let fees = config::get_fee_settings();
+    Ok(TopUpEstimate {
+        operational,
+        threshold: settings.threshold,
+        funding_balance_e8s: funding_balance.e8s(),
+        amount_e8s: settings.amount_e8s,
+        estimated_cycles: estimated_cycles(settings.amount_e8s, rate.data.xdr_permyriad_per_icp),
+        next_allowed_at,
+        would_top_up: operational < settings.threshold
+            && next_allowed_at.map_or(true, |next| ic_cdk::api::time() >= next)
+            && funding_balance.e8s() >= settings.amount_e8s.saturating_add(fees.icp_fee),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{estimated_cycles, is_principal_subaccount};
+    use candid::Principal;
+    use ic_ledger_types::Subaccount;
+
+    #[test]
+    pub fn test_estimated_cycles() {
+        // 1 ICP at 5 XDR mints 5T cycles
+        assert_eq!(estimated_cycles(100_000_000, 50_000), 5_000_000_000_000);
+        assert_eq!(estimated_cycles(0, 50_000), 0);
+        assert_eq!(estimated_cycles(1_000_000, 123_456), 123_456_000_000);
+    }
+
+    #[test]
+    pub fn test_is_principal_subaccount() {
+        for principal in [
+            Principal::from_slice(&[7; 29]),
+            Principal::from_slice(&[7; 10]),
+            Principal::anonymous(),
+        ] {
+            assert!(is_principal_subaccount(&Subaccount::from(principal).0));
+        }
+        assert!(!is_principal_subaccount(&[0; 32]));
+        let mut dedicated = [0u8; 32];
+        dedicated[..16].copy_from_slice(b"dod-top-up-funds");
+        assert!(!is_principal_subaccount(&dedicated));
+    }
+}
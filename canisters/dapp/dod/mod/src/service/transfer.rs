@@ -0,0 +1,60 @@
+use crate::memory::{INTERNAL_TRANSFERS, USER_TRANSFERS};
+use candid::Principal;
+use dod_utils::types::InternalTransfer;
+
+pub const MAX_TRANSFER_MEMO_LEN: usize = 32;
+pub const MAX_TRANSFERS_PAGE_LIMIT: u64 = 100;
+
+pub fn check_memo(memo: &Option<Vec<u8>>) -> Result<(), String> {
+    match memo {
+        Some(memo) if memo.len() > MAX_TRANSFER_MEMO_LEN => Err(format!(
+            "Memo can not be longer than {} bytes",
+            MAX_TRANSFER_MEMO_LEN
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Stores the transfer and indexes it under both parties with the counterparty as value,
+/// so each side can page its own transfers.
+pub fn record_internal_transfer(
+    from: Principal,
+    to: Principal,
+    amount: u128,
+    memo: Option<Vec<u8>>,
+    time: u64,
+) -> u64 {
+    let id = INTERNAL_TRANSFERS.with_borrow_mut(|v| {
+        let id = v.last_key_value().map_or(0, |(k, _)| k + 1);
+        v.insert(
+            id,
+            InternalTransfer {
+                id,
+                from,
+                to,
+                amount,
+                memo,
+                time,
+            },
+        );
+        id
+    });
+    USER_TRANSFERS.with_borrow_mut(|v| {
+        v.insert((from, id), to);
+        v.insert((to, id), from);
+    });
+    id
+}
+
+/// Transfers sent or received by `user`, oldest first.
+pub fn get_internal_transfers(user: Principal, page: u64, limit: u64) -> Vec<InternalTransfer> {
+    let limit = limit.min(MAX_TRANSFERS_PAGE_LIMIT);
+    let ids = USER_TRANSFERS.with_borrow(|v| {
+        v.range((user, 0)..=(user, u64::MAX))
+            .skip(page.saturating_mul(limit) as usize)
+            .take(limit as usize)
+            .map(|((_, id), _)| id)
+            .collect::<Vec<u64>>()
+    });
+    INTERNAL_TRANSFERS.with_borrow(|v| ids.into_iter().filter_map(|id| v.get(&id)).collect())
+}
@@ -0,0 +1,151 @@
+use crate::memory::CONFIG;
+use crate::service::config;
+use dod_utils::types::{TreasuryBucket, TreasuryBucketInfo, TreasurySplit};
+
+pub const TREASURY_BUCKETS: [TreasuryBucket; 3] = [
+    TreasuryBucket::Emission,
+    TreasuryBucket::Team,
+    TreasuryBucket::Ecosystem,
+];
+
+/// Subaccount of `bucket` in the treasury. Emission keeps the block subaccount that rewards
+/// were always minted to, the other buckets differ from it in the last byte.
+pub fn bucket_subaccount(block_subaccount: [u8; 32], bucket: &TreasuryBucket) -> [u8; 32] {
+    let mut subaccount = block_subaccount;
+    subaccount[31] ^= match bucket {
+        TreasuryBucket::Emission => 0,
+        TreasuryBucket::Team => 1,
+        TreasuryBucket::Ecosystem => 2,
+    };
+    subaccount
+}
+
+pub fn get_bucket_subaccount(bucket: &TreasuryBucket) -> Result<[u8; 32], String> {
+    Ok(bucket_subaccount(config::get_dod_block_account()?, bucket))
+}
+
+/// DOD minted to the team and ecosystem buckets along with `reward`, which goes to emission
+/// in full so that block rewards stay what stakers and miners are credited.
+pub fn bucket_shares(split: &TreasurySplit, reward: u64) -> (u64, u64) {
+    let emission = split.emission_percent() as u128;
+    let share = |percent: u8| (reward as u128 * percent as u128 / emission) as u64;
+    (share(split.team_percent), share(split.ecosystem_percent))
+}
+
+pub fn get_treasury_split() -> Option<TreasurySplit> {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.treasury_split.clone())
+    })
+}
+
+pub fn set_treasury_split(split: Option<TreasurySplit>) -> Result<(), String> {
+    if let Some(split) = split.as_ref() {
+        split.validate()?;
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.treasury_split = split;
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+// applies `f` to the (minted, transferred) totals of `bucket`
+fn update_bucket(bucket: TreasuryBucket, f: impl FnOnce(&mut u64, &mut u64)) {
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            let buckets = dod_service.treasury_buckets.get_or_insert_with(Vec::new);
+            let index = match buckets.iter().position(|(b, _, _)| *b == bucket) {
+                Some(index) => index,
+                None => {
+                    buckets.push((bucket, 0, 0));
+                    buckets.len() - 1
+                }
+            };
+            let (_, minted, transferred) = &mut buckets[index];
+            f(minted, transferred);
+        }
+    })
+}
+
+pub fn record_minted(bucket: TreasuryBucket, amount: u64) {
+    update_bucket(bucket, |minted, _| *minted = minted.saturating_add(amount));
+}
+
+pub fn record_transferred(bucket: TreasuryBucket, amount: u64) {
+    update_bucket(bucket, |_, transferred| {
+        *transferred = transferred.saturating_add(amount)
+    });
+}
+
+/// Every bucket with its subaccount and the DOD minted to it and transferred out of it by
+/// owners. Rewards claimed by users and burns leave the emission bucket and are not counted.
+pub fn get_treasury_buckets() -> Result<Vec<TreasuryBucketInfo>, String> {
+    let block_subaccount = config::get_dod_block_account()?;
+    let recorded = CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.treasury_buckets.clone())
+            .unwrap_or_default()
+    });
+    Ok(TREASURY_BUCKETS
+        .iter()
+        .map(|bucket| {
+            let (minted, transferred) = recorded
+                .iter()
+                .find(|(b, _, _)| b == bucket)
+                .map_or((0, 0), |(_, minted, transferred)| (*minted, *transferred));
+            TreasuryBucketInfo {
+                bucket: bucket.clone(),
+                subaccount: bucket_subaccount(block_subaccount, bucket),
+                minted,
+                transferred,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bucket_subaccount() {
+        let base = [7u8; 32];
+        let subaccounts: Vec<[u8; 32]> = TREASURY_BUCKETS
+            .iter()
+            .map(|b| bucket_subaccount(base, b))
+            .collect();
+        assert_eq!(subaccounts[0], base);
+        assert_ne!(subaccounts[1], subaccounts[0]);
+        assert_ne!(subaccounts[2], subaccounts[0]);
+        assert_ne!(subaccounts[2], subaccounts[1]);
+    }
+
+    #[test]
+    fn test_bucket_shares() {
+        let split = TreasurySplit {
+            team_percent: 10,
+            ecosystem_percent: 10,
+        };
+        assert!(split.validate().is_ok());
+        assert_eq!(bucket_shares(&split, 800), (100, 100));
+        assert_eq!(bucket_shares(&split, 1), (0, 0));
+        let split = TreasurySplit {
+            team_percent: 60,
+            ecosystem_percent: 40,
+        };
+        assert!(split.validate().is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::common::ONE_MINUTE_NS;
+use crate::memory::{CONFIG, TIMER_IDS, WATCHDOG_TIMER};
+use crate::service::event::add_event;
+use crate::service::{config, cycles, generation, import, settlement, DodService};
+use crate::{log_debug, log_warn};
+use dod_utils::types::{BlockData, BlockStatus, EventKind, HealthStatus};
+use ic_cdk::id;
+use std::time::Duration;
+
+pub const WATCHDOG_INTERVAL: u64 = ONE_MINUTE_NS;
+pub const DEFAULT_WATCHDOG_FACTOR: u64 = 3;
+/// Below two intervals the watchdog could restart a generation that is merely slow.
+pub const MIN_WATCHDOG_FACTOR: u64 = 2;
+
+pub fn get_watchdog_factor() -> u64 {
+    CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .and_then(|dod_service| dod_service.watchdog_factor)
+            .unwrap_or(DEFAULT_WATCHDOG_FACTOR)
+    })
+}
+
+pub fn set_watchdog_factor(factor: u64) -> Result<(), String> {
+    if factor < MIN_WATCHDOG_FACTOR {
+        return Err(format!(
+            "Watchdog factor must be at least {}",
+            MIN_WATCHDOG_FACTOR
+        ));
+    }
+    CONFIG.with(|config| {
+        config
+            .borrow_mut()
+            .dod_service
+            .as_mut()
+            .map(|dod_service| {
+                dod_service.watchdog_factor = Some(factor);
+                Ok(())
+            })
+            .unwrap_or_else(|| Err("No service found".to_string()))
+    })
+}
+
+/// Arms the watchdog, once. Started with block production and again after an upgrade.
+pub fn start() {
+    if WATCHDOG_TIMER.with_borrow(|t| t.is_some()) {
+        return;
+    }
+    let timer_id =
+        ic_cdk_timers::set_timer_interval(Duration::from_nanos(WATCHDOG_INTERVAL), check);
+    WATCHDOG_TIMER.with_borrow_mut(|t| *t = Some(timer_id));
+}
+
+fn overdue_by(block: &BlockData, now: u64) -> u64 {
+    now.saturating_sub(block.submission_cutoff())
+}
+
+/// Whether generation would move `block` forward. A finalized last block was stopped on
+/// purpose, when nobody deposited cycles in it, and waits for `start_generate_blocks`.
+fn restartable(block: &BlockData) -> bool {
+    match block.status() {
+        BlockStatus::Open => true,
+        BlockStatus::Closing => !settlement::is_complete(block.height),
+        _ => false,
+    }
+}
+
+fn check() {
+    // production is not restarted where start_generating_blocks would refuse to start it
+    if import::check_not_importing().is_err() || config::get_maintenance_status().enabled {
+        return;
+    }
+    let block = match DodService::get_last_block() {
+        Some((_, block)) => block,
+        None => return,
+    };
+    let interval = match DodService::get_block_time_interval() {
+        Ok(interval) => interval,
+        Err(_) => return,
+    };
+    let overdue_by = overdue_by(&block, ic_cdk::api::time());
+    if overdue_by <= interval.saturating_mul(get_watchdog_factor()) || !restartable(&block) {
+        return;
+    }
+    log_warn!(
+        "watchdog: block {} overdue by {}ns, restarting production",
+        block.height,
+        overdue_by
+    );
+    CONFIG.with(|config| {
+        if let Some(dod_service) = config.borrow_mut().dod_service.as_mut() {
+            dod_service.watchdog_restarts = Some(dod_service.watchdog_restarts.unwrap_or(0) + 1);
+            dod_service.last_watchdog_restart_at = Some(ic_cdk::api::time());
+        }
+    });
+    add_event(
+        id(),
+        EventKind::WatchdogRestart {
+            height: block.height,
+            overdue_by,
+        },
+    );
+    // the lost timer may still be listed, the generation arms a fresh one
+    TIMER_IDS.with_borrow_mut(|ids| {
+        for timer_id in ids.drain(..) {
+            log_debug!("watchdog: clearing timer ID {timer_id:?}");
+            ic_cdk_timers::clear_timer(timer_id);
+        }
+    });
+    DodService::generate_blocks();
+}
+
+pub fn health() -> HealthStatus {
+    let last_block = DodService::get_last_block().map(|(_, block)| block);
+    let (watchdog_restarts, last_watchdog_restart_at) = CONFIG.with(|config| {
+        config
+            .borrow()
+            .dod_service
+            .as_ref()
+            .map_or((0, None), |dod_service| {
+                (
+                    dod_service.watchdog_restarts.unwrap_or(0),
+                    dod_service.last_watchdog_restart_at,
+                )
+            })
+    });
+    HealthStatus {
+        height: last_block.as_ref().map(|block| block.height),
+        block_status: last_block.as_ref().map(|block| block.status()),
+        overdue_by: last_block
+            .as_ref()
+            .map_or(0, |block| overdue_by(block, ic_cdk::api::time())),
+        watchdog_armed: WATCHDOG_TIMER.with_borrow(|t| t.is_some()),
+        watchdog_factor: get_watchdog_factor(),
+        watchdog_restarts,
+        last_watchdog_restart_at,
+        skipped_generation_ticks: generation::get_skipped_ticks(),
+        cycles: cycles::get_cycles_health(),
+    }
+}
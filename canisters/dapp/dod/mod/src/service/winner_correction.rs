@@ -0,0 +1,139 @@
+use crate::log_error;
+use crate::memory::BLOCKS;
+use crate::service::event::add_event;
+use crate::service::{assets, cycles, miner, replication, reward, staker, DodService};
+use crate::verifier::parse_reveal_envelope;
+use base64::Engine;
+use candid::{Nat, Principal};
+use dod_utils::types::{
+    BlockStatus, EventKind, Height, MinerCandidate, MinerInfo, MinerReward, StateChange,
+};
+
+/// Whether the PSBTs of `candidate` decode and its reveal carries an envelope.
+fn is_valid_candidate(candidate: &MinerCandidate) -> bool {
+    let engine = base64::engine::general_purpose::STANDARD;
+    engine.decode(&candidate.signed_commit_psbt).is_ok()
+        && engine.decode(&candidate.signed_reveal_psbt).is_ok()
+        && parse_reveal_envelope(&candidate.signed_reveal_psbt).is_ok()
+}
+
+/// The candidate that would have won block `height` after `demoted`, in settlement order.
+fn next_candidate(height: Height, demoted: &str) -> Option<(MinerCandidate, MinerInfo)> {
+    let mut candidates = DodService::get_block_candidates(height);
+    miner::sort_candidates(&mut candidates, &DodService::get_bid_currency());
+    candidates
+        .into_iter()
+        .filter(|c| {
+            c.btc_address != demoted && assets::is_block_candidate(c) && is_valid_candidate(c)
+        })
+        .find_map(|c| {
+            let miner = miner::get_miner_by_address(c.btc_address.clone())?;
+            Some((c, miner))
+        })
+}
+
+// takes back up to `cycles` from the cycles balance of `owner`
+fn recover_cycles(owner: Principal, cycles: u128) -> u128 {
+    let balance = DodService::get_user_detail(owner).map_or(0, |detail| {
+        u128::try_from(detail.balance.0).unwrap_or(u128::MAX)
+    });
+    let recovered = cycles.min(balance);
+    if recovered == 0
+        || DodService::decrease_user_cycle_balance(owner, Nat::from(recovered)).is_err()
+    {
+        return 0;
+    }
+    recovered
+}
+
+/// Demotes the winner of the finalized block `height`. The cycles and DOD it was credited are
+/// taken back as far as they are unspent and unclaimed. With `promote_next`, the next candidate
+/// whose PSBTs are valid becomes the winner and is paid at most what was taken back; cycles it
+/// is not paid are no longer user cycles, DOD it is not credited stays in the treasury.
+pub fn invalidate_winner(
+    caller: Principal,
+    height: Height,
+    reason: String,
+    promote_next: bool,
+) -> Result<(), String> {
+    let mut block = BLOCKS
+        .with_borrow(|v| v.get(&height))
+        .ok_or_else(|| "Block not found".to_string())?;
+    if block.status() != BlockStatus::Finalized {
+        return Err("Only the winner of a finalized block can be invalidated".to_string());
+    }
+    let demoted = block
+        .winner
+        .clone()
+        .ok_or_else(|| "Block has no winner".to_string())?;
+
+    let cycles_recovered = recover_cycles(demoted.owner, demoted.reward_cycles.unwrap_or(0));
+    // blocks settled before miner rewards were recorded credited the winner share
+    let winner_share = miner::get_miner_reward(height).map_or_else(
+        || {
+            DodService::get_reward_policy()
+                .map_or(0, |policy| reward::split_reward(&policy, block.rewards).0)
+        },
+        |r| r.credited,
+    );
+    let dod_recovered = miner::debit_miner_reward(&demoted.btc_address, winner_share);
+
+    let promoted = if promote_next {
+        next_candidate(height, &demoted.btc_address)
+    } else {
+        None
+    };
+    let mut cycles_paid = 0;
+    miner::clear_winner(height);
+    block.winner = match promoted.as_ref() {
+        Some((candidate, miner_info)) => {
+            cycles_paid = candidate.cycles_price.min(cycles_recovered);
+            // the cycles are paid to the owner's balance, it becomes a staker if it was not
+            if let Err(e) = staker::register_user(miner_info.owner).and_then(|_| {
+                DodService::increase_user_cycle_balance(miner_info.owner, Nat::from(cycles_paid))
+            }) {
+                log_error!("can not pay promoted winner of {}: {}", height, e);
+                cycles_paid = 0;
+            }
+            miner::credit_miner_reward(&miner_info.btc_address, dod_recovered);
+            miner::replace_miner_reward(
+                height,
+                Some(MinerReward {
+                    btc_address: miner_info.btc_address.clone(),
+                    credited: dod_recovered,
+                    debited: 0,
+                    updated_at: ic_cdk::api::time(),
+                }),
+            );
+            if let Err(e) = miner::record_winner_sigs(height, candidate) {
+                log_error!("can not keep sigs of promoted winner of {}: {}", height, e);
+            }
+            miner::record_winner_payment(height, miner_info, cycles_paid);
+            Some(MinerInfo {
+                reward_cycles: Some(cycles_paid),
+                ..miner_info.clone()
+            })
+        }
+        None => {
+            miner::replace_miner_reward(height, None);
+            None
+        }
+    };
+    cycles::take_from_user_pool(cycles_recovered - cycles_paid);
+
+    BLOCKS.with_borrow_mut(|v| v.insert(height, block.clone()));
+    replication::record(StateChange::BlockWritten(block));
+    add_event(
+        caller,
+        EventKind::WinnerInvalidated {
+            height,
+            demoted: demoted.btc_address,
+            promoted: promoted.map(|(candidate, _)| candidate.btc_address),
+            reason,
+            cycles_recovered,
+            cycles_paid,
+            dod_recovered,
+        },
+    );
+    Ok(())
+}
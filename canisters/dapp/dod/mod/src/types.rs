@@ -13,6 +13,11 @@ use ic_ledger_types::Subaccount;
 use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue;
 use icrc_ledger_types::icrc1::account::Account;
 
+use dod_utils::types::{
+    AssetReward, BlockData, DodCanisters, HalvingSettings, Integrator, MinerInfo,
+    NewBlockOrderValue, ReferralInfo, UserBlockOrderRes,
+};
+
 #[allow(dead_code)]
 const MAX_STATE_SIZE: u32 = 2 * 1024 * 1024;
 const MAX_USER_PROFILE_SIZE: u32 = 1 * 1024 * 1024;
@@ -80,6 +85,9 @@ pub struct UserDetail {
     pub(crate) claimed_dod: u64,
     pub(crate) total_dod: u64,
     pub(crate) cycle_burning_rate: u128,
+    /// reward fractions below one DOD unit, scaled by `REWARD_SCALE`, carried by the
+    /// `Accumulate` dust policy
+    pub(crate) reward_remainder: Option<u64>,
 }
 
 impl Storable for crate::types::UserDetail {
@@ -96,6 +104,267 @@ impl Storable for crate::types::UserDetail {
     };
 }
 
+/// Everything a front-end needs on page load, user fields are `None` for the anonymous principal.
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct Dashboard {
+    pub last_block: Option<BlockData>,
+    pub halving_settings: Option<HalvingSettings>,
+    pub canisters: Option<DodCanisters>,
+    pub user: Option<UserDetail>,
+    pub burning_range: Option<NewBlockOrderValue>,
+    /// filled orders of the most recent blocks
+    pub orders: Option<UserBlockOrderRes>,
+}
+
+/// Everything linked to a principal as a miner owner, a staker and an operator, for support
+/// to untangle how its balances and claims interact.
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct IdentityOverview {
+    pub principal: Principal,
+    pub is_miner: bool,
+    /// it deposited cycles or holds a burning range
+    pub is_staker: bool,
+    pub is_miner_operator: bool,
+    pub is_integrator: bool,
+    /// the miner it owns, whose winner cycles are credited to its staker balance
+    pub miner: Option<MinerInfo>,
+    pub staker: Option<UserDetail>,
+    pub burning_range: Option<NewBlockOrderValue>,
+    /// principals allowed to submit for its miner
+    pub miner_operators: Vec<Principal>,
+    /// owners of the miners it submits for as an operator
+    pub operates_for: Vec<Principal>,
+    pub integrator: Option<Integrator>,
+    pub referral: Option<ReferralInfo>,
+    pub unclaimed_staker_dod: u64,
+    pub unclaimed_miner_dod: u64,
+    /// when the claim in progress took the claim lock
+    pub claim_locked_at: Option<u64>,
+}
+
+/// Lifetime aggregates of a staker, updated at deposit and settlement time.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UserStats {
+    pub cycles_deposited: u128,
+    /// debited orders and priority fees, minus failed block refunds
+    pub cycles_burned: u128,
+    pub blocks_participated: u64,
+    pub first_activity: Option<u64>,
+    pub last_block: Option<u64>,
+}
+
+impl Storable for UserStats {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Heap-only bookkeeping of the block generation in progress,
+/// `in_flight` is set while the synchronous run goes, its ledger calls go through the ledger queue.
+#[derive(Default)]
+pub struct GenerationState {
+    pub in_flight: u32,
+    pub started_at: u64,
+    pub deferred: bool,
+    pub skipped_ticks: u64,
+}
+
+/// Outcome of one payout leg of a split claim.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct ClaimLegResult {
+    pub to: Account,
+    pub amount: u64,
+    pub result: Result<Nat, String>,
+}
+
+/// The balance a claim is paid from.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Eq, PartialEq)]
+pub enum ClaimSource {
+    Staker,
+    Miner,
+    Asset { asset: String, height: u64 },
+}
+
+/// A claim transfer whose outcome is not known yet. Retries send it with the same
+/// `created_at_time`, so the ledger deduplicates a transfer that landed already.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct PendingClaim {
+    pub source: ClaimSource,
+    pub to: Account,
+    /// debited from the source, the ledger fee included
+    pub amount: u64,
+    pub created_at_time: u64,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+/// Reward of one block a claim is attributed to.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct ClaimedBlock {
+    pub block: u64,
+    pub amount: u64,
+}
+
+/// Where the next claim of a source starts taking rewards from, `consumed` of the reward of
+/// `block` was taken by earlier claims.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClaimCursor {
+    pub block: u64,
+    pub consumed: u64,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default)]
+pub struct ClaimCursors {
+    pub staker: Option<ClaimCursor>,
+    pub miner: Option<ClaimCursor>,
+}
+
+impl Storable for ClaimCursors {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Running totals of the block pools up to `height`, the pool averages of a window are the
+/// differences of two of them.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PoolAccumulator {
+    pub height: u64,
+    pub blocks: u64,
+    pub cycles: u128,
+    /// pool cycles times the nanoseconds the block was open
+    pub weighted_cycles: u128,
+    pub duration: u64,
+}
+
+impl Storable for PoolAccumulator {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A mint or burn of a settled block waiting for its turn on the ledger, they are sent one at
+/// a time in the order the blocks were settled.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Eq, PartialEq)]
+pub enum LedgerOp {
+    /// the block reward minted to the treasury and its bucket shares
+    MintReward {
+        height: u64,
+        amount: u64,
+    },
+    /// the DOD of the block taken out of the treasury
+    Burn {
+        height: u64,
+        amount: u64,
+    },
+    MintAssets {
+        rewards: Vec<AssetReward>,
+    },
+    /// the treasury surplus of a buyback epoch burned, queued once the queue drained
+    Buyback,
+}
+
+impl LedgerOp {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LedgerOp::MintReward { .. } => "mint_reward",
+            LedgerOp::Burn { .. } => "burn",
+            LedgerOp::MintAssets { .. } => "mint_assets",
+            LedgerOp::Buyback => "buyback",
+        }
+    }
+}
+
+impl Storable for LedgerOp {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A queued ledger operation that failed or got no reply, set aside with the sequence number it
+/// was queued under until an owner requeues it.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct LedgerDeadLetter {
+    pub op: LedgerOp,
+    pub error: String,
+    pub failed_at: u64,
+}
+
+impl Storable for LedgerDeadLetter {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// The blocks whose rewards a claim transfer paid out, oldest rewards first.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct ClaimStatement {
+    pub tx_index: u64,
+    pub user: Principal,
+    pub source: ClaimSource,
+    pub to: Account,
+    /// debited from the source, the ledger fee included
+    pub amount: u64,
+    pub fee: u64,
+    pub recorded_at: u64,
+    pub blocks: Vec<ClaimedBlock>,
+    /// part of the amount no block reward was found for, such as referral rebates or rewards
+    /// beyond the blocks looked at
+    pub unattributed: u64,
+}
+
+impl Storable for ClaimStatement {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default)]
+pub struct PendingClaims {
+    pub claims: Vec<PendingClaim>,
+}
+
+impl Storable for PendingClaims {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 /// We define an example key with String
 /// because String is expandable, cannot store in stable structure directly,
 /// so we use a struct to wrap it.
@@ -1,11 +1,13 @@
 use crate::protocol::{vec_to_u832, DodAssets, DodOps, ParsedEnvelope, MAGIC_VALUE};
+use crate::{log_debug, log_error};
 use bitcoin::key::Secp256k1;
 use bitcoin::key::XOnlyPublicKey;
 use bitcoin::psbt::{Prevouts, Psbt};
 use bitcoin::sighash::SighashCache;
 use bitcoin::taproot::TapTweakHash;
-use bitcoin::Network::{Bitcoin, Testnet};
 use bitcoin::{secp256k1, Address, AddressType, Network, ScriptBuf};
+use dod_utils::block_hash::BlockHash;
+use dod_utils::types::{AddressValidationError, BtcNetwork};
 use std::str::FromStr;
 
 pub struct AddressInfo {
@@ -15,42 +17,47 @@ pub struct AddressInfo {
     pub address_type: AddressType,
 }
 
-pub fn get_script_from_address(address: String) -> Result<AddressInfo, String> {
-    let mut network = Bitcoin;
-    let mut address_type = AddressType::P2tr;
-
-    if address.starts_with("bc1q") {
-        address_type = AddressType::P2wpkh;
-        network = Bitcoin;
-    } else if address.starts_with("bc1p") {
-        address_type = AddressType::P2tr;
-        network = Bitcoin;
-    } else if address.starts_with('1') {
-        address_type = AddressType::P2pkh;
-        network = Bitcoin;
-    } else if address.starts_with('3') {
-        address_type = AddressType::P2sh;
-        network = Bitcoin;
-    } else if address.starts_with("tb1q") {
-        address_type = AddressType::P2wpkh;
-        network = Testnet;
-    } else if address.starts_with('m') || address.starts_with('n') {
-        address_type = AddressType::P2pkh;
-        network = Testnet;
-    } else if address.starts_with('2') {
-        address_type = AddressType::P2sh;
-        network = Testnet;
-    } else if address.starts_with("tb1p") {
-        address_type = AddressType::P2tr;
-        network = Testnet;
+pub fn to_bitcoin_network(network: BtcNetwork) -> Network {
+    match network {
+        BtcNetwork::Mainnet => Network::Bitcoin,
+        BtcNetwork::Testnet => Network::Testnet,
+        BtcNetwork::Signet => Network::Signet,
+        BtcNetwork::Regtest => Network::Regtest,
     }
-    let addr = Address::from_str(address.as_str())
-        .map_err(|e| format!("Cannot gen address {:?}", e).to_string())?;
+}
+
+/// Parses an address for `network`, or for the first network it is valid for when `None`.
+/// Testnet and signet share their encoding, so an unconfigured `tb1` address resolves to testnet.
+pub fn parse_address(
+    address: &str,
+    network: Option<BtcNetwork>,
+) -> Result<AddressInfo, AddressValidationError> {
+    let addr = Address::from_str(address)
+        .map_err(|e| AddressValidationError::Malformed(format!("{:?}", e)))?;
 
-    let addr_checked = addr
-        .clone()
-        .require_network(network)
-        .map_err(|e| format!("Cannot require network {:?}", e).to_string())?;
+    let network = match network {
+        Some(expected) => {
+            let network = to_bitcoin_network(expected);
+            if !addr.is_valid_for_network(network) {
+                return Err(AddressValidationError::NetworkMismatch { expected });
+            }
+            network
+        }
+        None => [
+            Network::Bitcoin,
+            Network::Testnet,
+            Network::Signet,
+            Network::Regtest,
+        ]
+        .into_iter()
+        .find(|n| addr.is_valid_for_network(*n))
+        .ok_or_else(|| AddressValidationError::Malformed("unknown network".to_string()))?,
+    };
+
+    let addr_checked = addr.assume_checked();
+    let address_type = addr_checked
+        .address_type()
+        .ok_or_else(|| AddressValidationError::UnsupportedType("unknown".to_string()))?;
 
     Ok(AddressInfo {
         address: addr_checked.to_string(),
@@ -60,10 +67,68 @@ pub fn get_script_from_address(address: String) -> Result<AddressInfo, String> {
     })
 }
 
+/// Only taproot addresses can receive the reveal output of a mining transaction.
+pub fn validate_mining_address(
+    address: &str,
+    network: Option<BtcNetwork>,
+) -> Result<AddressInfo, AddressValidationError> {
+    let info = parse_address(address, network)?;
+    if info.address_type != AddressType::P2tr {
+        return Err(AddressValidationError::UnsupportedType(
+            info.address_type.to_string(),
+        ));
+    }
+    Ok(info)
+}
+
+/// Normalizes a miner key to 33 byte compressed SEC1, the verifiers take the x-only key from
+/// `pubkey[1..]`. A 32 byte x-only key is taken with even y, as BIP340 does.
+pub fn normalize_miner_pubkey(pubkey: &[u8]) -> Result<Vec<u8>, String> {
+    match pubkey.len() {
+        33 => secp256k1::PublicKey::from_slice(pubkey)
+            .map(|key| key.serialize().to_vec())
+            .map_err(|_| "Ecdsa pubkey is not a valid compressed key".to_string()),
+        32 => XOnlyPublicKey::from_slice(pubkey)
+            .map(|key| [&[0x02u8][..], &key.serialize()[..]].concat())
+            .map_err(|_| "Ecdsa pubkey is not a valid x-only key".to_string()),
+        len => Err(format!(
+            "Ecdsa pubkey must be 33 bytes compressed or 32 bytes x-only, got {} bytes",
+            len
+        )),
+    }
+}
+
+/// Checks that the taproot address is the key path address of `pubkey` without a script tree.
+pub fn check_pubkey_matches_address(pubkey: &[u8], info: &AddressInfo) -> Result<(), String> {
+    let xonly = XOnlyPublicKey::from_slice(&pubkey[1..])
+        .map_err(|_| "Ecdsa pubkey is not a valid x-only key".to_string())?;
+    let secp = Secp256k1::verification_only();
+    if Address::p2tr(&secp, xonly, None, info.network).script_pubkey() != info.script_buf {
+        return Err(format!(
+            "Ecdsa pubkey does not match taproot address {}",
+            info.address
+        ));
+    }
+    Ok(())
+}
+
+/// Txid of a signed commit psbt, `None` if it can not be decoded.
+pub fn commit_txid(psbt_b64: &str) -> Option<String> {
+    Psbt::from_str(psbt_b64)
+        .ok()
+        .map(|psbt| psbt.extract_tx().txid().to_string())
+}
+
+pub fn get_script_from_address(address: String) -> Result<AddressInfo, String> {
+    parse_address(address.as_str(), None).map_err(|e| e.to_string())
+}
+
+/// Checks a commit psbt spends output 0 of the txid `block_hash.to_txid_hex()`, returns its
+/// txid and the script of its first output.
 pub fn checked_signed_commit_psbt_b64(
     psbt_b64: &str,
     pubkey: Vec<u8>,
-    input_hash: Vec<u8>,
+    block_hash: &BlockHash,
 ) -> Result<(String, ScriptBuf), String> {
     let Ok(mut psbt) = Psbt::from_str(psbt_b64) else {
         return Err("Cannot decode psbt".to_string());
@@ -80,7 +145,7 @@ pub fn checked_signed_commit_psbt_b64(
 
         if psbt.inputs[0].witness_utxo.is_some()
             && psbt.inputs[0].clone().witness_utxo.unwrap().value == MAGIC_VALUE
-            && tx.input[0].previous_output.txid.to_string() == hex::encode(input_hash)
+            && tx.input[0].previous_output.txid.to_string() == block_hash.to_txid_hex()
             && tx.input[0].previous_output.vout == 0
             && tx.output[0].script_pubkey.is_v1_p2tr()
         {
@@ -93,13 +158,28 @@ pub fn checked_signed_commit_psbt_b64(
     }
 }
 
+/// The txid and the envelope of a reveal transaction already checked at submission.
+pub fn parse_reveal_envelope(psbt_b64: &str) -> Result<(String, ParsedEnvelope), String> {
+    let Ok(psbt) = Psbt::from_str(psbt_b64) else {
+        return Err("Cannot decode psbt".to_string());
+    };
+    let tx = psbt.extract_tx();
+    let mut parsed = ParsedEnvelope::from_transaction(&tx);
+    if parsed.len() != 1 {
+        return Err("ParsedEnvelope length is not 1".to_string());
+    }
+    Ok((tx.txid().to_string(), parsed.remove(0)))
+}
+
+/// Checks the reveal spends the commit to the miner and carries one mining envelope, returns
+/// the asset the envelope mines.
 pub fn check_signed_reveal_psbt(
     psbt_b64: &str,
     prev_script: ScriptBuf,
     pubkey: Vec<u8>,
     commit_id: String,
     miner_address: String,
-) -> Result<(), String> {
+) -> Result<DodAssets, String> {
     let Ok(psbt) = Psbt::from_str(psbt_b64) else {
         return Err("Cannot decode psbt".to_string());
     };
@@ -127,13 +207,12 @@ pub fn check_signed_reveal_psbt(
                 if p.op_type != Some(DodOps::Mine) {
                     return Err("Op type is not mine".to_string());
                 }
+                let mut asset = DodAssets::DMT;
                 if let Some(payload) = p.payload {
-                    if payload.t != DodAssets::DMT {
-                        return Err("Asset type is not DMT".to_string());
-                    }
                     if payload.dmt.is_none() {
                         return Err("DMT is none".to_string());
                     }
+                    asset = payload.t;
                 }
 
                 if p.stakers.len() != 1
@@ -142,7 +221,7 @@ pub fn check_signed_reveal_psbt(
                 {
                     return Err("Staker is not match".to_string());
                 }
-                Ok(())
+                Ok(asset)
             }
         } else {
             Err("Validation failed, block hash might be changed".to_string())
@@ -192,7 +271,7 @@ pub fn psbt_verifier(decoded_psbt: Psbt, mut err: Option<String>) -> Option<Stri
                             .unwrap();
                         match secp.verify_schnorr(&sig, &message, &tweaked_key) {
                             Ok(_) => {
-                                println!("Signature verified");
+                                log_debug!("Signature verified");
                                 continue;
                             }
                             Err(e) => {
@@ -201,14 +280,14 @@ pub fn psbt_verifier(decoded_psbt: Psbt, mut err: Option<String>) -> Option<Stri
                                     i,
                                     e.to_string()
                                 ));
-                                println!("err {:?}", err);
+                                log_error!("err {:?}", err);
                                 break;
                             }
                         }
                     }
                     Err(e) => {
                         err = Some(format!("Input {}: {}", i, e));
-                        println!("err {:?}", err);
+                        log_error!("err {:?}", err);
                         break;
                     }
                 }
@@ -223,7 +302,12 @@ pub fn psbt_verifier(decoded_psbt: Psbt, mut err: Option<String>) -> Option<Stri
 
 #[cfg(test)]
 mod test {
-    use crate::verifier::{check_signed_reveal_psbt, checked_signed_commit_psbt_b64};
+    use crate::verifier::{
+        check_pubkey_matches_address, check_signed_reveal_psbt, checked_signed_commit_psbt_b64,
+        normalize_miner_pubkey, validate_mining_address,
+    };
+    use dod_utils::block_hash::BlockHash;
+    use dod_utils::types::{AddressValidationError, BtcNetwork};
 
     #[test]
     pub fn test_commit() {
@@ -232,10 +316,23 @@ mod test {
             commit_psbt,
             hex::decode("02afee55a2cdcb6c47a593d629b04e13399354d348a3d84ad19310e2b6396e7237")
                 .unwrap(),
-            hex::decode("95a4bac3e21a5febcd54804e60250f6b9e8bb4c36fa83ccd64d86c6baf719e8f")
-                .unwrap(),
+            &BlockHash::from_display_hex(
+                "8f9e71af6b6cd864cd3ca86fc3b48b9e6b0f25604e8054cdeb5f1ae2c3baa495",
+            )
+            .unwrap(),
         )
         .unwrap();
+        // the commit spends the block hash reversed, not the hash as blocks show it
+        assert!(checked_signed_commit_psbt_b64(
+            commit_psbt,
+            hex::decode("02afee55a2cdcb6c47a593d629b04e13399354d348a3d84ad19310e2b6396e7237")
+                .unwrap(),
+            &BlockHash::from_display_hex(
+                "95a4bac3e21a5febcd54804e60250f6b9e8bb4c36fa83ccd64d86c6baf719e8f",
+            )
+            .unwrap(),
+        )
+        .is_err());
 
         let reveal_psbt = "cHNidP8BAF4BAAAAAQGvInD6DU8qnfn7O4oMVah3ofKqe2IjsBUqb0EXU5yPAAAAAAD9////ASICAAAAAAAAIlEgYfAjsZJUC0C0WemqYq7c64dObqWZcj0hqnJ05d3DvokAAAAAAAEBK0wFAAAAAAAAIlEgdHgSymyd9yRSOxAvVACefwEo5N7+RC772lRiykp4G+YBCLcDQO6qytI7SOuVrLV0Qr1is1fMCgN3E84TytiUqYu7xw0aHFfPHZv5I3PHRrhzwcRUtWRbmCsNvHxqPpEz64vJeNNSIK/uVaLNy2xHpZPWKbBOEzmTVNNIo9hK0ZMQ4rY5bnI3rABjA2RvZAFZJqJhdGNETVRjZG10o2NibGsAZHRpbWUaZVPxAGVub25jZRoAmJZ/aCHBr+5Vos3LbEelk9YpsE4TOZNU00ij2ErRkxDitjlucjcAAA==";
         let res_reveal = check_signed_reveal_psbt(
@@ -252,4 +349,53 @@ mod test {
 
     #[test]
     pub fn test_reveal() {}
+
+    #[test]
+    pub fn test_miner_pubkey() {
+        let compressed =
+            hex::decode("02afee55a2cdcb6c47a593d629b04e13399354d348a3d84ad19310e2b6396e7237")
+                .unwrap();
+        assert_eq!(
+            normalize_miner_pubkey(&compressed[1..]).unwrap(),
+            compressed
+        );
+        assert_eq!(normalize_miner_pubkey(&compressed).unwrap(), compressed);
+        assert!(normalize_miner_pubkey(&compressed[2..]).is_err());
+        assert!(normalize_miner_pubkey(&[0u8; 33]).is_err());
+
+        let info = validate_mining_address(
+            "tb1pv8cz8vvj2s95pdzeax4x9tkuawr5um49n9er6gd2wf6wthwrh6ysqnkcq9",
+            None,
+        )
+        .unwrap();
+        assert!(check_pubkey_matches_address(&compressed, &info).is_ok());
+        let other =
+            hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        assert!(check_pubkey_matches_address(&other, &info).is_err());
+    }
+
+    #[test]
+    pub fn test_validate_mining_address() {
+        let taproot = "tb1pv8cz8vvj2s95pdzeax4x9tkuawr5um49n9er6gd2wf6wthwrh6ysqnkcq9";
+        assert!(validate_mining_address(taproot, None).is_ok());
+        assert!(validate_mining_address(taproot, Some(BtcNetwork::Testnet)).is_ok());
+        assert_eq!(
+            validate_mining_address(taproot, Some(BtcNetwork::Mainnet)).err(),
+            Some(AddressValidationError::NetworkMismatch {
+                expected: BtcNetwork::Mainnet
+            })
+        );
+        assert!(matches!(
+            validate_mining_address(
+                "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+                Some(BtcNetwork::Mainnet)
+            ),
+            Err(AddressValidationError::UnsupportedType(_))
+        ));
+        assert!(matches!(
+            validate_mining_address("not an address", None),
+            Err(AddressValidationError::Malformed(_))
+        ));
+    }
 }
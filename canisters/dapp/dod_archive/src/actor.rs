@@ -0,0 +1,45 @@
+use crate::store;
+use candid::{candid_method, Principal};
+use dod_utils::types::{BlockData, BlockRange, Height};
+use ic_cdk::caller;
+use ic_cdk_macros::*;
+
+pub fn dod_guard() -> Result<(), String> {
+    if store::is_dod_canister(caller()) {
+        Ok(())
+    } else {
+        Err("Only the DOD canister appends blocks".to_string())
+    }
+}
+
+#[init]
+#[candid_method(init, rename = "init")]
+fn canister_init(dod_canister: Principal) {
+    store::set_dod_canister(dod_canister);
+}
+
+/// Stores blocks the DOD canister moves out, returns the height expected next.
+#[update(name = "append_blocks", guard = "dod_guard")]
+#[candid_method(update, rename = "append_blocks")]
+pub fn append_blocks(blocks: Vec<BlockData>) -> Result<Height, String> {
+    store::append_blocks(blocks)
+}
+
+#[query(name = "get_block_by_height")]
+#[candid_method(query, rename = "get_block_by_height")]
+pub fn get_block_by_height(height: Height) -> Option<BlockData> {
+    store::get_block_by_height(height)
+}
+
+/// Archived blocks `from` to `to`, at most `MAX_BLOCKS_PER_CALL` of them.
+#[query(name = "get_blocks_range")]
+#[candid_method(query, rename = "get_blocks_range")]
+pub fn get_blocks_range(from: Height, to: Height) -> Result<Vec<BlockData>, String> {
+    Ok(store::get_blocks_range(BlockRange::inclusive(from, to)?))
+}
+
+#[query(name = "get_archived_range")]
+#[candid_method(query, rename = "get_archived_range")]
+pub fn get_archived_range() -> Option<(Height, Height)> {
+    store::get_archived_range()
+}
@@ -0,0 +1,18 @@
+//! Archive of DOD blocks the DOD canister no longer keeps, see `block_archive` in `dod_mod`.
+
+pub mod actor;
+pub mod store;
+
+#[allow(unused_imports)]
+use candid::Principal;
+#[allow(unused_imports)]
+use dod_utils::types::*;
+
+candid::export_service!();
+
+#[no_mangle]
+pub fn get_candid_pointer() -> *mut std::os::raw::c_char {
+    let c_string = std::ffi::CString::new(__export_service()).unwrap();
+
+    c_string.into_raw()
+}
@@ -0,0 +1,146 @@
+use candid::Principal;
+use dod_utils::types::{BlockData, BlockRange, Height};
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell};
+use std::cell::RefCell;
+
+type VM = VirtualMemory<DefaultMemoryImpl>;
+
+const DOD_CANISTER_ID: MemoryId = MemoryId::new(0);
+const BLOCKS_ID: MemoryId = MemoryId::new(1);
+
+/// Blocks appended or returned at once.
+pub const MAX_BLOCKS_PER_CALL: u64 = 500;
+
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+
+    // the DOD canister, the only one appending blocks, as principal bytes
+    static DOD_CANISTER: RefCell<StableCell<Vec<u8>, VM>> = MEMORY_MANAGER.with(|mm| {
+        RefCell::new(
+            StableCell::init(mm.borrow().get(DOD_CANISTER_ID), vec![])
+                .expect("Unable to init the DOD canister cell"),
+        )
+    });
+
+    static BLOCKS: RefCell<StableBTreeMap<Height, BlockData, VM>> = MEMORY_MANAGER.with(|mm| {
+        RefCell::new(StableBTreeMap::init(mm.borrow().get(BLOCKS_ID)))
+    });
+}
+
+pub fn set_dod_canister(canister: Principal) {
+    DOD_CANISTER.with_borrow_mut(|c| {
+        c.set(canister.as_slice().to_vec())
+            .expect("Unable to set the DOD canister");
+    });
+}
+
+pub fn is_dod_canister(caller: Principal) -> bool {
+    DOD_CANISTER.with_borrow(|c| c.get().as_slice() == caller.as_slice())
+}
+
+/// Checks that `blocks` go on one height after the other from `next_height` or before it,
+/// blocks below `next_height` are sent again when the DOD canister missed the reply.
+pub fn check_append(next_height: Option<Height>, blocks: &[BlockData]) -> Result<(), String> {
+    if blocks.len() as u64 > MAX_BLOCKS_PER_CALL {
+        return Err(format!(
+            "At most {} blocks are appended at once",
+            MAX_BLOCKS_PER_CALL
+        ));
+    }
+    let Some(first) = blocks.first() else {
+        return Ok(());
+    };
+    if let Some(next) = next_height.filter(|next| first.height > *next) {
+        return Err(format!(
+            "Block {} appended, block {} expected",
+            first.height, next
+        ));
+    }
+    blocks
+        .iter()
+        .zip(first.height..)
+        .find(|(block, height)| block.height != *height)
+        .map_or(Ok(()), |(block, height)| {
+            Err(format!(
+                "Block {} appended, block {} expected",
+                block.height, height
+            ))
+        })
+}
+
+/// The height after the last archived block, `None` while nothing is archived.
+pub fn next_height() -> Option<Height> {
+    BLOCKS.with_borrow(|v| v.last_key_value().map(|(height, _)| height + 1))
+}
+
+pub fn append_blocks(blocks: Vec<BlockData>) -> Result<Height, String> {
+    check_append(next_height(), blocks.as_slice())?;
+    BLOCKS.with_borrow_mut(|v| {
+        for block in blocks {
+            v.insert(block.height, block);
+        }
+    });
+    next_height().ok_or_else(|| "No block is archived".to_string())
+}
+
+pub fn get_block_by_height(height: Height) -> Option<BlockData> {
+    BLOCKS.with_borrow(|v| v.get(&height))
+}
+
+pub fn get_blocks_range(range: BlockRange) -> Vec<BlockData> {
+    BLOCKS.with_borrow(|v| {
+        v.range(range.limit(MAX_BLOCKS_PER_CALL).blocks())
+            .map(|(_, block)| block)
+            .collect()
+    })
+}
+
+/// First and last archived heights.
+pub fn get_archived_range() -> Option<(Height, Height)> {
+    BLOCKS.with_borrow(|v| {
+        let (first, _) = v.first_key_value()?;
+        let (last, _) = v.last_key_value()?;
+        Some((first, last))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dod_utils::bitwork::Bitwork;
+
+    fn block(height: Height) -> BlockData {
+        BlockData {
+            height,
+            rewards: 0,
+            winner: None,
+            difficulty: Bitwork {
+                pre: 0,
+                post_hex: "0".to_string(),
+            },
+            hash: vec![],
+            block_time: 0,
+            next_block_time: 0,
+            history: false,
+            cycle_burned: 0,
+            dod_burned: 0,
+            status: None,
+            broadcast: None,
+            submission_cutoff: None,
+        }
+    }
+
+    #[test]
+    fn test_check_append() {
+        let blocks = vec![block(5), block(6), block(7)];
+        assert!(check_append(None, &blocks).is_ok());
+        assert!(check_append(Some(5), &blocks).is_ok());
+        // sent again after a missed reply
+        assert!(check_append(Some(8), &blocks).is_ok());
+        assert!(check_append(Some(4), &blocks).is_err());
+        assert!(check_append(Some(5), &[block(5), block(7)]).is_err());
+        assert!(check_append(Some(5), &[]).is_ok());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::types::WorkEstimate;
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
 use std::cmp::Ordering;
@@ -291,6 +292,68 @@ pub fn bitwork_match_hash(
     }
 }
 
+/// Work of `current_hash` against `target_hash`: the length of their common hex prefix and
+/// the value of the first digit after it. Scores compare as tuples, higher is better.
+pub fn bitwork_score(
+    current_hash: &str,
+    target_hash: &str,
+    reverse: bool,
+) -> Result<(u64, u32), String> {
+    let mut target = hex::decode(target_hash).map_err(|_| "Invalid target hash".to_string())?;
+    if target.len() != 32 {
+        return Err("Invalid target hash width".to_string());
+    }
+    if reverse {
+        target.reverse();
+    }
+    let target_string = hex::encode(target);
+    let prefix = current_hash
+        .chars()
+        .zip(target_string.chars())
+        .take_while(|(c, t)| c == t)
+        .count();
+    let next = current_hash
+        .chars()
+        .nth(prefix)
+        .and_then(|c| c.to_digit(16))
+        .unwrap_or(0);
+    Ok((prefix as u64, next))
+}
+
+/// Hex digits of `current_hash` matching `target_hash` beyond the `pre` digits `bitwork` asks
+/// for, meant for a hash that matches `bitwork`.
+pub fn bitwork_match_depth(
+    current_hash: &str,
+    target_hash: &str,
+    bitwork: &Bitwork,
+    reverse: bool,
+) -> Result<u64, String> {
+    let (prefix, _) = bitwork_score(current_hash, target_hash, reverse)?;
+    Ok(prefix.saturating_sub(bitwork.pre))
+}
+
+/// Attempts expected before a hash matches `bitwork`: its `pre` hex digits must equal the
+/// target, and the digit after them must be at least `post_hex`.
+pub fn expected_attempts(bitwork: &Bitwork) -> Result<f64, String> {
+    bitwork.validate()?;
+    let post = u32::from_str_radix(bitwork.post_hex.as_str(), 16).unwrap_or(0);
+    Ok(16f64.powi(bitwork.pre as i32) * 16.0 / (16 - post) as f64)
+}
+
+/// Expected attempts and wall-clock time to match `bitwork` at `hashrate` hashes per second.
+pub fn estimate_work(bitwork: Bitwork, hashrate: u64) -> Result<WorkEstimate, String> {
+    if hashrate == 0 {
+        return Err("Hash rate can not be zero".to_string());
+    }
+    let expected_attempts = expected_attempts(&bitwork)?;
+    Ok(WorkEstimate {
+        difficulty: bitwork,
+        probability: 1.0 / expected_attempts,
+        expected_attempts,
+        expected_seconds: expected_attempts / hashrate as f64,
+    })
+}
+
 pub fn merge_bitwork(bitwork_height: Bitwork, bitwork_tx: Bitwork) -> Bitwork {
     let mut pre = bitwork_height.pre + bitwork_tx.pre;
     let post = u32::from_str_radix(bitwork_height.post_hex.as_str(), 16).unwrap()
@@ -315,6 +378,58 @@ pub fn merge_bitwork(bitwork_height: Bitwork, bitwork_tx: Bitwork) -> Bitwork {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_estimate_work() {
+        let bitwork = |pre: u64, post_hex: &str| Bitwork {
+            pre,
+            post_hex: post_hex.to_string(),
+        };
+        assert_eq!(expected_attempts(&bitwork(0, "0")), Ok(1.0));
+        assert_eq!(expected_attempts(&bitwork(1, "0")), Ok(16.0));
+        assert_eq!(expected_attempts(&bitwork(1, "8")), Ok(32.0));
+        assert_eq!(expected_attempts(&bitwork(2, "f")), Ok(4096.0));
+        assert!(expected_attempts(&bitwork(65, "0")).is_err());
+        assert!(expected_attempts(&bitwork(1, "g")).is_err());
+
+        let estimate = estimate_work(bitwork(6, "0"), 1_000_000).unwrap();
+        assert_eq!(estimate.expected_attempts, 16_777_216.0);
+        assert_eq!(estimate.expected_seconds, 16.777216);
+        assert_eq!(estimate.probability, 1.0 / 16_777_216.0);
+        assert!(estimate_work(bitwork(6, "0"), 0).is_err());
+    }
+
+    #[test]
+    fn test_bitwork_match_depth() {
+        let bitwork = |pre: u64, post_hex: &str| Bitwork {
+            pre,
+            post_hex: post_hex.to_string(),
+        };
+        let target = "00".repeat(32);
+        let current = format!("0001{}", "0".repeat(60));
+        assert_eq!(
+            bitwork_match_depth(&current, &target, &bitwork(2, "0"), false),
+            Ok(1)
+        );
+        assert_eq!(
+            bitwork_match_depth(&current, &target, &bitwork(3, "1"), false),
+            Ok(0)
+        );
+        assert!(bitwork_match_depth(&current, "00", &bitwork(2, "0"), false).is_err());
+    }
+
+    #[test]
+    fn test_bitwork_score() {
+        let target = "abcd".to_string() + &"0".repeat(60);
+        assert_eq!(bitwork_score("abc1ff", target.as_str(), false), Ok((3, 1)));
+        assert_eq!(bitwork_score("abcdff", target.as_str(), false), Ok((4, 15)));
+        assert_eq!(bitwork_score("0bcd", target.as_str(), false), Ok((0, 0)));
+        assert!(
+            bitwork_score("abcdff", target.as_str(), false).unwrap()
+                > bitwork_score("abc1ff", target.as_str(), false).unwrap()
+        );
+        assert!(bitwork_score("abcd", "abcd", false).is_err());
+    }
+
     #[test]
     fn test_bitwork_plus_bit_hex() {
         let bitwork = Bitwork {
@@ -0,0 +1,86 @@
+use crate::types::BlockData;
+
+/// Hash of a block, in the byte order `BlockData::hash` keeps it.
+///
+/// Miners see the hash two ways, both fixed by the tests below:
+/// - `to_display_hex` is the hash as blocks show it, the bitwork target their commit txid must match;
+/// - `to_txid_hex` is the txid their commit transaction spends output 0 of, as bitcoin displays
+///   txids: the same bytes reversed (`to_txid_bytes`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockHash(Vec<u8>);
+
+impl BlockHash {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn from_display_hex(hash: &str) -> Result<Self, String> {
+        hex::decode(hash)
+            .map(Self)
+            .map_err(|_| format!("Invalid block hash {}", hash))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    pub fn to_display_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    pub fn to_txid_bytes(&self) -> Vec<u8> {
+        self.0.iter().rev().copied().collect()
+    }
+
+    pub fn to_txid_hex(&self) -> String {
+        hex::encode(self.to_txid_bytes())
+    }
+}
+
+impl From<&BlockData> for BlockHash {
+    fn from(block: &BlockData) -> Self {
+        Self(block.hash.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_hash_byte_order() {
+        let hash = BlockHash::new((0u8..32).collect());
+        assert_eq!(
+            hash.to_display_hex(),
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        );
+        assert_eq!(
+            hash.to_txid_hex(),
+            "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100"
+        );
+        assert_eq!(hash.to_txid_bytes()[0], 0x1f);
+        assert_eq!(
+            BlockHash::from_display_hex(&hash.to_display_hex()),
+            Ok(hash.clone())
+        );
+        assert!(BlockHash::from_display_hex("zz").is_err());
+    }
+
+    #[test]
+    fn test_block_hash_txid_is_bitcoin_display_order() {
+        use bitcoin::hashes::Hash;
+        use bitcoin::Txid;
+
+        // the txid bitcoin builds from the stored bytes displays as `to_txid_hex`
+        let hash = BlockHash::from_display_hex(
+            "8f9e71af6b6cd864cd3ca86fc3b48b9e6b0f25604e8054cdeb5f1ae2c3baa495",
+        )
+        .unwrap();
+        let txid = Txid::from_slice(hash.as_bytes()).unwrap();
+        assert_eq!(txid.to_string(), hash.to_txid_hex());
+        assert_eq!(
+            hash.to_txid_hex(),
+            "95a4bac3e21a5febcd54804e60250f6b9e8bb4c36fa83ccd64d86c6baf719e8f"
+        );
+    }
+}
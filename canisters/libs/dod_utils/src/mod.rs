@@ -1,5 +1,7 @@
 pub mod bitwork;
+pub mod block_hash;
 pub mod error;
+pub mod tcycles;
 pub mod types;
 
 use std::mem::size_of;
@@ -0,0 +1,64 @@
+/// Cycles in one T (trillion cycles).
+pub const CYCLES_PER_T: u128 = 1_000_000_000_000;
+/// Decimals of the T amounts returned next to raw cycles, enough to keep every cycle.
+pub const TCYCLES_DECIMALS: usize = 12;
+
+/// `cycles` in T with `TCYCLES_DECIMALS` decimals, e.g. `1.500000000000` for 1.5T cycles.
+pub fn to_tcycles(cycles: u128) -> String {
+    format!(
+        "{}.{:0width$}",
+        cycles / CYCLES_PER_T,
+        cycles % CYCLES_PER_T,
+        width = TCYCLES_DECIMALS
+    )
+}
+
+/// Raw cycles of an amount in T like `2`, `0.25` or `1.500000000000`, errors on more than
+/// `TCYCLES_DECIMALS` decimals rather than rounding.
+pub fn from_tcycles(tcycles: &str) -> Result<u128, String> {
+    let invalid = || format!("Invalid T cycles amount {}", tcycles);
+    let (whole, fraction) = tcycles.split_once('.').unwrap_or((tcycles, ""));
+    if whole.is_empty()
+        || fraction.len() > TCYCLES_DECIMALS
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let whole = whole.parse::<u128>().map_err(|_| invalid())?;
+    let fraction = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u128>().map_err(|_| invalid())?
+            * 10u128.pow((TCYCLES_DECIMALS - fraction.len()) as u32)
+    };
+    whole
+        .checked_mul(CYCLES_PER_T)
+        .and_then(|c| c.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tcycles() {
+        assert_eq!(to_tcycles(0), "0.000000000000");
+        assert_eq!(to_tcycles(1_500_000_000_000), "1.500000000000");
+        assert_eq!(to_tcycles(42), "0.000000000042");
+        for cycles in [0, 1, 999_999_999_999, 2 * CYCLES_PER_T + 7, u128::MAX] {
+            assert_eq!(from_tcycles(&to_tcycles(cycles)), Ok(cycles));
+        }
+        assert_eq!(from_tcycles("2"), Ok(2 * CYCLES_PER_T));
+        assert_eq!(from_tcycles("0.25"), Ok(CYCLES_PER_T / 4));
+        assert_eq!(from_tcycles("3."), Ok(3 * CYCLES_PER_T));
+        assert!(from_tcycles("0.0000000000001").is_err());
+        assert!(from_tcycles(".5").is_err());
+        assert!(from_tcycles("-1").is_err());
+        assert!(from_tcycles("1,5").is_err());
+        assert!(from_tcycles(&format!("{}", u128::MAX)).is_err());
+    }
+}
@@ -25,7 +25,7 @@ impl Storable for BtcAddress {
     };
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub enum MinerStatus {
     Activate,
     Deactivate,
@@ -69,6 +69,177 @@ pub struct BlockData {
     pub history: bool,
     pub cycle_burned: u128,
     pub dod_burned: u64,
+    pub status: Option<BlockStatus>,
+    pub broadcast: Option<BroadcastStatus>,
+    /// last instant a submission is accepted, `next_block_time` plus the grace window
+    /// configured when the block was opened
+    pub submission_cutoff: Option<u64>,
+}
+
+/// Lifecycle of a block. Blocks only move forward:
+/// `Open -> Closing -> Settled -> Finalized`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum BlockStatus {
+    /// accepting miner submissions until the submission cutoff
+    Open,
+    /// picked up by block generation, no more submissions
+    Closing,
+    /// winner and user balances written
+    Settled,
+    /// burns recorded, the block will not change anymore
+    Finalized,
+}
+
+impl BlockStatus {
+    pub fn can_transition_to(&self, next: &BlockStatus) -> bool {
+        matches!(
+            (self, next),
+            (BlockStatus::Open, BlockStatus::Closing)
+                | (BlockStatus::Closing, BlockStatus::Settled)
+                | (BlockStatus::Settled, BlockStatus::Finalized)
+        )
+    }
+}
+
+impl BlockData {
+    /// Blocks written before `status` existed are inferred from `history`.
+    pub fn status(&self) -> BlockStatus {
+        match self.status.as_ref() {
+            Some(status) => status.clone(),
+            None if self.history => BlockStatus::Finalized,
+            None => BlockStatus::Open,
+        }
+    }
+
+    pub fn transition(&mut self, next: BlockStatus) -> Result<(), String> {
+        let current = self.status();
+        if !current.can_transition_to(&next) {
+            return Err(format!(
+                "Block {} can not move from {:?} to {:?}",
+                self.height, current, next
+            ));
+        }
+        if next == BlockStatus::Finalized {
+            self.history = true;
+        }
+        self.status = Some(next);
+        Ok(())
+    }
+
+    /// Blocks written before the grace window existed close at `next_block_time`.
+    pub fn submission_cutoff(&self) -> u64 {
+        self.submission_cutoff.unwrap_or(self.next_block_time)
+    }
+
+    pub fn accepts_submissions(&self, now: u64) -> bool {
+        self.status() == BlockStatus::Open
+            && self.winner.is_none()
+            && now <= self.submission_cutoff()
+    }
+}
+
+/// Expected timing, difficulty and reward of an upcoming block.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduledBlock {
+    pub height: Height,
+    pub open_at: u64,
+    pub close_at: u64,
+    pub submission_cutoff: u64,
+    pub difficulty: Bitwork,
+    pub reward: u64,
+}
+
+/// Block `height` opens at `time` on the block schedule, the blocks after it one slot of
+/// interval and grace apart.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduleAnchor {
+    pub height: Height,
+    pub time: u64,
+}
+
+/// Constants of the protocol and the limits it is configured with. `version` changes when
+/// fields are added, `revision` whenever any value changes.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProtocolConstants {
+    pub version: u32,
+    pub revision: u64,
+    pub magic_value: u64,
+    pub memo_top_up: u64,
+    pub memo_transfer: u64,
+    pub memo_burn_dod: u64,
+    pub memo_burn_cycles: u64,
+    pub memo_top_up_canister: u64,
+    pub cycles_burner_fee: u128,
+    pub burn_orders_limit: u128,
+    pub fee_settings: FeeSettings,
+    pub max_icp_fee_e8s: u64,
+    pub max_min_icp_stake_e8s: u64,
+    pub block_time_interval: u64,
+    pub submission_grace: u64,
+    pub difficulty_adjust_epoch: u64,
+    pub claim_dedup_window: u64,
+    pub min_donation: u128,
+    pub max_donation_blocks_ahead: u64,
+    pub max_transfer_memo_len: u64,
+    pub max_blocks_page_limit: u64,
+    pub max_full_blocks: u64,
+    pub max_block_orders_page: u64,
+    pub max_schedule_blocks: u64,
+    pub max_burn_receipts_per_call: u64,
+    pub max_asset_reward_blocks: u64,
+    pub max_mining_assets: u64,
+    pub max_asset_id_len: u64,
+    pub max_miner_operators: u64,
+}
+
+/// Progress of importing the blocks, sigs and miners of a previous deployment. Block
+/// production resumes after the last imported block once the import is sealed.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct BlockImport {
+    pub next_height: Height,
+    pub blocks: u64,
+    pub sigs: u64,
+    pub miners: u64,
+    pub sealed_at: Option<u64>,
+}
+
+/// The `dod_archive` canister older blocks are moved to, reads of them are passed on to it.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlockArchive {
+    pub canister: Principal,
+    /// blocks kept below the last block, older settled ones are moved
+    pub retain_blocks: u64,
+    /// blocks below this height are in the archive canister
+    pub archived_to: Height,
+    pub last_archived_at: Option<u64>,
+    /// why the last move failed, cleared by the next one that succeeds
+    pub last_error: Option<String>,
+}
+
+/// Average total cycles per block over the last `blocks` settled blocks up to `to_height`,
+/// by block and weighted by how long each block was open.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PoolTwap {
+    pub window_blocks: u64,
+    pub from_height: Height,
+    pub to_height: Height,
+    pub blocks: u64,
+    pub duration: u64,
+    pub block_weighted: u128,
+    pub time_weighted: u128,
+    /// `block_weighted` and `time_weighted` in T, see `tcycles::to_tcycles`
+    pub block_weighted_t: String,
+    pub time_weighted_t: String,
+}
+
+/// Filter for `get_blocks_filtered`, every `None` condition matches all blocks.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BlockFilter {
+    pub from: Option<Height>,
+    pub to: Option<Height>,
+    /// `Some(true)` only blocks with a winner, `Some(false)` only blocks without one
+    pub winner: Option<bool>,
+    pub min_cycle_burned: Option<u128>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -97,6 +268,9 @@ impl Storable for BlockData {
 pub struct BlockSigs {
     pub commit_tx: Vec<u8>,
     pub reveal_tx: Vec<u8>,
+    /// Set when the transactions are kept in the PSBT blob store, which leaves them empty here.
+    pub commit_hash: Option<Vec<u8>>,
+    pub reveal_hash: Option<Vec<u8>>,
 }
 
 impl Storable for BlockSigs {
@@ -114,6 +288,24 @@ impl Storable for BlockSigs {
     };
 }
 
+/// A PSBT kept once under its sha256, with the number of candidates and sigs referencing it.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PsbtBlob {
+    pub bytes: Vec<u8>,
+    pub refs: u64,
+}
+
+impl Storable for PsbtBlob {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone)]
 pub struct BootStrapParams {
     pub dod_token_canister: Option<Principal>,
@@ -132,12 +324,27 @@ pub struct MinerSubmitPayload {
     pub signed_commit_psbt: String,
     pub signed_reveal_psbt: String,
     pub cycles_price: u128,
+    /// DOD offered for the block when miners bid in DOD, `cycles_price` is then ignored
+    pub dod_bid: Option<u64>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone)]
 pub struct MinerSubmitResponse {
     pub block_height: u64,
     pub cycles_price: u128,
+    pub dod_bid: Option<u64>,
+    /// candidates competing for the same reward, this one included
+    pub candidates: u64,
+    /// bid of the first candidate in settlement order
+    pub best_cycles_price: u128,
+    pub best_dod_bid: Option<u64>,
+    /// `cycles_price` and `best_cycles_price` in T, see `tcycles::to_tcycles`
+    pub cycles_price_t: String,
+    pub best_cycles_price_t: String,
+    /// place of this candidate in settlement order, 1 for the first
+    pub rank: u64,
+    /// nanoseconds left until the block stops taking submissions
+    pub time_remaining: u64,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -164,6 +371,15 @@ pub struct MinerCandidate {
     pub cycles_price: u128,
     pub signed_commit_psbt: String,
     pub signed_reveal_psbt: String,
+    /// Set when the PSBTs are kept in the PSBT blob store, which leaves them empty here.
+    pub signed_commit_psbt_hash: Option<Vec<u8>>,
+    pub signed_reveal_psbt_hash: Option<Vec<u8>>,
+    /// DOD the miner offers to burn from its balance, only set when miners bid in DOD
+    pub dod_bid: Option<u64>,
+    /// hex digits the commit txid matches of the block hash beyond the difficulty
+    pub match_depth: Option<u64>,
+    /// registered asset the reveal mines, `None` for DMT
+    pub asset: Option<String>,
 }
 
 impl Ord for MinerCandidate {
@@ -283,6 +499,30 @@ pub struct UserBlockOrderRes {
     pub data: Vec<UserBlockOrder>,
 }
 
+/// An order of a user in any status, with the times of its block.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct UserOrder {
+    pub block: u64,
+    pub amount: u128,
+    pub status: OrderStatus,
+    /// share and reward are only known once the block is filled
+    pub share: f64,
+    pub reward: u64,
+    pub open_at: u64,
+    pub close_at: u64,
+    /// the block is not open yet, its times follow the current interval and grace
+    pub estimated: bool,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct UserOrdersRes {
+    pub total: u64,
+    pub from: u64,
+    pub to: u64,
+    pub status: Option<OrderStatus>,
+    pub data: Vec<UserOrder>,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct HalvingSettings {
     pub interval: u64,
@@ -294,9 +534,210 @@ pub struct DodCanisters {
     pub ledger: Principal,
     pub index: Principal,
     pub archive: Principal,
+    /// what `set_dod_canisters` found when it called them, ignored when set
+    pub verification: Option<CanistersVerification>,
+}
+
+/// Answer of a canister to the call `set_dod_canisters` checks it with.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CanisterCheck {
+    pub method: String,
+    pub ok: bool,
+    pub detail: String,
+    pub checked_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CanistersVerification {
+    pub ledger: CanisterCheck,
+    pub index: CanisterCheck,
+    pub archive: CanisterCheck,
+}
+
+impl CanistersVerification {
+    /// The failed checks, as `canister method: detail`.
+    pub fn failures(&self) -> Vec<String> {
+        [
+            ("ledger", &self.ledger),
+            ("index", &self.index),
+            ("archive", &self.archive),
+        ]
+        .into_iter()
+        .filter(|(_, check)| !check.ok)
+        .map(|(name, check)| format!("{} {}: {}", name, check.method, check.detail))
+        .collect()
+    }
+}
+
+/// A canister `deploy_canisters` would create, with the cycles attached to it.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct PlannedCanister {
+    pub name: String,
+    pub wasm_staged: bool,
+    pub wasm_size: u64,
+    /// cycles attached when creating it
+    pub cycles: u128,
+    /// part of them the IC charges for the creation
+    pub creation_fee: u128,
+    /// part of them left on the canister
+    pub initial_cycles: u128,
+    pub init_args: String,
+}
+
+/// What `deploy_canisters` would do, `ready` when it has nothing in `issues`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DeployPreview {
+    pub ready: bool,
+    pub issues: Vec<String>,
+    pub canisters: Vec<PlannedCanister>,
+    pub total_cycles: u128,
+    pub canister_balance: u128,
+    /// cycles of the canister besides the user pool
+    pub available_cycles: u128,
+    /// controllers the canisters get
+    pub controllers: Vec<Principal>,
+    pub deployed: Option<DodCanisters>,
+}
+
+/// Cycles one principal donated to a block.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct BlockDonation {
+    pub donor: Principal,
+    pub amount: u128,
+}
+
+/// Cycles donated to a block, added to its pool without earning their donors any reward.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct BlockDonations {
+    pub height: Height,
+    pub total: u128,
+    pub donations: Vec<BlockDonation>,
+}
+
+/// Treasury balance against the rewards it owes, `surplus` or `deficit` is the difference.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SolvencyReport {
+    pub checked_at: u64,
+    /// balance of the block treasury subaccount on the ledger
+    pub treasury_balance: u64,
+    pub staker_unclaimed: u64,
+    pub miner_unclaimed: u64,
+    /// claims sent to the ledger whose outcome is not known, landed ones already left the treasury
+    pub pending_claims: u64,
+    pub surplus: u64,
+    pub deficit: u64,
+    pub solvent: bool,
+}
+
+/// State of a ledger canister, checked before its controllers are removed.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ChildCanisterCheck {
+    pub canister_id: Principal,
+    pub running: bool,
+    pub module_hash: Option<Vec<u8>>,
+    pub cycles: u128,
 }
+
+/// Request to remove the controllers of the ledger, index and archive, carried out by a
+/// second call before `expires_at`. `blackholed` lists the canisters whose controllers are
+/// removed already, once there is one the proposal no longer expires.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlackholeProposal {
+    pub proposed_by: Principal,
+    pub proposed_at: u64,
+    pub expires_at: u64,
+    pub checks: Vec<ChildCanisterCheck>,
+    pub blackholed: Option<Vec<Principal>>,
+}
+
 pub type BlockNumber = u64;
-pub type BlockRange = (BlockNumber, BlockNumber);
+
+/// Blocks `start` to `end`, `end` excluded. Built with `new` from an exclusive end or with
+/// `inclusive` from the last block, both reject a start after the end. Encoded as the
+/// `(start, end)` tuple it replaces, so ranges stored before read back the same.
+#[derive(
+    CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, Ord, PartialOrd, Eq, PartialEq,
+)]
+pub struct BlockRange(BlockNumber, BlockNumber);
+
+impl BlockRange {
+    /// Blocks `start` to `end`, `end` excluded. Empty when they are equal.
+    pub fn new(start: BlockNumber, end: BlockNumber) -> Result<Self, String> {
+        if start > end {
+            return Err(format!(
+                "Invalid block range, start {} is after end {}",
+                start, end
+            ));
+        }
+        Ok(Self(start, end))
+    }
+
+    /// Blocks `from` to `to`, both included. A range up to `BlockNumber::MAX` stops one block
+    /// short of it.
+    pub fn inclusive(from: BlockNumber, to: BlockNumber) -> Result<Self, String> {
+        if from > to {
+            return Err(format!(
+                "Invalid block range, from {} is after to {}",
+                from, to
+            ));
+        }
+        Ok(Self(from, to.saturating_add(1)))
+    }
+
+    /// `len` blocks from `start`, `None` when the end does not fit a block number.
+    pub fn with_len(start: BlockNumber, len: u64) -> Option<Self> {
+        start.checked_add(len).map(|end| Self(start, end))
+    }
+
+    pub fn start(&self) -> BlockNumber {
+        self.0
+    }
+
+    /// The first block after the range.
+    pub fn end(&self) -> BlockNumber {
+        self.1
+    }
+
+    /// The last block of the range, `None` when it is empty.
+    pub fn last(&self) -> Option<BlockNumber> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.1 - 1)
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.1.saturating_sub(self.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 >= self.1
+    }
+
+    pub fn contains(&self, block: BlockNumber) -> bool {
+        self.0 <= block && block < self.1
+    }
+
+    pub fn blocks(&self) -> std::ops::Range<BlockNumber> {
+        self.0..self.1
+    }
+
+    /// The first `max` blocks of the range.
+    pub fn limit(&self, max: u64) -> Self {
+        Self(self.0, self.1.min(self.0.saturating_add(max)))
+    }
+
+    /// Blocks of both ranges, `None` when they do not overlap.
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let range = Self(self.0.max(other.0), self.1.min(other.1));
+        if range.is_empty() {
+            None
+        } else {
+            Some(range)
+        }
+    }
+}
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub struct NewBlockOrderValue {
@@ -331,10 +772,42 @@ pub struct UserBlockOrderData {
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct BlockDataFull {
     pub block: BlockData,
+    /// the first filled orders of the block, `get_block_orders` pages through all of them
     pub user_data: Vec<UserBlockOrderData>,
+    /// filled orders of the block
+    pub user_data_total: Option<u64>,
     pub miners: Vec<MinerCandidateExt>,
 }
 
+/// Blocks of a range with their orders, cut to `max_blocks` blocks. `next` is the first block
+/// left out when the range goes on.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlockDataFullPage {
+    pub data: Vec<BlockDataFull>,
+    pub max_blocks: u64,
+    pub next: Option<u64>,
+}
+
+/// Filled orders `offset` to `offset + limit` of a block, out of `total`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlockOrdersPage {
+    pub height: Height,
+    pub total: u64,
+    pub offset: u64,
+    pub limit: u64,
+    pub data: Vec<UserBlockOrderData>,
+}
+
+/// A staged wasm as `get_*_wasm_chunk` serves it, `chunks` chunks of `chunk_size` bytes, the
+/// last one shorter.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct WasmInfo {
+    pub size: u64,
+    pub sha256: Vec<u8>,
+    pub chunk_size: u64,
+    pub chunks: u64,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct MinerCandidateExt {
     pub miner_principal: Principal,
@@ -343,6 +816,30 @@ pub struct MinerCandidateExt {
     pub cycles_price: u128,
     pub signed_commit_psbt: String,
     pub signed_reveal_psbt: String,
+    /// `cycles_price` in T, see `tcycles::to_tcycles`
+    pub cycles_price_t: String,
+}
+
+/// A candidate without its signed psbts.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CandidateSummary {
+    pub btc_address: String,
+    pub submit_time: u64,
+    pub cycles_price: u128,
+    /// `cycles_price` in T, see `tcycles::to_tcycles`
+    pub cycles_price_t: String,
+}
+
+/// Everything an explorer shows for one block.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlockBundle {
+    pub block: BlockData,
+    /// winner sigs, unless the block has no winner or they were pruned
+    pub sigs: Option<BlockSigs>,
+    /// empty while the block is the last one, like `get_history_miner_candidates`
+    pub candidates: Vec<CandidateSummary>,
+    /// the genesis message, only on block 0
+    pub message: Option<String>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -381,3 +878,1489 @@ pub struct MinerBlockData {
     pub submit_time: u64,
     pub difficulty: Bitwork,
 }
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RecoverySettings {
+    pub recovery_principal: Principal,
+    pub inactivity_window: u64, // nanoseconds
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RecoveryStatus {
+    pub settings: Option<RecoverySettings>,
+    pub last_owner_activity: Option<u64>,
+    pub claimable_at: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum EventKind {
+    RecoveryConfigured {
+        recovery_principal: Principal,
+        inactivity_window: u64,
+    },
+    RecoveryCancelled,
+    OwnershipClaimed {
+        last_owner_activity: u64,
+    },
+    BuybackConfigured {
+        settings: Option<BuybackSettings>,
+    },
+    BuybackBurned {
+        amount: u64,
+        treasury_balance: u64,
+        unclaimed_rewards: u64,
+    },
+    BroadcastConfirmed {
+        height: Height,
+        txid: String,
+    },
+    MaintenanceChanged {
+        enabled: bool,
+        reason: Option<String>,
+    },
+    ClaimsHaltChanged {
+        halted: bool,
+        reason: Option<String>,
+    },
+    TopUpConfigured {
+        settings: Option<TopUpSettings>,
+    },
+    TopUpCompleted {
+        amount_e8s: u64,
+        block_index: u64,
+        cycles: u128,
+    },
+    TopUpFailed {
+        amount_e8s: u64,
+        block_index: Option<u64>,
+        error: String,
+    },
+    WatchdogRestart {
+        height: Height,
+        overdue_by: u64,
+    },
+    WinnerPaid {
+        height: Height,
+        owner: Principal,
+        cycles: u128,
+    },
+    BlackholeProposed {
+        expires_at: u64,
+    },
+    GovernanceMigrated {
+        sns_governance: Principal,
+        legacy_owners: Vec<Principal>,
+    },
+    WinnerInvalidated {
+        height: Height,
+        demoted: String,
+        promoted: Option<String>,
+        reason: String,
+        cycles_recovered: u128,
+        cycles_paid: u128,
+        dod_recovered: u64,
+    },
+    BlackholeCancelled,
+    BlackholeFailed {
+        blackholed: Vec<Principal>,
+        canister_id: Principal,
+        error: String,
+    },
+    LedgerBlackholed {
+        ledger: Principal,
+        index: Principal,
+        archive: Principal,
+    },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DodEvent {
+    pub id: u64,
+    pub time: u64,
+    pub caller: Principal,
+    pub kind: EventKind,
+}
+
+impl Storable for DodEvent {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Effect of placing a new order range on the user's existing one,
+/// all ranges are half-open `[from, to)`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct OrderPreview {
+    pub range: BlockRange,
+    pub amount: u128,
+    pub previous: Option<NewBlockOrderValue>,
+    pub kept: Option<BlockRange>,
+    pub extended: Vec<BlockRange>,
+    pub cancelled: Option<BlockRange>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GenesisParams {
+    pub hash: Option<Vec<u8>>,
+    pub timestamp: Option<u64>,
+    pub message: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct GenesisInfo {
+    pub hash: Vec<u8>,
+    pub timestamp: u64,
+    pub message: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum BuybackPolicy {
+    FixedAmount(u64),
+    TreasuryPercentage(u8),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BuybackSettings {
+    pub policy: BuybackPolicy,
+    pub interval: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct EmissionStatus {
+    pub height: Option<Height>,
+    pub block_reward: u64,
+    pub halving_settings: Option<HalvingSettings>,
+    pub buyback_settings: Option<BuybackSettings>,
+    pub total_buyback_burned: u64,
+    pub last_buyback_at: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlockEfficiency {
+    pub height: Height,
+    pub rewards: u64,
+    pub cycle_burned: u128,
+    /// `None` when no cycles were burned on the block
+    pub dod_per_cycle: Option<f64>,
+}
+
+/// DOD minted per cycle burned over the finalized blocks of a window.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct EfficiencyStats {
+    pub blocks: Vec<BlockEfficiency>,
+    pub total_rewards: u64,
+    pub total_cycle_burned: u128,
+    pub dod_per_cycle: Option<f64>,
+}
+
+/// Whether the winner's commit/reveal transactions reached Bitcoin.
+/// `None` on a won block means the winner has not reported a broadcast yet.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum BroadcastStatus {
+    Reported,
+    Confirmed,
+}
+
+/// A page of won heights without a reported broadcast, `next` is where the following page
+/// starts, `None` once every stored winner sigs were visited.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct UnbroadcastWinsPage {
+    pub heights: Vec<Height>,
+    pub next: Option<Height>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BroadcastInfo {
+    pub height: Height,
+    pub txid: String,
+    pub reported_at: u64,
+    pub confirmed_at: Option<u64>,
+}
+
+impl Storable for BroadcastInfo {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CyclesHealth {
+    pub canister_balance: u128,
+    pub user_pool: u128,
+    pub operational: u128,
+    pub alert_threshold: Option<u128>,
+    pub low: bool,
+    /// the balances in T, see `tcycles::to_tcycles`
+    pub canister_balance_t: String,
+    pub user_pool_t: String,
+    pub operational_t: String,
+}
+
+/// Converts ICP held in a subaccount of this canister to cycles whenever the
+/// operational cycles run low.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct TopUpSettings {
+    /// subaccount of this canister on the ICP ledger holding the funding ICP
+    pub funding_subaccount: [u8; 32],
+    /// a top-up starts when the operational cycles fall below this
+    pub threshold: u128,
+    /// ICP converted by each top-up
+    pub amount_e8s: u64,
+    /// minimum time between two top-ups in nanoseconds
+    pub interval: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TopUpEstimate {
+    pub operational: u128,
+    pub threshold: u128,
+    pub funding_balance_e8s: u64,
+    pub amount_e8s: u64,
+    /// cycles the CMC would mint for `amount_e8s` at its current rate
+    pub estimated_cycles: u128,
+    /// earliest time the interval allows the next top-up
+    pub next_allowed_at: Option<u64>,
+    pub would_top_up: bool,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BtcNetwork {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum AddressValidationError {
+    Malformed(String),
+    NetworkMismatch { expected: BtcNetwork },
+    UnsupportedType(String),
+}
+
+impl std::fmt::Display for AddressValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressValidationError::Malformed(e) => write!(f, "Malformed btc address: {}", e),
+            AddressValidationError::NetworkMismatch { expected } => {
+                write!(f, "Btc address is not valid for {:?}", expected)
+            }
+            AddressValidationError::UnsupportedType(t) => {
+                write!(f, "Unsupported btc address type {}, only p2tr can mine", t)
+            }
+        }
+    }
+}
+
+/// Why an amount of cycles or a block count could not be computed.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum AmountError {
+    ZeroRate,
+    AmountTooLow,
+    /// the value does not fit in the named integer type
+    DoesNotFit(String),
+    /// the named result overflows
+    Overflow(String),
+}
+
+impl std::fmt::Display for AmountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmountError::ZeroRate => write!(f, "Burn rate can not be zero"),
+            AmountError::AmountTooLow => write!(f, "Amount too low"),
+            AmountError::DoesNotFit(t) => write!(f, "Amount does not fit in {}", t),
+            AmountError::Overflow(what) => write!(f, "Overflow computing {}", what),
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    pub reason: Option<String>,
+    pub since: Option<u64>,
+}
+
+/// What miners bid for a block.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub enum BidCurrency {
+    /// the lowest cycles price wins, the winner is paid that price in cycles
+    #[default]
+    Cycles,
+    /// the highest DOD bid wins, the bid is burned from the winner's balance and the winner is
+    /// paid no cycles
+    Dod,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub enum GovernanceMode {
+    /// parameters are changed by the canister owners
+    #[default]
+    Owners,
+    /// parameters are changed by proposals of an SNS, through its governance canister
+    Sns,
+}
+
+/// Who changes parameters, with the owners left when governance was handed to an SNS.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GovernanceStatus {
+    pub mode: GovernanceMode,
+    pub sns_governance: Option<Principal>,
+    pub migrated_at: Option<u64>,
+    pub legacy_owners: Vec<Principal>,
+}
+
+/// Emergency stop of reward claims, independent of maintenance mode.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ClaimsHaltStatus {
+    pub halted: bool,
+    pub reason: Option<String>,
+    pub since: Option<u64>,
+}
+
+/// How far the difficulty moves after a run of solved or unsolved blocks, in sixteenths of a
+/// hex character.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct DifficultySteps {
+    pub increase: u8,
+    pub decrease: u8,
+}
+
+impl Default for DifficultySteps {
+    fn default() -> Self {
+        DifficultySteps {
+            increase: 1,
+            decrease: 1,
+        }
+    }
+}
+
+impl DifficultySteps {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=15).contains(&self.increase) || !(1..=15).contains(&self.decrease) {
+            return Err("Difficulty steps must be between 1 and 15".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What happens to the cycles debited for a block that produced no winner.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum FailedBlockPolicy {
+    BurnAll,
+    RefundAll,
+    RefundPercent(u8),
+}
+
+impl FailedBlockPolicy {
+    pub fn refund_percent(&self) -> u8 {
+        match self {
+            FailedBlockPolicy::BurnAll => 0,
+            FailedBlockPolicy::RefundAll => 100,
+            FailedBlockPolicy::RefundPercent(p) => (*p).min(100),
+        }
+    }
+}
+
+/// What a difficulty tier bonus is paid on.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TierBonusTarget {
+    /// the cycles price paid to the winner, up to the cycles deposited in the block
+    #[default]
+    Cycles,
+    /// the DOD winner share of the reward policy, taken from the treasury share
+    Dod,
+}
+
+/// A winning hash matching at least `extra_digits` hex digits of the block hash beyond the
+/// difficulty earns `bonus_percent` more.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct DifficultyTier {
+    pub extra_digits: u64,
+    pub bonus_percent: u16,
+}
+
+/// Bonus tiers for blocks solved at a higher difficulty than required, the highest tier
+/// reached applies.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct DifficultyTiers {
+    pub tiers: Vec<DifficultyTier>,
+    pub target: TierBonusTarget,
+}
+
+pub const MAX_DIFFICULTY_TIERS: usize = 8;
+pub const MAX_TIER_BONUS_PERCENT: u16 = 1000;
+
+impl DifficultyTiers {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tiers.len() > MAX_DIFFICULTY_TIERS {
+            return Err(format!(
+                "At most {} difficulty tiers can be set",
+                MAX_DIFFICULTY_TIERS
+            ));
+        }
+        let mut last: Option<&DifficultyTier> = None;
+        for tier in self.tiers.iter() {
+            if tier.extra_digits == 0 {
+                return Err("A tier needs at least one extra digit".to_string());
+            }
+            if tier.bonus_percent == 0 || tier.bonus_percent > MAX_TIER_BONUS_PERCENT {
+                return Err(format!(
+                    "Tier bonus must be 1 to {} percent",
+                    MAX_TIER_BONUS_PERCENT
+                ));
+            }
+            if let Some(last) = last {
+                if tier.extra_digits <= last.extra_digits
+                    || tier.bonus_percent <= last.bonus_percent
+                {
+                    return Err(
+                        "Tiers must be ordered by extra digits with increasing bonuses".to_string(),
+                    );
+                }
+            }
+            last = Some(tier);
+        }
+        Ok(())
+    }
+
+    /// Bonus of the highest tier `match_depth` reaches, 0 below the first tier.
+    pub fn bonus_percent(&self, match_depth: u64) -> u16 {
+        self.tiers
+            .iter()
+            .filter(|t| t.extra_digits <= match_depth)
+            .map(|t| t.bonus_percent)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// How the DOD reward of a block is allocated. The treasury share, and the winner share of
+/// blocks without a winner, are burned from the treasury.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct RewardPolicy {
+    pub winner_percent: u8,
+    pub stakers_percent: u8,
+    pub treasury_percent: u8,
+    pub max_exposure: Option<ExposureCap>,
+}
+
+impl RewardPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        let total =
+            self.winner_percent as u16 + self.stakers_percent as u16 + self.treasury_percent as u16;
+        if total != 100 {
+            return Err(format!("Reward percentages must sum to 100, got {}", total));
+        }
+        if let Some(cap) = self.max_exposure.as_ref() {
+            cap.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Raises the reward share of stakers whose order range is long, by up to
+/// `max_bonus_percent` for ranges of `full_bonus_blocks` blocks or more.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct LockInBonus {
+    pub full_bonus_blocks: u64,
+    pub max_bonus_percent: u8,
+}
+
+impl LockInBonus {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.full_bonus_blocks == 0 {
+            return Err("Full bonus blocks can not be zero".to_string());
+        }
+        if self.max_bonus_percent == 0 || self.max_bonus_percent > 100 {
+            return Err("Lock-in bonus must be 1 to 100 percent".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The most cycles one staker takes part with in a block, the rest of an order is not bet
+/// and stays in the staker's balance.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum ExposureCap {
+    Cycles(u128),
+    PoolPercent(u8),
+}
+
+impl ExposureCap {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ExposureCap::Cycles(0) => Err("Exposure cap can not be zero".to_string()),
+            ExposureCap::PoolPercent(p) if *p == 0 || *p > 100 => Err(format!(
+                "Exposure cap must be between 1 and 100 percent, got {}",
+                p
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The largest bet counted in a block with `pool` cycles deposited.
+    pub fn limit(&self, pool: u128) -> u128 {
+        match self {
+            ExposureCap::Cycles(c) => *c,
+            ExposureCap::PoolPercent(p) => pool / 100 * *p as u128 + pool % 100 * *p as u128 / 100,
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum TreasuryBucket {
+    /// block rewards, claimed by stakers and miners
+    Emission,
+    Team,
+    Ecosystem,
+}
+
+/// Shares of the minted DOD that go to the team and ecosystem buckets, the rest is emission.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct TreasurySplit {
+    pub team_percent: u8,
+    pub ecosystem_percent: u8,
+}
+
+impl TreasurySplit {
+    pub fn emission_percent(&self) -> u8 {
+        100u8
+            .saturating_sub(self.team_percent)
+            .saturating_sub(self.ecosystem_percent)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.team_percent as u16 + self.ecosystem_percent as u16 >= 100 {
+            return Err("Emission must keep part of the minted DOD".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TreasuryBucketInfo {
+    pub bucket: TreasuryBucket,
+    pub subaccount: [u8; 32],
+    pub minted: u64,
+    pub transferred: u64,
+}
+
+pub const MAX_REWARD_BOOST_PERCENT: u32 = 300;
+
+/// Extra DOD for blocks with few deposits. The boost is `max_boost_percent` for a block
+/// without deposits and decays linearly to nothing at `threshold_cycles`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct RewardBoost {
+    pub threshold_cycles: u128,
+    pub max_boost_percent: u32,
+}
+
+impl RewardBoost {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threshold_cycles == 0 {
+            return Err("Boost threshold can not be zero".to_string());
+        }
+        if self.max_boost_percent > MAX_REWARD_BOOST_PERCENT {
+            return Err(format!(
+                "Boost must be at most {} percent",
+                MAX_REWARD_BOOST_PERCENT
+            ));
+        }
+        Ok(())
+    }
+
+    /// `reward` boosted for a block with `deposits` cycles.
+    pub fn apply(&self, reward: u64, deposits: u128) -> u64 {
+        if deposits >= self.threshold_cycles {
+            return reward;
+        }
+        let boost_percent = self.max_boost_percent as u128 * (self.threshold_cycles - deposits)
+            / self.threshold_cycles;
+        let boosted = reward as u128 * (100 + boost_percent) / 100;
+        u64::try_from(boosted).unwrap_or(u64::MAX)
+    }
+}
+
+pub const MAX_ICP_FEE_E8S: u64 = 1_000_000;
+pub const MAX_MIN_ICP_STAKE_E8S: u64 = 100 * 100_000_000;
+pub const MIN_CYCLES_CREATE_FEE: u128 = 500_000_000_000;
+pub const MAX_CYCLES_CREATE_FEE: u128 = 10_000_000_000_000;
+
+/// Fees and minimums that follow the IC fee schedule and product decisions.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct FeeSettings {
+    /// ICP ledger transfer fee
+    pub icp_fee: u64,
+    /// smallest ICP deposit converted to cycles
+    pub min_icp_stake_e8s: u64,
+    /// cycles attached to each token canister created at bootstrap
+    pub cycles_create_fee: u128,
+}
+
+impl FeeSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.icp_fee == 0 || self.icp_fee > MAX_ICP_FEE_E8S {
+            return Err(format!("ICP fee must be 1 to {} e8s", MAX_ICP_FEE_E8S));
+        }
+        if self.min_icp_stake_e8s <= self.icp_fee || self.min_icp_stake_e8s > MAX_MIN_ICP_STAKE_E8S
+        {
+            return Err(format!(
+                "Minimum ICP stake must be above the ICP fee and at most {} e8s",
+                MAX_MIN_ICP_STAKE_E8S
+            ));
+        }
+        if self.cycles_create_fee < MIN_CYCLES_CREATE_FEE
+            || self.cycles_create_fee > MAX_CYCLES_CREATE_FEE
+        {
+            return Err(format!(
+                "Cycles create fee must be {} to {}",
+                MIN_CYCLES_CREATE_FEE, MAX_CYCLES_CREATE_FEE
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What happens to participants whose share of a block reward is below one DOD unit.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum RewardDustPolicy {
+    /// the fraction is dropped, the cycles stay burned
+    Floor,
+    /// the order is not debited for that block
+    Refund,
+    /// fractions are carried in the user's reward remainder until they add up to a unit
+    Accumulate,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct AdminAuditEntry {
+    pub id: u64,
+    pub time: u64,
+    pub caller: Principal,
+    pub method: String,
+    /// sha256 of the candid encoded arguments
+    pub args_hash: Vec<u8>,
+    pub result: Result<(), String>,
+}
+
+impl Storable for AdminAuditEntry {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Priority fees paid by the orders settled in a block. Orders are settled highest fee first
+/// and the fees are burned with the block. Past the settlement capacity the remaining orders
+/// miss the block.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PriorityStats {
+    pub height: Height,
+    pub settled_orders: u64,
+    pub prioritized_orders: u64,
+    pub total_fees: u128,
+    pub max_fee: u128,
+    pub missed_orders: Option<u64>,
+}
+
+impl Storable for PriorityStats {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Preimage of a block hash, `hash = sha256(rand || prev_hash || candidates_hash)` where
+/// `candidates_hash` is the sha256 over the sorted commit txids of the previous block's candidates.
+/// Kept outside `BlockData`, which is bounded.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlockEntropy {
+    pub height: Height,
+    pub rand: Vec<u8>,
+    /// `false` when `raw_rand` had not answered and time seeded bytes were used
+    pub from_raw_rand: bool,
+    pub prev_hash: Vec<u8>,
+    pub commit_txids: Vec<String>,
+    pub candidates_hash: Vec<u8>,
+    /// the era whose salt is hashed before `rand`, `None` for blocks before eras
+    pub era_id: Option<u64>,
+}
+
+/// Blocks are generated in eras, each started by a bootstrap or a clean up. The salt of the
+/// era is mixed into every block hash, so PSBTs signed in an earlier era never match.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct BlockEra {
+    pub id: u64,
+    pub started_at: u64,
+    pub salt: Vec<u8>,
+}
+
+impl Storable for BlockEntropy {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct InternalTransfer {
+    pub id: u64,
+    pub from: Principal,
+    pub to: Principal,
+    pub amount: u128,
+    pub memo: Option<Vec<u8>>,
+    pub time: u64,
+}
+
+impl Storable for InternalTransfer {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct UpgradeRecord {
+    pub time: u64,
+    /// last block height when the upgrade happened, `None` before genesis
+    pub height: Option<Height>,
+    pub version: String,
+    pub git_commit: String,
+}
+
+impl Storable for UpgradeRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BuildInfo {
+    pub version: String,
+    pub git_commit: String,
+    /// hex encoded sha256 of the candid interface
+    pub candid_hash: String,
+    pub upgrades: Vec<UpgradeRecord>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum DepositStatus {
+    /// recorded, the ICP transfer to the CMC has not returned yet
+    Pending,
+    /// ICP reached the CMC, the top-up still has to be notified
+    Transferred,
+    /// a top-up notification is in flight
+    Notifying,
+    Completed,
+    Failed,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DepositRecord {
+    pub id: u64,
+    pub user: Principal,
+    pub amount_e8s: u64,
+    /// ICP ledger block of the transfer to the CMC
+    pub block_index: Option<u64>,
+    /// cycles credited once completed
+    pub cycles: Option<u128>,
+    pub status: DepositStatus,
+    /// last transfer or notification error
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl Storable for DepositRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A canister staking on behalf of its own end users, each end user gets an account
+/// derived from the integrator and the end user id.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct Integrator {
+    pub name: String,
+    pub registered_at: u64,
+    /// number of end user accounts funded so far
+    pub accounts: u64,
+    pub total_deposited: u128,
+}
+
+impl Storable for Integrator {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A request of the HTTP gateway to the canister.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// A mining pool submitting the solutions of its workers. Each worker mines with a miner
+/// owned by an account derived from the pool and the worker address.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MiningPool {
+    pub name: String,
+    pub registered_at: u64,
+    pub max_candidates_per_block: u64,
+    /// number of worker miners registered through the pool
+    pub workers: u64,
+    pub total_submitted: u64,
+    /// the block the pool last submitted for, with the entries counted for it
+    pub window_height: u64,
+    pub window_count: u64,
+}
+
+/// What the worker at `btc_address` won, moved to the staker account of its pool.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct PoolWorkerClaim {
+    pub btc_address: String,
+    pub cycles: u128,
+    pub dod: u64,
+}
+
+impl Storable for MiningPool {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// DOD the winner of a block was credited and debited as a miner, apart from what its owner
+/// earns as a staker.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MinerReward {
+    pub btc_address: String,
+    /// winner share of the reward policy, with its difficulty tier bonus
+    pub credited: u64,
+    /// winning DOD bid burned from the miner's balance
+    pub debited: u64,
+    pub updated_at: u64,
+}
+
+impl Storable for MinerReward {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Miners whose DOD counters were corrected against the recorded miner rewards.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MinerRewardsReconciliation {
+    pub miners: u64,
+    /// miners whose total was below what the recorded rewards add up to
+    pub raised: u64,
+    /// miners that had claimed more than their total
+    pub clamped: u64,
+}
+
+pub const MAX_MINING_ASSETS: usize = 16;
+pub const MAX_ASSET_ID_LEN: usize = 16;
+
+/// Asset ids are tickers of uppercase letters and digits, as DMT is.
+pub fn is_asset_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ASSET_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// An asset mined next to DMT. Its best candidate of a block wins `rewards`, halved on its own
+/// schedule counted from `start_height`, minted to the asset's treasury subaccount.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MiningAsset {
+    pub id: String,
+    pub rewards: u64,
+    pub halving_settings: Option<HalvingSettings>,
+    pub start_height: Height,
+    /// inactive assets keep their rewards claimable but take no new candidates
+    pub active: bool,
+    pub treasury_subaccount: Vec<u8>,
+    pub registered_at: u64,
+}
+
+impl Storable for MiningAsset {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MiningAssetArgs {
+    pub id: String,
+    pub rewards: u64,
+    pub halving_settings: Option<HalvingSettings>,
+    pub active: bool,
+}
+
+impl MiningAssetArgs {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_asset_id(self.id.as_str()) {
+            return Err(format!(
+                "Asset id must be 1 to {} uppercase letters or digits",
+                MAX_ASSET_ID_LEN
+            ));
+        }
+        if self.id == "DMT" {
+            return Err("DMT is the block asset, it is not registered".to_string());
+        }
+        if self.rewards == 0 {
+            return Err("Asset rewards must be greater than 0".to_string());
+        }
+        if let Some(halving) = self.halving_settings.as_ref() {
+            if halving.interval == 0 || !(halving.ratio > 0.0 && halving.ratio <= 1.0) {
+                return Err(
+                    "Halving interval must be greater than 0 and ratio in (0, 1]".to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub struct AssetRewardKey {
+    pub asset: String,
+    pub height: Height,
+}
+
+impl Storable for AssetRewardKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 128,
+        is_fixed_size: false,
+    };
+}
+
+/// What the best candidate of a registered asset won in a block.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct AssetReward {
+    pub asset: String,
+    pub height: Height,
+    pub btc_address: String,
+    pub reward: u64,
+    /// set once the reward reached the asset's treasury subaccount, it is claimable from then
+    pub minted: bool,
+    pub claimed: bool,
+}
+
+impl Storable for AssetReward {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A worker solution submitted by a pool.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PoolCandidate {
+    pub btc_address: String,
+    /// needed on the first entry of a worker, to register its miner
+    pub ecdsa_pubkey: Option<Vec<u8>>,
+    pub signed_commit_psbt: String,
+    pub signed_reveal_psbt: String,
+    pub cycles_price: u128,
+    pub dod_bid: Option<u64>,
+}
+
+/// Balances of a staker after a settlement, as mirrored by read replicas.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StakerBalance {
+    pub principal: Principal,
+    pub balance: u128,
+    pub claimed_dod: u64,
+    pub total_dod: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum StateChange {
+    /// a block was opened, closed or finalized, replaces the block at its height
+    BlockWritten(BlockData),
+    /// the stakers whose balances changed settling `height`
+    Settled {
+        height: Height,
+        stakers: Vec<StakerBalance>,
+    },
+    /// a miner was registered or its rewards changed, replaces the miner at its address
+    MinerUpdated(MinerInfo),
+    /// a staker was registered or its balances changed outside of a settlement, by a deposit,
+    /// a transfer, a claim or a rebate
+    StakerUpdated(StakerBalance),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StateDelta {
+    pub seq: u64,
+    pub time: u64,
+    pub change: StateChange,
+}
+
+impl Storable for StateDelta {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Answer to a replica handshake.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ReplicationInfo {
+    pub protocol_version: u32,
+    /// oldest delta still kept, a replica behind it has to resync from the full queries
+    pub first_seq: Option<u64>,
+    pub last_seq: Option<u64>,
+    /// what `init_genesis` set, block 0 is replicated without its message
+    pub genesis: Option<GenesisInfo>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RebateKind {
+    /// credited to the referrer's cycle balance
+    Cycles,
+    /// the DOD the rebated cycles earned in the block, paid from the treasury share
+    Dod,
+}
+
+/// Referrers get back `rebate_percent` of the cycles burned by the users they referred.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ReferralSettings {
+    pub enabled: bool,
+    pub rebate_percent: u8,
+    pub kind: RebateKind,
+}
+
+/// The referral of a user who registered with a code.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ReferralInfo {
+    pub referrer: Principal,
+    pub code: String,
+    pub registered_at: u64,
+}
+
+impl Storable for ReferralInfo {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ReferrerStats {
+    pub code: String,
+    pub referred_users: u64,
+    pub rebate_cycles: u128,
+    pub rebate_dod: u64,
+}
+
+impl Storable for ReferrerStats {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(
+    CandidType, Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd,
+)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct LogEntry {
+    pub id: u64,
+    pub time: u64,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+impl Storable for LogEntry {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SettlementPhase {
+    /// user balances are being settled, batch by batch
+    Balances,
+    /// every order is settled, the next block may start
+    Complete,
+}
+
+/// Progress of the settlement of a block, persisted so a settlement spread over several
+/// timer ticks resumes where it stopped.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SettlementCheckpoint {
+    pub height: Height,
+    pub phase: SettlementPhase,
+    /// total cycles of the block when the settlement started
+    pub cycle_deposit: u128,
+    pub refund_percent: u8,
+    /// the winner with its cycles price, decided before the balances
+    pub winner: Option<MinerInfo>,
+    /// the DOD bid taken from the winner's balance, burned when the block is finalized
+    pub winner_dod_bid: Option<u64>,
+    /// the last order settled
+    pub last_key: Option<Principal>,
+    pub refunded: u128,
+    pub stats: PriorityStats,
+    pub started_at: u64,
+    pub updated_at: u64,
+    /// DOD rebated to referrers so far, kept out of the treasury burn
+    pub dod_rebates: Option<u64>,
+    /// lock-in bonuses paid so far, kept out of the treasury burn
+    pub lock_in_bonuses: Option<u64>,
+}
+
+/// Where a user sends ICP before staking it, in the encodings front-ends need.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct DepositAccount {
+    /// hex encoded ICP ledger account identifier
+    pub account_id: String,
+    /// ICRC-1 textual encoding of the same account
+    pub icrc1_account: String,
+    pub subaccount: [u8; 32],
+}
+
+/// Totals of the blocks of one difficulty epoch, written when its last block is finalized.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct EpochSummary {
+    pub index: u64,
+    pub start_height: Height,
+    pub end_height: Height,
+    pub solved_blocks: u64,
+    pub unsolved_blocks: u64,
+    /// difficulty of the first block of the epoch
+    pub difficulty_before: Bitwork,
+    /// difficulty of the block following the epoch
+    pub difficulty_after: Bitwork,
+    pub dod_minted: u64,
+    pub dod_burned: u64,
+    pub cycles_burned: u128,
+    /// distinct addresses that submitted a candidate
+    pub unique_miners: u64,
+    /// distinct users with a settled order, the treasury excluded
+    pub unique_stakers: u64,
+    pub created_at: u64,
+}
+
+impl Storable for EpochSummary {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Transfer fee and decimals of the DOD ledger, read from the ledger when it is configured.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct LedgerMetadata {
+    pub fee: u64,
+    pub decimals: u8,
+    pub fetched_at: u64,
+}
+
+/// Block production and cycles status, see `health`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct HealthStatus {
+    pub height: Option<Height>,
+    pub block_status: Option<BlockStatus>,
+    /// time past the submission cutoff of the last block, zero while it is on time
+    pub overdue_by: u64,
+    pub watchdog_armed: bool,
+    /// production is restarted once overdue by this many block intervals
+    pub watchdog_factor: u64,
+    pub watchdog_restarts: u64,
+    pub last_watchdog_restart_at: Option<u64>,
+    pub skipped_generation_ticks: u64,
+    pub cycles: CyclesHealth,
+}
+
+/// DMT payload of the reveal transaction of a winning block, parsed once at settlement.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct WinnerEnvelope {
+    pub height: Height,
+    pub reveal_txid: String,
+    pub name: Option<String>,
+    /// mining time and nonce the miner put in the payload
+    pub time: Option<u32>,
+    pub nonce: Option<u32>,
+    /// hex encoded x-only keys of the envelope stakers
+    pub stakers: Vec<String>,
+}
+
+impl Storable for WinnerEnvelope {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Expected work to find a txid matching a difficulty at a given hash rate.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WorkEstimate {
+    pub difficulty: Bitwork,
+    /// chance of a single attempt to match
+    pub probability: f64,
+    pub expected_attempts: f64,
+    pub expected_seconds: f64,
+}
+
+/// Estimated DOD of an order that is not settled yet, from the deposits placed in its block
+/// so far. More deposits lower it.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PendingReward {
+    pub height: Height,
+    pub bet: u128,
+    pub pool: u128,
+    pub estimated_dod: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PendingRewards {
+    pub blocks: Vec<PendingReward>,
+    pub estimated_total_dod: u64,
+}
+
+/// What a user committed to by placing orders.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderPlacementResult {
+    pub blocks_covered: u64,
+    pub total_committed: u128,
+    /// DOD the orders would earn, against the largest and the smallest recent block pools
+    pub projected_reward_range: (u64, u64),
+    pub replaced_previous: bool,
+}
+
+/// Ledger block indices of the transactions made for a DOD block, `None` when the transfer
+/// failed or was not needed.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BlockLedgerLinks {
+    pub height: Height,
+    pub mint_index: Option<u64>,
+    pub burn_index: Option<u64>,
+}
+
+impl Storable for BlockLedgerLinks {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A page of registered miners, `total` counts every miner matching the filter.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MinersPage {
+    pub miners: Vec<MinerInfo>,
+    pub total: u64,
+}
+
+/// Reminders a user wants, `None` turns a reminder off.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NotificationPreferences {
+    pub notify_on_win: bool,
+    /// remind when the order range ends within this many blocks
+    pub order_expiry_blocks: Option<u64>,
+    /// remind when the unclaimed DOD is above this amount
+    pub claimable_above: Option<u64>,
+}
+
+impl Storable for NotificationPreferences {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum UserNotification {
+    Won {
+        height: Height,
+        btc_address: String,
+    },
+    OrderExpiring {
+        last_height: Height,
+        blocks_left: u64,
+    },
+    Claimable {
+        amount: u64,
+    },
+}
+
+/// Cycles burned for a block, with the canister balance right before and after the burn.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BurnReceipt {
+    pub height: Height,
+    pub burned: u128,
+    pub balance_before: u128,
+    pub balance_after: u128,
+    pub timestamp: u64,
+}
+
+impl Storable for BurnReceipt {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// What the winner of a block was paid, kept when the cycles are credited.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct WinnerPaymentProof {
+    pub height: Height,
+    pub winner: Principal,
+    pub btc_address: String,
+    pub cycles_credited: u128,
+    /// id of the `WinnerPaid` event, `None` for blocks settled before it was recorded
+    pub event_id: Option<u64>,
+    pub credited_at: Option<u64>,
+}
+
+impl Storable for WinnerPaymentProof {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MapUsage {
+    pub name: String,
+    pub entries: u64,
+    /// estimated from a sample of the entries
+    pub approx_bytes: u64,
+    /// wasm pages of 64KiB the map's memory has grown to
+    pub pages: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MemoryReport {
+    pub maps: Vec<MapUsage>,
+    /// wasm pages allocated by the memory manager for every map
+    pub stable_pages: u64,
+}